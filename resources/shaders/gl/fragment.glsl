@@ -1,14 +1,21 @@
 #version 330 core
 
 uniform bool discard_transparent;
+uniform bool checkerboard;
+uniform float opacity;
 
 in vec4 vColor;
 
 out vec4 color;
 
 void main() {
+    if (checkerboard && vColor.a < 0.01) {
+        bool dark = mod(floor(gl_FragCoord.x / 6.0) + floor(gl_FragCoord.y / 6.0), 2.0) < 1.0;
+        color = dark ? vec4(0.45, 0.45, 0.45, 0.35) : vec4(0.8, 0.8, 0.8, 0.35);
+        return;
+    }
     if (discard_transparent && vColor.a < 0.01) {
         discard;
     }
-    color = vec4(vColor);
+    color = vec4(vColor.rgb, vColor.a * opacity);
 }
@@ -38,6 +38,7 @@ mod imp {
             let obj = self.obj();
             obj.set_accels_for_action("win.undo", &["<Primary>Z", "<Meta>Z"]);
             obj.set_accels_for_action("win.redo", &["<Primary><Shift>Z", "<Meta><Shift>Z"]);
+            obj.set_accels_for_action("win.paste-skin", &["<Primary>V", "<Meta>V"]);
         }
     }
     impl ApplicationImpl for Application {
@@ -0,0 +1,562 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use image::RgbaImage;
+use nalgebra_glm as glm;
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+
+use crate::blockbench::{bone_name, inflate};
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::renderer::part_geometry;
+use crate::glium_area::rig::{player_rig, Cuboid, Rig, RigError, SIDE_LAYOUTS};
+use crate::glium_area::skin_parser::ModelType;
+use crate::glium_area::software_renderer::Facet;
+
+/// Oldest format with the `minecraft:geometry` list.
+const MINIMUM_FORMAT: (u32, u32) = (1, 12);
+const EXPORT_FORMAT: &str = "1.12.0";
+/// Skin pixels in a model unit.
+const PIXELS_PER_UNIT: f32 = 8.0;
+/// Height of the model origin in pixels, Bedrock puts the soles at 0.
+const FLOOR: f32 = 16.0;
+/// Face names in [`CubeSide`](crate::glium_area::cube_side::CubeSide) order.
+const FACE_NAMES: [&str; 6] = ["north", "west", "south", "east", "up", "down"];
+/// Cells of a cube whose vertexes still fit the 16 bit indices of a model object.
+const MAX_CUBE_CELLS: usize = u16::MAX as usize / 4;
+
+#[derive(Debug)]
+pub enum BedrockGeometryError {
+    IoError(io::Error),
+    JsonError(serde_json::Error),
+    UnsupportedFormat(String),
+    NoGeometry,
+    /// A cube has more cells than a model object can index.
+    CubeTooLarge(String),
+    RigError(RigError),
+}
+
+impl From<io::Error> for BedrockGeometryError {
+    fn from(error: io::Error) -> Self {
+        BedrockGeometryError::IoError(error)
+    }
+}
+
+impl From<serde_json::Error> for BedrockGeometryError {
+    fn from(error: serde_json::Error) -> Self {
+        BedrockGeometryError::JsonError(error)
+    }
+}
+
+#[derive(Deserialize)]
+struct GeometryFile {
+    format_version: String,
+    #[serde(rename = "minecraft:geometry", default)]
+    geometries: Vec<Geometry>,
+}
+
+#[derive(Deserialize)]
+pub struct Geometry {
+    pub description: Description,
+    #[serde(default)]
+    pub bones: Vec<Bone>,
+}
+
+#[derive(Deserialize)]
+pub struct Description {
+    pub identifier: String,
+    #[serde(default = "default_texture_size")]
+    pub texture_width: u32,
+    #[serde(default = "default_texture_size")]
+    pub texture_height: u32,
+}
+
+fn default_texture_size() -> u32 {
+    64
+}
+
+/// Lengths are in pixels with y up and the soles at 0.
+#[derive(Deserialize)]
+pub struct Bone {
+    pub name: String,
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub pivot: [f32; 3],
+    /// Degrees around x, y and z.
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default)]
+    pub mirror: bool,
+    #[serde(default)]
+    pub inflate: f32,
+    #[serde(default)]
+    pub cubes: Vec<Cube>,
+}
+
+#[derive(Deserialize)]
+pub struct Cube {
+    /// Lowest corner.
+    pub origin: [f32; 3],
+    pub size: [f32; 3],
+    #[serde(default)]
+    pub uv: CubeUv,
+    /// Overrides the inflation of the bone.
+    #[serde(default)]
+    pub inflate: Option<f32>,
+    /// Rotation of the cube around its own pivot, after the bone.
+    #[serde(default)]
+    pub pivot: Option<[f32; 3]>,
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default)]
+    pub mirror: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum CubeUv {
+    /// Top left corner of the box UV layout.
+    Box([f32; 2]),
+    Faces(BTreeMap<String, FaceUv>),
+}
+
+impl Default for CubeUv {
+    fn default() -> Self {
+        CubeUv::Box([0.0, 0.0])
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FaceUv {
+    pub uv: [f32; 2],
+    /// Negative sizes flip the texture.
+    pub uv_size: [f32; 2],
+}
+
+/// Reads the geometries of a Bedrock `geo.json` file, format 1.12.0 or newer.
+pub fn load_geometries(path: &Path) -> Result<Vec<Geometry>, BedrockGeometryError> {
+    let file: GeometryFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let mut version = file.format_version.split('.').map(|number| number.parse::<u32>().unwrap_or(0));
+    let (major, minor) = (version.next().unwrap_or(0), version.next().unwrap_or(0));
+    if (major, minor) < MINIMUM_FORMAT {
+        return Err(BedrockGeometryError::UnsupportedFormat(file.format_version));
+    }
+    if file.geometries.is_empty() {
+        return Err(BedrockGeometryError::NoGeometry);
+    }
+    Ok(file.geometries)
+}
+
+/// Position in the editor, in model units with the front at +z.
+fn to_editor(point: &[f32; 3]) -> glm::Vec3 {
+    glm::vec3(point[0], point[1] - FLOOR, -point[2]) / PIXELS_PER_UNIT
+}
+
+/// Turns `rotation` around `pivot`, both in Bedrock terms, z first and x last. The editor
+/// has z the other way around, which turns the rotations around x and y the other way.
+fn rotation_about(pivot: &[f32; 3], rotation: &[f32; 3]) -> glm::Mat4 {
+    let pivot = to_editor(pivot);
+    let [x, y, z] = rotation.map(f32::to_radians);
+    let mut matrix = glm::translate(&glm::Mat4::identity(), &pivot);
+    matrix = glm::rotate_x(&matrix, x);
+    matrix = glm::rotate_y(&matrix, -y);
+    matrix = glm::rotate_z(&matrix, -z);
+    glm::translate(&matrix, &-pivot)
+}
+
+/// A textured cell of a cube, see [`Geometry::cells`].
+pub struct GeometryCell {
+    /// Model units in the editor.
+    pub corners: [glm::Vec3; 4],
+    /// Texture position in the pixels of the geometry texture size.
+    pub texel: [f32; 2],
+    pub cube_center: glm::Vec3,
+    pub inflated: bool,
+}
+
+impl Geometry {
+    /// Transform of every bone with its parents, by name.
+    fn bone_transforms(&self) -> BTreeMap<&str, glm::Mat4> {
+        let bones: BTreeMap<&str, &Bone> = self.bones.iter().map(|bone| (bone.name.as_str(), bone)).collect();
+        let mut transforms = BTreeMap::new();
+        for bone in &self.bones {
+            let mut matrix = glm::Mat4::identity();
+            let mut current = Some(bone);
+            // The depth limit guards against parent cycles
+            for _ in 0..bones.len() {
+                let Some(link) = current else { break };
+                matrix = rotation_about(&link.pivot, &link.rotation) * matrix;
+                current = link.parent.as_deref().and_then(|parent| bones.get(parent).copied());
+            }
+            transforms.insert(bone.name.as_str(), matrix);
+        }
+        transforms
+    }
+
+    /// Every texel of every cube face as a cell, like the cells of the editor.
+    pub fn cells(&self) -> Vec<GeometryCell> {
+        let transforms = self.bone_transforms();
+        let mut cells = vec![];
+
+        for bone in &self.bones {
+            for cube in &bone.cubes {
+                let inflate = cube.inflate.unwrap_or(bone.inflate);
+                let mirror = cube.mirror.unwrap_or(bone.mirror);
+                let low = cube.origin.map(|value| value - inflate);
+                let high = [0, 1, 2].map(|axis| cube.origin[axis] + cube.size[axis] + inflate);
+                let (low, high) = (to_editor(&low), to_editor(&high));
+                let center = (low + high) / 2.0;
+                let half_size = (high - low).abs() / 2.0;
+
+                let mut matrix = transforms[bone.name.as_str()];
+                if let Some(pivot) = &cube.pivot {
+                    matrix *= rotation_about(pivot, &cube.rotation);
+                }
+                let place = |point: glm::Vec3| (matrix * glm::vec4(point.x, point.y, point.z, 1.0)).xyz();
+
+                for ((corner, column_step, row_step), region) in SIDE_LAYOUTS.iter().zip(face_regions(cube, mirror)) {
+                    let Some([u, v, width, height]) = region else { continue };
+                    let columns = width.abs().round().max(1.0) as u32;
+                    let rows = height.abs().round().max(1.0) as u32;
+                    let corner = center + glm::make_vec3(corner).component_mul(&half_size);
+                    let across = glm::make_vec3(column_step).component_mul(&half_size) * 2.0 / columns as f32;
+                    let down = glm::make_vec3(row_step).component_mul(&half_size) * 2.0 / rows as f32;
+
+                    for row in 0..rows {
+                        for column in 0..columns {
+                            let top_left = corner + across * column as f32 + down * row as f32;
+                            cells.push(GeometryCell {
+                                corners: [top_left, top_left + across, top_left + across + down, top_left + down].map(place),
+                                texel: [
+                                    u + (column as f32 + 0.5) * width / columns as f32,
+                                    v + (row as f32 + 0.5) * height / rows as f32,
+                                ],
+                                cube_center: place(center),
+                                inflated: inflate > 0.0,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        cells
+    }
+
+    /// Every cube as a cuboid of a rig with a cell per pixel of the geometry texture size,
+    /// named after its bone. Inflated cubes are the outer layer of the first cube of their
+    /// parent bone, like the hat of the head.
+    pub fn to_rig(&self) -> Result<Rig, BedrockGeometryError> {
+        let transforms = self.bone_transforms();
+        let (texture_width, texture_height) = (self.description.texture_width as i32, self.description.texture_height as i32);
+        let mut cuboids: Vec<(BodyPart, Cuboid)> = vec![];
+        let mut first_cubes: BTreeMap<&str, BodyPart> = BTreeMap::new();
+
+        for bone in &self.bones {
+            for (index, cube) in bone.cubes.iter().enumerate() {
+                let inflate = cube.inflate.unwrap_or(bone.inflate);
+                let mirror = cube.mirror.unwrap_or(bone.mirror);
+                let low = cube.origin.map(|value| value - inflate);
+                let high = [0, 1, 2].map(|axis| cube.origin[axis] + cube.size[axis] + inflate);
+                let size = (glm::make_vec3(&high) - glm::make_vec3(&low)).abs();
+                let position = (to_editor(&low) + to_editor(&high)) / 2.0;
+
+                let mut placement = transforms[bone.name.as_str()];
+                if let Some(pivot) = &cube.pivot {
+                    placement *= rotation_about(pivot, &cube.rotation);
+                }
+                // Faces reaching out of the texture are left without cells
+                let faces = face_regions(cube, mirror).map(|region| {
+                    let Some(region) = region else { return [0; 4] };
+                    let [x, y, width, height] = region.map(|value| value.round() as i32);
+                    let inside = x.min(x + width) >= 0 && y.min(y + height) >= 0
+                        && x.max(x + width) <= texture_width && y.max(y + height) <= texture_height;
+                    if inside { [x, y, width, height] } else { [0; 4] }
+                });
+                let mut cuboid = Cuboid::with_faces(size, position, placement, faces);
+
+                let cells: usize = cuboid.cells_per_side().iter().sum();
+                if cells == 0 {
+                    continue;
+                }
+                let name = if index == 0 { bone.name.clone() } else { format!("{} {}", bone.name, index + 1) };
+                if cells > MAX_CUBE_CELLS {
+                    return Err(BedrockGeometryError::CubeTooLarge(name));
+                }
+                let body_part = BodyPart::from_id(&name);
+                if inflate > 0.0 {
+                    let inner = bone.parent.as_deref().and_then(|parent| first_cubes.get(parent));
+                    cuboid.outer_of = inner.copied().filter(|inner| {
+                        cuboids.iter().all(|(_, other)| other.outer_of != Some(*inner))
+                    });
+                }
+                first_cubes.entry(bone.name.as_str()).or_insert(body_part);
+                cuboids.push((body_part, cuboid));
+            }
+        }
+
+        let texture_size = [self.description.texture_width, self.description.texture_height];
+        Rig::new(self.description.identifier.clone(), texture_size, cuboids).map_err(BedrockGeometryError::RigError)
+    }
+
+    /// The cells colored by a texture, for [`render_facets`](crate::glium_area::software_renderer::render_facets).
+    pub fn facets(&self, texture: &RgbaImage) -> Vec<Facet> {
+        let scale_x = texture.width() as f32 / self.description.texture_width as f32;
+        let scale_y = texture.height() as f32 / self.description.texture_height as f32;
+        self.cells()
+            .into_iter()
+            .filter_map(|cell| {
+                let x = (cell.texel[0] * scale_x).floor();
+                let y = (cell.texel[1] * scale_y).floor();
+                if x < 0.0 || y < 0.0 || x >= texture.width() as f32 || y >= texture.height() as f32 {
+                    return None;
+                }
+                Some(Facet {
+                    corners: cell.corners,
+                    color: *texture.get_pixel(x as u32, y as u32),
+                    center: cell.cube_center,
+                    outer: cell.inflated,
+                })
+            })
+            .collect()
+    }
+
+    /// Model type of the player arms, if the geometry has the player right arm.
+    pub fn player_model_type(&self) -> Option<ModelType> {
+        let arm = player_rig(&ModelType::Classic).cuboid(BodyPart::RightArm).expect("the player has a right arm");
+        let arm_uv = arm.uv.map(|value| value as f32);
+        self.bones.iter()
+            .flat_map(|bone| &bone.cubes)
+            .find(|cube| matches!(cube.uv, CubeUv::Box(uv) if uv == arm_uv))
+            .map(|cube| if cube.size[0] < 3.5 { ModelType::Slim } else { ModelType::Classic })
+    }
+
+    /// Bones with cubes that are not a part of the player model.
+    pub fn extra_bones(&self) -> Vec<&str> {
+        let model_type = self.player_model_type().unwrap_or(ModelType::Classic);
+        let rig = player_rig(&model_type);
+        let is_player_cube = |cube: &Cube| rig.cuboids().any(|(_, cuboid)| {
+            let size = cuboid.size.map(|value| value as f32);
+            let uv = cuboid.uv.map(|value| value as f32);
+            matches!(cube.uv, CubeUv::Box(cube_uv) if cube_uv == uv) && cube.size == size
+        });
+        self.bones.iter()
+            .filter(|bone| !bone.cubes.iter().all(is_player_cube))
+            .map(|bone| bone.name.as_str())
+            .collect()
+    }
+}
+
+/// Texture areas of the faces in [`SIDE_LAYOUTS`] order as x, y, width and height.
+/// Faces without one are not drawn.
+fn face_regions(cube: &Cube, mirror: bool) -> [Option<[f32; 4]>; 6] {
+    match &cube.uv {
+        CubeUv::Box([u, v]) => {
+            let [width, height, depth] = cube.size;
+            let mut regions = [
+                [u + depth, v + depth, width, height],
+                [u + depth + width, v + depth, depth, height],
+                [u + 2.0 * depth + width, v + depth, width, height],
+                [*u, v + depth, depth, height],
+                [u + depth, *v, width, depth],
+                [u + depth + width, *v, width, depth],
+            ];
+            // Mirrored boxes swap the sides and flip every face
+            if mirror {
+                regions.swap(1, 3);
+                for region in &mut regions {
+                    region[0] += region[2];
+                    region[2] = -region[2];
+                }
+            }
+            regions.map(Some)
+        }
+        CubeUv::Faces(faces) => FACE_NAMES.map(|name| {
+            let face = faces.get(name)?;
+            let [width, height] = face.uv_size;
+            (width != 0.0 && height != 0.0).then_some([face.uv[0], face.uv[1], width, height])
+        }),
+    }
+}
+
+/// Bone of the outer layer of a part, named like in the Bedrock player geometry.
+fn outer_bone_name(body_part: BodyPart) -> &'static str {
+    match body_part {
+        BodyPart::HeadOuter => "hat",
+        BodyPart::TorsoOuter => "jacket",
+        BodyPart::RightArmOuter => "rightSleeve",
+        BodyPart::LeftArmOuter => "leftSleeve",
+        BodyPart::RightLegOuter => "rightPants",
+        _ => "leftPants",
+    }
+}
+
+fn to_bedrock(position: &glm::Vec3) -> glm::Vec3 {
+    // Adding zero keeps -0 out of the file
+    glm::vec3(position.x, position.y, -position.z) * PIXELS_PER_UNIT + glm::vec3(0.0, FLOOR, 0.0)
+}
+
+/// Player geometry of the given arms for skin packs, format 1.12.0.
+pub fn player_geometry(model_type: &ModelType) -> Value {
+    let rig = player_rig(model_type);
+    let mut bones = vec![];
+    for (&body_part, cuboid) in rig.cuboids() {
+        let geometry = part_geometry(body_part, model_type);
+        let size = glm::vec3(cuboid.size[0] as f32, cuboid.size[1] as f32, cuboid.size[2] as f32);
+        let origin = to_bedrock(&geometry.translation) - size / 2.0;
+        let pivot = to_bedrock(&geometry.pivot);
+
+        let (name, parent) = match (body_part, body_part.inner_counterpart()) {
+            (_, Some(inner)) => (outer_bone_name(body_part), Some(bone_name(inner))),
+            (BodyPart::Torso | BodyPart::RightLeg | BodyPart::LeftLeg, _) => (bone_name(body_part), None),
+            _ => (bone_name(body_part), Some(bone_name(BodyPart::Torso))),
+        };
+        let mut cube = json!({ "origin": [origin.x, origin.y, origin.z], "size": cuboid.size, "uv": cuboid.uv });
+        if body_part.is_outer() {
+            cube["inflate"] = json!(inflate(body_part));
+        }
+        let mut bone = json!({ "name": name, "pivot": [pivot.x, pivot.y, pivot.z], "cubes": [cube] });
+        if let Some(parent) = parent {
+            bone["parent"] = json!(parent);
+        }
+        bones.push(bone);
+    }
+
+    let identifier = match model_type {
+        ModelType::Classic => "geometry.humanoid.custom",
+        ModelType::Slim => "geometry.humanoid.customSlim",
+    };
+    json!({
+        "format_version": EXPORT_FORMAT,
+        "minecraft:geometry": [{
+            "description": {
+                "identifier": identifier,
+                "texture_width": rig.texture_size[0],
+                "texture_height": rig.texture_size[1],
+                "visible_bounds_width": 2,
+                "visible_bounds_height": 2,
+                "visible_bounds_offset": [0, 1, 0],
+            },
+            "bones": bones,
+        }],
+    })
+}
+
+pub fn export_player_geometry(model_type: &ModelType, path: &Path) -> Result<(), BedrockGeometryError> {
+    fs::write(path, serde_json::to_string_pretty(&player_geometry(model_type))?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::DynamicImage;
+
+    use super::*;
+    use crate::glium_area::cube_side::CubeSide;
+    use crate::glium_area::skin_parser::SkinParser;
+
+    fn geometry(json: Value) -> Geometry {
+        let mut file: GeometryFile = serde_json::from_value(json).unwrap();
+        file.geometries.remove(0)
+    }
+
+    #[test]
+    fn exported_player_has_no_extra_bones() {
+        let geometry = geometry(player_geometry(&ModelType::Slim));
+        assert_eq!(geometry.player_model_type(), Some(ModelType::Slim));
+        assert!(geometry.extra_bones().is_empty());
+        // Every texel of the twelve parts becomes a cell
+        let cells: usize = player_rig(&ModelType::Slim).cuboids()
+            .map(|(_, cuboid)| cuboid.cells_per_side().iter().sum::<usize>())
+            .sum();
+        assert_eq!(geometry.cells().len(), cells);
+    }
+
+    #[test]
+    fn bones_turn_around_their_pivot_with_their_parents() {
+        let geometry = geometry(json!({
+            "format_version": "1.16.0",
+            "minecraft:geometry": [{
+                "description": { "identifier": "geometry.tail" },
+                "bones": [
+                    { "name": "body", "pivot": [0, 24, 0], "rotation": [-90, 0, 0] },
+                    { "name": "tail", "parent": "body", "pivot": [0, 12, 0], "cubes": [
+                        { "origin": [-1, 0, -1], "size": [2, 12, 2], "uv": { "north": { "uv": [0, 0], "uv_size": [2, 12] } } }
+                    ] }
+                ]
+            }]
+        }));
+        assert_eq!(geometry.extra_bones(), vec!["tail"]);
+
+        let cells = geometry.cells();
+        assert_eq!(cells.len(), 24);
+        // Hanging from y 12 down to the soles, turned -90° around y 24 it points to the front
+        let far_end = cells.iter().flat_map(|cell| cell.corners).map(|corner| corner.z).fold(f32::MIN, f32::max);
+        assert!((far_end - 3.0).abs() < 1e-5);
+        assert_eq!(cells[0].texel, [0.5, 0.5]);
+
+        // The editable cuboid lies where the preview draws the cells
+        let rig = geometry.to_rig().unwrap();
+        let tail = rig.cuboid(BodyPart::from_id("tail")).unwrap();
+        assert_eq!(tail.cells_per_side(), [24, 0, 0, 0, 0, 0]);
+        let place = tail.placement * glm::translate(&glm::Mat4::identity(), &tail.position);
+        let corner = tail.vertexes[0].position;
+        let corner = (place * glm::vec4(corner[0], corner[1], corner[2], 1.0)).xyz();
+        assert!((corner - cells[0].corners[0]).norm() < 1e-5);
+    }
+
+    #[test]
+    fn player_geometry_becomes_an_editable_rig() {
+        let rig = geometry(player_geometry(&ModelType::Classic)).to_rig().unwrap();
+        let player = player_rig(&ModelType::Classic);
+        assert_eq!(rig.parts().count(), 12);
+        let (head, hat) = (BodyPart::from_id("head"), BodyPart::from_id("hat"));
+        assert_eq!(rig.inner_part(hat), Some(head));
+        assert_eq!(rig.cuboid(head).unwrap().side_regions(), player.cuboid(BodyPart::Head).unwrap().side_regions());
+        assert_eq!(rig.cuboid(head).unwrap().vertexes, player.cuboid(BodyPart::Head).unwrap().vertexes);
+
+        // The texture comes back out where it was read
+        let texture = RgbaImage::from_fn(64, 64, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let parser = SkinParser::for_rig(&rig);
+        let color_map = parser.load_from_bytes(&DynamicImage::ImageRgba8(texture.clone())).unwrap();
+        let mut exported = RgbaImage::new(64, 64);
+        for (body_part, sides) in &color_map {
+            let cells: Vec<_> = sides.values().flatten().copied().collect();
+            parser.export_cells(body_part, &mut exported, &cells);
+        }
+        assert_eq!(exported.get_pixel(8, 8), texture.get_pixel(8, 8));
+        assert_eq!(exported.get_pixel(40, 8), texture.get_pixel(40, 8));
+    }
+
+    #[test]
+    fn mirrored_and_flipped_faces_keep_their_texture() {
+        let geometry = geometry(json!({
+            "format_version": "1.12.0",
+            "minecraft:geometry": [{
+                "description": { "identifier": "geometry.flag", "texture_width": 16, "texture_height": 16 },
+                "bones": [
+                    { "name": "pole", "mirror": true, "cubes": [{ "origin": [0, 0, 0], "size": [1, 4, 1], "uv": [0, 0] }] },
+                    { "name": "cloth", "cubes": [
+                        { "origin": [1, 2, 0], "size": [4, 2, 0], "uv": { "north": { "uv": [12, 4], "uv_size": [-4, -2] } } },
+                        { "origin": [0, 0, 0], "size": [1, 1, 1], "uv": { "north": { "uv": [15, 15], "uv_size": [4, 4] } } }
+                    ] }
+                ]
+            }]
+        }));
+        let rig = geometry.to_rig().unwrap();
+        // The second cloth cube has no face inside the texture
+        assert_eq!(rig.parts().count(), 2);
+        let cloth = rig.cuboid(BodyPart::from_id("cloth")).unwrap();
+        assert_eq!(cloth.side_regions()[0].1, [8, 2, 4, 2]);
+        assert_eq!(cloth.side_flips()[0], (true, true));
+        assert_eq!(rig.cuboid(BodyPart::from_id("pole")).unwrap().side_flips()[1], (true, false));
+
+        let texture = RgbaImage::from_fn(16, 16, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let color_map = SkinParser::for_rig(&rig).load_from_bytes(&DynamicImage::ImageRgba8(texture)).unwrap();
+        let front = &color_map[&BodyPart::from_id("cloth")][&CubeSide::Front];
+        // Flipped both ways, the top left cell shows the bottom right pixel
+        assert_eq!(front[0], image::Rgba([11, 3, 0, 255]));
+    }
+}
@@ -0,0 +1,376 @@
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::{ImageError, ImageFormat, RgbaImage};
+use nalgebra_glm as glm;
+use serde_json::{json, Value};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::cube_side::CubeSide;
+use crate::glium_area::renderer::part_geometry;
+use crate::glium_area::skin_parser::{texture_layout, ModelType, SkinParser, TextureLoadError, TextureType};
+use crate::utils::guess_model_type;
+
+const FORMAT_VERSION: &str = "4.10";
+const DATA_URL_PREFIX: &str = "data:image/png;base64,";
+/// Height of the soles in model units, Blockbench puts them at 0.
+const FLOOR: f32 = -2.0;
+const PIXELS_PER_UNIT: f32 = 8.0;
+/// Model type of the exported skin, Blockbench leaves unknown keys alone.
+const MODEL_TYPE_KEY: &str = "mc_skin_editor_model_type";
+
+#[derive(Debug)]
+pub enum BlockbenchError {
+    IoError(io::Error),
+    JsonError(serde_json::Error),
+    ImageError(ImageError),
+    Base64Error(base64::DecodeError),
+    TextureLoadError(TextureLoadError),
+    NoTexture,
+    NotPlayerModel(String),
+}
+
+impl From<io::Error> for BlockbenchError {
+    fn from(error: io::Error) -> Self {
+        BlockbenchError::IoError(error)
+    }
+}
+
+impl From<serde_json::Error> for BlockbenchError {
+    fn from(error: serde_json::Error) -> Self {
+        BlockbenchError::JsonError(error)
+    }
+}
+
+impl From<ImageError> for BlockbenchError {
+    fn from(error: ImageError) -> Self {
+        BlockbenchError::ImageError(error)
+    }
+}
+
+impl From<base64::DecodeError> for BlockbenchError {
+    fn from(error: base64::DecodeError) -> Self {
+        BlockbenchError::Base64Error(error)
+    }
+}
+
+impl From<TextureLoadError> for BlockbenchError {
+    fn from(error: TextureLoadError) -> Self {
+        BlockbenchError::TextureLoadError(error)
+    }
+}
+
+/// Skin found in a Blockbench project.
+pub struct BlockbenchSkin {
+    pub image: RgbaImage,
+    pub model_type: ModelType,
+}
+
+/// Bone of a body part, named like the bones of the Bedrock player geometry.
+pub fn bone_name(body_part: BodyPart) -> &'static str {
+    match body_part {
+        BodyPart::Head | BodyPart::HeadOuter => "head",
+        BodyPart::Torso | BodyPart::TorsoOuter => "body",
+        BodyPart::RightArm | BodyPart::RightArmOuter => "rightArm",
+        BodyPart::LeftArm | BodyPart::LeftArmOuter => "leftArm",
+        BodyPart::RightLeg | BodyPart::RightLegOuter => "rightLeg",
+        BodyPart::LeftLeg | BodyPart::LeftLegOuter => "leftLeg",
+        other => other.id(),
+    }
+}
+
+/// Inflation of the outer layer in pixels, the same as in the game.
+pub fn inflate(body_part: BodyPart) -> f32 {
+    match body_part {
+        BodyPart::HeadOuter => 0.5,
+        part if part.is_outer() => 0.25,
+        _ => 0.0,
+    }
+}
+
+/// Box of a body part in pixels with the soles at 0, as its lowest corner and size.
+/// Blockbench models look at -z, so the model is turned around to keep the right arm
+/// on the right.
+pub fn part_box(body_part: BodyPart, model_type: &ModelType) -> (glm::Vec3, glm::Vec3) {
+    let parser = SkinParser::new(model_type, TextureType::Normal);
+    let region = |side: CubeSide| parser.side_regions(&body_part)
+        .into_iter()
+        .find(|(region_side, _)| *region_side == side)
+        .map(|(_, region)| region)
+        .unwrap();
+    let [_, _, width, height] = region(CubeSide::Front);
+    let [_, _, depth, _] = region(CubeSide::Right);
+    let size = glm::vec3(width as f32, height as f32, depth as f32);
+    let center = to_blockbench(&part_geometry(body_part, model_type).translation);
+    (center - size / 2.0, size)
+}
+
+/// Pivot of the bone of a body part in Blockbench pixels.
+pub fn part_origin(body_part: BodyPart, model_type: &ModelType) -> glm::Vec3 {
+    to_blockbench(&part_geometry(body_part, model_type).pivot)
+}
+
+fn to_blockbench(position: &glm::Vec3) -> glm::Vec3 {
+    // Adding zero keeps -0 out of the file
+    glm::vec3(-position.x, position.y - FLOOR, -position.z) * PIXELS_PER_UNIT + glm::Vec3::zeros()
+}
+
+/// Top left corner of the box UV layout of a part, where its texture area starts.
+pub fn uv_offset(parser: &SkinParser, body_part: BodyPart) -> [u32; 2] {
+    let regions = parser.side_regions(&body_part);
+    let x = regions.iter().map(|(_, [x, ..])| *x).min().unwrap();
+    let y = regions.iter().map(|(_, [_, y, ..])| *y).min().unwrap();
+    [x, y]
+}
+
+fn face_name(side: CubeSide) -> &'static str {
+    match side {
+        CubeSide::Front => "north",
+        CubeSide::Back => "south",
+        CubeSide::Right => "east",
+        CubeSide::Left => "west",
+        CubeSide::Top => "up",
+        CubeSide::Bottom => "down",
+    }
+}
+
+/// UV rectangle of a face, flipped the way Blockbench maps the top and bottom of a box.
+fn face_uv(side: CubeSide, [x, y, width, height]: [u32; 4]) -> [u32; 4] {
+    match side {
+        CubeSide::Top => [x + width, y + height, x, y],
+        CubeSide::Bottom => [x + width, y, x, y + height],
+        _ => [x, y, x + width, y + height],
+    }
+}
+
+fn random_uuid() -> String {
+    let bits = rand::random::<u128>() & !(0xf000 << 64) & !(0xc << 60) | 0x4000 << 64 | 0x8 << 60;
+    let hex = format!("{:032x}", bits);
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+fn project_json(image: &RgbaImage, model_type: &ModelType) -> Result<Value, BlockbenchError> {
+    // Box UVs take their size from the cubes, so they stay in the pixels of a 64x64 skin and
+    // Blockbench scales an HD texture to them
+    let (_, resolution) = texture_layout(image.width(), image.height())?;
+    let parser = SkinParser::new(model_type, TextureType::Normal);
+    let mut elements = vec![];
+    let mut outliner: Vec<Value> = vec![];
+
+    for body_part in BodyPart::PLAYER {
+        let (from, size) = part_box(body_part, model_type);
+        let to = from + size;
+        let origin = part_origin(body_part, model_type);
+        let faces: serde_json::Map<String, Value> = parser.side_regions(&body_part)
+            .into_iter()
+            .map(|(side, region)| (face_name(side).to_string(), json!({ "uv": face_uv(side, region), "texture": 0 })))
+            .collect();
+        let uuid = random_uuid();
+        elements.push(json!({
+            "name": format!("{:?}", body_part),
+            "box_uv": true,
+            "rescale": false,
+            "locked": false,
+            "from": [from.x, from.y, from.z],
+            "to": [to.x, to.y, to.z],
+            "autouv": 0,
+            "color": 0,
+            "inflate": inflate(body_part),
+            "origin": [origin.x, origin.y, origin.z],
+            "uv_offset": uv_offset(&parser, body_part),
+            "faces": faces,
+            "type": "cube",
+            "uuid": uuid,
+        }));
+
+        let name = bone_name(body_part);
+        match outliner.iter_mut().find(|group| group["name"] == name) {
+            Some(group) => group["children"].as_array_mut().unwrap().push(json!(uuid)),
+            None => outliner.push(json!({
+                "name": name,
+                "origin": [origin.x, origin.y, origin.z],
+                "rotation": [0, 0, 0],
+                "color": 0,
+                "uuid": random_uuid(),
+                "export": true,
+                "isOpen": false,
+                "visibility": true,
+                "autouv": 0,
+                "children": [uuid],
+            })),
+        }
+    }
+
+    let mut png = Cursor::new(vec![]);
+    image.write_to(&mut png, ImageFormat::Png)?;
+    let (width, height) = image.dimensions();
+    let (uv_width, uv_height) = (width / resolution, height / resolution);
+    Ok(json!({
+        "meta": { "format_version": FORMAT_VERSION, "model_format": "skin", "box_uv": true },
+        "name": "skin",
+        "resolution": { "width": uv_width, "height": uv_height },
+        MODEL_TYPE_KEY: serde_json::to_value(model_type)?,
+        "elements": elements,
+        "outliner": outliner,
+        "textures": [{
+            "name": "skin.png",
+            "id": "0",
+            "width": width,
+            "height": height,
+            "uv_width": uv_width,
+            "uv_height": uv_height,
+            "particle": false,
+            "render_mode": "default",
+            "visible": true,
+            "mode": "bitmap",
+            "saved": false,
+            "uuid": random_uuid(),
+            "source": format!("{}{}", DATA_URL_PREFIX, STANDARD.encode(png.into_inner())),
+        }],
+    }))
+}
+
+/// Saves the skin as a Blockbench project of the player model with the texture embedded.
+pub fn export_project(image: &RgbaImage, model_type: &ModelType, path: &Path) -> Result<(), BlockbenchError> {
+    let project = project_json(image, model_type)?;
+    fs::write(path, serde_json::to_string_pretty(&project)?)?;
+    Ok(())
+}
+
+/// Body part a cube of a project stands for, by where its texture starts.
+fn cube_part(cube: &Value, parser: &SkinParser) -> Option<BodyPart> {
+    let offset: Option<[u32; 2]> = serde_json::from_value(cube["uv_offset"].clone()).ok();
+    match offset {
+        Some(offset) => BodyPart::PLAYER.into_iter().find(|&part| uv_offset(parser, part) == offset),
+        // Cubes without box UV are matched by their front face
+        None => {
+            let uv: [f32; 4] = serde_json::from_value(cube["faces"]["north"]["uv"].clone()).ok()?;
+            let corner = [uv[0].min(uv[2]) as u32, uv[1].min(uv[3]) as u32];
+            BodyPart::PLAYER.into_iter().find(|part| {
+                parser.side_regions(part)
+                    .iter()
+                    .any(|(side, [x, y, ..])| *side == CubeSide::Front && [*x, *y] == corner)
+            })
+        }
+    }
+}
+
+fn load_texture(project: &Value, folder: &Path) -> Result<RgbaImage, BlockbenchError> {
+    let texture = project["textures"].get(0).ok_or(BlockbenchError::NoTexture)?;
+    let bytes = match texture["source"].as_str().and_then(|source| source.strip_prefix(DATA_URL_PREFIX)) {
+        Some(data) => STANDARD.decode(data)?,
+        None => {
+            let path = texture["path"].as_str()
+                .filter(|path| !path.is_empty())
+                .or_else(|| texture["relative_path"].as_str())
+                .ok_or(BlockbenchError::NoTexture)?;
+            fs::read(folder.join(path))?
+        }
+    };
+    Ok(image::load_from_memory(&bytes)?.to_rgba8())
+}
+
+fn parse_project(project: &Value, folder: &Path) -> Result<BlockbenchSkin, BlockbenchError> {
+    let image = load_texture(project, folder)?;
+    let (width, height) = image.dimensions();
+    if !matches!(texture_layout(width, height), Ok((TextureType::Normal, _))) {
+        return Err(BlockbenchError::NotPlayerModel(format!("the texture is {}x{} instead of 64x64 or its HD size", width, height)));
+    }
+
+    // The UVs are in the pixels of the project resolution, which may be the HD one
+    let uv_resolution = project["resolution"]["width"].as_u64().map_or(1, |uv_width| (uv_width / 64).max(1) as u32);
+    let parser = SkinParser::with_resolution(&ModelType::Classic, TextureType::Normal, uv_resolution);
+    let cubes: Vec<&Value> = project["elements"].as_array()
+        .map(|elements| elements.iter().filter(|element| element["type"].as_str().unwrap_or("cube") == "cube").collect())
+        .unwrap_or_default();
+    let mut right_arm_width = None;
+    for cube in &cubes {
+        match cube_part(cube, &parser) {
+            Some(BodyPart::RightArm) => {
+                let from: Option<[f32; 3]> = serde_json::from_value(cube["from"].clone()).ok();
+                let to: Option<[f32; 3]> = serde_json::from_value(cube["to"].clone()).ok();
+                right_arm_width = from.zip(to).map(|(from, to)| (to[0] - from[0]).abs().round() as u32);
+            }
+            Some(_) => {}
+            None => {
+                let name = cube["name"].as_str().unwrap_or("unnamed");
+                return Err(BlockbenchError::NotPlayerModel(format!("the cube \"{}\" is not a part of the player", name)));
+            }
+        }
+    }
+    if cubes.is_empty() {
+        return Err(BlockbenchError::NotPlayerModel("the project has no cubes".to_string()));
+    }
+
+    let model_type = serde_json::from_value(project[MODEL_TYPE_KEY].clone()).ok()
+        .or(match right_arm_width {
+            Some(3) => Some(ModelType::Slim),
+            Some(4) => Some(ModelType::Classic),
+            _ => None,
+        })
+        .unwrap_or_else(|| guess_model_type(&image).model_type);
+    Ok(BlockbenchSkin { image, model_type })
+}
+
+/// Reads the skin of a Blockbench project that uses the player model.
+pub fn import_project(path: &Path) -> Result<BlockbenchSkin, BlockbenchError> {
+    let project: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let folder = path.parent().unwrap_or(Path::new("."));
+    parse_project(&project, folder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_project_imports_with_its_model_type() {
+        let image = RgbaImage::from_fn(64, 64, |x, y| image::Rgba([x as u8 * 4, y as u8 * 4, 0, 255]));
+        let mut project = project_json(&image, &ModelType::Slim).unwrap();
+
+        let skin = parse_project(&project, Path::new(".")).unwrap();
+        assert_eq!(skin.model_type, ModelType::Slim);
+        assert_eq!(skin.image, image);
+
+        // Without the recorded type the width of the arms tells
+        project.as_object_mut().unwrap().remove(MODEL_TYPE_KEY);
+        let skin = parse_project(&project, Path::new(".")).unwrap();
+        assert_eq!(skin.model_type, ModelType::Slim);
+    }
+
+    #[test]
+    fn cube_uvs_follow_the_skin_layout() {
+        let image = RgbaImage::new(64, 64);
+        let project = project_json(&image, &ModelType::Classic).unwrap();
+        let head = project["elements"].as_array().unwrap()
+            .iter()
+            .find(|element| element["name"] == "Head")
+            .unwrap();
+
+        assert_eq!(head["uv_offset"], json!([0, 0]));
+        assert_eq!(head["faces"]["north"]["uv"], json!([8, 8, 16, 16]));
+        assert_eq!(head["from"], json!([-4.0, 24.0, -4.0]));
+        assert_eq!(head["to"], json!([4.0, 32.0, 4.0]));
+    }
+
+    #[test]
+    fn hd_skins_keep_the_uvs_of_a_64x64_skin() {
+        let image = RgbaImage::from_fn(128, 128, |x, y| image::Rgba([x as u8 * 2, y as u8 * 2, 0, 255]));
+        let project = project_json(&image, &ModelType::Classic).unwrap();
+        assert_eq!(project["resolution"], json!({ "width": 64, "height": 64 }));
+        assert_eq!(project["textures"][0]["width"], json!(128));
+        assert_eq!(project["textures"][0]["uv_width"], json!(64));
+        let head = project["elements"].as_array().unwrap()
+            .iter()
+            .find(|element| element["name"] == "Head")
+            .unwrap();
+        assert_eq!(head["faces"]["north"]["uv"], json!([8, 8, 16, 16]));
+
+        let skin = parse_project(&project, Path::new(".")).unwrap();
+        assert_eq!(skin.image, image);
+        assert_eq!(skin.model_type, ModelType::Classic);
+    }
+}
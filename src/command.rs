@@ -1,36 +1,70 @@
 use std::collections::BTreeMap;
+use std::rc::Rc;
 
 use gtk::prelude::WidgetExt;
+use image::{DynamicImage, GenericImageView};
 
+use crate::glium_area::arm_conversion::{ConversionStrategy, HiddenColumns};
 use crate::glium_area::body_part::BodyPart;
 use crate::glium_area::GliumArea;
 use crate::glium_area::renderer::ModelCell;
+use crate::glium_area::rig::{same_custom_rig, Rig};
+use crate::glium_area::skin_parser::{texture_layout, ModelType};
 
 pub trait Action {
     fn execute(&self);
 }
 
 #[derive(Clone)]
-struct CellChange {
-    body_part: BodyPart,
-    cell_index: usize,
-    before: [f32; 4],
-    after: [f32; 4],
+pub(crate) struct CellChange {
+    pub(crate) body_part: BodyPart,
+    pub(crate) cell_index: usize,
+    pub(crate) before: [f32; 4],
+    pub(crate) after: [f32; 4],
 }
 
-type ModelSnapshot = BTreeMap<(BodyPart, usize), [f32; 4]>;
+pub(crate) type ModelSnapshot = BTreeMap<(BodyPart, usize), [f32; 4]>;
 
-fn snapshot_model(gl_area: &GliumArea) -> ModelSnapshot {
+/// Everything a change of the model type, the resolution or the rig touches.
+struct LayoutSnapshot {
+    model_type: ModelType,
+    resolution: u32,
+    custom_rig: Option<Rc<Rig>>,
+    cells: ModelSnapshot,
+    hidden_columns: HiddenColumns,
+}
+
+fn snapshot_layout(gl_area: &GliumArea) -> LayoutSnapshot {
+    let renderer = gl_area.renderer().expect("Renderer is not initialized");
+    let renderer = renderer.borrow();
+    LayoutSnapshot {
+        model_type: renderer.get_model_type(),
+        resolution: renderer.resolution(),
+        custom_rig: renderer.custom_rig(),
+        cells: renderer.snapshot_cells(),
+        hidden_columns: renderer.hidden_columns().clone(),
+    }
+}
+
+fn restore_layout(gl_area: &GliumArea, layout: &LayoutSnapshot) {
     let renderer = gl_area.renderer().expect("Renderer is not initialized");
-    let snapshot = renderer.borrow().snapshot_cells();
-    snapshot
+    renderer.borrow_mut().restore_layout(
+        &layout.model_type, layout.resolution, layout.custom_rig.clone(), &layout.cells, layout.hidden_columns.clone());
+    gl_area.queue_draw();
+}
+
+/// A step of the drawing history. Cell indices depend on the model type, the resolution
+/// and the rig, so steps that change them keep the whole model.
+enum HistoryEntry {
+    Cells(Vec<CellChange>),
+    Layout { before: LayoutSnapshot, after: LayoutSnapshot },
 }
 
 fn colors_differ(a: [f32; 4], b: [f32; 4]) -> bool {
     a != b
 }
 
-fn diff_snapshots(before: &ModelSnapshot, after: &ModelSnapshot) -> Vec<CellChange> {
+pub(crate) fn diff_snapshots(before: &ModelSnapshot, after: &ModelSnapshot) -> Vec<CellChange> {
     let mut changes = Vec::new();
 
     for ((body_part, cell_index), &after_color) in after {
@@ -158,6 +192,116 @@ impl Action for Replace {
     }
 }
 
+/// Paints cells with colors of their own, e.g. a stamped picture.
+pub struct Paint {
+    gl_area: GliumArea,
+    cells: Vec<ModelCell>,
+}
+impl Paint {
+    pub fn new(gl_area: GliumArea, cells: Vec<ModelCell>) -> Paint {
+        Paint { gl_area, cells }
+    }
+}
+impl Action for Paint {
+    fn execute(&self) {
+        let renderer = self.gl_area.renderer().unwrap();
+        let mut renderer = renderer.borrow_mut();
+        for cell in &self.cells {
+            renderer.set_cell(cell);
+        }
+        self.gl_area.queue_draw();
+    }
+}
+
+/// Paints a whole skin over the model, e.g. a composed outfit.
+pub struct LoadImage {
+    gl_area: GliumArea,
+    image: DynamicImage,
+    model_type: ModelType,
+    ignore_transparent: bool,
+}
+impl LoadImage {
+    pub fn new(gl_area: GliumArea, image: DynamicImage, model_type: ModelType) -> LoadImage {
+        LoadImage { gl_area, image, model_type, ignore_transparent: false }
+    }
+
+    /// Paints only the opaque pixels of the image, e.g. a template.
+    pub fn over(gl_area: GliumArea, image: DynamicImage, model_type: ModelType) -> LoadImage {
+        LoadImage { gl_area, image, model_type, ignore_transparent: true }
+    }
+}
+impl Action for LoadImage {
+    fn execute(&self) {
+        let renderer = self.gl_area.renderer().unwrap();
+        let mut renderer = renderer.borrow_mut();
+        let result = texture_layout(self.image.width(), self.image.height()).and_then(|(texture_type, _)| {
+            renderer.load_texture_from_bytes(&self.image, self.model_type, texture_type, self.ignore_transparent)
+        });
+        if let Err(error) = result {
+            println!("Error loading image: {:?}", error);
+        }
+        self.gl_area.queue_draw();
+    }
+}
+
+/// Swaps the model for the cuboids of a rig, e.g. a Bedrock geometry, painted with a texture.
+pub struct LoadRig {
+    gl_area: GliumArea,
+    rig: Rc<Rig>,
+    texture: DynamicImage,
+}
+impl LoadRig {
+    pub fn new(gl_area: GliumArea, rig: Rig, texture: DynamicImage) -> LoadRig {
+        LoadRig { gl_area, rig: Rc::new(rig), texture }
+    }
+}
+impl Action for LoadRig {
+    fn execute(&self) {
+        let renderer = self.gl_area.renderer().unwrap();
+        if let Err(error) = renderer.borrow_mut().load_rig(self.rig.clone(), &self.texture) {
+            println!("Error loading rig: {:?}", error);
+        }
+        self.gl_area.queue_draw();
+    }
+}
+
+/// Switches the model type, converting the arms with the given strategy.
+pub struct ChangeModelType {
+    gl_area: GliumArea,
+    model_type: ModelType,
+    strategy: ConversionStrategy,
+}
+impl ChangeModelType {
+    pub fn new(gl_area: GliumArea, model_type: ModelType, strategy: ConversionStrategy) -> ChangeModelType {
+        ChangeModelType { gl_area, model_type, strategy }
+    }
+}
+impl Action for ChangeModelType {
+    fn execute(&self) {
+        let renderer = self.gl_area.renderer().unwrap();
+        renderer.borrow_mut().reset_model_type(&self.model_type, self.strategy);
+        self.gl_area.queue_draw();
+    }
+}
+
+/// Scales the skin to another resolution, see [`Renderer::set_resolution`](crate::glium_area::renderer::Renderer::set_resolution).
+pub struct ChangeResolution {
+    gl_area: GliumArea,
+    resolution: u32,
+}
+impl ChangeResolution {
+    pub fn new(gl_area: GliumArea, resolution: u32) -> ChangeResolution {
+        ChangeResolution { gl_area, resolution }
+    }
+}
+impl Action for ChangeResolution {
+    fn execute(&self) {
+        let renderer = self.gl_area.renderer().unwrap();
+        renderer.borrow_mut().set_resolution(self.resolution);
+        self.gl_area.queue_draw();
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum Tool {
     Pencil,
@@ -165,7 +309,11 @@ pub enum Tool {
     ColorPicker,
     Fill,
     Random,
-    Replace
+    Replace,
+    Text,
+    Shade,
+    Noise,
+    Gradient
 }
 
 impl Default for Tool {
@@ -176,8 +324,8 @@ impl Default for Tool {
 
 pub struct DrawingHistory {
     gl_area: GliumArea,
-    undo_stack: Vec<Vec<CellChange>>,
-    redo_stack: Vec<Vec<CellChange>>,
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
     last_modified_cell: Option<ModelCell>,
 }
 
@@ -187,37 +335,53 @@ impl DrawingHistory {
     }
 
     pub fn add_command(&mut self, command: Box<dyn Action>) -> bool {
-        let before = snapshot_model(&self.gl_area);
+        let before = snapshot_layout(&self.gl_area);
         command.execute();
-        let after = snapshot_model(&self.gl_area);
-        let diff = diff_snapshots(&before, &after);
-
-        if !diff.is_empty() {
-            self.undo_stack.push(diff);
-            self.redo_stack.clear();
-        }
+        let after = snapshot_layout(&self.gl_area);
+
+        let layout_changed = before.model_type != after.model_type
+            || before.resolution != after.resolution
+            || !same_custom_rig(before.custom_rig.as_ref(), after.custom_rig.as_ref())
+            || before.hidden_columns != after.hidden_columns;
+        let entry = if layout_changed {
+            HistoryEntry::Layout { before, after }
+        } else {
+            let diff = diff_snapshots(&before.cells, &after.cells);
+            if diff.is_empty() {
+                return true;
+            }
+            HistoryEntry::Cells(diff)
+        };
+        self.undo_stack.push(entry);
+        self.redo_stack.clear();
 
         true
     }
 
     pub fn undo(&mut self) {
         if self.undo_stack.is_empty() { return }
-        let diff = self.undo_stack.pop()
+        let entry = self.undo_stack.pop()
             .expect("Error popping a diff from undo_stack");
 
-        apply_diff(&self.gl_area, &diff, true);
-        self.redo_stack.push(diff);
+        match &entry {
+            HistoryEntry::Cells(diff) => apply_diff(&self.gl_area, diff, true),
+            HistoryEntry::Layout { before, .. } => restore_layout(&self.gl_area, before),
+        }
+        self.redo_stack.push(entry);
 
         self.last_modified_cell.take();
     }
 
     pub fn redo(&mut self) {
         if self.redo_stack.is_empty() { return }
-        let diff = self.redo_stack.pop()
+        let entry = self.redo_stack.pop()
             .expect("Error popping a diff from redo_stack.");
 
-        apply_diff(&self.gl_area, &diff, false);
-        self.undo_stack.push(diff);
+        match &entry {
+            HistoryEntry::Cells(diff) => apply_diff(&self.gl_area, diff, false),
+            HistoryEntry::Layout { after, .. } => restore_layout(&self.gl_area, after),
+        }
+        self.undo_stack.push(entry);
 
         self.last_modified_cell.take();
     }
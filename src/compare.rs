@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+
+use image::DynamicImage;
+use image::imageops::FilterType;
+
+use crate::command::{diff_snapshots, ModelSnapshot};
+use crate::glium_area::arm_conversion::{convert_part, ConversionStrategy};
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::skin_parser::{ModelType, SkinParser, TextureLoadError, TextureType};
+
+/// Marks the cells that differ in [`CompareMode::Differences`].
+const HIGHLIGHT_COLOR: [f32; 4] = [1.0, 0.0, 0.8, 1.0];
+/// How much of its color an unchanged cell keeps in [`CompareMode::Differences`].
+const UNCHANGED_SATURATION: f32 = 0.15;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CompareMode {
+    /// The other skin next to the current one.
+    SideBySide,
+    /// The current skin blended into the other one.
+    Overlay,
+    /// The current skin with the cells that differ highlighted.
+    Differences,
+}
+
+/// The skin the current one is compared with, laid out for the model type in the editor.
+pub struct SkinComparison {
+    pub name: String,
+    image: DynamicImage,
+    model_type: ModelType,
+    cells: ModelSnapshot,
+    /// Model type, resolution and arm conversion the cells are laid out with.
+    cells_layout: (ModelType, u32, ConversionStrategy),
+}
+
+impl SkinComparison {
+    pub fn new(name: String, image: DynamicImage, model_type: ModelType) -> Result<Self, TextureLoadError> {
+        let strategy = ConversionStrategy::default();
+        let cells = skin_cells(&image, model_type, model_type, 1, strategy)?;
+        Ok(SkinComparison { name, image, model_type, cells, cells_layout: (model_type, 1, strategy) })
+    }
+
+    /// Cells of the skin for `model_type` at `resolution`. Arms are converted with
+    /// `strategy` when the skin was made for the other model type, and the skin is scaled
+    /// to the resolution, so the skins can be compared cell by cell.
+    pub fn cells(&mut self, model_type: ModelType, resolution: u32, strategy: ConversionStrategy) -> Result<&ModelSnapshot, TextureLoadError> {
+        if self.cells_layout != (model_type, resolution, strategy) {
+            self.cells = skin_cells(&self.image, self.model_type, model_type, resolution, strategy)?;
+            self.cells_layout = (model_type, resolution, strategy);
+        }
+        Ok(&self.cells)
+    }
+}
+
+/// How many cells of every body part differ. Parts without differences are left out.
+pub fn count_differences(current: &ModelSnapshot, other: &ModelSnapshot) -> BTreeMap<BodyPart, usize> {
+    let mut counts = BTreeMap::new();
+    for change in diff_snapshots(&normalize(other), &normalize(current)) {
+        *counts.entry(change.body_part).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Colors to show in the compare viewport. `blend` is how much of the current skin
+/// is mixed into the other one in [`CompareMode::Overlay`].
+pub fn compare_cells(current: &ModelSnapshot, other: &ModelSnapshot, mode: CompareMode, blend: f32) -> ModelSnapshot {
+    match mode {
+        CompareMode::SideBySide => other.clone(),
+        CompareMode::Overlay => other
+            .iter()
+            .map(|(&cell, &other_color)| {
+                let current_color = current.get(&cell).copied().unwrap_or([0.0; 4]);
+                (cell, mix(other_color, current_color, blend))
+            })
+            .collect(),
+        CompareMode::Differences => {
+            let mut cells: ModelSnapshot = current.iter().map(|(&cell, &color)| (cell, desaturate(color))).collect();
+            for change in diff_snapshots(&normalize(other), &normalize(current)) {
+                cells.insert((change.body_part, change.cell_index), HIGHLIGHT_COLOR);
+            }
+            cells
+        }
+    }
+}
+
+fn skin_cells(image: &DynamicImage, from: ModelType, to: ModelType, resolution: u32, strategy: ConversionStrategy) -> Result<ModelSnapshot, TextureLoadError> {
+    let size = 64 * resolution;
+    let scaled = image.resize_exact(size, size, FilterType::Nearest);
+
+    let parser = SkinParser::with_resolution(&from, TextureType::Normal, resolution);
+    let color_map = parser.load_from_bytes(&scaled)?;
+    let mut cells = ModelSnapshot::new();
+    for (body_part, sides) in color_map {
+        let colors: Vec<[f32; 4]> = sides.values().flatten().map(|pixel| [0, 1, 2, 3].map(|channel| f32::from(pixel[channel]) / 255.0)).collect();
+        for (cell_index, color) in convert_part(&colors, body_part, from, to, strategy, resolution as usize).into_iter().enumerate() {
+            cells.insert((body_part, cell_index), color);
+        }
+    }
+    Ok(cells)
+}
+
+/// Colors painted in the editor aren't limited to 8 bits per channel and cleared cells
+/// keep their RGB, so both are brought to what would be saved in a PNG.
+fn normalize(cells: &ModelSnapshot) -> ModelSnapshot {
+    cells
+        .iter()
+        .map(|(&cell, &color)| {
+            let color = if color[3] == 0.0 {
+                [0.0; 4]
+            } else {
+                color.map(|channel| (channel * 255.0).round() / 255.0)
+            };
+            (cell, color)
+        })
+        .collect()
+}
+
+fn mix(from: [f32; 4], to: [f32; 4], amount: f32) -> [f32; 4] {
+    [0, 1, 2, 3].map(|channel| from[channel] + (to[channel] - from[channel]) * amount)
+}
+
+fn desaturate(color: [f32; 4]) -> [f32; 4] {
+    let luma = 0.299 * color[0] + 0.587 * color[1] + 0.114 * color[2];
+    let gray = [luma, luma, luma, color[3]];
+    mix(gray, color, UNCHANGED_SATURATION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_cells(name: &str, model_type: ModelType) -> ModelSnapshot {
+        let image = image::open(format!("tests/skins/{}.png", name)).unwrap();
+        let mut comparison = SkinComparison::new(name.to_string(), image, model_type).unwrap();
+        comparison.cells(model_type, 1, ConversionStrategy::default()).unwrap().clone()
+    }
+
+    /// The classic fixture with three head cells and one torso cell painted red.
+    fn edited(cells: &ModelSnapshot) -> ModelSnapshot {
+        let mut edited = cells.clone();
+        for cell in [(BodyPart::Head, 0), (BodyPart::Head, 1), (BodyPart::Head, 70), (BodyPart::Torso, 5)] {
+            edited.insert(cell, [1.0, 0.0, 0.0, 1.0]);
+        }
+        edited
+    }
+
+    #[test]
+    fn differences_are_counted_per_part() {
+        let original = fixture_cells("classic", ModelType::Classic);
+        assert!(count_differences(&original, &original).is_empty());
+
+        let counts = count_differences(&edited(&original), &original);
+        assert_eq!(counts, BTreeMap::from([(BodyPart::Head, 3), (BodyPart::Torso, 1)]));
+    }
+
+    #[test]
+    fn unsaved_precision_and_cleared_colors_are_not_differences() {
+        let original = fixture_cells("classic", ModelType::Classic);
+        let mut current = original.clone();
+        let color = current.get_mut(&(BodyPart::Head, 0)).unwrap();
+        color[0] = (color[0] + 0.001).min(1.0);
+        current.insert((BodyPart::HeadOuter, 0), [0.0; 4]);
+        let mut other = original.clone();
+        other.insert((BodyPart::HeadOuter, 0), [0.5, 0.2, 0.7, 0.0]);
+
+        assert!(count_differences(&current, &other).is_empty());
+    }
+
+    #[test]
+    fn side_by_side_shows_the_other_skin() {
+        let classic = fixture_cells("classic", ModelType::Classic);
+        let slim = fixture_cells("slim", ModelType::Classic);
+        assert_eq!(compare_cells(&classic, &slim, CompareMode::SideBySide, 0.5), slim);
+    }
+
+    #[test]
+    fn overlay_blends_the_current_skin_into_the_other_one() {
+        let other = ModelSnapshot::from([((BodyPart::Head, 0), [0.0, 0.0, 1.0, 1.0]), ((BodyPart::Head, 1), [0.2, 0.4, 0.6, 1.0])]);
+        let current = ModelSnapshot::from([((BodyPart::Head, 0), [1.0, 0.0, 0.0, 1.0])]);
+
+        assert_eq!(compare_cells(&current, &other, CompareMode::Overlay, 0.0), other);
+        let blended = compare_cells(&current, &other, CompareMode::Overlay, 0.5);
+        assert_eq!(blended[&(BodyPart::Head, 0)], [0.5, 0.0, 0.5, 1.0]);
+        // Cells missing from the current skin count as transparent
+        assert_eq!(blended[&(BodyPart::Head, 1)], [0.1, 0.2, 0.3, 0.5]);
+        let blended = compare_cells(&current, &other, CompareMode::Overlay, 1.0);
+        assert_eq!(blended[&(BodyPart::Head, 0)], [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn differences_are_highlighted_on_the_faded_current_skin() {
+        let other = fixture_cells("classic", ModelType::Classic);
+        let current = edited(&other);
+        let cells = compare_cells(&current, &other, CompareMode::Differences, 0.5);
+
+        assert_eq!(cells.len(), current.len());
+        let highlighted: Vec<&(BodyPart, usize)> = cells.iter().filter(|(_, &color)| color == HIGHLIGHT_COLOR).map(|(cell, _)| cell).collect();
+        assert_eq!(highlighted, [&(BodyPart::Head, 0), &(BodyPart::Head, 1), &(BodyPart::Head, 70), &(BodyPart::Torso, 5)]);
+        let cell = (BodyPart::Torso, 6);
+        assert_eq!(cells[&cell], desaturate(current[&cell]));
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+
+use gtk::gio::Cancellable;
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ButtonExt, FileExt, GLAreaExt, RangeExt, ToggleButtonExt, WidgetExt};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+use image::{DynamicImage, GenericImageView};
+
+use crate::compare::{compare_cells, count_differences, CompareMode, SkinComparison};
+use crate::glium_area::arm_conversion::ConversionStrategy;
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::skin_parser::{texture_layout, ModelType, TextureType};
+use crate::utils;
+use crate::window::Window;
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+
+    use gtk::{CompositeTemplate, glib, TemplateChild};
+    use gtk::glib::subclass::InitializingObject;
+    use gtk::glib::WeakRef;
+    use gtk::subclass::prelude::{BoxImpl, CompositeTemplate, CompositeTemplateInitializingExt, ObjectImpl, ObjectSubclass, WidgetImpl};
+    use gtk::subclass::widget::WidgetClassExt;
+
+    use crate::compare::SkinComparison;
+    use crate::glium_area::GliumArea;
+    use crate::window::Window;
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(file = "../resources/ui/compare-panel.ui")]
+    pub struct ComparePanel {
+        #[template_child]
+        pub open_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub snapshot_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub mode_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub overlay_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub flip_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub blend_scale: TemplateChild<gtk::Scale>,
+        #[template_child]
+        pub compare_area: TemplateChild<GliumArea>,
+        #[template_child]
+        pub summary_label: TemplateChild<gtk::Label>,
+
+        pub comparison: RefCell<Option<SkinComparison>>,
+        pub window: WeakRef<Window>,
+        /// Revision of the skin in the editor the panel was last refreshed for.
+        pub seen_revision: Cell<Option<u64>>,
+        pub refresh_scheduled: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ComparePanel {
+        const NAME: &'static str = "ComparePanel";
+        type Type = super::ComparePanel;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ComparePanel {}
+    impl WidgetImpl for ComparePanel {}
+    impl BoxImpl for ComparePanel {}
+}
+
+glib::wrapper! {
+    pub struct ComparePanel(ObjectSubclass<imp::ComparePanel>)
+        @extends gtk::Widget, gtk::Box,
+        @implements gtk::Accessible, gtk::Actionable, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl Default for ComparePanel {
+    fn default() -> Self {
+        ComparePanel::new()
+    }
+}
+
+impl ComparePanel {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    pub fn setup(&self, win: &Window) {
+        self.imp().window.set(Some(win));
+
+        let compare_area = self.imp().compare_area.get();
+        compare_area.setup_viewer();
+        win.gl_area().link_camera(&compare_area);
+        compare_area.connect_realize(clone!(#[weak(rename_to = panel)] self, move |_| {
+            panel.window().gl_area().sync_linked_camera();
+            panel.refresh();
+        }));
+
+        // The editor viewport is redrawn after every change of the skin
+        win.gl_area().connect_render(clone!(#[weak(rename_to = panel)] self, #[upgrade_or] glib::Propagation::Proceed, move |_, _| {
+            panel.schedule_refresh();
+            glib::Propagation::Proceed
+        }));
+
+        self.imp().open_button.connect_clicked(clone!(#[weak(rename_to = panel)] self, move |_| {
+            panel.choose_skin_file();
+        }));
+        self.imp().snapshot_button.connect_clicked(clone!(#[weak(rename_to = panel)] self, move |_| {
+            let win = panel.window();
+            let image = DynamicImage::ImageRgba8(win.export_texture());
+            let model_type = win.editor().model_type();
+            panel.set_comparison("Snapshot".to_string(), image, model_type);
+        }));
+        self.imp().mode_selector.connect_selected_notify(clone!(#[weak(rename_to = panel)] self, move |_| {
+            panel.imp().overlay_box.set_visible(panel.mode() == CompareMode::Overlay);
+            panel.refresh();
+        }));
+        self.imp().flip_button.connect_toggled(clone!(#[weak(rename_to = panel)] self, move |button| {
+            panel.imp().blend_scale.set_value(if button.is_active() { 1.0 } else { 0.0 });
+        }));
+        self.imp().blend_scale.connect_value_changed(clone!(#[weak(rename_to = panel)] self, move |_| {
+            panel.refresh();
+        }));
+    }
+
+    fn window(&self) -> Window {
+        self.imp().window.upgrade().expect("ComparePanel has no window")
+    }
+
+    fn mode(&self) -> CompareMode {
+        match self.imp().mode_selector.selected() {
+            1 => CompareMode::Overlay,
+            2 => CompareMode::Differences,
+            _ => CompareMode::SideBySide,
+        }
+    }
+
+    fn choose_skin_file(&self) {
+        let file_dialog = gtk::FileDialog::builder().title("Compare with").build();
+        file_dialog.open(Some(&self.window()), Cancellable::NONE, clone!(#[weak(rename_to = panel)] self, move |file| {
+            let Some(path) = file.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            let image = match image::open(&path) {
+                Ok(image) if matches!(texture_layout(image.width(), image.height()), Ok((TextureType::Normal, _))) => image,
+                Ok(_) => {
+                    println!("Only 64x64 skins and their HD versions can be compared");
+                    return;
+                }
+                Err(error) => {
+                    println!("Error opening {:?}: {:?}", path, error);
+                    return;
+                }
+            };
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let model_type = utils::guess_model_type(&image).model_type;
+            panel.set_comparison(name, image, model_type);
+        }));
+    }
+
+    fn set_comparison(&self, name: String, image: DynamicImage, model_type: ModelType) {
+        match SkinComparison::new(name, image, model_type) {
+            Ok(comparison) => {
+                self.imp().comparison.replace(Some(comparison));
+                self.refresh();
+            }
+            Err(error) => println!("Error loading the skin to compare with: {:?}", error),
+        }
+    }
+
+    /// Refreshes once the editor is idle, if the skin changed since the last refresh.
+    fn schedule_refresh(&self) {
+        if !self.is_visible() || self.imp().refresh_scheduled.get() {
+            return;
+        }
+        if self.imp().seen_revision.get() == Some(self.window().editor().revision()) {
+            return;
+        }
+        self.imp().refresh_scheduled.set(true);
+        glib::idle_add_local_once(clone!(#[weak(rename_to = panel)] self, move || {
+            panel.imp().refresh_scheduled.set(false);
+            panel.refresh();
+        }));
+    }
+
+    pub fn refresh(&self) {
+        let Some(renderer) = self.imp().compare_area.renderer() else {
+            return;
+        };
+        let win = self.window();
+        if win.editor().renderer().is_none() {
+            return;
+        }
+        self.imp().seen_revision.set(Some(win.editor().revision()));
+
+        let mut comparison = self.imp().comparison.borrow_mut();
+        let Some(comparison) = comparison.as_mut() else {
+            return;
+        };
+        let model_type = win.editor().model_type();
+        let resolution = win.editor().resolution();
+        let current = win.editor().snapshot_cells();
+        let other = match comparison.cells(model_type, resolution, win.conversion_strategy()) {
+            Ok(other) => other,
+            Err(error) => {
+                println!("Error comparing skins: {:?}", error);
+                return;
+            }
+        };
+
+        let blend = self.imp().blend_scale.value() as f32;
+        let cells = compare_cells(&current, other, self.mode(), blend);
+        let mut renderer = renderer.borrow_mut();
+        renderer.set_resolution(resolution);
+        renderer.reset_model_type(&model_type, ConversionStrategy::default());
+        renderer.load_cells(&cells);
+        drop(renderer);
+        self.imp().compare_area.queue_draw();
+
+        let counts = count_differences(&current, other);
+        self.imp().summary_label.set_label(&Self::describe(&comparison.name, &counts));
+    }
+
+    fn describe(name: &str, counts: &BTreeMap<BodyPart, usize>) -> String {
+        if counts.is_empty() {
+            return format!("Same as {}", name);
+        }
+        let total: usize = counts.values().sum();
+        let mut lines = vec![format!("{} cells differ from {}", total, name)];
+        for (body_part, count) in counts {
+            lines.push(format!("{}: {}", body_part.label(), count));
+        }
+        lines.join("\n")
+    }
+}
@@ -4,18 +4,22 @@ use std::rc::Rc;
 use gtk::prelude::WidgetExt;
 use image::{DynamicImage, ImageBuffer, Rgba};
 
-use crate::command::{Action, DrawingHistory, Tool};
+use crate::command::{Action, DrawingHistory, ModelSnapshot, Tool};
 use crate::glium_area::body_part::BodyPart;
 use crate::glium_area::GliumArea;
 use crate::glium_area::renderer::{ModelCell, Renderer};
 use crate::glium_area::skin_parser::{ModelType, TextureLoadError, TextureType};
+use crate::shading::ShadingSettings;
 
 pub struct EditorSession {
     viewport: GliumArea,
     history: DrawingHistory,
     tool: Tool,
+    shading: ShadingSettings,
     tools_enabled: bool,
     dirty: bool,
+    /// Bumped on every change of the skin, so views of it know when to refresh.
+    revision: u64,
 }
 
 impl EditorSession {
@@ -25,8 +29,10 @@ impl EditorSession {
             viewport,
             history,
             tool: Tool::default(),
+            shading: ShadingSettings::default(),
             tools_enabled: true,
             dirty: false,
+            revision: 0,
         }
     }
 
@@ -42,6 +48,14 @@ impl EditorSession {
         self.tool = tool;
     }
 
+    pub fn shading(&self) -> ShadingSettings {
+        self.shading
+    }
+
+    pub fn set_shading(&mut self, shading: ShadingSettings) {
+        self.shading = shading;
+    }
+
     pub fn tools_enabled(&self) -> bool {
         self.tools_enabled
     }
@@ -52,10 +66,12 @@ impl EditorSession {
 
     pub fn undo(&mut self) {
         self.history.undo();
+        self.revision += 1;
     }
 
     pub fn redo(&mut self) {
         self.history.redo();
+        self.revision += 1;
     }
 
     pub fn add_command(&mut self, command: Box<dyn Action>) {
@@ -69,6 +85,11 @@ impl EditorSession {
 
     pub fn mark_dirty(&mut self) {
         self.dirty = true;
+        self.revision += 1;
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
     }
 
     pub fn clear_dirty(&mut self) {
@@ -138,13 +159,24 @@ impl EditorSession {
         Ok(())
     }
 
-    pub fn load_template(&mut self, path: &str) -> Result<(), TextureLoadError> {
+    pub fn model_type(&self) -> ModelType {
         let renderer = self.renderer().expect("Renderer is not initialized");
         let model_type = renderer.borrow().get_model_type();
-        let mut renderer = renderer.borrow_mut();
-        renderer.load_texture(path, &model_type, true)?;
-        self.mark_dirty();
-        Ok(())
+        model_type
+    }
+
+    /// Cells per skin pixel along an edge.
+    pub fn resolution(&self) -> u32 {
+        let renderer = self.renderer().expect("Renderer is not initialized");
+        let resolution = renderer.borrow().resolution();
+        resolution
+    }
+
+    /// Whether a custom rig, e.g. a Bedrock geometry, is edited instead of the player model.
+    pub fn has_custom_rig(&self) -> bool {
+        let renderer = self.renderer().expect("Renderer is not initialized");
+        let has_custom_rig = renderer.borrow().custom_rig().is_some();
+        has_custom_rig
     }
 
     pub fn reset_skin(&mut self) {
@@ -171,10 +203,10 @@ impl EditorSession {
         }
     }
 
-    pub fn reset_model_type(&mut self, model_type: &ModelType) {
+    pub fn snapshot_cells(&self) -> ModelSnapshot {
         let renderer = self.renderer().expect("Renderer is not initialized");
-        renderer.borrow_mut().reset_model_type(model_type);
-        self.mark_dirty();
+        let snapshot = renderer.borrow().snapshot_cells();
+        snapshot
     }
 
     pub fn export_texture(&self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
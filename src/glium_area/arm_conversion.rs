@@ -0,0 +1,355 @@
+use std::collections::BTreeMap;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::skin_parser::ModelType;
+
+type Color = [f32; 4];
+
+/// How the 4 cell wide sides of a classic arm become the 3 cell wide sides of a slim
+/// one, and back. Only the front, back, top and bottom change; the arm sides are kept.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionStrategy {
+    /// Drops the column at the edge of the arm and keeps it in [`HiddenColumns`],
+    /// so converting back restores the classic arm exactly.
+    #[default]
+    KeepHidden,
+    /// Drops the column next to that edge so both seams are kept.
+    DropMiddle,
+    /// Averages the two middle columns into one.
+    Merge,
+    /// Resamples the whole row.
+    Stretch,
+}
+
+/// Columns of classic arms dropped by [`ConversionStrategy::KeepHidden`], one cell per
+/// changed row, waiting to be put back when the arms become classic again.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct HiddenColumns {
+    columns: BTreeMap<BodyPart, Vec<Color>>,
+}
+
+impl HiddenColumns {
+    pub fn clear(&mut self) {
+        self.columns.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+}
+
+/// A side of an arm: first cell on a classic and on a slim arm, height in rows and
+/// whether its width changes between the models.
+struct ArmSide {
+    classic_start: usize,
+    slim_start: usize,
+    rows: usize,
+    resized: bool,
+}
+
+/// Sides in mesh order: front, right, back, left, top, bottom.
+const ARM_SIDES: [ArmSide; 6] = [
+    ArmSide { classic_start: 0, slim_start: 0, rows: 12, resized: true },
+    ArmSide { classic_start: 48, slim_start: 36, rows: 12, resized: false },
+    ArmSide { classic_start: 96, slim_start: 84, rows: 12, resized: true },
+    ArmSide { classic_start: 144, slim_start: 120, rows: 12, resized: false },
+    ArmSide { classic_start: 192, slim_start: 168, rows: 4, resized: true },
+    ArmSide { classic_start: 208, slim_start: 180, rows: 4, resized: true },
+];
+
+pub const CLASSIC_ARM_CELLS: usize = 224;
+pub const SLIM_ARM_CELLS: usize = 192;
+
+/// Whether the column at the edge of the arm is the first one of each row of the
+/// resized sides, in the order of [`ARM_SIDES`]. It is the last one otherwise.
+fn edge_is_first(arm: BodyPart) -> [bool; 6] {
+    match arm {
+        BodyPart::LeftArm | BodyPart::LeftArmOuter => [false, false, true, false, false, false],
+        BodyPart::RightArm | BodyPart::RightArmOuter => [true, false, false, false, true, true],
+        _ => panic!("Wrong arm type passed to the function"),
+    }
+}
+
+/// `resolution` is the number of cells per skin pixel along an edge. On HD skins every
+/// column of cells within a skin pixel is converted on its own.
+pub fn classic_to_slim(
+    classic: &[Color],
+    arm: BodyPart,
+    strategy: ConversionStrategy,
+    hidden: &mut HiddenColumns,
+    resolution: usize,
+) -> Vec<Color> {
+    let mut slim = vec![[0.0; 4]; SLIM_ARM_CELLS * resolution * resolution];
+    let mut dropped = vec![];
+
+    for (side, edge_is_first) in ARM_SIDES.iter().zip(edge_is_first(arm)) {
+        let (classic_start, slim_start) = (side.classic_start * resolution * resolution, side.slim_start * resolution * resolution);
+        for row in 0..side.rows * resolution {
+            if !side.resized {
+                let classic_row = classic_start + row * 4 * resolution;
+                let slim_row = slim_start + row * 4 * resolution;
+                slim[slim_row..slim_row + 4 * resolution].copy_from_slice(&classic[classic_row..classic_row + 4 * resolution]);
+                continue;
+            }
+
+            let start = classic_start + row * 4 * resolution;
+            let mut cells = classic[start..start + 4 * resolution].to_vec();
+            if edge_is_first {
+                cells.reverse();
+            }
+
+            let mut narrow = vec![[0.0; 4]; 3 * resolution];
+            for offset in 0..resolution {
+                let column = [0, 1, 2, 3].map(|pixel| cells[pixel * resolution + offset]);
+                dropped.push(column[3]);
+                for (pixel, color) in shrink_row(column, strategy).into_iter().enumerate() {
+                    narrow[pixel * resolution + offset] = color;
+                }
+            }
+            if edge_is_first {
+                narrow.reverse();
+            }
+            let start = slim_start + row * 3 * resolution;
+            slim[start..start + 3 * resolution].copy_from_slice(&narrow);
+        }
+    }
+
+    if strategy == ConversionStrategy::KeepHidden {
+        hidden.columns.insert(arm, dropped);
+    }
+    slim
+}
+
+/// Takes the hidden columns of the arm back when they are used.
+pub fn slim_to_classic(
+    slim: &[Color],
+    arm: BodyPart,
+    strategy: ConversionStrategy,
+    hidden: &mut HiddenColumns,
+    resolution: usize,
+) -> Vec<Color> {
+    let mut classic = vec![[0.0; 4]; CLASSIC_ARM_CELLS * resolution * resolution];
+    let dropped = match strategy {
+        ConversionStrategy::KeepHidden => hidden.columns.remove(&arm),
+        _ => None,
+    };
+    let mut dropped = dropped.into_iter().flatten();
+
+    for (side, edge_is_first) in ARM_SIDES.iter().zip(edge_is_first(arm)) {
+        let (classic_start, slim_start) = (side.classic_start * resolution * resolution, side.slim_start * resolution * resolution);
+        for row in 0..side.rows * resolution {
+            if !side.resized {
+                let classic_row = classic_start + row * 4 * resolution;
+                let slim_row = slim_start + row * 4 * resolution;
+                classic[classic_row..classic_row + 4 * resolution].copy_from_slice(&slim[slim_row..slim_row + 4 * resolution]);
+                continue;
+            }
+
+            let start = slim_start + row * 3 * resolution;
+            let mut cells = slim[start..start + 3 * resolution].to_vec();
+            if edge_is_first {
+                cells.reverse();
+            }
+
+            let mut wide = vec![[0.0; 4]; 4 * resolution];
+            for offset in 0..resolution {
+                let column = [0, 1, 2].map(|pixel| cells[pixel * resolution + offset]);
+                for (pixel, color) in widen_row(column, strategy, dropped.next()).into_iter().enumerate() {
+                    wide[pixel * resolution + offset] = color;
+                }
+            }
+            if edge_is_first {
+                wide.reverse();
+            }
+            let start = classic_start + row * 4 * resolution;
+            classic[start..start + 4 * resolution].copy_from_slice(&wide);
+        }
+    }
+    classic
+}
+
+/// Lays out the cells of a body part of a `from` skin for a `to` skin, e.g. a part taken
+/// from another skin. Only arms differ, hidden columns are not kept.
+pub fn convert_part(
+    cells: &[Color],
+    body_part: BodyPart,
+    from: ModelType,
+    to: ModelType,
+    strategy: ConversionStrategy,
+    resolution: usize,
+) -> Vec<Color> {
+    let is_arm = matches!(body_part, BodyPart::RightArm | BodyPart::LeftArm | BodyPart::RightArmOuter | BodyPart::LeftArmOuter);
+    if from == to || !is_arm {
+        return cells.to_vec();
+    }
+    let mut hidden = HiddenColumns::default();
+    match to {
+        ModelType::Slim => classic_to_slim(cells, body_part, strategy, &mut hidden, resolution),
+        ModelType::Classic => slim_to_classic(cells, body_part, strategy, &mut hidden, resolution),
+    }
+}
+
+/// `cells` run towards the edge of the arm.
+fn shrink_row([a, b, c, d]: [Color; 4], strategy: ConversionStrategy) -> [Color; 3] {
+    match strategy {
+        ConversionStrategy::KeepHidden => [a, b, c],
+        ConversionStrategy::DropMiddle => [a, b, d],
+        ConversionStrategy::Merge => [a, blend(&[(b, 1.0), (c, 1.0)]), d],
+        // Every slim cell covers 4/3 of a classic one
+        ConversionStrategy::Stretch => [
+            blend(&[(a, 3.0), (b, 1.0)]),
+            blend(&[(b, 1.0), (c, 1.0)]),
+            blend(&[(c, 1.0), (d, 3.0)]),
+        ],
+    }
+}
+
+/// `cells` run towards the edge of the arm. `dropped` is the hidden edge cell, if any.
+fn widen_row([a, b, c]: [Color; 3], strategy: ConversionStrategy, dropped: Option<Color>) -> [Color; 4] {
+    match strategy {
+        ConversionStrategy::KeepHidden => [a, b, c, dropped.unwrap_or(c)],
+        ConversionStrategy::DropMiddle => [a, b, b, c],
+        ConversionStrategy::Merge => [a, b, b, c],
+        // Every classic cell covers 3/4 of a slim one
+        ConversionStrategy::Stretch => [
+            a,
+            blend(&[(a, 1.0), (b, 2.0)]),
+            blend(&[(b, 2.0), (c, 1.0)]),
+            c,
+        ],
+    }
+}
+
+/// Weighted average with premultiplied alpha, so transparent cells don't darken the result.
+fn blend(colors: &[(Color, f32)]) -> Color {
+    if colors.iter().all(|(color, _)| *color == colors[0].0) {
+        return colors[0].0;
+    }
+    let total: f32 = colors.iter().map(|(_, weight)| weight).sum();
+    let alpha: f32 = colors.iter().map(|(color, weight)| color[3] * weight).sum::<f32>() / total;
+    if alpha == 0.0 {
+        return [0.0; 4];
+    }
+    let channel = |index: usize| {
+        colors.iter().map(|(color, weight)| color[index] * color[3] * weight).sum::<f32>() / total / alpha
+    };
+    [channel(0), channel(1), channel(2), alpha]
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+    use image::imageops::FilterType;
+
+    use super::*;
+    use crate::glium_area::skin_parser::{ModelType, SkinParser, TextureType};
+
+    const ARMS: [BodyPart; 4] = [BodyPart::RightArm, BodyPart::LeftArm, BodyPart::RightArmOuter, BodyPart::LeftArmOuter];
+
+    /// Hand-drawn skins laid out like the ones players use: shaded arms, see-through
+    /// overlay cells and, on the slim one, the unused arm columns left empty.
+    fn skin(model_type: ModelType) -> DynamicImage {
+        let bytes: &[u8] = match model_type {
+            ModelType::Classic => include_bytes!("../../tests/skins/classic.png"),
+            ModelType::Slim => include_bytes!("../../tests/skins/slim.png"),
+        };
+        image::load_from_memory(bytes).expect("load skin fixture")
+    }
+
+    fn arm_cells(image: &DynamicImage, model_type: ModelType, arm: BodyPart) -> Vec<Color> {
+        hd_arm_cells(image, model_type, arm, 1)
+    }
+
+    fn hd_arm_cells(image: &DynamicImage, model_type: ModelType, arm: BodyPart, resolution: u32) -> Vec<Color> {
+        let parser = SkinParser::with_resolution(&model_type, TextureType::Normal, resolution);
+        let color_map = parser.load_from_bytes(image).expect("parse skin");
+        color_map[&arm]
+            .values()
+            .flatten()
+            .map(|pixel| [0, 1, 2, 3].map(|channel| f32::from(pixel[channel]) / 255.0))
+            .collect()
+    }
+
+    #[test]
+    fn classic_round_trip_restores_hidden_columns() {
+        let image = skin(ModelType::Classic);
+        for arm in ARMS {
+            let classic = arm_cells(&image, ModelType::Classic, arm);
+            let mut hidden = HiddenColumns::default();
+            let slim = classic_to_slim(&classic, arm, ConversionStrategy::KeepHidden, &mut hidden, 1);
+            let restored = slim_to_classic(&slim, arm, ConversionStrategy::KeepHidden, &mut hidden, 1);
+            assert_eq!(restored, classic, "{:?}", arm);
+            assert!(hidden.is_empty());
+        }
+    }
+
+    #[test]
+    fn keep_hidden_drops_the_outer_column() {
+        let image = skin(ModelType::Classic);
+        let mut texture = RgbaImage::new(64, 64);
+        let parser = SkinParser::new(&ModelType::Slim, TextureType::Normal);
+        for arm in ARMS {
+            let slim = classic_to_slim(&arm_cells(&image, ModelType::Classic, arm), arm, ConversionStrategy::KeepHidden, &mut HiddenColumns::default(), 1);
+            let pixels: Vec<Rgba<u8>> = slim.iter().map(|color| Rgba(color.map(|channel| (channel * 255.0).round() as u8))).collect();
+            parser.export_cells(&arm, &mut texture, &pixels);
+        }
+        // Fronts of the arms: the right ones lose their first column, the left ones their last
+        for (classic_x, slim_x, y) in [(45, 44, 20), (45, 44, 36), (36, 36, 52), (52, 52, 52)] {
+            for row in y..y + 12 {
+                for column in 0..3 {
+                    assert_eq!(*texture.get_pixel(slim_x + column, row), image.get_pixel(classic_x + column, row), "{} {}", slim_x + column, row);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn slim_round_trip_is_lossless() {
+        let image = skin(ModelType::Slim);
+        let strategies = [ConversionStrategy::KeepHidden, ConversionStrategy::DropMiddle, ConversionStrategy::Merge];
+        for arm in ARMS {
+            let slim = arm_cells(&image, ModelType::Slim, arm);
+            for strategy in strategies {
+                let mut hidden = HiddenColumns::default();
+                let classic = slim_to_classic(&slim, arm, strategy, &mut hidden, 1);
+                let restored = classic_to_slim(&classic, arm, strategy, &mut hidden, 1);
+                assert_eq!(restored, slim, "{:?} {:?}", arm, strategy);
+            }
+        }
+    }
+
+    #[test]
+    fn hd_arms_convert_like_upscaled_arms() {
+        let image = skin(ModelType::Classic);
+        let upscale = |image: DynamicImage| image.resize(128, 128, FilterType::Nearest);
+        for arm in ARMS {
+            let slim = classic_to_slim(&arm_cells(&image, ModelType::Classic, arm), arm, ConversionStrategy::DropMiddle, &mut HiddenColumns::default(), 1);
+            let pixels: Vec<Rgba<u8>> = slim.iter().map(|color| Rgba(color.map(|channel| (channel * 255.0).round() as u8))).collect();
+            let mut texture = RgbaImage::new(64, 64);
+            SkinParser::new(&ModelType::Slim, TextureType::Normal).export_cells(&arm, &mut texture, &pixels);
+
+            let hd_classic = hd_arm_cells(&upscale(image.clone()), ModelType::Classic, arm, 2);
+            let hd_slim = classic_to_slim(&hd_classic, arm, ConversionStrategy::DropMiddle, &mut HiddenColumns::default(), 2);
+            assert_eq!(hd_slim, hd_arm_cells(&upscale(texture.into()), ModelType::Slim, arm, 2), "{:?}", arm);
+        }
+    }
+
+    #[test]
+    fn stretch_keeps_solid_colors() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let classic = vec![red; CLASSIC_ARM_CELLS];
+        let slim = classic_to_slim(&classic, BodyPart::RightArm, ConversionStrategy::Stretch, &mut HiddenColumns::default(), 1);
+        assert!(slim.iter().all(|color| *color == red));
+        let back = slim_to_classic(&slim, BodyPart::RightArm, ConversionStrategy::Stretch, &mut HiddenColumns::default(), 1);
+        assert!(back.iter().all(|color| *color == red));
+    }
+
+    #[test]
+    fn blend_ignores_transparent_colors() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        assert_eq!(blend(&[(red, 1.0), ([0.0; 4], 1.0)]), [1.0, 0.0, 0.0, 0.5]);
+    }
+}
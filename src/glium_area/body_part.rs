@@ -1,30 +1,86 @@
-#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone, Debug)]
-pub enum BodyPart {
-    Head,
-    Torso,
-    RightArm,
-    LeftArm,
-    RightLeg,
-    LeftLeg,
-    HeadOuter,
-    TorsoOuter,
-    RightArmOuter,
-    LeftArmOuter,
-    RightLegOuter,
-    LeftLegOuter,
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Deserializer};
+
+/// A cuboid of a model, named by the id its rig gives it.
+///
+/// Ids are interned: parts compare in the order their ids were first seen, so the
+/// player parts come first, inner parts before outer ones.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
+pub struct BodyPart(u16);
+
+const PLAYER_IDS: [&str; 12] = [
+    "Head", "Torso", "RightArm", "LeftArm", "RightLeg", "LeftLeg",
+    "HeadOuter", "TorsoOuter", "RightArmOuter", "LeftArmOuter", "RightLegOuter", "LeftLegOuter",
+];
+
+fn ids() -> &'static Mutex<Vec<&'static str>> {
+    static IDS: OnceLock<Mutex<Vec<&'static str>>> = OnceLock::new();
+    IDS.get_or_init(|| Mutex::new(PLAYER_IDS.to_vec()))
 }
 
+#[allow(non_upper_case_globals)]
 impl BodyPart {
+    pub const Head: BodyPart = BodyPart(0);
+    pub const Torso: BodyPart = BodyPart(1);
+    pub const RightArm: BodyPart = BodyPart(2);
+    pub const LeftArm: BodyPart = BodyPart(3);
+    pub const RightLeg: BodyPart = BodyPart(4);
+    pub const LeftLeg: BodyPart = BodyPart(5);
+    pub const HeadOuter: BodyPart = BodyPart(6);
+    pub const TorsoOuter: BodyPart = BodyPart(7);
+    pub const RightArmOuter: BodyPart = BodyPart(8);
+    pub const LeftArmOuter: BodyPart = BodyPart(9);
+    pub const RightLegOuter: BodyPart = BodyPart(10);
+    pub const LeftLegOuter: BodyPart = BodyPart(11);
+
+    /// Parts of the player rigs, inner parts first.
+    pub const PLAYER: [BodyPart; 12] = [
+        BodyPart::Head, BodyPart::Torso, BodyPart::RightArm, BodyPart::LeftArm, BodyPart::RightLeg, BodyPart::LeftLeg,
+        BodyPart::HeadOuter, BodyPart::TorsoOuter, BodyPart::RightArmOuter, BodyPart::LeftArmOuter,
+        BodyPart::RightLegOuter, BodyPart::LeftLegOuter,
+    ];
+
+    /// The part named `id` in a rig, the same part for every rig using that id.
+    pub fn from_id(id: &str) -> BodyPart {
+        let mut ids = ids().lock().unwrap();
+        let index = match ids.iter().position(|known| *known == id) {
+            Some(index) => index,
+            None => {
+                ids.push(Box::leak(id.to_owned().into_boxed_str()));
+                ids.len() - 1
+            }
+        };
+        BodyPart(u16::try_from(index).expect("too many body part ids"))
+    }
+
+    pub fn id(&self) -> &'static str {
+        ids().lock().unwrap()[self.0 as usize]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            Self::Head => "Head",
+            Self::Torso => "Torso",
+            Self::RightArm => "Right arm",
+            Self::LeftArm => "Left arm",
+            Self::RightLeg => "Right leg",
+            Self::LeftLeg => "Left leg",
+            Self::HeadOuter => "Head, outer layer",
+            Self::TorsoOuter => "Torso, outer layer",
+            Self::RightArmOuter => "Right arm, outer layer",
+            Self::LeftArmOuter => "Left arm, outer layer",
+            Self::RightLegOuter => "Right leg, outer layer",
+            Self::LeftLegOuter => "Left leg, outer layer",
+            other => other.id(),
+        }
+    }
+
+    /// Whether this is the outer layer of a player part. Other rigs pair their layers
+    /// with `outer_of`, see [`Rig::inner_part`](crate::glium_area::rig::Rig::inner_part).
     pub fn is_outer(&self) -> bool {
-        matches!(
-            self,
-            Self::HeadOuter
-                | Self::TorsoOuter
-                | Self::RightArmOuter
-                | Self::LeftArmOuter
-                | Self::RightLegOuter
-                | Self::LeftLegOuter
-        )
+        self.inner_counterpart().is_some()
     }
 
     pub fn outer_counterpart(self) -> Option<Self> {
@@ -51,3 +107,16 @@ impl BodyPart {
         }
     }
 }
+
+impl fmt::Debug for BodyPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for BodyPart {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Ok(BodyPart::from_id(&id))
+    }
+}
@@ -9,6 +9,9 @@ pub struct Camera {
 }
 
 impl Camera {
+    /// Degrees the camera turns per pixel the mouse moves.
+    pub const SENSITIVITY: f32 = 0.5;
+
     pub fn new() -> Self {
         Camera {
             position: glm::TVec3::new(0.0, 0.0, 3.0),
@@ -23,14 +26,19 @@ impl Camera {
     }
 
     pub fn update_yaw_and_pitch(&mut self, mouse_delta: Vec2) {
-        const SENSITIVITY: f32 = 0.5;
-
         // set the rotation angle limit; after 360 the counting starts from zero
-        self.yaw = (self.yaw + mouse_delta.x * SENSITIVITY) % 360.0;
-        self.pitch += mouse_delta.y * SENSITIVITY;
+        self.yaw = (self.yaw + mouse_delta.x * Self::SENSITIVITY) % 360.0;
+        self.pitch += mouse_delta.y * Self::SENSITIVITY;
+
+        self.set_yaw_and_pitch(self.yaw, self.pitch);
+    }
+
+    /// Points the camera at fixed angles (in degrees), e.g. for offscreen renders.
+    pub fn set_yaw_and_pitch(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw % 360.0;
 
         // limit the pitch to avoid camera rollover
-        self.pitch = self.pitch.clamp(-90.0, 90.0);
+        self.pitch = pitch.clamp(-90.0, 90.0);
 
         // update rotation matrix
         self.rotation_matrix = glm::rotate_x(&Mat4::identity(), self.pitch.to_radians());
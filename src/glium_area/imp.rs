@@ -12,9 +12,12 @@ use crate::glium_area::renderer::Renderer;
 
 #[derive(Default)]
 pub struct GliumGLArea {
-    pub renderer: RefCell<Option<Rc<RefCell<Renderer>>>>
+    pub renderer: RefCell<Option<Rc<RefCell<Renderer>>>>,
     // 1. RefCell<...> - for assign Renderer by immutable reference in "WidgetImpl::realize" function
     // 2. Option<...> - for Default trait implementation and setting "None" in "WidgetImpl::unrealize" function
+
+    /// Viewport whose camera follows this one, see `GliumArea::link_camera`.
+    pub linked_area: glib::WeakRef<super::GliumArea>,
 }
 
 #[glib::object_subclass]
@@ -1,4 +1,4 @@
-use gtk::prelude::{GestureExt, WidgetExt};
+use gtk::prelude::{GestureDragExt, GestureExt, WidgetExt};
 
 use crate::command::*;
 use crate::command::Tool;
@@ -6,6 +6,7 @@ use crate::editor_host::EditorHost;
 use crate::glium_area::GliumArea;
 use crate::glium_area::hover::Hover;
 use crate::glium_area::renderer::ModelCell;
+use crate::shading::{gradient, jitter, shade};
 use crate::utils::{random_brightness, rgba_to_f32};
 
 impl GliumArea {
@@ -14,6 +15,46 @@ impl GliumArea {
         self.connect_click(host);
     }
 
+    pub(super) fn connect_viewer_signals(&self) {
+        self.connect_scroll();
+        self.connect_orbit();
+    }
+
+    fn connect_orbit(&self) {
+        let drag = gtk::GestureDrag::new();
+        let gl_area = self.clone();
+        drag.connect_drag_begin(move |_, x, y| {
+            if let Some(renderer) = gl_area.renderer() {
+                renderer.borrow_mut().start_motion(x as f32, y as f32);
+            }
+        });
+
+        let gl_area = self.clone();
+        drag.connect_drag_update(move |gesture, offset_x, offset_y| {
+            let Some((start_x, start_y)) = gesture.start_point() else {
+                return;
+            };
+            let Some(renderer) = gl_area.renderer() else {
+                return;
+            };
+            let mut renderer = renderer.borrow_mut();
+            renderer.mouse_move((start_x + offset_x) as f32, (start_y + offset_y) as f32);
+            renderer.update_camera();
+            drop(renderer);
+            gl_area.queue_draw();
+            gl_area.sync_linked_camera();
+        });
+
+        let gl_area = self.clone();
+        drag.connect_drag_end(move |_, _, _| {
+            if let Some(renderer) = gl_area.renderer() {
+                renderer.borrow_mut().stop_motion();
+            }
+        });
+
+        self.add_controller(drag);
+    }
+
     fn connect_click<H: EditorHost + Clone + 'static>(&self, host: H) {
         let click_handler = self.get_click_handler(host.clone());
         let click = gtk::GestureClick::new();
@@ -51,6 +92,7 @@ impl GliumArea {
                     renderer.update_camera();
                     drop(renderer);
                     gl_area.queue_draw();
+                    gl_area.sync_linked_camera();
                 }
             }
         });
@@ -75,6 +117,7 @@ impl GliumArea {
             if let Some(renderer) = gl_area.renderer() {
                 renderer.borrow_mut().update_scale(y as f32 * 0.025);
                 gl_area.queue_draw();
+                gl_area.sync_linked_camera();
             }
             gtk::glib::Propagation::Proceed
         });
@@ -116,6 +159,14 @@ impl GliumArea {
                 Tool::Random => Self::handle_random(gl_area.clone(), cell, &host),
                 Tool::Replace => Self::handle_replace(gl_area.clone(), cell, &host),
                 Tool::ColorPicker => Self::handle_color_picker(&gl_area, x, y, &host),
+                Tool::Shade => Self::handle_shade(gl_area.clone(), cell, &host),
+                Tool::Noise => Self::handle_noise(gl_area.clone(), cell, &host),
+                Tool::Gradient => Self::handle_gradient(gl_area.clone(), cell, &host),
+                Tool::Text => {
+                    if !updating {
+                        host.place_text(cell);
+                    }
+                }
             }
         }
     }
@@ -175,6 +226,49 @@ impl GliumArea {
         )));
     }
 
+    /// Steps the color of the cell along the ramp, leaving empty cells alone.
+    fn handle_shade<H: EditorHost>(gl_area: GliumArea, cell: ModelCell, host: &H) {
+        let trying_draw_same_cell = host
+            .last_modified_cell()
+            .is_some_and(|last| last.same_cell(cell));
+        if !trying_draw_same_cell && cell.color[3] != 0.0 {
+            let color = shade(cell.color, &host.shading().shade);
+            host.add_command(Box::new(Draw::new(gl_area, cell, color)));
+            host.set_last_modified(cell);
+        }
+    }
+    fn handle_noise<H: EditorHost>(gl_area: GliumArea, cell: ModelCell, host: &H) {
+        let color = rgba_to_f32(host.active_color());
+        let trying_draw_same_cell = host
+            .last_modified_cell()
+            .is_some_and(|last| last.same_cell(cell));
+        if !trying_draw_same_cell {
+            let shading = host.shading();
+            let color = jitter(color, &shading.jitter, shading.seed, cell.body_part, cell.cell_index);
+            host.add_command(Box::new(Draw::new(gl_area, cell, color)));
+            host.set_last_modified(cell);
+        }
+    }
+    fn handle_gradient<H: EditorHost>(gl_area: GliumArea, cell: ModelCell, host: &H) {
+        let Some(renderer) = gl_area.renderer() else {
+            return;
+        };
+        let renderer = renderer.borrow();
+        let (Some(cells), Some([width, height])) = (
+            renderer.get_side_cells(&cell.body_part, cell.cell_index),
+            renderer.get_side_size(&cell.body_part, cell.cell_index),
+        ) else {
+            return;
+        };
+        drop(renderer);
+        let colors = gradient(rgba_to_f32(host.active_color()), &host.shading().gradient, width, height);
+        let cells = cells
+            .into_iter()
+            .zip(colors)
+            .map(|(cell, color)| ModelCell { color, ..cell })
+            .collect();
+        host.add_command(Box::new(Paint::new(gl_area, cells)));
+    }
     fn handle_fill<H: EditorHost>(gl_area: GliumArea, cell: ModelCell, host: &H) {
         let Some(renderer) = gl_area.renderer() else {
             return;
@@ -13,6 +13,7 @@ mod imp;
 mod input;
 pub mod camera;
 pub mod renderer;
+pub mod software_renderer;
 mod vertex;
 pub(crate) mod model_object;
 mod model;
@@ -20,10 +21,13 @@ pub mod skin_parser;
 mod ray;
 mod pick;
 pub mod hover;
+pub mod overlay_view;
 mod mouse_move;
 pub mod body_part;
+pub mod arm_conversion;
 mod cross_info;
 pub mod cube_side;
+pub mod rig;
 
 glib::wrapper! {
     pub struct GliumArea(ObjectSubclass<imp::GliumGLArea>)
@@ -73,6 +77,37 @@ impl GliumArea {
         self.connect_signals(host);
     }
 
+    /// Sets up a viewport that only shows a model: dragging anywhere turns it.
+    pub fn setup_viewer(&self) {
+        self.set_vexpand(true);
+        self.set_hexpand(true);
+        self.connect_viewer_signals();
+    }
+
+    /// Keeps the cameras of both viewports pointed the same way.
+    pub fn link_camera(&self, other: &GliumArea) {
+        self.imp().linked_area.set(Some(other));
+        other.imp().linked_area.set(Some(self));
+        self.sync_linked_camera();
+    }
+
+    /// Copies the camera of this viewport to the linked one.
+    pub fn sync_linked_camera(&self) {
+        let Some(linked_area) = self.imp().linked_area.upgrade() else {
+            return;
+        };
+        let (Some(renderer), Some(linked_renderer)) = (self.renderer(), linked_area.renderer()) else {
+            return;
+        };
+        linked_renderer.borrow_mut().copy_camera_from(&renderer.borrow());
+        linked_area.queue_draw();
+    }
+
+    /// Yaw and pitch of the camera in degrees, once the viewport is realized.
+    pub fn camera_angles(&self) -> Option<(f32, f32)> {
+        self.renderer().map(|renderer| renderer.borrow().camera_angles())
+    }
+
     pub fn renderer(&self) -> Option<Rc<RefCell<Renderer>>> {
         self.imp().renderer.borrow().clone()
     }
@@ -1,12 +1,9 @@
+// The modelled meshes the rig generator is checked against
+#[cfg(test)]
 pub mod meshes;
+#[cfg(test)]
 mod obj_loader;
 
-pub use meshes::{
-    body_grid, body_vertices, cuboid_3x12x4, cuboid_4x12x4, grid_3x12x4, grid_4x12x4, head_grid,
-    head_vertices, BODY_CELLS_PER_SIDE, HEAD_CELLS_PER_SIDE, LIMB_3_CELLS_PER_SIDE,
-    LIMB_4_CELLS_PER_SIDE,
-};
-
 #[cfg(test)]
 mod export_obj;
 
@@ -29,7 +29,8 @@ pub struct ModelObject {
     index_buffer: IndexBuffer<u16>,
 
     model_matrix: Mat4,
-    translation_matrix: Mat4,
+    /// Moves the object to its position, then turns it with the bones it hangs from.
+    placement_matrix: Mat4,
     scale_matrix: Mat4,
 
     local_bounds_min: glm::Vec3,
@@ -57,12 +58,13 @@ impl ModelObject {
         camera: Rc<RefCell<Camera>>,
         vertexes: &[Vertex],
         model_object_type: ModelObjectType,
+        placement: &Mat4,
         translation_vector: &glm::Vec3,
         scale_vector: &glm::Vec3,
         cells_per_side: [usize; 6],
     ) -> Self {
         let model_matrix = glm::Mat4::identity();
-        let translation_matrix = glm::translate(&glm::Mat4::identity(), translation_vector);
+        let placement_matrix = placement * glm::translate(&glm::Mat4::identity(), translation_vector);
         let scale_matrix = glm::scale(&glm::Mat4::identity(), scale_vector);
         let index_buffer = Self::create_index_buffer(context.clone(), vertexes, model_object_type);
         let draw_parameters = Self::create_draw_parameters(model_object_type);
@@ -79,7 +81,7 @@ impl ModelObject {
             vertexes,
             vertex_buffer,
             index_buffer,
-            translation_matrix,
+            placement_matrix,
             scale_matrix,
             local_bounds_min,
             local_bounds_max,
@@ -134,8 +136,14 @@ impl ModelObject {
     }
 
     pub fn draw_pass(&mut self, frame: &mut Frame, pass: ModelDrawPass) {
+        self.draw_pass_with(frame, pass, 1.0, false);
+    }
+
+    /// Draws with the alpha of every cell scaled by `opacity`, and empty cells as a
+    /// checkerboard when `checkerboard` is set.
+    pub fn draw_pass_with(&mut self, frame: &mut Frame, pass: ModelDrawPass, opacity: f32, checkerboard: bool) {
         let rotation_matrix = self.camera.borrow().get_rotation_matrix();
-        self.model_matrix = rotation_matrix * self.translation_matrix * self.scale_matrix;
+        self.model_matrix = rotation_matrix * self.placement_matrix * self.scale_matrix;
         let view_matrix = self.camera.borrow().get_view_matrix();
         let projection_matrix = self.get_projection();
 
@@ -164,6 +172,8 @@ impl ModelObject {
             view_matrix: *view_matrix.as_ref(),
             perspective_matrix: *projection_matrix.as_ref(),
             discard_transparent: discard_transparent,
+            checkerboard: checkerboard,
+            opacity: opacity,
         };
         frame
             .draw(
@@ -192,6 +202,16 @@ impl ModelObject {
         self.vertex_buffer.write(&self.vertexes);
     }
 
+    /// Paints every cell at once, in cell order.
+    pub fn set_colors(&mut self, colors: &[[f32; 4]]) {
+        for (cell, color) in colors.iter().enumerate() {
+            for vertex in &mut self.vertexes[cell * 4..cell * 4 + 4] {
+                vertex.color = *color;
+            }
+        }
+        self.vertex_buffer.write(&self.vertexes);
+    }
+
     fn write_cell_vertices(&self, index: usize) {
         self.vertex_buffer
             .slice(index..index + 4)
@@ -245,7 +265,7 @@ impl ModelObject {
 
     fn object_world_matrix(&self) -> Mat4 {
         let rotation_matrix = self.camera.borrow().get_rotation_matrix();
-        rotation_matrix * self.translation_matrix * self.scale_matrix
+        rotation_matrix * self.placement_matrix * self.scale_matrix
     }
 
     pub fn cross(&self, ray: &Ray) -> Option<CrossInfo> {
@@ -0,0 +1,33 @@
+/// How the outer layer is drawn over the base layer.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum OverlayView {
+    #[default]
+    Normal,
+    /// The outer layer lets the base layer show through.
+    Translucent,
+    /// The base layer is ghosted behind the outer one.
+    OuterOnly,
+    /// Empty cells of the outer layer are a see-through checkerboard.
+    XRay,
+}
+
+impl OverlayView {
+    /// Opacity of the base and the outer layer.
+    pub fn opacity(&self) -> (f32, f32) {
+        match self {
+            OverlayView::Translucent => (1.0, 0.5),
+            OverlayView::OuterOnly => (0.2, 1.0),
+            _ => (1.0, 1.0),
+        }
+    }
+}
+
+/// The layer the tools paint on.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum PaintTarget {
+    /// The outer layer where it is shown, the base layer elsewhere.
+    #[default]
+    Automatic,
+    Base,
+    Outer,
+}
@@ -17,22 +17,19 @@ use nalgebra_glm::Mat4;
 
 use CubeSide::*;
 
+use crate::glium_area::arm_conversion;
+use crate::glium_area::arm_conversion::{ConversionStrategy, HiddenColumns};
 use crate::glium_area::body_part::BodyPart;
-use crate::glium_area::body_part::BodyPart::*;
 use crate::glium_area::camera::Camera;
 use crate::glium_area::cube_side::CubeSide;
 use crate::glium_area::hover::Hover;
-use crate::glium_area::model::{
-    body_grid, body_vertices, cuboid_3x12x4, cuboid_4x12x4, grid_3x12x4, grid_4x12x4, head_grid,
-    head_vertices, BODY_CELLS_PER_SIDE, HEAD_CELLS_PER_SIDE, LIMB_3_CELLS_PER_SIDE,
-    LIMB_4_CELLS_PER_SIDE,
-};
 use crate::glium_area::model_object::{ModelDrawPass, ModelObject, ModelObjectType};
 use crate::glium_area::mouse_move::MouseMove;
+use crate::glium_area::overlay_view::{OverlayView, PaintTarget};
 use crate::glium_area::ray::Ray;
-use crate::glium_area::skin_parser::{ColorMap, ModelType, SkinParser, TextureLoadError, TextureType};
+use crate::glium_area::rig::{player_rig_at, same_custom_rig, Cuboid, Rig};
+use crate::glium_area::skin_parser::{texture_layout, ColorMap, ModelType, SkinParser, TextureLoadError, TextureType};
 use crate::glium_area::vertex::{Vertex, VertexTex};
-use crate::utils;
 
 fn shader_api_dir(context: &Context) -> &'static str {
     match context.get_opengl_version().0 {
@@ -288,77 +285,87 @@ pub struct Renderer {
     visible_objects: BTreeSet<BodyPart>,
     current_color: glm::Vec4,
     mouse_hover: Option<Hover>,
+    overlay_view: OverlayView,
+    paint_target: PaintTarget,
 
     grid: bool,
     grid_objects: BTreeMap<BodyPart, ModelObject>,
 
     model_type: ModelType,
+    /// Cells per skin pixel along an edge, see [`RESOLUTIONS`](crate::glium_area::rig::RESOLUTIONS).
+    resolution: u32,
+    /// Cuboids shown instead of the player model, e.g. a Bedrock geometry.
+    custom_rig: Option<Rc<Rig>>,
+    /// Arm columns put aside by the last switch to slim, see [`ConversionStrategy::KeepHidden`].
+    hidden_columns: HiddenColumns,
     face_indicator: FaceIndicator,
 }
 
 
 const BACKGROUND_COLOR: (f32, f32, f32, f32) = (1.0, 1.0, 1.0, 1.0);
-const INNER_SCALE: glm::Vec3 = glm::Vec3::new(1.0, 1.0, 1.0);
-const OUTER_SCALE: glm::Vec3 = glm::Vec3::new(1.15, 1.15, 1.15);
 const GRID_SCALE: f32 = 1.005;
 
 
-impl Renderer {
-    fn create_model_objects(context: Rc<Context>, program: Rc<Program>, camera: Rc<RefCell<Camera>>, model_type: &ModelType) -> BTreeMap<BodyPart, ModelObject> {
-        let factory = ModelObjectFactory::new(context.clone(), program.clone(), camera.clone());
+/// Mesh and placement of a body part, shared by the GL renderer and offscreen renders.
+pub(crate) struct PartGeometry<'a> {
+    pub vertexes: &'a [Vertex],
+    pub grid: &'a [Vertex],
+    pub translation: glm::Vec3,
+    /// Turns applied after the translation, see [`Cuboid::placement`].
+    pub placement: glm::Mat4,
+    /// Point the part turns around: the neck, the hips for the torso and legs, and the
+    /// shoulders 2 pixels below the top of the arms.
+    pub pivot: glm::Vec3,
+    pub scale: glm::Vec3,
+    pub cells_per_side: [usize; 6],
+}
 
-        let head = factory.create_body_part(head_vertices(), &glm::vec3(0., 1.5, 0.), &INNER_SCALE, HEAD_CELLS_PER_SIDE);
-        let body = factory.create_body_part(body_vertices(), &glm::vec3(0., 0.25, 0.), &INNER_SCALE, BODY_CELLS_PER_SIDE);
-        let right_leg = factory.create_body_part(cuboid_4x12x4(), &glm::vec3(-0.25, -1.25, 0.), &INNER_SCALE, LIMB_4_CELLS_PER_SIDE);
-        let left_leg = factory.create_body_part(cuboid_4x12x4(), &glm::vec3(0.25, -1.25, 0.), &INNER_SCALE, LIMB_4_CELLS_PER_SIDE);
-        let head_outer = factory.create_body_part(head_vertices(), &glm::vec3(0.0, 1.5, 0.0), &OUTER_SCALE, HEAD_CELLS_PER_SIDE);
-        let body_outer = factory.create_body_part(body_vertices(), &glm::vec3(0., 0.25, 0.), &OUTER_SCALE.scale(1.001), BODY_CELLS_PER_SIDE);
-        let right_leg_outer = factory.create_body_part(cuboid_4x12x4(), &glm::vec3(-0.25, -1.25, 0.), &OUTER_SCALE.scale(1.0005), LIMB_4_CELLS_PER_SIDE);
-        let left_leg_outer = factory.create_body_part(cuboid_4x12x4(), &glm::vec3(0.25, -1.25, 0.), &OUTER_SCALE, LIMB_4_CELLS_PER_SIDE);
-
-        let mut model_objects: BTreeMap<BodyPart, ModelObject> = BTreeMap::new();
-        model_objects.insert(BodyPart::Head, head);
-        model_objects.insert(BodyPart::Torso, body);
-        model_objects.insert(BodyPart::RightLeg, right_leg);
-        model_objects.insert(BodyPart::LeftLeg, left_leg);
-        model_objects.insert(BodyPart::HeadOuter, head_outer);
-        model_objects.insert(BodyPart::TorsoOuter, body_outer);
-        model_objects.insert(BodyPart::RightLegOuter, right_leg_outer);
-        model_objects.insert(BodyPart::LeftLegOuter, left_leg_outer);
-
-        model_objects.extend(
-            Renderer::get_arms(context.clone(), program.clone(), camera.clone(), model_type)
-        );
+pub(crate) fn part_geometry(body_part: BodyPart, model_type: &ModelType) -> PartGeometry<'static> {
+    part_geometry_at(body_part, model_type, 1)
+}
+
+/// Like [`part_geometry`] with `resolution` cells per skin pixel along an edge.
+pub(crate) fn part_geometry_at(body_part: BodyPart, model_type: &ModelType, resolution: u32) -> PartGeometry<'static> {
+    let cuboid = player_rig_at(model_type, resolution)
+        .cuboid(body_part)
+        .expect("not a part of the player model");
+    cuboid_geometry(body_part, cuboid)
+}
 
-        model_objects
+/// Mesh and placement of a cuboid of any rig.
+pub(crate) fn cuboid_geometry(body_part: BodyPart, cuboid: &Cuboid) -> PartGeometry<'_> {
+    // Small per-part offsets keep overlapping outer shells from z-fighting
+    let scale = match body_part {
+        BodyPart::TorsoOuter => cuboid.scale().scale(1.001),
+        BodyPart::RightLegOuter => cuboid.scale().scale(1.0005),
+        _ => cuboid.scale(),
+    };
+
+    PartGeometry {
+        vertexes: &cuboid.vertexes,
+        grid: &cuboid.grid,
+        translation: cuboid.position,
+        placement: cuboid.placement,
+        pivot: cuboid.pivot,
+        scale,
+        cells_per_side: cuboid.cells_per_side(),
     }
-    fn create_grid_objects(context: Rc<Context>, program: Rc<Program>, camera: Rc<RefCell<Camera>>, model_type: &ModelType) -> BTreeMap<BodyPart, ModelObject> {
-        let factory = ModelObjectFactory::new(context.clone(), program.clone(), camera.clone());
+}
 
-        let head_grid_mesh = factory.create_grid(head_grid(), &glm::vec3(0., 1.5, 0.), &INNER_SCALE.scale(GRID_SCALE));
-        let body_grid_mesh = factory.create_grid(body_grid(), &glm::vec3(0., 0.25, 0.), &INNER_SCALE.scale(GRID_SCALE));
-        let right_leg_grid = factory.create_grid(grid_4x12x4(), &glm::vec3(-0.25, -1.25, 0.), &INNER_SCALE.scale(GRID_SCALE));
-        let left_leg_grid = factory.create_grid(grid_4x12x4(), &glm::vec3(0.25, -1.25, 0.), &INNER_SCALE.scale(GRID_SCALE));
-        let head_outer_grid = factory.create_grid(head_grid(), &glm::vec3(0.0, 1.5, 0.0), &OUTER_SCALE.scale(GRID_SCALE));
-        let body_outer_grid = factory.create_grid(body_grid(), &glm::vec3(0., 0.25, 0.), &OUTER_SCALE.scale(GRID_SCALE).scale(1.001));
-        let right_leg_outer_grid = factory.create_grid(grid_4x12x4(), &glm::vec3(-0.25, -1.25, 0.), &OUTER_SCALE.scale(GRID_SCALE).scale(1.0005));
-        let left_leg_outer_grid = factory.create_grid(grid_4x12x4(), &glm::vec3(0.25, -1.25, 0.), &OUTER_SCALE.scale(GRID_SCALE));
-
-        let mut grid_objects: BTreeMap<BodyPart, ModelObject> = BTreeMap::new();
-        grid_objects.insert(BodyPart::Head, head_grid_mesh);
-        grid_objects.insert(BodyPart::Torso, body_grid_mesh);
-        grid_objects.insert(BodyPart::RightLeg, right_leg_grid);
-        grid_objects.insert(BodyPart::LeftLeg, left_leg_grid);
-        grid_objects.insert(BodyPart::HeadOuter, head_outer_grid);
-        grid_objects.insert(BodyPart::TorsoOuter, body_outer_grid);
-        grid_objects.insert(BodyPart::RightLegOuter, right_leg_outer_grid);
-        grid_objects.insert(BodyPart::LeftLegOuter, left_leg_outer_grid);
-
-        grid_objects.extend(
-            Renderer::get_arm_grids(context.clone(), program.clone(), camera.clone(), model_type)
-        );
+const ARM_PARTS: [BodyPart; 4] = [BodyPart::RightArm, BodyPart::LeftArm, BodyPart::RightArmOuter, BodyPart::LeftArmOuter];
 
-        grid_objects
+impl Renderer {
+    fn create_model_objects(context: Rc<Context>, program: Rc<Program>, camera: Rc<RefCell<Camera>>, rig: &Rig) -> BTreeMap<BodyPart, ModelObject> {
+        let factory = ModelObjectFactory::new(context.clone(), program.clone(), camera.clone());
+        rig.cuboids()
+            .map(|(&body_part, cuboid)| (body_part, factory.create_body_part(&cuboid_geometry(body_part, cuboid))))
+            .collect()
+    }
+    fn create_grid_objects(context: Rc<Context>, program: Rc<Program>, camera: Rc<RefCell<Camera>>, rig: &Rig) -> BTreeMap<BodyPart, ModelObject> {
+        let factory = ModelObjectFactory::new(context.clone(), program.clone(), camera.clone());
+        rig.cuboids()
+            .map(|(&body_part, cuboid)| (body_part, factory.create_grid(&cuboid_geometry(body_part, cuboid))))
+            .collect()
     }
 
     pub fn new(context: Rc<Context>) -> Self {
@@ -375,14 +382,11 @@ impl Renderer {
         let projection_matrix = glm::Mat4::identity();
         let view_matrix = glm::Mat4::identity();
         let model_type = ModelType::Slim;
-        let model_objects = Renderer::create_model_objects(
-            context.clone(), program.clone(), camera.clone(), &model_type);
-        let grid_objects = Renderer::create_grid_objects(
-            context.clone(), program.clone(), camera.clone(), &model_type);
-        let mut visible_objects = BTreeSet::from([
-            Head, Torso, RightArm, LeftArm, RightLeg, LeftLeg,
-            HeadOuter, TorsoOuter, RightArmOuter, LeftArmOuter, RightLegOuter, LeftLegOuter
-        ]);
+        let resolution = 1;
+        let rig = player_rig_at(&model_type, resolution);
+        let model_objects = Renderer::create_model_objects(context.clone(), program.clone(), camera.clone(), rig);
+        let grid_objects = Renderer::create_grid_objects(context.clone(), program.clone(), camera.clone(), rig);
+        let mut visible_objects = model_objects.keys().copied().collect();
         let current_color = glm::Vec4::new(0., 0., 1., 1.);
         let face_indicator = FaceIndicator::new(context.clone(), camera.clone());
 
@@ -399,84 +403,152 @@ impl Renderer {
             visible_objects,
             current_color,
             mouse_hover: None,
+            overlay_view: OverlayView::default(),
+            paint_target: PaintTarget::default(),
 
             grid: true,
             grid_objects,
 
             model_type,
+            resolution,
+            custom_rig: None,
+            hidden_columns: HiddenColumns::default(),
             face_indicator,
         }
     }
 
-    fn get_arms(context: Rc<Context>, program: Rc<Program>, camera: Rc<RefCell<Camera>>, model_type: &ModelType) -> BTreeMap<BodyPart, ModelObject> {
+    fn get_arms(context: Rc<Context>, program: Rc<Program>, camera: Rc<RefCell<Camera>>, model_type: &ModelType, resolution: u32) -> BTreeMap<BodyPart, ModelObject> {
         let factory = ModelObjectFactory::new(context.clone(), program.clone(), camera.clone());
-        let mut arms: BTreeMap<BodyPart, ModelObject> = BTreeMap::new();
+        ARM_PARTS
+            .iter()
+            .map(|&body_part| (body_part, factory.create_body_part(&part_geometry_at(body_part, model_type, resolution))))
+            .collect()
+    }
 
-        let (vertexes, translation_x, cells_per_side) = match model_type {
-            ModelType::Classic => (cuboid_4x12x4(), 0.75, LIMB_4_CELLS_PER_SIDE),
-            ModelType::Slim => (cuboid_3x12x4(), 0.6875, LIMB_3_CELLS_PER_SIDE),
-        };
+    fn get_arm_grids(context: Rc<Context>, program: Rc<Program>, camera: Rc<RefCell<Camera>>, model_type: &ModelType, resolution: u32) -> BTreeMap<BodyPart, ModelObject>{
+        let factory = ModelObjectFactory::new(context.clone(), program.clone(), camera.clone());
+        ARM_PARTS
+            .iter()
+            .map(|&body_part| (body_part, factory.create_grid(&part_geometry_at(body_part, model_type, resolution))))
+            .collect()
+    }
 
-        let right_arm = factory.create_body_part(vertexes, &glm::Vec3::new(-translation_x, 0.25, 0.), &INNER_SCALE, cells_per_side);
-        let left_arm = factory.create_body_part(vertexes, &glm::Vec3::new(translation_x, 0.25, 0.), &INNER_SCALE, cells_per_side);
-        let right_arm_outer = factory.create_body_part(vertexes, &glm::Vec3::new(-translation_x, 0.25, 0.), &OUTER_SCALE, cells_per_side);
-        let left_arm_outer = factory.create_body_part(vertexes, &glm::Vec3::new(translation_x, 0.25, 0.), &OUTER_SCALE, cells_per_side);
+    pub fn reset_skin(&mut self) {
+        for model_object in self.model_objects.values_mut() {
+            model_object.clear();
+        }
+    }
 
-        arms.insert(BodyPart::RightArm, right_arm);
-        arms.insert(BodyPart::LeftArm, left_arm);
-        arms.insert(BodyPart::RightArmOuter, right_arm_outer);
-        arms.insert(BodyPart::LeftArmOuter, left_arm_outer);
+    /// Switches the arms to the other model type, converting their cells with `strategy`.
+    /// Custom rigs have no player arms and are left as they are.
+    pub fn reset_model_type(&mut self, model_type: &ModelType, strategy: ConversionStrategy) {
+        if self.model_type == *model_type || self.custom_rig.is_some() {
+            return
+        }
 
-        arms
+        let mut arms = Renderer::get_arms(self.context.clone(), self.program.clone(), self.camera.clone(), model_type, self.resolution);
+        let resolution = self.resolution as usize;
+        for part in ARM_PARTS {
+            let old_cells = self.model_objects[&part].get_pixels();
+            let new_cells = match model_type {
+                ModelType::Slim => arm_conversion::classic_to_slim(&old_cells, part, strategy, &mut self.hidden_columns, resolution),
+                ModelType::Classic => arm_conversion::slim_to_classic(&old_cells, part, strategy, &mut self.hidden_columns, resolution),
+            };
+            arms.get_mut(&part).unwrap().set_colors(&new_cells);
+        }
+        self.replace_arms(arms, model_type);
     }
 
-    fn get_arm_grids(context: Rc<Context>, program: Rc<Program>, camera: Rc<RefCell<Camera>>, model_type: &ModelType) -> BTreeMap<BodyPart, ModelObject>{
-        let factory = ModelObjectFactory::new(context.clone(), program.clone(), camera.clone());
-        let mut grids: BTreeMap<BodyPart, ModelObject> = BTreeMap::new();
-        let translation_classic = 0.75;
-        let translation_slim = 0.6875;
-        let (vertexes, translation_x) = match model_type {
-            ModelType::Classic => (grid_4x12x4(), translation_classic),
-            ModelType::Slim => (grid_3x12x4(), translation_slim),
-        };
+    /// Puts the model back to a state saved with [`Renderer::snapshot_cells`] and
+    /// [`Renderer::hidden_columns`], without converting anything.
+    pub fn restore_layout(&mut self,
+                          model_type: &ModelType,
+                          resolution: u32,
+                          custom_rig: Option<Rc<Rig>>,
+                          cells: &BTreeMap<(BodyPart, usize), [f32; 4]>,
+                          hidden_columns: HiddenColumns)
+    {
+        if !same_custom_rig(self.custom_rig.as_ref(), custom_rig.as_ref()) || self.resolution != resolution {
+            self.rebuild_model(model_type, resolution, custom_rig);
+        } else if self.model_type != *model_type {
+            let arms = Renderer::get_arms(self.context.clone(), self.program.clone(), self.camera.clone(), model_type, self.resolution);
+            self.replace_arms(arms, model_type);
+        }
+        self.load_cells(cells);
+        self.hidden_columns = hidden_columns;
+    }
 
-        let right_arm_grid = factory.create_grid(vertexes, &glm::Vec3::new(-translation_x, 0.25, 0.), &INNER_SCALE.scale(GRID_SCALE));
-        let left_arm_grid = factory.create_grid(vertexes, &glm::Vec3::new(translation_x, 0.25, 0.), &INNER_SCALE.scale(GRID_SCALE));
-        let right_arm_grid_outer = factory.create_grid(vertexes, &glm::Vec3::new(-translation_x, 0.25, 0.), &OUTER_SCALE.scale(GRID_SCALE));
-        let left_arm_grid_outer = factory.create_grid(vertexes, &glm::Vec3::new(translation_x, 0.25, 0.), &OUTER_SCALE.scale(GRID_SCALE));
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
 
-        grids.insert(BodyPart::RightArm, right_arm_grid);
-        grids.insert(BodyPart::LeftArm, left_arm_grid);
-        grids.insert(BodyPart::RightArmOuter, right_arm_grid_outer);
-        grids.insert(BodyPart::LeftArmOuter, left_arm_grid_outer);
+    /// The cuboids the model is built from.
+    fn rig(&self) -> &Rig {
+        match &self.custom_rig {
+            Some(rig) => rig,
+            None => player_rig_at(&self.model_type, self.resolution),
+        }
+    }
 
-        grids
+    /// The rig shown instead of the player model, see [`Renderer::load_rig`].
+    pub fn custom_rig(&self) -> Option<Rc<Rig>> {
+        self.custom_rig.clone()
     }
 
-    pub fn reset_skin(&mut self) {
-        for model_object in self.model_objects.values_mut() {
-            model_object.clear();
-        }
+    /// Swaps the model for the cuboids of `rig` painted with `texture`, scaled to the
+    /// texture size of the rig.
+    pub fn load_rig(&mut self, rig: Rc<Rig>, texture: &image::DynamicImage) -> Result<(), TextureLoadError> {
+        let [width, height] = rig.texture_size;
+        let scaled;
+        let texture = if (texture.width(), texture.height()) == (width, height) {
+            texture
+        } else {
+            scaled = texture.resize_exact(width, height, image::imageops::FilterType::Nearest);
+            &scaled
+        };
+        let color_map = SkinParser::for_rig(&rig).load_from_bytes(texture)?;
+
+        let (model_type, resolution) = (self.model_type, self.resolution);
+        self.rebuild_model(&model_type, resolution, Some(rig));
+        self.load_from_color_map(color_map, false);
+        Ok(())
     }
 
-    pub fn reset_model_type(&mut self, model_type: &ModelType) {
-        if self.model_type == *model_type {
+    /// Splits or merges the cells for another resolution, scaling the skin with it.
+    /// Custom rigs keep a cell per texture pixel.
+    pub fn set_resolution(&mut self, resolution: u32) {
+        if self.resolution == resolution || self.custom_rig.is_some() {
             return
         }
-        
-        let mut arms = Renderer::get_arms(self.context.clone(), self.program.clone(), self.camera.clone(), model_type);
-        
-        let parts = [RightArm, RightArmOuter, LeftArm, LeftArmOuter];
-        for part in parts {
-            let old_arm = self.model_objects.get(&part).unwrap();
-            let new_arm = arms.get_mut(&part).unwrap();
-            match model_type {
-                ModelType::Slim => utils::classic_to_slim_arm(old_arm, new_arm, part),
-                ModelType::Classic => utils::slim_to_classic_arm(old_arm, new_arm, part)
-            }
+        let size = 64 * resolution;
+        let texture = image::imageops::resize(&self.export_texture(), size, size, image::imageops::FilterType::Nearest);
+        let model_type = self.model_type;
+        self.rebuild_model(&model_type, resolution, None);
+        let parser = SkinParser::with_resolution(&model_type, TextureType::Normal, resolution);
+        let color_map = parser.load_from_bytes(&texture.into()).expect("scaled texture fits the model");
+        self.load_from_color_map(color_map, false);
+    }
+
+    /// Builds empty body parts and grids for the model type and resolution, or for the
+    /// custom rig when there is one.
+    fn rebuild_model(&mut self, model_type: &ModelType, resolution: u32, custom_rig: Option<Rc<Rig>>) {
+        self.model_type = *model_type;
+        self.resolution = resolution;
+        self.custom_rig = custom_rig;
+        let rig = match &self.custom_rig {
+            Some(rig) => rig.as_ref(),
+            None => player_rig_at(model_type, resolution),
+        };
+        self.model_objects = Renderer::create_model_objects(self.context.clone(), self.program.clone(), self.camera.clone(), rig);
+        self.grid_objects = Renderer::create_grid_objects(self.context.clone(), self.program.clone(), self.camera.clone(), rig);
+        if self.custom_rig.is_some() {
+            self.visible_objects.extend(self.model_objects.keys().copied());
         }
-        
-        let arm_grids = Renderer::get_arm_grids(self.context.clone(), self.program.clone(), self.camera.clone(), model_type);
+        self.hidden_columns.clear();
+    }
+
+    fn replace_arms(&mut self, arms: BTreeMap<BodyPart, ModelObject>, model_type: &ModelType) {
+        let arm_grids = Renderer::get_arm_grids(self.context.clone(), self.program.clone(), self.camera.clone(), model_type, self.resolution);
 
         self.model_objects.extend(arms);
         self.grid_objects.extend(arm_grids);
@@ -484,6 +556,10 @@ impl Renderer {
         self.model_type = *model_type;
     }
 
+    pub fn hidden_columns(&self) -> &HiddenColumns {
+        &self.hidden_columns
+    }
+
     pub fn set_viewport_size(&mut self, width: i32, height: i32) {
         self.viewport_width = width.max(1) as f32;
         self.viewport_height = height.max(1) as f32;
@@ -520,13 +596,9 @@ impl Renderer {
     }
 
     pub fn load_texture(&mut self, path: &str, model_type: &ModelType, ignore_transparent: bool) -> Result<(), TextureLoadError> {
-        let parser = SkinParser::new(model_type, TextureType::Normal);
-        let color_map = parser.load_from_path(path)?;
-
-        self.reset_model_type(&model_type);
-        self.load_from_color_map(color_map, ignore_transparent);
-
-        Ok(())
+        let image = image::open(path).map_err(TextureLoadError::Image)?;
+        let (texture_type, _) = texture_layout(image.width(), image.height())?;
+        self.load_texture_from_bytes(&image, *model_type, texture_type, ignore_transparent)
     }
 
     pub fn load_texture_from_bytes(&mut self,
@@ -536,16 +608,35 @@ impl Renderer {
                                    texture_type: TextureType,
                                    ignore_transparent: bool) -> Result<(), TextureLoadError>
     {
-        let parser = SkinParser::new(&model_type, texture_type);
+        // HD skins switch the model to their resolution, what is kept is scaled to it. A texture
+        // painted over the skin never lowers its resolution, it is scaled up instead.
+        let (_, image_resolution) = texture_layout(image.width(), image.height())?;
+        let resolution = if ignore_transparent { image_resolution.max(self.resolution) } else { image_resolution };
+        let scaled;
+        let image = if resolution == image_resolution {
+            image
+        } else {
+            let factor = resolution / image_resolution;
+            scaled = image.resize_exact(image.width() * factor, image.height() * factor, image::imageops::FilterType::Nearest);
+            &scaled
+        };
+        let parser = SkinParser::with_resolution(&model_type, texture_type, resolution);
         let color_map = parser.load_from_bytes(image)?;
-        
-        self.reset_model_type(&model_type);
+
+        // Skins go back on the player model
+        if self.custom_rig.is_some() {
+            let current_model_type = self.model_type;
+            self.rebuild_model(&current_model_type, self.resolution, None);
+        }
+        self.set_resolution(resolution);
+        self.reset_model_type(&model_type, ConversionStrategy::default());
         self.load_from_color_map(color_map, ignore_transparent);
 
         Ok(())
     }
 
     fn load_from_color_map(&mut self, color_map: ColorMap, ignore_transparent: bool) {
+        self.hidden_columns.clear();
         for (body_part, model_object) in self.model_objects.iter_mut() {
             if let Some(color_map) = color_map.get(body_part) {
                 model_object.set_pixels(color_map, ignore_transparent);
@@ -571,10 +662,15 @@ impl Renderer {
 
         frame.clear_color_and_depth(BACKGROUND_COLOR, 1.0);
 
-        for body_part in &self.visible_objects {
-            if body_part.is_outer() {
-                continue;
-            }
+        let (base_opacity, outer_opacity) = self.overlay_view.opacity();
+        // Visible parts of another rig have no objects to draw
+        let rig = self.rig();
+        let (outer_parts, inner_parts): (Vec<BodyPart>, Vec<BodyPart>) = self.visible_objects
+            .iter()
+            .filter(|body_part| rig.cuboid(**body_part).is_some())
+            .partition(|body_part| rig.inner_part(**body_part).is_some());
+
+        for body_part in &inner_parts {
             if self.grid {
                 self.grid_objects
                     .get_mut(body_part)
@@ -584,19 +680,17 @@ impl Renderer {
             self.model_objects
                 .get_mut(body_part)
                 .expect("Some body part is missed")
-                .draw(&mut frame);
+                .draw_pass_with(&mut frame, ModelDrawPass::Standard, base_opacity, false);
         }
 
-        for body_part in &self.visible_objects {
-            if !body_part.is_outer() {
-                continue;
-            }
+        for body_part in &outer_parts {
             let model_object = self
                 .model_objects
                 .get_mut(body_part)
                 .expect("Some body part is missed");
-            model_object.draw_pass(&mut frame, ModelDrawPass::OuterBackFaces);
-            model_object.draw_pass(&mut frame, ModelDrawPass::OuterFrontFaces);
+            let checkerboard = self.overlay_view == OverlayView::XRay;
+            model_object.draw_pass_with(&mut frame, ModelDrawPass::OuterBackFaces, outer_opacity, checkerboard);
+            model_object.draw_pass_with(&mut frame, ModelDrawPass::OuterFrontFaces, outer_opacity, checkerboard);
             if self.grid {
                 self.grid_objects
                     .get_mut(body_part)
@@ -654,6 +748,23 @@ impl Renderer {
         self.camera.borrow_mut().update_distance(distance);
     }
 
+    /// Yaw and pitch of the camera in degrees.
+    pub fn camera_angles(&self) -> (f32, f32) {
+        let camera = self.camera.borrow();
+        (camera.yaw, camera.pitch)
+    }
+
+    /// Points the camera like the camera of another renderer.
+    pub fn copy_camera_from(&mut self, other: &Renderer) {
+        let (yaw, pitch, position) = {
+            let camera = other.camera.borrow();
+            (camera.yaw, camera.pitch, camera.position)
+        };
+        let mut camera = self.camera.borrow_mut();
+        camera.position = position;
+        camera.set_yaw_and_pitch(yaw, pitch);
+    }
+
     fn screen_to_ndc(&self, screen_x: f32, screen_y: f32) -> (f32, f32) {
         let ndc_x = (2.0 * screen_x / self.viewport_width) - 1.0;
         let ndc_y = 1.0 - (2.0 * screen_y / self.viewport_height);
@@ -689,12 +800,17 @@ impl Renderer {
     }
 
     fn pickable_parts(&self) -> Vec<BodyPart> {
+        let rig = self.rig();
         self.visible_objects
             .iter()
             .copied()
-            .filter(|part| {
-                part.outer_counterpart()
-                    .is_none_or(|outer| !self.visible_objects.contains(&outer))
+            .filter(|part| self.model_objects.contains_key(part))
+            .filter(|&part| match self.paint_target {
+                PaintTarget::Automatic => rig
+                    .outer_part(part)
+                    .is_none_or(|outer| !self.visible_objects.contains(&outer)),
+                PaintTarget::Base => rig.inner_part(part).is_none(),
+                PaintTarget::Outer => rig.inner_part(part).is_some(),
             })
             .collect()
     }
@@ -735,9 +851,11 @@ impl Renderer {
         closest.map(|(cell, _)| cell)
     }
 
+    /// Paints a cell, if its part is a part of the current model.
     pub fn set_cell(&mut self, cell: &ModelCell) {
-        let mut model_object = self.model_objects.get_mut(&cell.body_part).unwrap();
-        model_object.paint(cell.cell_index, cell.color);
+        if let Some(model_object) = self.model_objects.get_mut(&cell.body_part) {
+            model_object.paint(cell.cell_index, cell.color);
+        }
     }
 
     pub fn snapshot_cells(&self) -> BTreeMap<(BodyPart, usize), [f32; 4]> {
@@ -750,6 +868,20 @@ impl Renderer {
         snapshot
     }
 
+    /// Repaints the model from a snapshot taken by [`Renderer::snapshot_cells`].
+    /// Cells missing from it keep their color.
+    pub fn load_cells(&mut self, cells: &BTreeMap<(BodyPart, usize), [f32; 4]>) {
+        for (body_part, model_object) in self.model_objects.iter_mut() {
+            let mut colors = model_object.get_pixels();
+            for (cell_index, color) in colors.iter_mut().enumerate() {
+                if let Some(new_color) = cells.get(&(*body_part, cell_index)) {
+                    *color = *new_color;
+                }
+            }
+            model_object.set_colors(&colors);
+        }
+    }
+
     pub fn paint(&mut self, x: f32, y: f32, color: [f32; 4]) {
         let clicked_cell = self.get_cell(x, y, false);
         if let Some(cell) = clicked_cell {
@@ -759,17 +891,22 @@ impl Renderer {
         }
     }
 
+    /// Width and height in cells of the side the cell is on.
+    pub fn get_side_size(&self, body_part: &BodyPart, cell_index: usize) -> Option<[usize; 2]> {
+        let cuboid = self.rig().cuboid(*body_part)?;
+        let mut start = 0;
+        for (count, (_, [_, _, width, height])) in cuboid.cells_per_side().into_iter().zip(cuboid.side_regions()) {
+            start += count;
+            if cell_index < start {
+                return Some([width as usize, height as usize]);
+            }
+        }
+        None
+    }
+
     pub fn get_side_cells(&self, body_part: &BodyPart, cell_index: usize) -> Option<Vec<ModelCell>> {
 
-        let cell_count_per_side: [usize; 6] = match body_part {
-            Head | HeadOuter => [64, 64, 64, 64, 64, 64],
-            Torso | TorsoOuter => [96, 48, 96, 48, 32, 32],
-            RightArm | LeftArm | RightArmOuter | LeftArmOuter => match self.model_type {
-                ModelType::Slim => [36, 48, 36, 48, 12, 12],
-                ModelType::Classic => [48, 48, 48, 48, 16, 16]
-            },
-            RightLeg | LeftLeg | RightLegOuter | LeftLegOuter => [48, 48, 48, 48, 16, 16]
-        };
+        let cell_count_per_side = self.rig().cuboid(*body_part)?.cells_per_side();
 
         if cell_index >= cell_count_per_side.iter().sum() {
             return None;
@@ -798,11 +935,11 @@ impl Renderer {
     }
 
     pub fn export_texture(&self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
-        let width = 64;
-        let height = 64;
+        let rig = self.rig();
+        let [width, height] = rig.texture_size;
         let mut imgbuf: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(width, height);
 
-        let parser = SkinParser::new(&self.model_type, TextureType::Normal);
+        let parser = SkinParser::for_rig(rig);
         for (body_part, cell_object) in &self.model_objects {
             parser.export_as(&body_part, &mut imgbuf, &cell_object.get_vertexes());
         }
@@ -810,6 +947,18 @@ impl Renderer {
         imgbuf
     }
 
+    pub fn set_overlay_view(&mut self, overlay_view: OverlayView) {
+        self.overlay_view = overlay_view;
+    }
+
+    pub fn set_paint_target(&mut self, paint_target: PaintTarget) {
+        self.paint_target = paint_target;
+    }
+
+    pub fn visible_body_parts(&self) -> Vec<BodyPart> {
+        self.visible_objects.iter().copied().collect()
+    }
+
     pub fn set_body_part_active(&mut self, body_part: &BodyPart, visible: bool) {
         if visible {
             self.visible_objects.insert(body_part.clone());
@@ -830,34 +979,30 @@ impl ModelObjectFactory {
         ModelObjectFactory { context, program, camera }
     }
 
-    fn create_body_part(
-        &self,
-        vertexes: &[Vertex],
-        translation: &glm::Vec3,
-        scale: &glm::Vec3,
-        cells_per_side: [usize; 6],
-    ) -> ModelObject {
+    fn create_body_part(&self, geometry: &PartGeometry) -> ModelObject {
         ModelObject::new(
             self.context.clone(),
             self.program.clone(),
             self.camera.clone(),
-            vertexes,
+            geometry.vertexes,
             ModelObjectType::Model,
-            translation,
-            scale,
-            cells_per_side,
+            &geometry.placement,
+            &geometry.translation,
+            &geometry.scale,
+            geometry.cells_per_side,
         )
     }
 
-    fn create_grid(&self, vertexes: &[Vertex], translation: &glm::Vec3, scale: &glm::Vec3) -> ModelObject {
+    fn create_grid(&self, geometry: &PartGeometry) -> ModelObject {
         ModelObject::new(
             self.context.clone(),
             self.program.clone(),
             self.camera.clone(),
-            vertexes,
+            geometry.grid,
             ModelObjectType::Grid,
-            translation,
-            scale,
+            &geometry.placement,
+            &geometry.translation,
+            &geometry.scale.scale(GRID_SCALE),
             [0; 6],
         )
     }
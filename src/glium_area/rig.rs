@@ -0,0 +1,444 @@
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use nalgebra_glm as glm;
+use serde_derive::Deserialize;
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::cube_side::CubeSide;
+use crate::glium_area::skin_parser::ModelType;
+use crate::glium_area::vertex::Vertex;
+
+const CELL_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.0];
+const GRID_COLOR: [f32; 4] = [0.65, 0.65, 0.65, 1.0];
+/// Skin pixels in a model unit.
+const PIXELS_PER_UNIT: f32 = 8.0;
+/// Supported cells per skin pixel along an edge: 64x64, 128x128 and 256x256 textures.
+pub const RESOLUTIONS: [u32; 3] = [1, 2, 4];
+
+#[derive(Debug)]
+pub enum RigError {
+    JsonError(serde_json::Error),
+    /// A cuboid has neither a size, a position and a pivot nor an inner part to take them from.
+    MissingGeometry(BodyPart),
+    /// `outer_of` names a part that is not defined before it.
+    UnknownInnerPart(BodyPart),
+    DuplicatePart(BodyPart),
+}
+
+/// Texture area of a side as x, y, width and height in skin pixels. Negative sizes flip
+/// the texture like Bedrock `uv_size`, an empty area leaves the side without cells.
+pub type FaceRegion = [i32; 4];
+
+impl From<serde_json::Error> for RigError {
+    fn from(error: serde_json::Error) -> Self {
+        RigError::JsonError(error)
+    }
+}
+
+#[derive(Deserialize)]
+struct RigDescription {
+    name: String,
+    texture_size: [u32; 2],
+    cuboids: Vec<CuboidDescription>,
+}
+
+/// A cuboid as written in a rig file, lengths in skin pixels.
+#[derive(Deserialize)]
+struct CuboidDescription {
+    part: BodyPart,
+    /// Outer layers take the size, position and pivot of their inner part.
+    #[serde(default)]
+    outer_of: Option<BodyPart>,
+    size: Option<[u32; 3]>,
+    /// Center of the cuboid.
+    position: Option<[f32; 3]>,
+    /// Point the part turns around.
+    pivot: Option<[f32; 3]>,
+    /// Top left corner of the box UV layout of the cuboid.
+    uv: [u32; 2],
+    /// Growth as a fraction of the size.
+    #[serde(default)]
+    inflate: f32,
+    /// Whether the texture is flipped, like the left limbs of legacy skins that reuse
+    /// the texture of the right ones.
+    #[serde(default)]
+    mirror: bool,
+}
+
+pub struct Cuboid {
+    pub size: [u32; 3],
+    /// Model units.
+    pub position: glm::Vec3,
+    /// Model units.
+    pub pivot: glm::Vec3,
+    /// In skin pixels of a 64x64 texture.
+    pub uv: [u32; 2],
+    pub inflate: f32,
+    pub outer_of: Option<BodyPart>,
+    /// The sides are flipped and the left and right ones swapped on the texture.
+    pub mirror: bool,
+    /// Texture areas of the sides when they are not laid out like box UV.
+    pub faces: Option<[FaceRegion; 6]>,
+    /// Turns of the bones the cuboid hangs from, applied after moving it to its position.
+    pub placement: glm::Mat4,
+    /// Cells per skin pixel along an edge.
+    pub resolution: u32,
+    /// Four vertexes per cell, side by side in [`CubeSide`] order, in model units around the center.
+    pub vertexes: Vec<Vertex>,
+    /// Line segments between the cells.
+    pub grid: Vec<Vertex>,
+}
+
+/// Every cuboid of a model with its texture layout.
+pub struct Rig {
+    pub name: String,
+    /// Scaled by the resolution.
+    pub texture_size: [u32; 2],
+    /// In the order of the rig file.
+    cuboids: Vec<(BodyPart, Cuboid)>,
+}
+
+impl Rig {
+    /// Builds the cuboids with `resolution` cells per skin pixel along an edge.
+    pub fn from_json(json: &str, resolution: u32) -> Result<Rig, RigError> {
+        let description: RigDescription = serde_json::from_str(json)?;
+        let mut cuboids: Vec<(BodyPart, Cuboid)> = vec![];
+
+        for cuboid in description.cuboids {
+            if cuboids.iter().any(|(part, _)| *part == cuboid.part) {
+                return Err(RigError::DuplicatePart(cuboid.part));
+            }
+            let inner = match cuboid.outer_of {
+                Some(inner) => Some(find(&cuboids, inner).ok_or(RigError::UnknownInnerPart(cuboid.part))?),
+                None => None,
+            };
+            let (size, position, pivot) = match (cuboid.size, cuboid.position, cuboid.pivot, inner) {
+                (Some(size), Some(position), Some(pivot), _) => {
+                    let to_units = |vector: [f32; 3]| glm::make_vec3(&vector) / PIXELS_PER_UNIT;
+                    (size, to_units(position), to_units(pivot))
+                }
+                (None, None, None, Some(inner)) => (inner.size, inner.position, inner.pivot),
+                _ => return Err(RigError::MissingGeometry(cuboid.part)),
+            };
+
+            cuboids.push((cuboid.part, Cuboid {
+                size,
+                position,
+                pivot,
+                uv: cuboid.uv,
+                inflate: cuboid.inflate,
+                outer_of: cuboid.outer_of,
+                mirror: cuboid.mirror,
+                faces: None,
+                placement: glm::Mat4::identity(),
+                resolution,
+                vertexes: cell_mesh(half_size(size), box_side_cells(size, resolution)),
+                grid: grid_mesh(half_size(size), box_side_cells(size, resolution)),
+            }));
+        }
+
+        let texture_size = description.texture_size.map(|length| length * resolution);
+        Ok(Rig { name: description.name, texture_size, cuboids })
+    }
+
+    /// A rig of cuboids built elsewhere, e.g. from a Bedrock geometry.
+    pub fn new(name: String, texture_size: [u32; 2], cuboids: Vec<(BodyPart, Cuboid)>) -> Result<Rig, RigError> {
+        for (index, (body_part, cuboid)) in cuboids.iter().enumerate() {
+            if cuboids[..index].iter().any(|(part, _)| part == body_part) {
+                return Err(RigError::DuplicatePart(*body_part));
+            }
+            if cuboid.outer_of.is_some_and(|inner| find(&cuboids[..index], inner).is_none()) {
+                return Err(RigError::UnknownInnerPart(*body_part));
+            }
+        }
+        Ok(Rig { name, texture_size, cuboids })
+    }
+
+    pub fn cuboid(&self, body_part: BodyPart) -> Option<&Cuboid> {
+        find(&self.cuboids, body_part)
+    }
+
+    pub fn cuboids(&self) -> impl Iterator<Item = (&BodyPart, &Cuboid)> {
+        self.cuboids.iter().map(|(body_part, cuboid)| (body_part, cuboid))
+    }
+
+    pub fn parts(&self) -> impl Iterator<Item = BodyPart> + '_ {
+        self.cuboids.iter().map(|(body_part, _)| *body_part)
+    }
+
+    /// The part whose outer layer `body_part` is.
+    pub fn inner_part(&self, body_part: BodyPart) -> Option<BodyPart> {
+        self.cuboid(body_part)?.outer_of
+    }
+
+    /// The outer layer of `body_part`.
+    pub fn outer_part(&self, body_part: BodyPart) -> Option<BodyPart> {
+        self.cuboids()
+            .find(|(_, cuboid)| cuboid.outer_of == Some(body_part))
+            .map(|(outer, _)| *outer)
+    }
+}
+
+/// Whether two models are built from the same custom rig, or both from a player rig.
+pub fn same_custom_rig(a: Option<&Rc<Rig>>, b: Option<&Rc<Rig>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+fn find(cuboids: &[(BodyPart, Cuboid)], body_part: BodyPart) -> Option<&Cuboid> {
+    cuboids.iter().find(|(part, _)| *part == body_part).map(|(_, cuboid)| cuboid)
+}
+
+const SIDES: [CubeSide; 6] = [CubeSide::Front, CubeSide::Left, CubeSide::Back, CubeSide::Right, CubeSide::Top, CubeSide::Bottom];
+
+impl Cuboid {
+    /// A cuboid with a texture area of its own on every side and a cell per texture pixel,
+    /// e.g. a cube of a Bedrock geometry. The size is in skin pixels and may be fractional.
+    pub fn with_faces(size: glm::Vec3, position: glm::Vec3, placement: glm::Mat4, faces: [FaceRegion; 6]) -> Cuboid {
+        let side_cells = faces.map(|[_, _, width, height]| (width.unsigned_abs(), height.unsigned_abs()));
+        Cuboid {
+            size: [size.x, size.y, size.z].map(|length| length.round() as u32),
+            position,
+            pivot: position,
+            uv: [0, 0],
+            inflate: 0.0,
+            outer_of: None,
+            mirror: false,
+            faces: Some(faces),
+            placement,
+            resolution: 1,
+            vertexes: cell_mesh(size / 2.0, side_cells),
+            grid: grid_mesh(size / 2.0, side_cells),
+        }
+    }
+
+    /// Columns and rows of cells on each side in [`CubeSide`] order.
+    fn side_cells(&self) -> [(u32, u32); 6] {
+        match &self.faces {
+            Some(faces) => faces.map(|[_, _, width, height]| {
+                (width.unsigned_abs() * self.resolution, height.unsigned_abs() * self.resolution)
+            }),
+            None => box_side_cells(self.size, self.resolution),
+        }
+    }
+
+    /// Cells on each side in [`CubeSide`] order.
+    pub fn cells_per_side(&self) -> [usize; 6] {
+        self.side_cells().map(|(columns, rows)| (columns * rows) as usize)
+    }
+
+    /// Texture area of every side in [`CubeSide`] order as x, y, width and height,
+    /// laid out like Minecraft box UV unless the cuboid has faces, and scaled by the resolution.
+    pub fn side_regions(&self) -> [(CubeSide, [u32; 4]); 6] {
+        if let Some(faces) = &self.faces {
+            let mut regions = SIDES.map(|side| (side, [0; 4]));
+            for ((_, region), &[x, y, width, height]) in regions.iter_mut().zip(faces) {
+                let corner = [x.min(x + width), y.min(y + height)].map(|value| value.max(0) as u32);
+                *region = [corner[0], corner[1], width.unsigned_abs(), height.unsigned_abs()].map(|value| value * self.resolution);
+            }
+            return regions;
+        }
+        let [u, v] = self.uv.map(|length| length * self.resolution);
+        let [width, height, depth] = self.size.map(|length| length * self.resolution);
+        let (left, right) = ([u + depth + width, v + depth, depth, height], [u, v + depth, depth, height]);
+        let (left, right) = if self.mirror { (right, left) } else { (left, right) };
+        [
+            (CubeSide::Front, [u + depth, v + depth, width, height]),
+            (CubeSide::Left, left),
+            (CubeSide::Back, [u + 2 * depth + width, v + depth, width, height]),
+            (CubeSide::Right, right),
+            (CubeSide::Top, [u + depth, v, width, depth]),
+            (CubeSide::Bottom, [u + depth + width, v, width, depth]),
+        ]
+    }
+
+    /// Whether the texture of every side is flipped horizontally and vertically.
+    pub fn side_flips(&self) -> [(bool, bool); 6] {
+        match &self.faces {
+            Some(faces) => faces.map(|[_, _, width, height]| (width < 0, height < 0)),
+            None => [(self.mirror, false); 6],
+        }
+    }
+
+    pub fn scale(&self) -> glm::Vec3 {
+        glm::Vec3::repeat(1.0 + self.inflate)
+    }
+}
+
+/// How the cells of a side are laid out: the top left corner in half sizes of the
+/// cuboid, the step to the next column and to the next row.
+pub(crate) const SIDE_LAYOUTS: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+    ([-1.0, 1.0, 1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+    ([1.0, 1.0, 1.0], [0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+    ([1.0, 1.0, -1.0], [-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+    ([-1.0, 1.0, -1.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+    ([-1.0, 1.0, -1.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+    ([-1.0, -1.0, -1.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+];
+
+/// Columns and rows of every side of a box UV layout, in cells.
+fn box_side_cells(size: [u32; 3], resolution: u32) -> [(u32, u32); 6] {
+    let [width, height, depth] = size.map(|length| length * resolution);
+    [(width, height), (depth, height), (width, height), (depth, height), (width, depth), (width, depth)]
+}
+
+fn half_size(size: [u32; 3]) -> glm::Vec3 {
+    glm::vec3(size[0] as f32, size[1] as f32, size[2] as f32) / 2.0
+}
+
+/// The top left corner of a side and the steps to the next column and row, which split
+/// the side evenly into its cells.
+fn side_steps(layout: &([f32; 3], [f32; 3], [f32; 3]), half_size: &glm::Vec3, columns: u32, rows: u32) -> [glm::Vec3; 3] {
+    let (corner, column_step, row_step) = layout;
+    [
+        glm::make_vec3(corner).component_mul(half_size),
+        glm::make_vec3(column_step).component_mul(half_size) * 2.0 / columns as f32,
+        glm::make_vec3(row_step).component_mul(half_size) * 2.0 / rows as f32,
+    ]
+}
+
+fn cell_mesh(half_size: glm::Vec3, side_cells: [(u32, u32); 6]) -> Vec<Vertex> {
+    let mut vertexes = vec![];
+
+    for (side, (layout, (columns, rows))) in SIDE_LAYOUTS.iter().zip(side_cells).enumerate() {
+        if columns == 0 || rows == 0 {
+            continue;
+        }
+        let [corner, column_step, row_step] = side_steps(layout, &half_size, columns, rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let top_left = corner + column_step * column as f32 + row_step * row as f32;
+                let mut cell = [top_left, top_left + column_step, top_left + column_step + row_step, top_left + row_step];
+                // Every side winds the same way seen from inside
+                if side == 5 {
+                    cell.swap(1, 3);
+                }
+                vertexes.extend(cell.map(|position| Vertex {
+                    position: (position / PIXELS_PER_UNIT).into(),
+                    color: CELL_COLOR,
+                }));
+            }
+        }
+    }
+    vertexes
+}
+
+fn grid_mesh(half_size: glm::Vec3, side_cells: [(u32, u32); 6]) -> Vec<Vertex> {
+    let mut vertexes = vec![];
+    let mut line = |from: glm::Vec3, to: glm::Vec3| {
+        for position in [from, to] {
+            vertexes.push(Vertex { position: (position / PIXELS_PER_UNIT).into(), color: GRID_COLOR });
+        }
+    };
+
+    for (layout, (columns, rows)) in SIDE_LAYOUTS.iter().zip(side_cells) {
+        if columns == 0 || rows == 0 {
+            continue;
+        }
+        let [corner, column_step, row_step] = side_steps(layout, &half_size, columns, rows);
+        for column in 0..=columns {
+            let start = corner + column_step * column as f32;
+            line(start, start + row_step * rows as f32);
+        }
+        for row in 0..=rows {
+            let start = corner + row_step * row as f32;
+            line(start, start + column_step * columns as f32);
+        }
+    }
+    vertexes
+}
+
+/// The player model for the given arms with a cell per pixel of a 64x64 skin.
+pub fn player_rig(model_type: &ModelType) -> &'static Rig {
+    player_rig_at(model_type, 1)
+}
+
+/// The player model for the given arms with `resolution` cells per skin pixel, one of
+/// [`RESOLUTIONS`].
+pub fn player_rig_at(model_type: &ModelType, resolution: u32) -> &'static Rig {
+    static CLASSIC: RigCache = [const { OnceLock::new() }; RESOLUTIONS.len()];
+    static SLIM: RigCache = [const { OnceLock::new() }; RESOLUTIONS.len()];
+    match model_type {
+        ModelType::Classic => cached_rig(&CLASSIC, include_str!("../../resources/rigs/classic.json"), resolution),
+        ModelType::Slim => cached_rig(&SLIM, include_str!("../../resources/rigs/slim.json"), resolution),
+    }
+}
+
+/// The player model laid out like 64x32 skins from before 1.8, where the left limbs
+/// take the mirrored texture of the right ones and there is no outer layer but the hat.
+pub fn legacy_player_rig_at(model_type: &ModelType, resolution: u32) -> &'static Rig {
+    static CLASSIC: RigCache = [const { OnceLock::new() }; RESOLUTIONS.len()];
+    static SLIM: RigCache = [const { OnceLock::new() }; RESOLUTIONS.len()];
+    match model_type {
+        ModelType::Classic => cached_rig(&CLASSIC, include_str!("../../resources/rigs/legacy_classic.json"), resolution),
+        ModelType::Slim => cached_rig(&SLIM, include_str!("../../resources/rigs/legacy_slim.json"), resolution),
+    }
+}
+
+type RigCache = [OnceLock<Rig>; RESOLUTIONS.len()];
+
+fn cached_rig(rigs: &'static RigCache, json: &str, resolution: u32) -> &'static Rig {
+    let index = RESOLUTIONS.iter()
+        .position(|supported| *supported == resolution)
+        .expect("unsupported skin resolution");
+    rigs[index].get_or_init(|| Rig::from_json(json, resolution).expect("embedded rig must be valid"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glium_area::model::meshes::{body_vertices, cuboid_3x12x4, cuboid_4x12x4, head_vertices};
+
+    #[test]
+    fn generated_cells_match_the_modelled_meshes() {
+        let slim = player_rig(&ModelType::Slim);
+        let classic = player_rig(&ModelType::Classic);
+
+        assert_eq!(classic.cuboid(BodyPart::Head).unwrap().vertexes, head_vertices());
+        assert_eq!(classic.cuboid(BodyPart::TorsoOuter).unwrap().vertexes, body_vertices());
+        assert_eq!(classic.cuboid(BodyPart::LeftArm).unwrap().vertexes, cuboid_4x12x4());
+        assert_eq!(slim.cuboid(BodyPart::RightArmOuter).unwrap().vertexes, cuboid_3x12x4());
+        assert_eq!(slim.cuboid(BodyPart::LeftLeg).unwrap().vertexes, cuboid_4x12x4());
+    }
+
+    #[test]
+    fn higher_resolutions_split_every_pixel_into_cells() {
+        let cuboid = player_rig_at(&ModelType::Classic, 2).cuboid(BodyPart::Head).unwrap();
+        let standard = player_rig(&ModelType::Classic).cuboid(BodyPart::Head).unwrap();
+        assert_eq!(cuboid.cells_per_side(), standard.cells_per_side().map(|cells| cells * 4));
+        assert_eq!(cuboid.vertexes.len(), standard.vertexes.len() * 4);
+        assert_eq!(cuboid.side_regions()[0], (CubeSide::Front, [16, 16, 16, 16]));
+
+        // Half a pixel wide, on the same box
+        let first_cell: Vec<[f32; 3]> = cuboid.vertexes[..4].iter().map(|vertex| vertex.position).collect();
+        assert_eq!(first_cell[0], standard.vertexes[0].position);
+        assert_eq!(first_cell[1][0] - first_cell[0][0], 0.5 / PIXELS_PER_UNIT);
+    }
+
+    #[test]
+    fn outer_layers_need_a_defined_inner_part() {
+        let json = r#"{ "name": "", "texture_size": [64, 64], "cuboids": [
+            { "part": "HeadOuter", "outer_of": "Head", "uv": [32, 0] }
+        ] }"#;
+        assert!(matches!(Rig::from_json(json, 1), Err(RigError::UnknownInnerPart(BodyPart::HeadOuter))));
+    }
+
+    #[test]
+    fn rigs_name_their_own_parts() {
+        let json = r#"{ "name": "Fox", "texture_size": [64, 32], "cuboids": [
+            { "part": "Tail", "size": [2, 2, 8], "position": [0, 0, 4], "pivot": [0, 0, 0], "uv": [0, 0] },
+            { "part": "TailFur", "outer_of": "Tail", "uv": [20, 0], "inflate": 0.1 }
+        ] }"#;
+        let rig = Rig::from_json(json, 1).unwrap();
+        let (tail, fur) = (BodyPart::from_id("Tail"), BodyPart::from_id("TailFur"));
+        assert_eq!(rig.parts().collect::<Vec<_>>(), vec![tail, fur]);
+        assert_eq!(rig.outer_part(tail), Some(fur));
+        assert_eq!(rig.inner_part(fur), Some(tail));
+        assert_eq!(rig.cuboid(fur).unwrap().size, [2, 2, 8]);
+        assert!(rig.cuboid(BodyPart::Head).is_none());
+        assert_eq!(tail.label(), "Tail");
+    }
+}
@@ -1,9 +1,11 @@
 use std::collections::{BTreeMap, HashMap};
 
 use image::{DynamicImage, GenericImage, GenericImageView, ImageBuffer, ImageError, Rgba};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::glium_area::body_part::BodyPart;
 use crate::glium_area::cube_side::CubeSide;
+use crate::glium_area::rig::{legacy_player_rig_at, player_rig_at, Rig, RESOLUTIONS};
 use crate::glium_area::vertex::Vertex;
 
 #[derive(Debug, Clone)]
@@ -32,10 +34,12 @@ impl Dimensions {
 struct SideMeta {
     position: Point,
     dimensions: Dimensions,
+    /// Whether the side is flipped horizontally and vertically on the texture.
+    flip: (bool, bool),
 }
 impl SideMeta {
-    pub fn new(position: Point, dimensions: Dimensions) -> Self {
-        SideMeta { position, dimensions }
+    pub fn new(position: Point, dimensions: Dimensions, flip: (bool, bool)) -> Self {
+        SideMeta { position, dimensions, flip }
     }
 }
 
@@ -47,7 +51,8 @@ const VEC_IN_CELL: usize = 4;
 pub type CubeSideColors = BTreeMap<CubeSide, Vec<Rgba<u8>>>;
 pub type ColorMap = HashMap<BodyPart, CubeSideColors>;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ModelType {
     Classic,
     Slim
@@ -65,18 +70,54 @@ pub enum TextureType {
     Legacy
 }
 
+/// Layout and resolution of a texture by its size: 64x64 skins and their 128x128 and
+/// 256x256 HD versions, or legacy 64x32 skins and their HD versions.
+pub fn texture_layout(width: u32, height: u32) -> Result<(TextureType, u32), TextureLoadError> {
+    let resolution = width / 64;
+    if width % 64 == 0 && RESOLUTIONS.contains(&resolution) {
+        if height == width {
+            return Ok((TextureType::Normal, resolution));
+        }
+        if height * 2 == width {
+            return Ok((TextureType::Legacy, resolution));
+        }
+    }
+    Err(TextureLoadError::ImageDimensionError(format!("Unsupported skin size {}x{}", width, height)))
+}
+
 pub struct SkinParser {
     helper_map: HelperMap,
-    texture_type: TextureType,
 }
 
 impl SkinParser {
     pub fn new(model_type: &ModelType, texture_type: TextureType) -> Self {
-        let helper_map = match texture_type {
-            TextureType::Normal => SkinParser::generate_helper_map(model_type),
-            TextureType::Legacy => SkinParser::generate_helper_map_legacy(model_type),
+        SkinParser::with_resolution(model_type, texture_type, 1)
+    }
+
+    /// Parser of a texture with `resolution` pixels per pixel of a 64x64 skin.
+    pub fn with_resolution(model_type: &ModelType, texture_type: TextureType, resolution: u32) -> Self {
+        let rig = match texture_type {
+            TextureType::Normal => player_rig_at(model_type, resolution),
+            TextureType::Legacy => legacy_player_rig_at(model_type, resolution),
         };
-        SkinParser { helper_map, texture_type }
+        SkinParser::for_rig(rig)
+    }
+
+    /// Parser of the texture of every cuboid of a rig.
+    pub fn for_rig(rig: &Rig) -> Self {
+        let helper_map = rig.cuboids()
+            .map(|(body_part, cuboid)| {
+                let sides = cuboid.side_regions()
+                    .into_iter()
+                    .zip(cuboid.side_flips())
+                    .map(|((side, [x, y, width, height]), flip)| {
+                        (side, SideMeta::new(Point::new(x, y), Dimensions::new(width, height), flip))
+                    })
+                    .collect();
+                (*body_part, sides)
+            })
+            .collect();
+        SkinParser { helper_map }
     }
 
     pub fn load_from_path(&self, path: &str) -> Result<ColorMap, TextureLoadError> {
@@ -102,6 +143,9 @@ impl SkinParser {
         for (body_part, helper) in &self.helper_map {
             let mut body_part_color_map: BTreeMap<CubeSide, Vec<Rgba<u8>>> = BTreeMap::new();
             for (side, meta) in helper {
+                if meta.position.x + meta.dimensions.width > img.width() || meta.position.y + meta.dimensions.height > img.height() {
+                    return Err(TextureLoadError::ImageDimensionError(format!("{:?} is outside of the texture", body_part)));
+                }
                 let image_slice = img.view(
                     meta.position.x,
                     meta.position.y,
@@ -109,17 +153,17 @@ impl SkinParser {
                     meta.dimensions.height
                 );
 
-                let colors: Vec<Rgba<u8>> = match self.texture_type {
-                    TextureType::Normal => image_slice.pixels().map(|(_, _, rgba)| rgba).collect(),
-                    TextureType::Legacy => match body_part {
-                        BodyPart::LeftArm | BodyPart::LeftLeg => {
-                            DynamicImage::from(image_slice.to_image())
-                                .fliph().pixels()
-                                .map(|(_, _, rgba)| rgba)
-                                .collect()
-                        },
-                        _ => image_slice.pixels().map(|(_, _, rgba)| rgba).collect()
+                let colors: Vec<Rgba<u8>> = if meta.flip != (false, false) {
+                    let mut image = DynamicImage::from(image_slice.to_image());
+                    if meta.flip.0 {
+                        image = image.fliph();
                     }
+                    if meta.flip.1 {
+                        image = image.flipv();
+                    }
+                    image.pixels().map(|(_, _, rgba)| rgba).collect()
+                } else {
+                    image_slice.pixels().map(|(_, _, rgba)| rgba).collect()
                 };
 
                 body_part_color_map.insert(side.clone(), colors);
@@ -149,11 +193,44 @@ impl SkinParser {
                     SkinParser::from_f32_to_u8_pixel(pixel)
                 })
                 .collect();
-            self.export_pixels(imgbuf, &pixels, meta.position.x, meta.position.y, meta.dimensions.width, meta.dimensions.height);
+            self.export_pixels(imgbuf, &pixels, meta);
             vec_passed += vec_count;
         }
     }
 
+    /// Writes the cells of a body part, in mesh order, into a texture.
+    pub fn export_cells(
+        &self,
+        body_part: &BodyPart,
+        imgbuf: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
+        cells: &[Rgba<u8>],
+    )
+    {
+        let body_part_helper = self.helper_map.get(body_part).unwrap();
+        let mut cells_passed = 0;
+        for (_, meta) in body_part_helper {
+            let cell_count = meta.dimensions.width as usize * meta.dimensions.height as usize;
+            let pixels = cells[cells_passed..cells_passed + cell_count].to_vec();
+            self.export_pixels(imgbuf, &pixels, meta);
+            cells_passed += cell_count;
+        }
+    }
+
+    /// Texture area of every side of a body part in mesh order, as x, y, width and height.
+    /// Parts of other rigs have none.
+    pub fn side_regions(&self, body_part: &BodyPart) -> Vec<(CubeSide, [u32; 4])> {
+        let Some(helper) = self.helper_map.get(body_part) else {
+            return vec![];
+        };
+        helper
+            .iter()
+            .map(|(side, meta)| {
+                let region = [meta.position.x, meta.position.y, meta.dimensions.width, meta.dimensions.height];
+                (*side, region)
+            })
+            .collect()
+    }
+
     fn image_slice(&self, img: &DynamicImage, x: u32, y: u32, width: u32, height: u32) -> Vec<Rgba<u8>> {
         // let mut slice = vec![];
         // for i in y..y + height {
@@ -173,17 +250,18 @@ impl SkinParser {
         &self,
         imgbuf: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
         pixels: &Vec<Rgba<u8>>,
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32
+        meta: &SideMeta,
     )
     {
+        let Dimensions { width, height } = meta.dimensions;
+        let (flip_x, flip_y) = meta.flip;
         for i in 0..height {
             for j in 0..width {
                 let index = (i * width + j) as usize;
                 let pixel = pixels[index];
-                imgbuf.put_pixel(x + j, y + i, pixel);
+                let x = if flip_x { width - 1 - j } else { j };
+                let y = if flip_y { height - 1 - i } else { i };
+                imgbuf.put_pixel(meta.position.x + x, meta.position.y + y, pixel);
             }
         }
     }
@@ -196,306 +274,58 @@ impl SkinParser {
             (pixel[3] * 255.0).round() as u8,
         ])
     }
+}
 
-    fn generate_helper_map(model_type: &ModelType) -> HelperMap {
-        let mut helper_map: HelperMap = HashMap::new();
-
-        let mut head_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            head_helper.insert(CubeSide::Front, SideMeta::new(Point::new(8, 8), Dimensions::new(8, 8)));
-            head_helper.insert(CubeSide::Left, SideMeta::new(Point::new(16, 8), Dimensions::new(8, 8)));
-            head_helper.insert(CubeSide::Back, SideMeta::new(Point::new(24, 8), Dimensions::new(8, 8)));
-            head_helper.insert(CubeSide::Right, SideMeta::new(Point::new(0, 8), Dimensions::new(8, 8)));
-            head_helper.insert(CubeSide::Top, SideMeta::new(Point::new(8, 0), Dimensions::new(8, 8)));
-            head_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(16, 0), Dimensions::new(8, 8)));
-        }
-
-        // -----------
-        let head_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::from([
-            (CubeSide::Front, SideMeta::new(Point::new(8, 8), Dimensions::new(8, 8))),
-            (CubeSide::Left, SideMeta::new(Point::new(16, 8), Dimensions::new(8, 8))),
-            (CubeSide::Back, SideMeta::new(Point::new(24, 8), Dimensions::new(8, 8))),
-            (CubeSide::Right, SideMeta::new(Point::new(0, 8), Dimensions::new(8, 8))),
-            (CubeSide::Top, SideMeta::new(Point::new(8, 0), Dimensions::new(8, 8))),
-            (CubeSide::Bottom, SideMeta::new(Point::new(16, 0), Dimensions::new(8, 8)))]
-        );
-        // -----------
-
-        let mut torso_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            torso_helper.insert(CubeSide::Front, SideMeta::new(Point::new(20, 20), Dimensions::new(8, 12)));
-            torso_helper.insert(CubeSide::Left, SideMeta::new(Point::new(28, 20), Dimensions::new(4, 12)));
-            torso_helper.insert(CubeSide::Back, SideMeta::new(Point::new(32, 20), Dimensions::new(8, 12)));
-            torso_helper.insert(CubeSide::Right, SideMeta::new(Point::new(16, 20), Dimensions::new(4, 12)));
-            torso_helper.insert(CubeSide::Top, SideMeta::new(Point::new(20, 16), Dimensions::new(8, 4)));
-            torso_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(28, 16), Dimensions::new(8, 4)));
-        }
-
-        let mut right_arm_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        match model_type {
-            ModelType::Classic => {
-                right_arm_helper.insert(CubeSide::Front, SideMeta::new(Point::new(44, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Left, SideMeta::new(Point::new(48, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Back, SideMeta::new(Point::new(52, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Right, SideMeta::new(Point::new(40, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Top, SideMeta::new(Point::new(44, 16), Dimensions::new(4, 4)));
-                right_arm_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(48, 16), Dimensions::new(4, 4)));
-            },
-            ModelType::Slim => {
-                right_arm_helper.insert(CubeSide::Front, SideMeta::new(Point::new(44, 20), Dimensions::new(3, 12)));
-                right_arm_helper.insert(CubeSide::Left, SideMeta::new(Point::new(47, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Back, SideMeta::new(Point::new(51, 20), Dimensions::new(3, 12)));
-                right_arm_helper.insert(CubeSide::Right, SideMeta::new(Point::new(40, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Top, SideMeta::new(Point::new(44, 16), Dimensions::new(3, 4)));
-                right_arm_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(47, 16), Dimensions::new(3, 4)));
-            }
-        }
-
-        let mut left_arm_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        match model_type {
-            ModelType::Classic => {
-                left_arm_helper.insert(CubeSide::Front, SideMeta::new(Point::new(36, 52), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Left, SideMeta::new(Point::new(40, 52), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Back, SideMeta::new(Point::new(44, 52), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Right, SideMeta::new(Point::new(32, 52), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Top, SideMeta::new(Point::new(36, 48), Dimensions::new(4, 4)));
-                left_arm_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(40, 48), Dimensions::new(4, 4)));
-            },
-            ModelType::Slim => {
-                left_arm_helper.insert(CubeSide::Front, SideMeta::new(Point::new(36, 52), Dimensions::new(3, 12)));
-                left_arm_helper.insert(CubeSide::Left, SideMeta::new(Point::new(39, 52), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Back, SideMeta::new(Point::new(43, 52), Dimensions::new(3, 12)));
-                left_arm_helper.insert(CubeSide::Right, SideMeta::new(Point::new(32, 52), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Top, SideMeta::new(Point::new(36, 48), Dimensions::new(3, 4)));
-                left_arm_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(39, 48), Dimensions::new(3, 4)));
-            }
-        }
-
-        let mut right_leg_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            right_leg_helper.insert(CubeSide::Front, SideMeta::new(Point::new(4, 20), Dimensions::new(4, 12)));
-            right_leg_helper.insert(CubeSide::Left, SideMeta::new(Point::new(8, 20), Dimensions::new(4, 12)));
-            right_leg_helper.insert(CubeSide::Back, SideMeta::new(Point::new(12, 20), Dimensions::new(4, 12)));
-            right_leg_helper.insert(CubeSide::Right, SideMeta::new(Point::new(0, 20), Dimensions::new(4, 12)));
-            right_leg_helper.insert(CubeSide::Top, SideMeta::new(Point::new(4, 16), Dimensions::new(4, 4)));
-            right_leg_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(8, 16), Dimensions::new(4, 4)));
-        }
-
-        let mut left_leg_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            left_leg_helper.insert(CubeSide::Front, SideMeta::new(Point::new(20, 52), Dimensions::new(4, 12)));
-            left_leg_helper.insert(CubeSide::Left, SideMeta::new(Point::new(24, 52), Dimensions::new(4, 12)));
-            left_leg_helper.insert(CubeSide::Back, SideMeta::new(Point::new(28, 52), Dimensions::new(4, 12)));
-            left_leg_helper.insert(CubeSide::Right, SideMeta::new(Point::new(16, 52), Dimensions::new(4, 12)));
-            left_leg_helper.insert(CubeSide::Top, SideMeta::new(Point::new(20, 48), Dimensions::new(4, 4)));
-            left_leg_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(24, 48), Dimensions::new(4, 4)));
-        }
+#[test]
+fn test() {
+}
 
-        let mut head_outer_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            head_outer_helper.insert(CubeSide::Front, SideMeta::new(Point::new(40, 8), Dimensions::new(8, 8)));
-            head_outer_helper.insert(CubeSide::Left, SideMeta::new(Point::new(48, 8), Dimensions::new(8, 8)));
-            head_outer_helper.insert(CubeSide::Back, SideMeta::new(Point::new(56, 8), Dimensions::new(8, 8)));
-            head_outer_helper.insert(CubeSide::Right, SideMeta::new(Point::new(32, 8), Dimensions::new(8, 8)));
-            head_outer_helper.insert(CubeSide::Top, SideMeta::new(Point::new(40, 0), Dimensions::new(8, 8)));
-            head_outer_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(48, 0), Dimensions::new(8, 8)));
-        }
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
 
-        let mut torso_outer_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            torso_outer_helper.insert(CubeSide::Front, SideMeta::new(Point::new(20, 36), Dimensions::new(8, 12)));
-            torso_outer_helper.insert(CubeSide::Left, SideMeta::new(Point::new(28, 36), Dimensions::new(4, 12)));
-            torso_outer_helper.insert(CubeSide::Back, SideMeta::new(Point::new(32, 36), Dimensions::new(8, 12)));
-            torso_outer_helper.insert(CubeSide::Right, SideMeta::new(Point::new(16, 36), Dimensions::new(4, 12)));
-            torso_outer_helper.insert(CubeSide::Top, SideMeta::new(Point::new(20, 32), Dimensions::new(8, 4)));
-            torso_outer_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(28, 32), Dimensions::new(8, 4)));
-        }
+    use super::*;
 
-        let mut right_arm_outer_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        match model_type {
-            ModelType::Classic => {
-                right_arm_outer_helper.insert(CubeSide::Front, SideMeta::new(Point::new(44, 36), Dimensions::new(4, 12)));
-                right_arm_outer_helper.insert(CubeSide::Left, SideMeta::new(Point::new(48, 36), Dimensions::new(4, 12)));
-                right_arm_outer_helper.insert(CubeSide::Back, SideMeta::new(Point::new(52, 36), Dimensions::new(4, 12)));
-                right_arm_outer_helper.insert(CubeSide::Right, SideMeta::new(Point::new(40, 36), Dimensions::new(4, 12)));
-                right_arm_outer_helper.insert(CubeSide::Top, SideMeta::new(Point::new(44, 32), Dimensions::new(4, 4)));
-                right_arm_outer_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(48, 32), Dimensions::new(4, 4)));
-            },
-            ModelType::Slim => {
-                right_arm_outer_helper.insert(CubeSide::Front, SideMeta::new(Point::new(44, 36), Dimensions::new(3, 12)));
-                right_arm_outer_helper.insert(CubeSide::Left, SideMeta::new(Point::new(47, 36), Dimensions::new(4, 12)));
-                right_arm_outer_helper.insert(CubeSide::Back, SideMeta::new(Point::new(51, 36), Dimensions::new(3, 12)));
-                right_arm_outer_helper.insert(CubeSide::Right, SideMeta::new(Point::new(40, 36), Dimensions::new(4, 12)));
-                right_arm_outer_helper.insert(CubeSide::Top, SideMeta::new(Point::new(44, 32), Dimensions::new(3, 4)));
-                right_arm_outer_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(47, 32), Dimensions::new(3, 4)));
-            }
-        }
-        let mut left_arm_outer_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        match model_type {
-            ModelType::Classic => {
-                left_arm_outer_helper.insert(CubeSide::Front, SideMeta::new(Point::new(52, 52), Dimensions::new(4, 12)));
-                left_arm_outer_helper.insert(CubeSide::Left, SideMeta::new(Point::new(56, 52), Dimensions::new(4, 12)));
-                left_arm_outer_helper.insert(CubeSide::Back, SideMeta::new(Point::new(60, 52), Dimensions::new(4, 12)));
-                left_arm_outer_helper.insert(CubeSide::Right, SideMeta::new(Point::new(48, 52), Dimensions::new(4, 12)));
-                left_arm_outer_helper.insert(CubeSide::Top, SideMeta::new(Point::new(52, 48), Dimensions::new(4, 4)));
-                left_arm_outer_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(56, 48), Dimensions::new(4, 4)));
-            },
-            ModelType::Slim => {
-                left_arm_outer_helper.insert(CubeSide::Front, SideMeta::new(Point::new(52, 52), Dimensions::new(3, 12)));
-                left_arm_outer_helper.insert(CubeSide::Left, SideMeta::new(Point::new(55, 52), Dimensions::new(4, 12)));
-                left_arm_outer_helper.insert(CubeSide::Back, SideMeta::new(Point::new(59, 52), Dimensions::new(3, 12)));
-                left_arm_outer_helper.insert(CubeSide::Right, SideMeta::new(Point::new(48, 52), Dimensions::new(4, 12)));
-                left_arm_outer_helper.insert(CubeSide::Top, SideMeta::new(Point::new(52, 48), Dimensions::new(3, 4)));
-                left_arm_outer_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(55, 48), Dimensions::new(3, 4)));
-            }
-        }
+    #[test]
+    fn hd_skins_keep_every_pixel() {
+        assert_eq!(texture_layout(256, 256).unwrap(), (TextureType::Normal, 4));
+        assert_eq!(texture_layout(128, 64).unwrap(), (TextureType::Legacy, 2));
+        assert!(texture_layout(96, 96).is_err());
 
-        let mut right_leg_outer_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            right_leg_outer_helper.insert(CubeSide::Front, SideMeta::new(Point::new(4, 36), Dimensions::new(4, 12)));
-            right_leg_outer_helper.insert(CubeSide::Left, SideMeta::new(Point::new(8, 36), Dimensions::new(4, 12)));
-            right_leg_outer_helper.insert(CubeSide::Back, SideMeta::new(Point::new(12, 36), Dimensions::new(4, 12)));
-            right_leg_outer_helper.insert(CubeSide::Right, SideMeta::new(Point::new(0, 36), Dimensions::new(4, 12)));
-            right_leg_outer_helper.insert(CubeSide::Top, SideMeta::new(Point::new(4, 32), Dimensions::new(4, 4)));
-            right_leg_outer_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(8, 32), Dimensions::new(4, 4)));
-        }
+        let image = RgbaImage::from_fn(128, 128, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let parser = SkinParser::with_resolution(&ModelType::Slim, TextureType::Normal, 2);
+        let color_map = parser.load_from_bytes(&DynamicImage::ImageRgba8(image.clone())).unwrap();
+        assert_eq!(color_map[&BodyPart::Head][&CubeSide::Front].len(), 16 * 16);
 
-        let mut left_leg_outer_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            left_leg_outer_helper.insert(CubeSide::Front, SideMeta::new(Point::new(4, 52), Dimensions::new(4, 12)));
-            left_leg_outer_helper.insert(CubeSide::Left, SideMeta::new(Point::new(8, 52), Dimensions::new(4, 12)));
-            left_leg_outer_helper.insert(CubeSide::Back, SideMeta::new(Point::new(12, 52), Dimensions::new(4, 12)));
-            left_leg_outer_helper.insert(CubeSide::Right, SideMeta::new(Point::new(0, 52), Dimensions::new(4, 12)));
-            left_leg_outer_helper.insert(CubeSide::Top, SideMeta::new(Point::new(4, 48), Dimensions::new(4, 4)));
-            left_leg_outer_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(8, 48), Dimensions::new(4, 4)));
+        let mut exported = RgbaImage::new(128, 128);
+        for (body_part, sides) in &color_map {
+            let cells: Vec<Rgba<u8>> = sides.values().flatten().copied().collect();
+            parser.export_cells(body_part, &mut exported, &cells);
         }
-
-        helper_map.insert(BodyPart::Head, head_helper);
-        helper_map.insert(BodyPart::Torso, torso_helper);
-        helper_map.insert(BodyPart::RightArm, right_arm_helper);
-        helper_map.insert(BodyPart::LeftArm, left_arm_helper);
-        helper_map.insert(BodyPart::RightLeg, right_leg_helper);
-        helper_map.insert(BodyPart::LeftLeg, left_leg_helper);
-
-        helper_map.insert(BodyPart::HeadOuter, head_outer_helper);
-        helper_map.insert(BodyPart::TorsoOuter, torso_outer_helper);
-        helper_map.insert(BodyPart::RightArmOuter, right_arm_outer_helper);
-        helper_map.insert(BodyPart::LeftArmOuter, left_arm_outer_helper);
-        helper_map.insert(BodyPart::RightLegOuter, right_leg_outer_helper);
-        helper_map.insert(BodyPart::LeftLegOuter, left_leg_outer_helper);
-
-        helper_map
+        // The front of the head, 8 pixels in from the corner of the texture at 2x
+        assert_eq!(exported.get_pixel(16, 16), image.get_pixel(16, 16));
+        assert_eq!(exported.get_pixel(47, 47), image.get_pixel(47, 47));
     }
 
-    fn generate_helper_map_legacy(model_type: &ModelType) -> HelperMap {
-        let mut helper_map: HelperMap = HashMap::new();
-
-        let head_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::from({
-            [(CubeSide::Front, SideMeta::new(Point::new(8, 8), Dimensions::new(8, 8))),
-                (CubeSide::Left, SideMeta::new(Point::new(16, 8), Dimensions::new(8, 8))),
-                (CubeSide::Back, SideMeta::new(Point::new(24, 8), Dimensions::new(8, 8))),
-                (CubeSide::Right, SideMeta::new(Point::new(0, 8), Dimensions::new(8, 8))),
-                (CubeSide::Top, SideMeta::new(Point::new(8, 0), Dimensions::new(8, 8))),
-                (CubeSide::Bottom, SideMeta::new(Point::new(16, 0), Dimensions::new(8, 8)))]
-        });
-
-        let mut torso_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            torso_helper.insert(CubeSide::Front, SideMeta::new(Point::new(20, 20), Dimensions::new(8, 12)));
-            torso_helper.insert(CubeSide::Left, SideMeta::new(Point::new(28, 20), Dimensions::new(4, 12)));
-            torso_helper.insert(CubeSide::Back, SideMeta::new(Point::new(32, 20), Dimensions::new(8, 12)));
-            torso_helper.insert(CubeSide::Right, SideMeta::new(Point::new(16, 20), Dimensions::new(4, 12)));
-            torso_helper.insert(CubeSide::Top, SideMeta::new(Point::new(20, 16), Dimensions::new(8, 4)));
-            torso_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(28, 16), Dimensions::new(8, 4)));
-        }
-
-        let mut right_arm_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        match model_type {
-            ModelType::Classic => {
-                right_arm_helper.insert(CubeSide::Front, SideMeta::new(Point::new(44, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Left, SideMeta::new(Point::new(48, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Back, SideMeta::new(Point::new(52, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Right, SideMeta::new(Point::new(40, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Top, SideMeta::new(Point::new(44, 16), Dimensions::new(4, 4)));
-                right_arm_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(48, 16), Dimensions::new(4, 4)));
-            },
-            ModelType::Slim => {
-                right_arm_helper.insert(CubeSide::Front, SideMeta::new(Point::new(44, 20), Dimensions::new(3, 12)));
-                right_arm_helper.insert(CubeSide::Left, SideMeta::new(Point::new(47, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Back, SideMeta::new(Point::new(51, 20), Dimensions::new(3, 12)));
-                right_arm_helper.insert(CubeSide::Right, SideMeta::new(Point::new(40, 20), Dimensions::new(4, 12)));
-                right_arm_helper.insert(CubeSide::Top, SideMeta::new(Point::new(44, 16), Dimensions::new(3, 4)));
-                right_arm_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(47, 16), Dimensions::new(3, 4)));
-            }
-        }
-
-        let mut left_arm_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        match model_type {
-            ModelType::Classic => {
-                left_arm_helper.insert(CubeSide::Front, SideMeta::new(Point::new(44, 20), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Left, SideMeta::new(Point::new(40, 20), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Back, SideMeta::new(Point::new(52, 20), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Right, SideMeta::new(Point::new(48, 20), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Top, SideMeta::new(Point::new(44, 16), Dimensions::new(4, 4)));
-                left_arm_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(48, 16), Dimensions::new(4, 4)));
-            },
-            ModelType::Slim => {
-                left_arm_helper.insert(CubeSide::Front, SideMeta::new(Point::new(44, 20), Dimensions::new(3, 12)));
-                left_arm_helper.insert(CubeSide::Left, SideMeta::new(Point::new(40, 20), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Back, SideMeta::new(Point::new(51, 20), Dimensions::new(3, 12)));
-                left_arm_helper.insert(CubeSide::Right, SideMeta::new(Point::new(47, 20), Dimensions::new(4, 12)));
-                left_arm_helper.insert(CubeSide::Top, SideMeta::new(Point::new(44, 16), Dimensions::new(3, 4)));
-                left_arm_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(47, 16), Dimensions::new(3, 4)));
-            }
-        }
-
-        let mut right_leg_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            right_leg_helper.insert(CubeSide::Front, SideMeta::new(Point::new(4, 20), Dimensions::new(4, 12)));
-            right_leg_helper.insert(CubeSide::Left, SideMeta::new(Point::new(8, 20), Dimensions::new(4, 12)));
-            right_leg_helper.insert(CubeSide::Back, SideMeta::new(Point::new(12, 20), Dimensions::new(4, 12)));
-            right_leg_helper.insert(CubeSide::Right, SideMeta::new(Point::new(0, 20), Dimensions::new(4, 12)));
-            right_leg_helper.insert(CubeSide::Top, SideMeta::new(Point::new(4, 16), Dimensions::new(4, 4)));
-            right_leg_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(8, 16), Dimensions::new(4, 4)));
-        }
-
-        let mut left_leg_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            left_leg_helper.insert(CubeSide::Front, SideMeta::new(Point::new(4, 20), Dimensions::new(4, 12)));
-            left_leg_helper.insert(CubeSide::Left, SideMeta::new(Point::new(0, 20), Dimensions::new(4, 12)));
-            left_leg_helper.insert(CubeSide::Back, SideMeta::new(Point::new(12, 20), Dimensions::new(4, 12)));
-            left_leg_helper.insert(CubeSide::Right, SideMeta::new(Point::new(8, 20), Dimensions::new(4, 12)));
-            left_leg_helper.insert(CubeSide::Top, SideMeta::new(Point::new(4, 16), Dimensions::new(4, 4)));
-            left_leg_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(8, 16), Dimensions::new(4, 4)));
-        }
-
-        let mut head_outer_helper: BTreeMap<CubeSide, SideMeta> = BTreeMap::new();
-        {
-            head_outer_helper.insert(CubeSide::Front, SideMeta::new(Point::new(40, 8), Dimensions::new(8, 8)));
-            head_outer_helper.insert(CubeSide::Left, SideMeta::new(Point::new(48, 8), Dimensions::new(8, 8)));
-            head_outer_helper.insert(CubeSide::Back, SideMeta::new(Point::new(56, 8), Dimensions::new(8, 8)));
-            head_outer_helper.insert(CubeSide::Right, SideMeta::new(Point::new(32, 8), Dimensions::new(8, 8)));
-            head_outer_helper.insert(CubeSide::Top, SideMeta::new(Point::new(40, 0), Dimensions::new(8, 8)));
-            head_outer_helper.insert(CubeSide::Bottom, SideMeta::new(Point::new(48, 0), Dimensions::new(8, 8)));
-        }
-
-        helper_map.insert(BodyPart::Head, head_helper);
-        helper_map.insert(BodyPart::Torso, torso_helper);
-        helper_map.insert(BodyPart::RightArm, right_arm_helper);
-        helper_map.insert(BodyPart::LeftArm, left_arm_helper);
-        helper_map.insert(BodyPart::RightLeg, right_leg_helper.clone());
-        helper_map.insert(BodyPart::LeftLeg, left_leg_helper);
-
-        helper_map.insert(BodyPart::HeadOuter, head_outer_helper);
-
-        helper_map
+    #[test]
+    fn legacy_left_limbs_mirror_the_right_ones() {
+        let image = RgbaImage::from_fn(64, 32, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let parser = SkinParser::new(&ModelType::Classic, TextureType::Legacy);
+        let color_map = parser.load_from_bytes(&DynamicImage::ImageRgba8(image.clone())).unwrap();
+        assert!(!color_map.contains_key(&BodyPart::TorsoOuter));
+
+        // The right side of the left arm is the left side of the right arm, flipped
+        let right_arm_left = &color_map[&BodyPart::RightArm][&CubeSide::Left];
+        let left_arm_right = &color_map[&BodyPart::LeftArm][&CubeSide::Right];
+        assert_eq!(left_arm_right[0], right_arm_left[3]);
+        assert_eq!(left_arm_right[0], Rgba([51, 20, 0, 255]));
+
+        let mut exported = RgbaImage::new(64, 32);
+        let cells: Vec<Rgba<u8>> = color_map[&BodyPart::LeftLeg].values().flatten().copied().collect();
+        parser.export_cells(&BodyPart::LeftLeg, &mut exported, &cells);
+        assert_eq!(exported.get_pixel(0, 20), image.get_pixel(0, 20));
+        assert_eq!(exported.get_pixel(7, 31), image.get_pixel(7, 31));
     }
-
-
-}
-
-#[test]
-fn test() {
 }
 
 struct Texture;
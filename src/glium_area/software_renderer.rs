@@ -0,0 +1,442 @@
+use std::collections::BTreeSet;
+
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
+use nalgebra_glm as glm;
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::camera::Camera;
+use crate::glium_area::renderer::part_geometry_at;
+use crate::glium_area::skin_parser::{texture_layout, ModelType, SkinParser, TextureLoadError};
+
+/// Direction the light comes from, in view space (upper left, slightly in front).
+const LIGHT_DIRECTION: glm::Vec3 = glm::Vec3::new(-0.35, 0.6, 0.72);
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Projection {
+    Orthographic,
+    /// Perspective as seen from the camera position.
+    Perspective,
+}
+
+/// How the limbs are turned. Parts rotate around their shoulder or hip like in the game.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Pose {
+    #[default]
+    Standing,
+    /// Mid stride, with the arms swinging against the legs.
+    Walking,
+    /// Right arm raised to the side.
+    Waving,
+    /// Walking with the arms and legs swung by the given degrees, for animations.
+    Stride(f32),
+}
+
+impl Pose {
+    pub const ALL: [Pose; 3] = [Pose::Standing, Pose::Walking, Pose::Waving];
+
+    /// Degrees around the x (forward swing) and z (sideways) axis.
+    fn joint_angles(self, body_part: BodyPart) -> Option<(f32, f32)> {
+        let swing = match self {
+            Pose::Walking => 25.0,
+            Pose::Stride(swing) => swing,
+            _ => 0.0,
+        };
+        match (self, body_part) {
+            (Pose::Walking | Pose::Stride(_), BodyPart::RightArm | BodyPart::RightArmOuter | BodyPart::LeftLeg | BodyPart::LeftLegOuter) => Some((swing, 0.0)),
+            (Pose::Walking | Pose::Stride(_), BodyPart::LeftArm | BodyPart::LeftArmOuter | BodyPart::RightLeg | BodyPart::RightLegOuter) => Some((-swing, 0.0)),
+            (Pose::Waving, BodyPart::RightArm | BodyPart::RightArmOuter) => Some((0.0, -150.0)),
+            (Pose::Waving, BodyPart::LeftArm | BodyPart::LeftArmOuter) => Some((0.0, 5.0)),
+            _ => None,
+        }
+    }
+
+    /// Transform of a part turning around `pivot`, applied after the part placement.
+    fn part_matrix(self, body_part: BodyPart, pivot: &glm::Vec3) -> glm::Mat4 {
+        let Some((swing, spread)) = self.joint_angles(body_part) else {
+            return glm::Mat4::identity();
+        };
+
+        let mut matrix = glm::translate(&glm::Mat4::identity(), pivot);
+        matrix = glm::rotate_z(&matrix, spread.to_radians());
+        matrix = glm::rotate_x(&matrix, swing.to_radians());
+        glm::translate(&matrix, &-pivot)
+    }
+}
+
+/// Everything that describes one offscreen render of a skin.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Degrees, same convention as `Camera`.
+    pub yaw: f32,
+    pub pitch: f32,
+    pub projection: Projection,
+    pub parts: BTreeSet<BodyPart>,
+    pub pose: Pose,
+    /// Only geometry above this height (model units) is used to frame the picture,
+    /// everything below may fall outside of the image. `None` frames the whole model.
+    pub frame_above: Option<f32>,
+    /// Frames the standing model as seen from any yaw, so that it keeps its size and
+    /// place in all frames of an animation. Overrides `frame_above`.
+    pub steady_frame: bool,
+    /// Fraction of the image kept free around the model.
+    pub margin: f32,
+    /// 0.0 renders flat colors like the editor, 1.0 is full directional shading.
+    pub lighting: f32,
+    pub background: Option<Rgba<u8>>,
+    /// Renders at `supersample` times the size and scales down for smooth edges.
+    pub supersample: u32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            width: 128,
+            height: 128,
+            yaw: 25.0,
+            pitch: 10.0,
+            projection: Projection::Orthographic,
+            parts: BodyPart::PLAYER.into_iter().collect(),
+            pose: Pose::Standing,
+            frame_above: None,
+            steady_frame: false,
+            margin: 0.05,
+            lighting: 0.35,
+            background: None,
+            supersample: 2,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_angles(mut self, yaw: f32, pitch: f32) -> Self {
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self
+    }
+
+    /// Head, torso and the upper half of the arms.
+    pub fn bust(mut self) -> Self {
+        self.parts.retain(|part| !matches!(
+            *part,
+            BodyPart::RightLeg | BodyPart::LeftLeg | BodyPart::RightLegOuter | BodyPart::LeftLegOuter
+        ));
+        self.frame_above = Some(0.0);
+        self
+    }
+}
+
+struct Quad {
+    corners: [glm::Vec3; 4],
+    color: [f32; 4],
+}
+
+/// Renders skins without a GL context, e.g. for thumbnails and image exports.
+/// Uses the same meshes and part placement as `Renderer`.
+pub fn render_skin(image: &DynamicImage, model_type: &ModelType, options: &RenderOptions) -> Result<RgbaImage, TextureLoadError> {
+    let (texture_type, resolution) = texture_layout(image.width(), image.height())?;
+    let color_map = SkinParser::with_resolution(model_type, texture_type, resolution).load_from_bytes(image)?;
+
+    let mut camera = Camera::new();
+    camera.set_yaw_and_pitch(options.yaw, options.pitch);
+    let rotation = camera.get_rotation_matrix();
+
+    let mut inner = vec![];
+    let mut outer = vec![];
+    let mut frame_points = vec![];
+
+    for &body_part in &options.parts {
+        let Some(sides) = color_map.get(&body_part) else { continue };
+        let geometry = part_geometry_at(body_part, model_type, resolution);
+        let placement = glm::translate(&glm::Mat4::identity(), &geometry.translation)
+            * glm::scale(&glm::Mat4::identity(), &geometry.scale);
+        let model_matrix = options.pose.part_matrix(body_part, &geometry.pivot) * placement;
+        let center = (rotation * model_matrix * glm::vec4(0.0, 0.0, 0.0, 1.0)).xyz();
+
+        let colors = sides.values().flatten();
+        for (cell, pixel) in geometry.vertexes.chunks(4).zip(colors) {
+            if pixel[3] == 0 {
+                continue;
+            }
+            let mut corners = [glm::Vec3::zeros(); 4];
+            for (corner, vertex) in corners.iter_mut().zip(cell) {
+                let position = glm::vec4(vertex.position[0], vertex.position[1], vertex.position[2], 1.0);
+                let world = model_matrix * position;
+                let view = rotation * world;
+                if options.steady_frame {
+                    frame_points.extend(steady_frame_points(&(placement * position).xyz(), camera.pitch));
+                } else if options.frame_above.is_none_or(|min_y| world.y >= min_y) {
+                    frame_points.push(project(&view.xyz(), &camera, options.projection));
+                }
+                *corner = view.xyz();
+            }
+            let color = [
+                f32::from(pixel[0]) / 255.0,
+                f32::from(pixel[1]) / 255.0,
+                f32::from(pixel[2]) / 255.0,
+                f32::from(pixel[3]) / 255.0,
+            ];
+            let quad = shade(Quad { corners, color }, &center, &camera, options);
+            match quad {
+                Some(quad) if body_part.is_outer() => outer.push(quad),
+                Some(quad) => inner.push(quad),
+                None => {}
+            }
+        }
+    }
+
+    Ok(draw_quads(&inner, &outer, &frame_points, &camera, options))
+}
+
+/// A colored cell of a model other than the player, in model units.
+pub struct Facet {
+    pub corners: [glm::Vec3; 4],
+    pub color: Rgba<u8>,
+    /// Center of the cuboid of the facet, faces are lit as seen from outside of it.
+    pub center: glm::Vec3,
+    /// Drawn after the others like the outer layer, so that it blends over them.
+    pub outer: bool,
+}
+
+/// Renders the cells of any model, framing all of them. Poses do not apply.
+pub fn render_facets(facets: &[Facet], options: &RenderOptions) -> RgbaImage {
+    let mut camera = Camera::new();
+    camera.set_yaw_and_pitch(options.yaw, options.pitch);
+    let rotation = camera.get_rotation_matrix();
+    let to_view = |point: &glm::Vec3| (rotation * glm::vec4(point.x, point.y, point.z, 1.0)).xyz();
+
+    let mut inner = vec![];
+    let mut outer = vec![];
+    let mut frame_points = vec![];
+    for facet in facets {
+        if facet.color[3] == 0 {
+            continue;
+        }
+        let corners = facet.corners.map(|corner| to_view(&corner));
+        frame_points.extend(corners.iter().map(|corner| project(corner, &camera, options.projection)));
+        let color = facet.color.0.map(|channel| f32::from(channel) / 255.0);
+        match shade(Quad { corners, color }, &to_view(&facet.center), &camera, options) {
+            Some(quad) if facet.outer => outer.push(quad),
+            Some(quad) => inner.push(quad),
+            None => {}
+        }
+    }
+
+    draw_quads(&inner, &outer, &frame_points, &camera, options)
+}
+
+fn draw_quads(inner: &[Quad], outer: &[Quad], frame_points: &[glm::Vec3], camera: &Camera, options: &RenderOptions) -> RgbaImage {
+    let mut target = Target::new(options.width * options.supersample.max(1), options.height * options.supersample.max(1));
+    let Some(fit) = Fit::new(frame_points, target.width, target.height, options.margin) else {
+        return finish(target, options);
+    };
+
+    for quad in inner.iter().chain(outer) {
+        let projected = quad.corners.map(|corner| fit.apply(project(&corner, camera, options.projection)));
+        target.draw_triangle([projected[1], projected[0], projected[2]], quad.color);
+        target.draw_triangle([projected[2], projected[0], projected[3]], quad.color);
+    }
+
+    finish(target, options)
+}
+
+/// Where a point of the standing model can appear when the model turns around the
+/// vertical axis, at the extremes of its circle.
+fn steady_frame_points(world: &glm::Vec3, pitch: f32) -> [glm::Vec3; 4] {
+    let radius = world.xz().norm();
+    let (sin, cos) = pitch.to_radians().sin_cos();
+    let y = world.y * cos;
+    let spread = radius * sin.abs();
+    [
+        glm::vec3(-radius, y - spread, 0.0),
+        glm::vec3(radius, y - spread, 0.0),
+        glm::vec3(-radius, y + spread, 0.0),
+        glm::vec3(radius, y + spread, 0.0),
+    ]
+}
+
+/// Screen position (x right, y up) and depth (bigger is closer) of a view space point.
+fn project(point: &glm::Vec3, camera: &Camera, projection: Projection) -> glm::Vec3 {
+    match projection {
+        Projection::Orthographic => *point,
+        Projection::Perspective => {
+            let distance = (camera.position.z - point.z).max(0.01);
+            let factor = camera.position.z / distance;
+            glm::vec3(point.x * factor, point.y * factor, point.z)
+        }
+    }
+}
+
+/// Applies the directional light. Faces seen from behind are lit like front faces,
+/// so the inside of the outer layer looks the same as in the editor.
+fn shade(mut quad: Quad, part_center: &glm::Vec3, camera: &Camera, options: &RenderOptions) -> Option<Quad> {
+    let [a, b, c, d] = quad.corners;
+    let face_center = (a + b + c + d) / 4.0;
+    let mut normal = (b - a).cross(&(d - a));
+    if normal.norm() == 0.0 {
+        return None;
+    }
+    normal = normal.normalize();
+    if normal.dot(&(face_center - part_center)) < 0.0 {
+        normal = -normal;
+    }
+
+    let to_camera = match options.projection {
+        Projection::Orthographic => glm::vec3(0.0, 0.0, 1.0),
+        Projection::Perspective => camera.position - face_center,
+    };
+    if normal.dot(&to_camera) <= 0.0 {
+        normal = -normal;
+    }
+
+    let lambert = normal.dot(&LIGHT_DIRECTION.normalize()).max(0.0);
+    let brightness = 1.0 - options.lighting.clamp(0.0, 1.0) * (1.0 - lambert);
+    for channel in &mut quad.color[..3] {
+        *channel *= brightness;
+    }
+    Some(quad)
+}
+
+/// Scales and centers projected points so that the framed ones fill the image.
+struct Fit {
+    scale: f32,
+    offset: glm::Vec2,
+    height: f32,
+}
+
+impl Fit {
+    fn new(points: &[glm::Vec3], width: u32, height: u32, margin: f32) -> Option<Self> {
+        let first = points.first()?;
+        let (mut min, mut max) = (first.xy(), first.xy());
+        for point in points {
+            min = glm::min2(&min, &point.xy());
+            max = glm::max2(&max, &point.xy());
+        }
+        let size = max - min;
+        let available_width = width as f32 * (1.0 - 2.0 * margin);
+        let available_height = height as f32 * (1.0 - 2.0 * margin);
+        let scale = (available_width / size.x.max(f32::EPSILON)).min(available_height / size.y.max(f32::EPSILON));
+
+        let center = (min + max) / 2.0;
+        let offset = glm::vec2(width as f32 / 2.0, height as f32 / 2.0) - center * scale;
+        Some(Fit { scale, offset, height: height as f32 })
+    }
+
+    /// Pixel coordinates with y pointing down.
+    fn apply(&self, point: glm::Vec3) -> glm::Vec3 {
+        let x = point.x * self.scale + self.offset.x;
+        let y = self.height - (point.y * self.scale + self.offset.y);
+        glm::vec3(x, y, point.z)
+    }
+}
+
+/// Premultiplied color buffer with a depth buffer.
+struct Target {
+    width: u32,
+    height: u32,
+    color: Vec<[f32; 4]>,
+    depth: Vec<f32>,
+}
+
+impl Target {
+    fn new(width: u32, height: u32) -> Self {
+        let size = (width * height) as usize;
+        Target { width, height, color: vec![[0.0; 4]; size], depth: vec![f32::NEG_INFINITY; size] }
+    }
+
+    fn draw_triangle(&mut self, vertices: [glm::Vec3; 3], color: [f32; 4]) {
+        let [a, b, c] = vertices;
+        let area = edge(&a, &b, &c);
+        if area.abs() < f32::EPSILON {
+            return;
+        }
+
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as u32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as u32;
+        let max_x = (a.x.max(b.x).max(c.x).ceil() as u32).min(self.width);
+        let max_y = (a.y.max(b.y).max(c.y).ceil() as u32).min(self.height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let point = glm::vec3(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+                let w0 = edge(&b, &c, &point) / area;
+                let w1 = edge(&c, &a, &point) / area;
+                let w2 = edge(&a, &b, &point) / area;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                let depth = w0 * a.z + w1 * b.z + w2 * c.z;
+                let index = (y * self.width + x) as usize;
+                if depth < self.depth[index] {
+                    continue;
+                }
+                self.depth[index] = depth;
+
+                let alpha = color[3];
+                let destination = &mut self.color[index];
+                for channel in 0..3 {
+                    destination[channel] = color[channel] * alpha + destination[channel] * (1.0 - alpha);
+                }
+                destination[3] = alpha + destination[3] * (1.0 - alpha);
+            }
+        }
+    }
+}
+
+fn edge(a: &glm::Vec3, b: &glm::Vec3, point: &glm::Vec3) -> f32 {
+    (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x)
+}
+
+/// Scales the supersampled buffer down and puts it over the background.
+fn finish(target: Target, options: &RenderOptions) -> RgbaImage {
+    let factor = options.supersample.max(1);
+    let samples = (factor * factor) as f32;
+    let background = options.background.map(|color| [
+        f32::from(color[0]) / 255.0,
+        f32::from(color[1]) / 255.0,
+        f32::from(color[2]) / 255.0,
+        f32::from(color[3]) / 255.0,
+    ]);
+
+    ImageBuffer::from_fn(options.width, options.height, |x, y| {
+        let mut sum = [0.0f32; 4];
+        for sample_y in 0..factor {
+            for sample_x in 0..factor {
+                let index = ((y * factor + sample_y) * target.width + x * factor + sample_x) as usize;
+                for channel in 0..4 {
+                    sum[channel] += target.color[index][channel];
+                }
+            }
+        }
+        let mut pixel = sum.map(|value| value / samples);
+
+        if let Some(background) = background {
+            let alpha = pixel[3];
+            for channel in 0..3 {
+                pixel[channel] += background[channel] * background[3] * (1.0 - alpha);
+            }
+            pixel[3] = alpha + background[3] * (1.0 - alpha);
+        }
+
+        // back from premultiplied alpha
+        let alpha = pixel[3];
+        if alpha <= 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let to_u8 = |value: f32| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+        Rgba([
+            to_u8(pixel[0] / alpha),
+            to_u8(pixel[1] / alpha),
+            to_u8(pixel[2] / alpha),
+            to_u8(alpha),
+        ])
+    })
+}
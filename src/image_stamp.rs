@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::cube_side::CubeSide;
+use crate::glium_area::skin_parser::{texture_layout, ModelType, SkinParser, TextureLoadError, TextureType};
+
+/// Colors of the skin offered as a palette, most used first.
+pub const PALETTE_SIZE: usize = 32;
+
+/// Threshold map of ordered dithering, in sixteenths.
+pub(crate) const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// How the picture is brought to the size of the region.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Fit {
+    /// The whole picture, with empty bands along the longer sides of the region.
+    #[default]
+    Contain,
+    /// The whole region, cutting the picture where it sticks out.
+    Cover,
+    Stretch,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Dither {
+    #[default]
+    None,
+    Ordered,
+    FloydSteinberg,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct StampOptions {
+    /// Part of the picture to use as x, y, width and height, the whole picture when `None`.
+    pub crop: Option<[u32; 4]>,
+    pub fit: Fit,
+    /// Colors the stamp is limited to, dithering only applies with a palette.
+    pub palette: Option<Vec<Rgba<u8>>>,
+    pub dither: Dither,
+}
+
+/// Where a picture is stamped: a face of a body part, or a rectangle of its cells.
+#[derive(Clone, Copy, Debug)]
+pub struct StampTarget {
+    pub body_part: BodyPart,
+    pub side: CubeSide,
+    /// Cells of the face as x, y, width and height, the whole face when `None`.
+    pub region: Option<[u32; 4]>,
+}
+
+/// A picture stamped on a skin.
+pub struct Stamp {
+    /// The skin with the picture on it.
+    pub image: RgbaImage,
+    /// Painted cells of the body part as cell index and color. Cells the picture leaves
+    /// transparent are kept as they were.
+    pub cells: Vec<(usize, Rgba<u8>)>,
+    /// Texture area of the face as x, y, width and height.
+    pub face: [u32; 4],
+}
+
+pub fn stamp(
+    skin: &RgbaImage,
+    model_type: &ModelType,
+    target: &StampTarget,
+    picture: &RgbaImage,
+    options: &StampOptions,
+) -> Result<Stamp, TextureLoadError> {
+    let (_, face) = face_region(skin, model_type, target.body_part, target.side)?;
+    let [_, _, width, height] = clamp_region(target.region.unwrap_or([0, 0, face[2], face[3]]), face[2], face[3]);
+    paint_face(skin, model_type, target, &prepare(picture, width, height, options))
+}
+
+/// Puts pixels sized for the target on the skin as they are, skipping transparent ones.
+pub fn paint_face(
+    skin: &RgbaImage,
+    model_type: &ModelType,
+    target: &StampTarget,
+    pixels: &RgbaImage,
+) -> Result<Stamp, TextureLoadError> {
+    let (first_cell, face) = face_region(skin, model_type, target.body_part, target.side)?;
+    let [x, y, width, height] = clamp_region(target.region.unwrap_or([0, 0, face[2], face[3]]), face[2], face[3]);
+
+    let mut image = skin.clone();
+    let mut cells = vec![];
+    for (pixel_x, pixel_y, pixel) in pixels.enumerate_pixels() {
+        if pixel[3] == 0 || pixel_x >= width || pixel_y >= height {
+            continue;
+        }
+        let (face_x, face_y) = (x + pixel_x, y + pixel_y);
+        image.put_pixel(face[0] + face_x, face[1] + face_y, *pixel);
+        cells.push((first_cell + (face_y * face[2] + face_x) as usize, *pixel));
+    }
+    Ok(Stamp { image, cells, face })
+}
+
+/// Index of the first cell of a face and its texture area as x, y, width and height.
+pub fn face_region(
+    skin: &RgbaImage,
+    model_type: &ModelType,
+    body_part: BodyPart,
+    side: CubeSide,
+) -> Result<(usize, [u32; 4]), TextureLoadError> {
+    let (_, resolution) = texture_layout(skin.width(), skin.height())?;
+    let parser = SkinParser::with_resolution(model_type, TextureType::Normal, resolution);
+    let mut first_cell = 0;
+    for (region_side, region) in parser.side_regions(&body_part) {
+        if region_side == side {
+            return Ok((first_cell, region));
+        }
+        first_cell += (region[2] * region[3]) as usize;
+    }
+    unreachable!("Every body part has six sides")
+}
+
+/// The face a cell of a body part is on.
+pub fn cell_side(model_type: &ModelType, resolution: u32, body_part: BodyPart, cell_index: usize) -> Option<CubeSide> {
+    let parser = SkinParser::with_resolution(model_type, TextureType::Normal, resolution);
+    let mut first_cell = 0;
+    for (side, [_, _, width, height]) in parser.side_regions(&body_part) {
+        first_cell += (width * height) as usize;
+        if cell_index < first_cell {
+            return Some(side);
+        }
+    }
+    None
+}
+
+fn clamp_region([x, y, width, height]: [u32; 4], face_width: u32, face_height: u32) -> [u32; 4] {
+    let x = x.min(face_width - 1);
+    let y = y.min(face_height - 1);
+    [x, y, width.clamp(1, face_width - x), height.clamp(1, face_height - y)]
+}
+
+/// Crops, fits and maps the picture to the palette. Pixels are either opaque or fully
+/// transparent, like the game draws them.
+pub fn prepare(picture: &RgbaImage, width: u32, height: u32, options: &StampOptions) -> RgbaImage {
+    let [crop_x, crop_y, crop_width, crop_height] = options.crop.unwrap_or([0, 0, picture.width(), picture.height()]);
+    let crop_x = crop_x.min(picture.width() - 1);
+    let crop_y = crop_y.min(picture.height() - 1);
+    let cropped = imageops::crop_imm(
+        picture,
+        crop_x,
+        crop_y,
+        crop_width.clamp(1, picture.width() - crop_x),
+        crop_height.clamp(1, picture.height() - crop_y),
+    ).to_image();
+
+    let mut fitted = fit(&cropped, width, height, options.fit);
+    for pixel in fitted.pixels_mut() {
+        pixel[3] = if pixel[3] < 128 { 0 } else { 255 };
+    }
+    match &options.palette {
+        Some(palette) if !palette.is_empty() => map_to_palette(&fitted, palette, options.dither),
+        _ => fitted,
+    }
+}
+
+fn fit(picture: &RgbaImage, width: u32, height: u32, fit: Fit) -> RgbaImage {
+    let scale_x = width as f32 / picture.width() as f32;
+    let scale_y = height as f32 / picture.height() as f32;
+    let scale = match fit {
+        Fit::Stretch => return imageops::resize(picture, width, height, FilterType::Triangle),
+        Fit::Contain => scale_x.min(scale_y),
+        Fit::Cover => scale_x.max(scale_y),
+    };
+    let scaled_width = ((picture.width() as f32 * scale).round() as u32).max(1);
+    let scaled_height = ((picture.height() as f32 * scale).round() as u32).max(1);
+    let scaled = imageops::resize(picture, scaled_width, scaled_height, FilterType::Triangle);
+
+    // Centered, the parts outside of the region are dropped
+    let mut fitted = RgbaImage::new(width, height);
+    let offset_x = (i64::from(width) - i64::from(scaled_width)) / 2;
+    let offset_y = (i64::from(height) - i64::from(scaled_height)) / 2;
+    imageops::replace(&mut fitted, &scaled, offset_x, offset_y);
+    fitted
+}
+
+/// The most used opaque colors of the skin, at most `limit` of them.
+pub fn skin_palette(skin: &RgbaImage, limit: usize) -> Vec<Rgba<u8>> {
+    let mut counts: HashMap<Rgba<u8>, usize> = HashMap::new();
+    for pixel in skin.pixels().filter(|pixel| pixel[3] == 255) {
+        *counts.entry(*pixel).or_default() += 1;
+    }
+    let mut colors: Vec<(Rgba<u8>, usize)> = counts.into_iter().collect();
+    colors.sort_by(|(color_a, count_a), (color_b, count_b)| count_b.cmp(count_a).then(color_a.0.cmp(&color_b.0)));
+    colors.into_iter().take(limit).map(|(color, _)| color).collect()
+}
+
+/// Replaces every opaque pixel with the nearest color of the palette.
+pub fn map_to_palette(image: &RgbaImage, palette: &[Rgba<u8>], dither: Dither) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let mut mapped = image.clone();
+    // Floyd–Steinberg carries the error of every pixel over to the ones not mapped yet
+    let mut errors = vec![[0.0f32; 3]; (width * height) as usize];
+    // Ordered dithering moves channels about as far as the palette colors are apart
+    let spread = 255.0 / (palette.len() as f32).cbrt();
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = image.get_pixel(x, y);
+            if pixel[3] == 0 {
+                continue;
+            }
+            let index = (y * width + x) as usize;
+            let mut wanted = [0, 1, 2].map(|channel| f32::from(pixel[channel]) + errors[index][channel]);
+            if dither == Dither::Ordered {
+                let threshold = (f32::from(BAYER_4X4[(y % 4) as usize][(x % 4) as usize]) + 0.5) / 16.0 - 0.5;
+                wanted = wanted.map(|channel| channel + threshold * spread);
+            }
+            let nearest = nearest_color(wanted, palette);
+            mapped.put_pixel(x, y, Rgba([nearest[0], nearest[1], nearest[2], 255]));
+
+            if dither == Dither::FloydSteinberg {
+                let error = [0, 1, 2].map(|channel| wanted[channel] - f32::from(nearest[channel]));
+                for (dx, dy, weight) in [(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)] {
+                    let (next_x, next_y) = (x as i64 + dx, y as i64 + dy);
+                    if next_x < 0 || next_x >= i64::from(width) || next_y >= i64::from(height) {
+                        continue;
+                    }
+                    let next = (next_y as u32 * width + next_x as u32) as usize;
+                    for channel in 0..3 {
+                        errors[next][channel] += error[channel] * weight / 16.0;
+                    }
+                }
+            }
+        }
+    }
+    mapped
+}
+
+fn nearest_color(wanted: [f32; 3], palette: &[Rgba<u8>]) -> Rgba<u8> {
+    *palette
+        .iter()
+        .min_by(|a, b| distance(wanted, a).total_cmp(&distance(wanted, b)))
+        .expect("The palette is not empty")
+}
+
+fn distance(wanted: [f32; 3], color: &Rgba<u8>) -> f32 {
+    (0..3).map(|channel| (wanted[channel] - f32::from(color[channel])).powi(2)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::DynamicImage;
+
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    #[test]
+    fn pictures_fit_the_region() {
+        let wide = RgbaImage::from_pixel(20, 10, RED);
+        let contained = prepare(&wide, 8, 8, &StampOptions::default());
+        assert_eq!(contained.get_pixel(0, 1)[3], 0);
+        assert_eq!(*contained.get_pixel(0, 2), RED);
+        assert_eq!(*contained.get_pixel(7, 5), RED);
+        assert_eq!(contained.get_pixel(7, 6)[3], 0);
+
+        let covered = prepare(&wide, 8, 8, &StampOptions { fit: Fit::Cover, ..Default::default() });
+        assert!(covered.pixels().all(|pixel| *pixel == RED));
+
+        // Only the cropped half is used, stretched over the whole region
+        let mut halves = RgbaImage::from_pixel(20, 10, WHITE);
+        imageops::replace(&mut halves, &RgbaImage::from_pixel(10, 10, RED), 0, 0);
+        let options = StampOptions { crop: Some([0, 0, 10, 10]), fit: Fit::Stretch, ..Default::default() };
+        assert!(prepare(&halves, 4, 12, &options).pixels().all(|pixel| *pixel == RED));
+    }
+
+    #[test]
+    fn stamped_cells_are_the_ones_of_the_face() {
+        let skin = RgbaImage::from_pixel(64, 64, BLACK);
+        let target = StampTarget { body_part: BodyPart::Torso, side: CubeSide::Front, region: Some([2, 3, 4, 4]) };
+        let stamp = stamp(&skin, &ModelType::Classic, &target, &RgbaImage::from_pixel(4, 4, RED), &StampOptions::default()).unwrap();
+        assert_eq!(stamp.face, [20, 20, 8, 12]);
+        assert_eq!(stamp.cells.len(), 16);
+        assert_eq!(*stamp.image.get_pixel(22, 23), RED);
+        assert_eq!(*stamp.image.get_pixel(21, 23), BLACK);
+
+        let parser = SkinParser::new(&ModelType::Classic, TextureType::Normal);
+        let torso: Vec<Rgba<u8>> = parser
+            .load_from_bytes(&DynamicImage::ImageRgba8(stamp.image.clone()))
+            .unwrap()[&BodyPart::Torso]
+            .values()
+            .flatten()
+            .copied()
+            .collect();
+        for (cell_index, color) in &stamp.cells {
+            assert_eq!(torso[*cell_index], *color);
+        }
+        assert_eq!(torso.iter().filter(|pixel| **pixel == RED).count(), 16);
+        assert!(stamp.cells.iter().all(|(cell_index, _)| {
+            cell_side(&ModelType::Classic, 1, BodyPart::Torso, *cell_index) == Some(CubeSide::Front)
+        }));
+    }
+
+    #[test]
+    fn dithering_mixes_palette_colors() {
+        let gray = RgbaImage::from_pixel(8, 8, Rgba([128, 128, 128, 255]));
+        let palette = [BLACK, WHITE];
+        let count_white = |image: &RgbaImage| image.pixels().filter(|pixel| **pixel == WHITE).count();
+
+        let flat = map_to_palette(&gray, &palette, Dither::None);
+        assert_eq!(count_white(&flat), 64);
+        let ordered = map_to_palette(&gray, &palette, Dither::Ordered);
+        assert!((24..=40).contains(&count_white(&ordered)), "{}", count_white(&ordered));
+        let diffused = map_to_palette(&gray, &palette, Dither::FloydSteinberg);
+        assert!((28..=36).contains(&count_white(&diffused)), "{}", count_white(&diffused));
+
+        let skin = RgbaImage::from_fn(4, 4, |x, _| if x == 0 { RED } else { BLACK });
+        assert_eq!(skin_palette(&skin, 1), vec![BLACK]);
+    }
+}
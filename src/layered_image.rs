@@ -0,0 +1,655 @@
+use std::fs;
+use std::io::{self, Cursor, Read, Write};
+use std::path::Path;
+
+use image::{ImageError, ImageFormat, Rgba, RgbaImage};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::skin_parser::{texture_layout, ModelType, SkinParser, TextureLoadError, TextureType};
+use crate::utils::guess_model_type;
+
+const ORA_MIMETYPE: &str = "image/openraster";
+
+#[derive(Debug)]
+pub enum LayeredImageError {
+    Io(io::Error),
+    Zip(ZipError),
+    Image(ImageError),
+    TextureLoad(TextureLoadError),
+    InvalidFile(String),
+}
+
+impl From<io::Error> for LayeredImageError {
+    fn from(error: io::Error) -> Self {
+        LayeredImageError::Io(error)
+    }
+}
+
+impl From<ZipError> for LayeredImageError {
+    fn from(error: ZipError) -> Self {
+        LayeredImageError::Zip(error)
+    }
+}
+
+impl From<ImageError> for LayeredImageError {
+    fn from(error: ImageError) -> Self {
+        LayeredImageError::Image(error)
+    }
+}
+
+impl From<TextureLoadError> for LayeredImageError {
+    fn from(error: TextureLoadError) -> Self {
+        LayeredImageError::TextureLoad(error)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LayeredFormat {
+    /// OpenRaster, read and written by Krita, GIMP and MyPaint.
+    Ora,
+    /// Photoshop document with uncompressed layers.
+    Psd,
+}
+
+impl LayeredFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LayeredFormat::Ora => "ora",
+            LayeredFormat::Psd => "psd",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<LayeredFormat> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "ora" => Some(LayeredFormat::Ora),
+            "psd" => Some(LayeredFormat::Psd),
+            _ => None,
+        }
+    }
+}
+
+/// A layer of a layered image, placed at `x`, `y` on the canvas.
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub name: String,
+    pub image: RgbaImage,
+    pub x: i64,
+    pub y: i64,
+    pub opacity: f32,
+    pub visible: bool,
+}
+
+/// Skin flattened from a layered image.
+pub struct LayeredSkin {
+    pub image: RgbaImage,
+    pub model_type: ModelType,
+}
+
+/// One canvas-sized layer per body part, named after it, inner parts below the outer ones.
+pub fn part_layers(image: &RgbaImage, model_type: &ModelType) -> Result<Vec<Layer>, TextureLoadError> {
+    let (_, resolution) = texture_layout(image.width(), image.height())?;
+    let parser = SkinParser::with_resolution(model_type, TextureType::Normal, resolution);
+    let layers = BodyPart::PLAYER
+        .into_iter()
+        .map(|body_part| {
+            let mut layer = RgbaImage::new(image.width(), image.height());
+            for (_, [x, y, width, height]) in parser.side_regions(&body_part) {
+                for pixel_y in y..y + height {
+                    for pixel_x in x..x + width {
+                        layer.put_pixel(pixel_x, pixel_y, *image.get_pixel(pixel_x, pixel_y));
+                    }
+                }
+            }
+            Layer { name: body_part.label().to_string(), image: layer, x: 0, y: 0, opacity: 1.0, visible: true }
+        })
+        .collect();
+    Ok(layers)
+}
+
+pub fn export_layered(image: &RgbaImage, model_type: &ModelType, format: LayeredFormat, path: &Path) -> Result<(), LayeredImageError> {
+    let layers = part_layers(image, model_type)?;
+    let bytes = match format {
+        LayeredFormat::Ora => write_ora(&layers, image)?,
+        LayeredFormat::Psd => write_psd(&layers, image),
+    };
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Reads an `.ora` or `.psd` file and flattens its layers into a skin.
+pub fn import_layered(path: &Path) -> Result<LayeredSkin, LayeredImageError> {
+    let bytes = fs::read(path)?;
+    let (layers, width, height) = match LayeredFormat::from_path(path) {
+        Some(LayeredFormat::Ora) => read_ora(&bytes)?,
+        Some(LayeredFormat::Psd) => read_psd(&bytes)?,
+        None => return Err(LayeredImageError::InvalidFile(format!("{:?} is not a layered image", path))),
+    };
+    let image = flatten(&layers, width, height)?;
+    let model_type = guess_model_type(&image).model_type;
+    Ok(LayeredSkin { image, model_type })
+}
+
+/// Draws the visible layers from the bottom up. Layers named after a body part only
+/// paint that part, so strokes that spill over stay in their layer; other layers, like
+/// a shading pass, paint everywhere.
+pub fn flatten(layers: &[Layer], width: u32, height: u32) -> Result<RgbaImage, LayeredImageError> {
+    let (texture_type, resolution) = texture_layout(width, height)?;
+    if texture_type != TextureType::Normal {
+        return Err(LayeredImageError::InvalidFile(format!("{}x{} is not a 64x64 skin", width, height)));
+    }
+    let mut canvas = RgbaImage::new(width, height);
+    for layer in layers.iter().filter(|layer| layer.visible) {
+        let part_mask = BodyPart::PLAYER
+            .into_iter()
+            .find(|body_part| body_part.label() == layer.name)
+            .map(|body_part| part_mask(body_part, width, resolution));
+        for (layer_x, layer_y, pixel) in layer.image.enumerate_pixels() {
+            let (x, y) = (layer.x + i64::from(layer_x), layer.y + i64::from(layer_y));
+            if x < 0 || y < 0 || x >= i64::from(width) || y >= i64::from(height) {
+                continue;
+            }
+            let (x, y) = (x as u32, y as u32);
+            if part_mask.as_ref().is_some_and(|mask| !mask[(y * width + x) as usize]) {
+                continue;
+            }
+            let alpha = (f32::from(pixel[3]) * layer.opacity).round() as u8;
+            let below = *canvas.get_pixel(x, y);
+            canvas.put_pixel(x, y, blend_over(below, Rgba([pixel[0], pixel[1], pixel[2], alpha])));
+        }
+    }
+    Ok(canvas)
+}
+
+/// Pixels the body part covers with either arm width.
+fn part_mask(body_part: BodyPart, width: u32, resolution: u32) -> Vec<bool> {
+    let mut mask = vec![false; (width * width) as usize];
+    for model_type in [ModelType::Classic, ModelType::Slim] {
+        let parser = SkinParser::with_resolution(&model_type, TextureType::Normal, resolution);
+        for (_, [x, y, region_width, region_height]) in parser.side_regions(&body_part) {
+            for pixel_y in y..y + region_height {
+                for pixel_x in x..x + region_width {
+                    mask[(pixel_y * width + pixel_x) as usize] = true;
+                }
+            }
+        }
+    }
+    mask
+}
+
+fn blend_over(below: Rgba<u8>, above: Rgba<u8>) -> Rgba<u8> {
+    match (below[3], above[3]) {
+        (_, 0) => below,
+        (0, _) | (_, 255) => above,
+        _ => {
+            let above_alpha = f32::from(above[3]) / 255.0;
+            let below_alpha = f32::from(below[3]) / 255.0 * (1.0 - above_alpha);
+            let alpha = above_alpha + below_alpha;
+            let channel = |index: usize| {
+                ((f32::from(above[index]) * above_alpha + f32::from(below[index]) * below_alpha) / alpha).round() as u8
+            };
+            Rgba([channel(0), channel(1), channel(2), (alpha * 255.0).round() as u8])
+        }
+    }
+}
+
+fn png_bytes(image: &RgbaImage) -> Result<Vec<u8>, ImageError> {
+    let mut bytes = Cursor::new(vec![]);
+    image.write_to(&mut bytes, ImageFormat::Png)?;
+    Ok(bytes.into_inner())
+}
+
+/// Writes an OpenRaster archive, the stack lists the top layer first.
+pub fn write_ora(layers: &[Layer], merged: &RgbaImage) -> Result<Vec<u8>, LayeredImageError> {
+    let mut writer = ZipWriter::new(Cursor::new(vec![]));
+    // The mimetype comes first and uncompressed so the file can be recognized by its header
+    writer.start_file("mimetype", SimpleFileOptions::default().compression_method(CompressionMethod::Stored))?;
+    writer.write_all(ORA_MIMETYPE.as_bytes())?;
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut stack = format!(
+        "<?xml version='1.0' encoding='UTF-8'?>\n<image version=\"0.0.3\" w=\"{}\" h=\"{}\">\n<stack>\n",
+        merged.width(), merged.height()
+    );
+    for (index, layer) in layers.iter().enumerate().rev() {
+        let source = format!("data/layer{}.png", index);
+        stack.push_str(&format!(
+            "<layer name=\"{}\" src=\"{}\" x=\"{}\" y=\"{}\" opacity=\"{}\" visibility=\"{}\" />\n",
+            escape_xml(&layer.name), source, layer.x, layer.y, layer.opacity,
+            if layer.visible { "visible" } else { "hidden" },
+        ));
+        writer.start_file(source, options)?;
+        writer.write_all(&png_bytes(&layer.image)?)?;
+    }
+    stack.push_str("</stack>\n</image>\n");
+    writer.start_file("stack.xml", options)?;
+    writer.write_all(stack.as_bytes())?;
+
+    writer.start_file("mergedimage.png", options)?;
+    writer.write_all(&png_bytes(merged)?)?;
+    writer.start_file("Thumbnails/thumbnail.png", options)?;
+    writer.write_all(&png_bytes(merged)?)?;
+
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Reads the layers of an OpenRaster archive bottom first, with the size of the canvas.
+/// Nested stacks are read as if they were flat.
+pub fn read_ora(bytes: &[u8]) -> Result<(Vec<Layer>, u32, u32), LayeredImageError> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    let mut stack = String::new();
+    archive.by_name("stack.xml")?.read_to_string(&mut stack)?;
+
+    let image_tag = xml_tags(&stack, "image")
+        .into_iter()
+        .next()
+        .ok_or(LayeredImageError::InvalidFile("stack.xml has no image".to_string()))?;
+    let size = |key: &str| {
+        xml_attribute(&image_tag, key)
+            .and_then(|value| value.parse::<u32>().ok())
+            .ok_or(LayeredImageError::InvalidFile(format!("the image has no {}", key)))
+    };
+    let (width, height) = (size("w")?, size("h")?);
+
+    let mut layers = vec![];
+    for tag in xml_tags(&stack, "layer").into_iter().rev() {
+        let Some(source) = xml_attribute(&tag, "src") else {
+            continue;
+        };
+        let mut png = vec![];
+        archive.by_name(&source)?.read_to_end(&mut png)?;
+        let number = |key: &str| xml_attribute(&tag, key).and_then(|value| value.parse::<f32>().ok());
+        layers.push(Layer {
+            name: xml_attribute(&tag, "name").unwrap_or_default(),
+            image: image::load_from_memory(&png)?.to_rgba8(),
+            x: number("x").unwrap_or(0.0) as i64,
+            y: number("y").unwrap_or(0.0) as i64,
+            opacity: number("opacity").unwrap_or(1.0).clamp(0.0, 1.0),
+            visible: xml_attribute(&tag, "visibility").as_deref() != Some("hidden"),
+        });
+    }
+    Ok((layers, width, height))
+}
+
+/// Contents of every `<name ...>` tag, in document order.
+fn xml_tags(xml: &str, name: &str) -> Vec<String> {
+    let opening = format!("<{}", name);
+    let mut tags = vec![];
+    let mut rest = xml;
+    while let Some(start) = rest.find(&opening) {
+        rest = &rest[start + opening.len()..];
+        if !rest.starts_with(|character: char| character.is_whitespace() || character == '>' || character == '/') {
+            continue;
+        }
+        let end = rest.find('>').unwrap_or(rest.len());
+        tags.push(rest[..end].to_string());
+        rest = &rest[end..];
+    }
+    tags
+}
+
+fn xml_attribute(tag: &str, key: &str) -> Option<String> {
+    let mut rest = tag;
+    loop {
+        let start = rest.find(key)?;
+        let before = rest[..start].chars().last();
+        rest = &rest[start + key.len()..];
+        let after_key = rest.trim_start();
+        if before.is_some_and(|character| !character.is_whitespace()) || !after_key.starts_with('=') {
+            continue;
+        }
+        let value = after_key[1..].trim_start();
+        let quote = value.chars().next().filter(|quote| *quote == '"' || *quote == '\'')?;
+        let end = value[1..].find(quote)?;
+        return Some(unescape_xml(&value[1..1 + end]));
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+/// Writes a Photoshop document with a canvas-sized layer for every given layer, bottom
+/// first as Photoshop stores them, and the merged image.
+pub fn write_psd(layers: &[Layer], merged: &RgbaImage) -> Vec<u8> {
+    let (width, height) = merged.dimensions();
+    let mut psd = vec![];
+    psd.extend_from_slice(b"8BPS");
+    psd.extend_from_slice(&1u16.to_be_bytes());
+    psd.extend_from_slice(&[0; 6]);
+    psd.extend_from_slice(&4u16.to_be_bytes());
+    psd.extend_from_slice(&height.to_be_bytes());
+    psd.extend_from_slice(&width.to_be_bytes());
+    psd.extend_from_slice(&8u16.to_be_bytes());
+    psd.extend_from_slice(&3u16.to_be_bytes());
+    // No color mode data and no image resources
+    psd.extend_from_slice(&0u32.to_be_bytes());
+    psd.extend_from_slice(&0u32.to_be_bytes());
+
+    let mut records = vec![];
+    let mut channel_data = vec![];
+    let channel_length = 2 + width * height;
+    for layer in layers {
+        let canvas = layer_on_canvas(layer, width, height);
+        for value in [0i32, 0, height as i32, width as i32] {
+            records.extend_from_slice(&value.to_be_bytes());
+        }
+        records.extend_from_slice(&4u16.to_be_bytes());
+        for channel_id in [-1i16, 0, 1, 2] {
+            records.extend_from_slice(&channel_id.to_be_bytes());
+            records.extend_from_slice(&channel_length.to_be_bytes());
+        }
+        records.extend_from_slice(b"8BIMnorm");
+        records.push((layer.opacity * 255.0).round() as u8);
+        records.push(0);
+        records.push(if layer.visible { 0 } else { 2 });
+        records.push(0);
+
+        let name: Vec<u8> = layer.name.bytes().filter(u8::is_ascii).take(255).collect();
+        let mut name_field = vec![name.len() as u8];
+        name_field.extend_from_slice(&name);
+        name_field.resize(name_field.len().div_ceil(4) * 4, 0);
+        records.extend_from_slice(&(8 + name_field.len() as u32).to_be_bytes());
+        // No layer mask and no blending ranges
+        records.extend_from_slice(&0u32.to_be_bytes());
+        records.extend_from_slice(&0u32.to_be_bytes());
+        records.extend_from_slice(&name_field);
+
+        for channel in [3, 0, 1, 2] {
+            channel_data.extend_from_slice(&0u16.to_be_bytes());
+            channel_data.extend(canvas.pixels().map(|pixel| pixel[channel]));
+        }
+    }
+
+    let mut layer_info = vec![];
+    layer_info.extend_from_slice(&(layers.len() as i16).to_be_bytes());
+    layer_info.extend(records);
+    layer_info.extend(channel_data);
+    if layer_info.len() % 2 == 1 {
+        layer_info.push(0);
+    }
+    psd.extend_from_slice(&(4 + layer_info.len() as u32 + 4).to_be_bytes());
+    psd.extend_from_slice(&(layer_info.len() as u32).to_be_bytes());
+    psd.extend(layer_info);
+    psd.extend_from_slice(&0u32.to_be_bytes());
+
+    psd.extend_from_slice(&0u16.to_be_bytes());
+    for channel in 0..4 {
+        psd.extend(merged.pixels().map(|pixel| pixel[channel]));
+    }
+    psd
+}
+
+fn layer_on_canvas(layer: &Layer, width: u32, height: u32) -> RgbaImage {
+    let mut canvas = RgbaImage::new(width, height);
+    image::imageops::replace(&mut canvas, &layer.image, layer.x, layer.y);
+    canvas
+}
+
+/// Reads the layers of an 8-bit RGB Photoshop document bottom first, with the size of the
+/// canvas. A document without layers gives its merged image as the only layer.
+pub fn read_psd(bytes: &[u8]) -> Result<(Vec<Layer>, u32, u32), LayeredImageError> {
+    let mut reader = PsdReader { bytes, position: 0 };
+    if reader.take(4)? != b"8BPS" || reader.u16()? != 1 {
+        return Err(LayeredImageError::InvalidFile("not a Photoshop document".to_string()));
+    }
+    reader.take(6)?;
+    let channels = reader.u16()?;
+    let height = reader.u32()?;
+    let width = reader.u32()?;
+    let depth = reader.u16()?;
+    let mode = reader.u16()?;
+    if depth != 8 || mode != 3 {
+        return Err(LayeredImageError::InvalidFile("only 8-bit RGB documents are supported".to_string()));
+    }
+    let color_mode_length = reader.u32()? as usize;
+    reader.take(color_mode_length)?;
+    let resources_length = reader.u32()? as usize;
+    reader.take(resources_length)?;
+
+    let section_length = reader.u32()? as usize;
+    let section_end = reader.position + section_length;
+    let mut layers = vec![];
+    if section_length > 0 {
+        let info_length = reader.u32()? as usize;
+        if info_length > 0 {
+            layers = read_psd_layers(&mut reader)?;
+        }
+    }
+    reader.position = section_end;
+
+    if layers.is_empty() {
+        if channels < 3 {
+            return Err(LayeredImageError::InvalidFile("the RGB image has less than 3 channels".to_string()));
+        }
+        let compression = reader.u16()?;
+        let planes = read_planes(&mut reader, compression, usize::from(channels), width, height)?;
+        let image = RgbaImage::from_fn(width, height, |x, y| {
+            let index = (y * width + x) as usize;
+            let alpha = planes.get(3).map(|plane| plane[index]).unwrap_or(255);
+            Rgba([planes[0][index], planes[1][index], planes[2][index], alpha])
+        });
+        layers.push(Layer { name: "Background".to_string(), image, x: 0, y: 0, opacity: 1.0, visible: true });
+    }
+    Ok((layers, width, height))
+}
+
+fn read_psd_layers(reader: &mut PsdReader) -> Result<Vec<Layer>, LayeredImageError> {
+    let count = reader.i16()?.unsigned_abs() as usize;
+    let mut records = vec![];
+    for _ in 0..count {
+        let [top, left, bottom, right] = [reader.i32()?, reader.i32()?, reader.i32()?, reader.i32()?];
+        let channel_count = reader.u16()?;
+        let mut channels = vec![];
+        for _ in 0..channel_count {
+            channels.push((reader.i16()?, reader.u32()? as usize));
+        }
+        reader.take(8)?;
+        let opacity = f32::from(reader.take(1)?[0]) / 255.0;
+        let flags = reader.take(3)?[1];
+        let extra_length = reader.u32()? as usize;
+        let extra_end = reader.position + extra_length;
+        let mask_length = reader.u32()? as usize;
+        reader.take(mask_length)?;
+        let ranges_length = reader.u32()? as usize;
+        reader.take(ranges_length)?;
+        let name_length = usize::from(reader.take(1)?[0]);
+        let name = String::from_utf8_lossy(reader.take(name_length)?).to_string();
+        reader.position = extra_end;
+        records.push((name, [top, left, bottom, right], channels, opacity, flags & 2 == 0));
+    }
+
+    let mut layers = vec![];
+    for (name, [top, left, bottom, right], channels, opacity, visible) in records {
+        let (width, height) = ((right - left).max(0) as u32, (bottom - top).max(0) as u32);
+        let mut planes: [Option<Vec<u8>>; 4] = Default::default();
+        for (channel_id, length) in channels {
+            let end = reader.position + length;
+            let plane_index = match channel_id {
+                0..=2 => channel_id as usize,
+                -1 => 3,
+                // Masks have a size of their own
+                _ => {
+                    reader.position = end;
+                    continue;
+                }
+            };
+            let compression = reader.u16()?;
+            planes[plane_index] = read_planes(reader, compression, 1, width, height)?.pop();
+            reader.position = end;
+        }
+        // Groups are written as empty layers around their children
+        if width == 0 || height == 0 {
+            continue;
+        }
+        let image = RgbaImage::from_fn(width, height, |x, y| {
+            let index = (y * width + x) as usize;
+            let value = |plane: usize, default: u8| planes[plane].as_ref().map(|plane| plane[index]).unwrap_or(default);
+            Rgba([value(0, 0), value(1, 0), value(2, 0), value(3, 255)])
+        });
+        layers.push(Layer { name, image, x: i64::from(left), y: i64::from(top), opacity, visible });
+    }
+    Ok(layers)
+}
+
+/// Reads `count` planes stored raw (compression 0) or with PackBits rows (compression 1).
+fn read_planes(reader: &mut PsdReader, compression: u16, count: usize, width: u32, height: u32) -> Result<Vec<Vec<u8>>, LayeredImageError> {
+    let plane_size = width.checked_mul(height)
+        .ok_or_else(|| LayeredImageError::InvalidFile(format!("the image of {}x{} is too large", width, height)))? as usize;
+    match compression {
+        0 => (0..count).map(|_| Ok(reader.take(plane_size)?.to_vec())).collect(),
+        1 => {
+            let mut row_lengths = vec![];
+            for _ in 0..count * height as usize {
+                row_lengths.push(reader.u16()? as usize);
+            }
+            let mut planes = vec![];
+            for rows in row_lengths.chunks(height.max(1) as usize) {
+                let mut plane = Vec::with_capacity(plane_size);
+                for length in rows {
+                    let mut row = unpack_bits(reader.take(*length)?);
+                    row.resize(width as usize, 0);
+                    plane.extend(row);
+                }
+                planes.push(plane);
+            }
+            Ok(planes)
+        }
+        _ => Err(LayeredImageError::InvalidFile(format!("compression {} is not supported", compression))),
+    }
+}
+
+fn unpack_bits(packed: &[u8]) -> Vec<u8> {
+    let mut unpacked = vec![];
+    let mut index = 0;
+    while index < packed.len() {
+        let header = packed[index] as i8;
+        index += 1;
+        match header {
+            0..=127 => {
+                let end = (index + header as usize + 1).min(packed.len());
+                unpacked.extend_from_slice(&packed[index..end]);
+                index = end;
+            }
+            -127..=-1 => {
+                if let Some(value) = packed.get(index) {
+                    unpacked.extend(std::iter::repeat(*value).take(1 + header.unsigned_abs() as usize));
+                }
+                index += 1;
+            }
+            -128 => {}
+        }
+    }
+    unpacked
+}
+
+struct PsdReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PsdReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], LayeredImageError> {
+        let end = self.position + length;
+        let slice = self.bytes
+            .get(self.position..end)
+            .ok_or(LayeredImageError::InvalidFile("the document ends too early".to_string()))?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, LayeredImageError> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> Result<i16, LayeredImageError> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, LayeredImageError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, LayeredImageError> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skin() -> RgbaImage {
+        RgbaImage::from_fn(64, 64, |x, y| {
+            let alpha = if y >= 32 && x < 16 { 128 } else { 255 };
+            Rgba([(x * 4) as u8, (y * 4) as u8, ((x + y) % 256) as u8, alpha])
+        })
+    }
+
+    /// The skin with only the pixels some body part of the model type uses.
+    fn visible_skin(model_type: &ModelType) -> RgbaImage {
+        let layers = part_layers(&skin(), model_type).unwrap();
+        flatten(&layers, 64, 64).unwrap()
+    }
+
+    #[test]
+    fn layers_survive_a_round_trip() {
+        let expected = visible_skin(&ModelType::Slim);
+        let layers = part_layers(&skin(), &ModelType::Slim).unwrap();
+        assert_eq!(layers.len(), 12);
+        assert_eq!(layers[6].name, "Head, outer layer");
+
+        let (ora_layers, width, height) = read_ora(&write_ora(&layers, &expected).unwrap()).unwrap();
+        assert_eq!((width, height), (64, 64));
+        assert_eq!(ora_layers.iter().map(|layer| &layer.name).collect::<Vec<_>>(), layers.iter().map(|layer| &layer.name).collect::<Vec<_>>());
+        assert_eq!(flatten(&ora_layers, width, height).unwrap(), expected);
+
+        let (psd_layers, width, height) = read_psd(&write_psd(&layers, &expected)).unwrap();
+        assert_eq!(psd_layers.len(), 12);
+        assert_eq!(psd_layers[11].name, "Left leg, outer layer");
+        assert_eq!(flatten(&psd_layers, width, height).unwrap(), expected);
+    }
+
+    #[test]
+    fn part_layers_only_paint_their_part() {
+        let mut layers = part_layers(&skin(), &ModelType::Classic).unwrap();
+        // A stroke on the head layer that spills onto the torso, and a hidden layer
+        let head = layers.iter_mut().find(|layer| layer.name == BodyPart::Head.label()).unwrap();
+        head.image.put_pixel(20, 20, Rgba([255, 0, 0, 255]));
+        head.image.put_pixel(8, 8, Rgba([0, 255, 0, 255]));
+        let mut shading = RgbaImage::new(4, 4);
+        shading.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+        layers.push(Layer { name: "Shading".to_string(), image: shading.clone(), x: 20, y: 20, opacity: 0.5, visible: true });
+        layers.push(Layer { name: "Sketch".to_string(), image: shading, x: 8, y: 8, opacity: 1.0, visible: false });
+
+        let flat = flatten(&layers, 64, 64).unwrap();
+        assert_eq!(*flat.get_pixel(8, 8), Rgba([0, 255, 0, 255]));
+        assert_eq!(*flat.get_pixel(20, 20), blend_over(*skin().get_pixel(20, 20), Rgba([0, 0, 0, 128])));
+        assert_ne!(*flat.get_pixel(20, 20), *skin().get_pixel(20, 20));
+    }
+
+    #[test]
+    fn packbits_rows_are_unpacked() {
+        assert_eq!(unpack_bits(&[0xFE, 7, 2, 1, 2, 3, 0x80]), vec![7, 7, 7, 1, 2, 3]);
+    }
+
+    #[test]
+    fn merged_images_need_three_channels() {
+        let mut psd = b"8BPS".to_vec();
+        psd.extend_from_slice(&1u16.to_be_bytes());
+        psd.extend_from_slice(&[0; 6]);
+        // One channel, 1x1, 8-bit RGB, no color mode data, resources or layers, a raw plane
+        for field in [&1u16.to_be_bytes()[..], &1u32.to_be_bytes(), &1u32.to_be_bytes(), &8u16.to_be_bytes(), &3u16.to_be_bytes()] {
+            psd.extend_from_slice(field);
+        }
+        psd.extend_from_slice(&[0; 12]);
+        psd.extend_from_slice(&[0, 0, 255]);
+        assert!(matches!(read_psd(&psd), Err(LayeredImageError::InvalidFile(_))));
+    }
+}
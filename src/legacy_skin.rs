@@ -0,0 +1,285 @@
+use image::{imageops, Rgba, RgbaImage};
+
+use crate::glium_area::arm_conversion::{slim_to_classic, ConversionStrategy, HiddenColumns};
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::cube_side::CubeSide;
+use crate::glium_area::skin_parser::{texture_layout, CubeSideColors, ModelType, SkinParser, TextureLoadError, TextureType};
+
+/// Faces Minecraft copies from the right limbs of a legacy skin to the left ones, flipped
+/// horizontally: the left limb face, the texture area it comes from as x, y, width and
+/// height, and where it goes in a 64x64 skin.
+const MIRRORED_FACES: [(BodyPart, CubeSide, [u32; 4], [u32; 2]); 12] = [
+    (BodyPart::LeftLeg, CubeSide::Top, [4, 16, 4, 4], [20, 48]),
+    (BodyPart::LeftLeg, CubeSide::Bottom, [8, 16, 4, 4], [24, 48]),
+    (BodyPart::LeftLeg, CubeSide::Left, [0, 20, 4, 12], [24, 52]),
+    (BodyPart::LeftLeg, CubeSide::Front, [4, 20, 4, 12], [20, 52]),
+    (BodyPart::LeftLeg, CubeSide::Right, [8, 20, 4, 12], [16, 52]),
+    (BodyPart::LeftLeg, CubeSide::Back, [12, 20, 4, 12], [28, 52]),
+    (BodyPart::LeftArm, CubeSide::Top, [44, 16, 4, 4], [36, 48]),
+    (BodyPart::LeftArm, CubeSide::Bottom, [48, 16, 4, 4], [40, 48]),
+    (BodyPart::LeftArm, CubeSide::Left, [40, 20, 4, 12], [40, 52]),
+    (BodyPart::LeftArm, CubeSide::Front, [44, 20, 4, 12], [36, 52]),
+    (BodyPart::LeftArm, CubeSide::Right, [48, 20, 4, 12], [32, 52]),
+    (BodyPart::LeftArm, CubeSide::Back, [52, 20, 4, 12], [44, 52]),
+];
+
+/// Areas of the base layer Minecraft makes opaque, as x, y, width and height: the head,
+/// the row of the torso and the right limbs, and the new left limbs.
+const OPAQUE_AREAS: [[u32; 4]; 3] = [[0, 0, 32, 16], [0, 16, 64, 16], [16, 48, 32, 16]];
+
+/// The hat of a legacy skin. Old skins often filled it with a solid color, so Minecraft
+/// clears it when none of its pixels is transparent.
+const HAT_AREA: [u32; 4] = [32, 0, 32, 16];
+
+/// A face of a left limb made from a right limb by [`upgrade_legacy`], in pixels of the skin.
+#[derive(Debug, PartialEq)]
+pub struct MirroredFace {
+    pub body_part: BodyPart,
+    pub side: CubeSide,
+    pub from: [u32; 4],
+    pub to: [u32; 2],
+}
+
+impl MirroredFace {
+    pub fn describe(&self) -> String {
+        let source = match self.body_part {
+            BodyPart::LeftLeg => BodyPart::RightLeg,
+            _ => BodyPart::RightArm,
+        };
+        let [x, y, width, height] = self.from;
+        format!(
+            "{} {} from the {} at {},{} ({}x{}), flipped, to {},{}",
+            self.body_part.label(), side_name(self.side), source.label().to_lowercase(),
+            x, y, width, height, self.to[0], self.to[1],
+        )
+    }
+}
+
+fn side_name(side: CubeSide) -> &'static str {
+    match side {
+        CubeSide::Front => "front",
+        CubeSide::Left => "left side",
+        CubeSide::Back => "back",
+        CubeSide::Right => "right side",
+        CubeSide::Top => "top",
+        CubeSide::Bottom => "bottom",
+    }
+}
+
+/// A 64x32 skin, or an HD version of it, turned into a 64x64 one.
+pub struct LegacyUpgrade {
+    pub image: RgbaImage,
+    pub mirrored: Vec<MirroredFace>,
+    /// The hat had no transparent pixel and was cleared.
+    pub hat_cleared: bool,
+}
+
+/// Upgrades a legacy skin the way Minecraft does: the top half is kept and the left limbs
+/// are the right ones mirrored. The base layer is made opaque and a hat without transparent
+/// pixels is cleared. The outer layers below the top half stay empty.
+pub fn upgrade_legacy(image: &RgbaImage) -> Result<LegacyUpgrade, TextureLoadError> {
+    let (texture_type, resolution) = texture_layout(image.width(), image.height())?;
+    if texture_type != TextureType::Legacy {
+        return Err(TextureLoadError::ImageDimensionError(
+            format!("{}x{} is not a legacy skin", image.width(), image.height())
+        ));
+    }
+
+    let mut upgraded = RgbaImage::new(image.width(), image.width());
+    imageops::replace(&mut upgraded, image, 0, 0);
+    let mut mirrored = vec![];
+    for (body_part, side, from, to) in MIRRORED_FACES {
+        let [x, y, width, height] = from.map(|length| length * resolution);
+        let face = imageops::flip_horizontal(&imageops::crop_imm(image, x, y, width, height).to_image());
+        imageops::replace(&mut upgraded, &face, i64::from(to[0] * resolution), i64::from(to[1] * resolution));
+        mirrored.push(MirroredFace { body_part, side, from: [x, y, width, height], to: to.map(|length| length * resolution) });
+    }
+
+    for area in OPAQUE_AREAS {
+        for (x, y) in area_pixels(area, resolution) {
+            upgraded.get_pixel_mut(x, y)[3] = 255;
+        }
+    }
+    // Pixels count as transparent below half opacity, like in the game
+    let hat_cleared = area_pixels(HAT_AREA, resolution).all(|(x, y)| upgraded.get_pixel(x, y)[3] >= 128);
+    if hat_cleared {
+        for (x, y) in area_pixels(HAT_AREA, resolution) {
+            upgraded.get_pixel_mut(x, y)[3] = 0;
+        }
+    }
+    Ok(LegacyUpgrade { image: upgraded, mirrored, hat_cleared })
+}
+
+fn area_pixels(area: [u32; 4], resolution: u32) -> impl Iterator<Item = (u32, u32)> {
+    let [x, y, width, height] = area.map(|length| length * resolution);
+    (y..y + height).flat_map(move |pixel_y| (x..x + width).map(move |pixel_x| (pixel_x, pixel_y)))
+}
+
+/// A skin cut down to the 64x32 layout.
+pub struct LegacyExport {
+    pub image: RgbaImage,
+    /// Parts that look different once the legacy skin is upgraded again: outer layers
+    /// other than the hat, and left limbs that are not a mirror of the right ones.
+    pub lost_parts: Vec<BodyPart>,
+    /// Legacy skins only have classic arms, slim arms are widened.
+    pub widened_arms: bool,
+}
+
+/// Cuts a 64x64 skin, or an HD version of it, down to the legacy layout.
+pub fn export_legacy(image: &RgbaImage, model_type: &ModelType) -> Result<LegacyExport, TextureLoadError> {
+    let (_, resolution) = texture_layout(image.width(), image.height())?;
+    let classic = match model_type {
+        ModelType::Classic => image.clone(),
+        ModelType::Slim => widen_arms(image, resolution)?,
+    };
+
+    let legacy = imageops::crop_imm(&classic, 0, 0, classic.width(), classic.height() / 2).to_image();
+    let upgraded = upgrade_legacy(&legacy)?.image;
+
+    let parser = SkinParser::with_resolution(&ModelType::Classic, TextureType::Normal, resolution);
+    let before = parser.load_from_bytes(&classic.into())?;
+    let after = parser.load_from_bytes(&upgraded.into())?;
+    let lost_parts = BodyPart::PLAYER
+        .into_iter()
+        .filter(|body_part| visible_colors(&before[body_part]) != visible_colors(&after[body_part]))
+        .collect();
+
+    Ok(LegacyExport { image: legacy, lost_parts, widened_arms: *model_type == ModelType::Slim })
+}
+
+/// Colors of the cells with every transparent cell alike.
+fn visible_colors(sides: &CubeSideColors) -> Vec<Rgba<u8>> {
+    sides.values().flatten().map(|pixel| if pixel[3] == 0 { Rgba([0; 4]) } else { *pixel }).collect()
+}
+
+/// Converts the arms of a slim skin to classic ones, repeating the column next to the edge.
+fn widen_arms(image: &RgbaImage, resolution: u32) -> Result<RgbaImage, TextureLoadError> {
+    let slim = SkinParser::with_resolution(&ModelType::Slim, TextureType::Normal, resolution);
+    let classic = SkinParser::with_resolution(&ModelType::Classic, TextureType::Normal, resolution);
+    let color_map = slim.load_from_bytes(&image.clone().into())?;
+    let mut widened = image.clone();
+    for arm in [BodyPart::RightArm, BodyPart::LeftArm, BodyPart::RightArmOuter, BodyPart::LeftArmOuter] {
+        // The slim arm is narrower, what is left of the old area is cleared first
+        for (_, [x, y, width, height]) in classic.side_regions(&arm) {
+            for pixel_y in y..y + height {
+                for pixel_x in x..x + width {
+                    widened.put_pixel(pixel_x, pixel_y, Rgba([0; 4]));
+                }
+            }
+        }
+        let cells: Vec<[f32; 4]> = color_map[&arm]
+            .values()
+            .flatten()
+            .map(|pixel| pixel.0.map(|channel| f32::from(channel) / 255.0))
+            .collect();
+        let cells = slim_to_classic(&cells, arm, ConversionStrategy::DropMiddle, &mut HiddenColumns::default(), resolution as usize);
+        let pixels: Vec<Rgba<u8>> = cells.iter().map(|color| Rgba(color.map(|channel| (channel * 255.0).round() as u8))).collect();
+        classic.export_cells(&arm, &mut widened, &pixels);
+    }
+    Ok(widened)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::DynamicImage;
+
+    use super::*;
+
+    /// A legacy skin where every pixel has its own color, opaque but for gaps in the hat.
+    fn legacy_skin() -> RgbaImage {
+        RgbaImage::from_fn(64, 32, |x, y| {
+            let alpha = if x >= 32 && y < 16 && (x + y) % 3 == 0 { 0 } else { 255 };
+            Rgba([(x * 4) as u8, (y * 8) as u8, ((x * 7 + y * 13) % 256) as u8, alpha])
+        })
+    }
+
+    fn fixture(name: &str) -> RgbaImage {
+        image::open(format!("tests/skins/{}.png", name)).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn upgrade_matches_the_reference() {
+        let upgrade = upgrade_legacy(&fixture("legacy")).unwrap();
+        assert_eq!(upgrade.image, fixture("legacy_upgraded"));
+        assert!(!upgrade.hat_cleared);
+    }
+
+    #[test]
+    fn base_layer_is_made_opaque() {
+        let mut legacy = legacy_skin();
+        for (x, y) in [(0, 0), (9, 9), (20, 22), (45, 22), (60, 30)] {
+            legacy.put_pixel(x, y, Rgba([1, 2, 3, 0]));
+        }
+        legacy.get_pixel_mut(5, 25)[3] = 96;
+        let upgraded = upgrade_legacy(&legacy).unwrap().image;
+
+        for (x, y) in [(0, 0), (9, 9), (20, 22), (45, 22), (60, 30)] {
+            assert_eq!(*upgraded.get_pixel(x, y), Rgba([1, 2, 3, 255]));
+        }
+        assert_eq!(upgraded.get_pixel(5, 25)[3], 255);
+        // The mirrored arm front is opaque too, the hat and the new outer layers are not
+        assert_eq!(*upgraded.get_pixel(38, 54), Rgba([1, 2, 3, 255]));
+        assert_eq!(upgraded.get_pixel(33, 0)[3], 0);
+        assert_eq!(upgraded.get_pixel(4, 36)[3], 0);
+    }
+
+    #[test]
+    fn solid_hats_are_cleared() {
+        for resolution in [1, 2] {
+            let mut legacy = imageops::resize(&legacy_skin(), 64 * resolution, 32 * resolution, imageops::FilterType::Nearest);
+            for (x, y) in area_pixels(HAT_AREA, resolution) {
+                legacy.get_pixel_mut(x, y)[3] = 128;
+            }
+            let upgrade = upgrade_legacy(&legacy).unwrap();
+            assert!(upgrade.hat_cleared);
+            assert!(area_pixels(HAT_AREA, resolution).all(|(x, y)| upgrade.image.get_pixel(x, y)[3] == 0));
+            assert_eq!(upgrade.image.get_pixel(0, 0)[3], 255);
+
+            // One see-through pixel keeps the hat
+            legacy.get_pixel_mut(40 * resolution, 8 * resolution)[3] = 127;
+            let upgrade = upgrade_legacy(&legacy).unwrap();
+            assert!(!upgrade.hat_cleared);
+            assert_eq!(*upgrade.image.get_pixel(33 * resolution, 0), *legacy.get_pixel(33 * resolution, 0));
+        }
+    }
+
+    #[test]
+    fn upgrade_matches_reading_the_legacy_layout() {
+        let legacy = legacy_skin();
+        let upgrade = upgrade_legacy(&legacy).unwrap();
+        assert_eq!(upgrade.mirrored.len(), 12);
+
+        // The parser reads legacy skins on its own, mirroring the limbs as it goes
+        let reference = SkinParser::new(&ModelType::Classic, TextureType::Legacy)
+            .load_from_bytes(&DynamicImage::ImageRgba8(legacy))
+            .unwrap();
+        let upgraded = SkinParser::new(&ModelType::Classic, TextureType::Normal)
+            .load_from_bytes(&DynamicImage::ImageRgba8(upgrade.image.clone()))
+            .unwrap();
+        for body_part in [BodyPart::Head, BodyPart::Torso, BodyPart::RightArm, BodyPart::LeftArm, BodyPart::RightLeg, BodyPart::LeftLeg, BodyPart::HeadOuter] {
+            assert_eq!(upgraded[&body_part], reference[&body_part], "{:?}", body_part);
+        }
+
+        // The front of the left leg starts with the last column of the right leg front
+        assert_eq!(upgrade.image.get_pixel(20, 52), upgrade.image.get_pixel(7, 20));
+        assert_eq!(upgrade.image.get_pixel(4, 36)[3], 0);
+    }
+
+    #[test]
+    fn export_reports_what_the_legacy_layout_cannot_hold() {
+        let upgraded = upgrade_legacy(&legacy_skin()).unwrap().image;
+        let export = export_legacy(&upgraded, &ModelType::Classic).unwrap();
+        assert_eq!(export.image, legacy_skin());
+        assert!(export.lost_parts.is_empty());
+
+        let mut jacket = upgraded.clone();
+        jacket.put_pixel(20, 36, Rgba([255, 0, 0, 255]));
+        jacket.put_pixel(0, 52, Rgba([0, 0, 255, 255]));
+        let export = export_legacy(&jacket, &ModelType::Classic).unwrap();
+        assert_eq!(export.lost_parts, vec![BodyPart::TorsoOuter, BodyPart::LeftLegOuter]);
+
+        let export = export_legacy(&upgraded, &ModelType::Slim).unwrap();
+        assert!(export.widened_arms);
+        assert_eq!(export.image.dimensions(), (64, 32));
+    }
+}
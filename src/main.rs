@@ -15,7 +15,35 @@ mod glium_area;
 mod model_switcher;
 mod window;
 mod template_list;
+mod template_library;
+mod template_archive;
+mod thumbnails;
+mod settings;
 mod template_widget_item;
+mod outfit;
+mod outfit_composer;
+mod compare;
+mod compare_panel;
+mod player_head;
+mod player_head_dialog;
+mod render_export;
+mod render_export_dialog;
+mod turntable;
+mod turntable_dialog;
+mod model_export;
+mod print_export;
+mod blockbench;
+mod bedrock_geometry;
+mod legacy_skin;
+mod layered_image;
+mod image_stamp;
+mod stamp_dialog;
+mod pixel_font;
+mod text_dialog;
+mod shading;
+mod shading_popover;
+mod skin_effects;
+mod part_transforms;
 mod skin_loader_popover;
 mod skin_dialog;
 mod application;
@@ -28,6 +56,7 @@ pub const APP_ID: &str = "io.redgradient.MCSkinEditor";
 lazy_static! {
     static ref ROOT_DIR: PathBuf = dirs::home_dir().expect("Home directory not found").join("MinecraftSkinEditor");
     static ref TEMPLATES_DIR: PathBuf = ROOT_DIR.join("templates");
+    static ref THUMBNAILS_DIR: PathBuf = ROOT_DIR.join("thumbnails");
 }
 
 fn load_gl_function() {
@@ -0,0 +1,552 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Cursor, Write};
+use std::path::Path;
+
+use image::{ImageError, ImageFormat, RgbaImage};
+use nalgebra_glm as glm;
+use serde_json::{json, Value};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::renderer::cuboid_geometry;
+use crate::glium_area::rig::{legacy_player_rig_at, player_rig_at};
+use crate::glium_area::skin_parser::{texture_layout, ModelType, SkinParser, TextureLoadError, TextureType};
+
+/// Size of a model unit (8 skin pixels) in meters, so that a block is a meter.
+const METERS_PER_UNIT: f32 = 0.5;
+/// Height of the soles in model units, exported models stand on the origin.
+const FLOOR: f32 = -2.0;
+/// How deep the voxels of [`OverlayGeometry::Voxels`] are, in model units (half a pixel).
+const VOXEL_DEPTH: f32 = 0.0625;
+/// Overlay pixels at least this opaque become voxels.
+const VOXEL_ALPHA_THRESHOLD: u8 = 128;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ModelFormat {
+    /// Binary glTF 2.0 with the texture embedded.
+    Glb,
+    /// Wavefront OBJ with an MTL file and the texture next to it.
+    Obj,
+}
+
+impl ModelFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ModelFormat::Glb => "glb",
+            ModelFormat::Obj => "obj",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum OverlayGeometry {
+    /// Textured boxes inflated like in the editor, transparent pixels are cut out by the material.
+    #[default]
+    Shell,
+    /// A small cube for every opaque overlay pixel, for tools without alpha cutout.
+    Voxels,
+    /// Only the inner layer.
+    None,
+}
+
+#[derive(Debug)]
+pub enum ModelExportError {
+    IoError(io::Error),
+    ImageError(ImageError),
+    JsonError(serde_json::Error),
+    /// The texture is not laid out like a skin.
+    TextureError(TextureLoadError),
+}
+
+impl From<io::Error> for ModelExportError {
+    fn from(error: io::Error) -> Self {
+        ModelExportError::IoError(error)
+    }
+}
+
+impl From<ImageError> for ModelExportError {
+    fn from(error: ImageError) -> Self {
+        ModelExportError::ImageError(error)
+    }
+}
+
+impl From<serde_json::Error> for ModelExportError {
+    fn from(error: serde_json::Error) -> Self {
+        ModelExportError::JsonError(error)
+    }
+}
+
+impl From<TextureLoadError> for ModelExportError {
+    fn from(error: TextureLoadError) -> Self {
+        ModelExportError::TextureError(error)
+    }
+}
+
+/// Triangles in meters relative to the pivot of their part. UVs are in texture space
+/// with v pointing down, like glTF.
+#[derive(Default)]
+struct Mesh {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+
+impl Mesh {
+    /// Adds a quad facing away from `inside`, with counter-clockwise front faces.
+    fn add_quad(&mut self, corners: [glm::Vec3; 4], uvs: [[f32; 2]; 4], inside: &glm::Vec3) {
+        let [a, b, c, d] = corners;
+        let mut normal = (b - a).cross(&(c - a));
+        if normal.norm() == 0.0 {
+            return;
+        }
+        normal = normal.normalize();
+        let counter_clockwise = normal.dot(&((a + b + c + d) / 4.0 - inside)) > 0.0;
+        if !counter_clockwise {
+            normal = -normal;
+        }
+
+        let base = self.positions.len() as u32;
+        for (corner, uv) in corners.iter().zip(uvs) {
+            self.positions.push([corner.x, corner.y, corner.z]);
+            self.normals.push([normal.x, normal.y, normal.z]);
+            self.uvs.push(uv);
+        }
+        let order = if counter_clockwise { [0, 1, 2, 0, 2, 3] } else { [0, 2, 1, 0, 3, 2] };
+        self.indices.extend(order.map(|index| base + index));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+}
+
+/// One node of the exported character.
+struct PartNode {
+    body_part: BodyPart,
+    /// Meters; outer layers share the pivot of their inner part and are its children.
+    pivot: glm::Vec3,
+    mesh: Mesh,
+}
+
+impl PartNode {
+    fn name(&self) -> String {
+        format!("{:?}", self.body_part)
+    }
+}
+
+/// Builds the nodes of all parts in the order of the player rig, inner parts first.
+fn build_parts(image: &RgbaImage, model_type: &ModelType, overlay: OverlayGeometry) -> Result<Vec<PartNode>, ModelExportError> {
+    let (texture_type, resolution) = texture_layout(image.width(), image.height())?;
+    let rig = match texture_type {
+        TextureType::Normal => player_rig_at(model_type, resolution),
+        TextureType::Legacy => legacy_player_rig_at(model_type, resolution),
+    };
+    let parser = SkinParser::for_rig(rig);
+    let texture_size = glm::vec2(image.width() as f32, image.height() as f32);
+    let mut nodes = vec![];
+
+    for (&body_part, cuboid) in rig.cuboids() {
+        if cuboid.outer_of.is_some() && overlay == OverlayGeometry::None {
+            continue;
+        }
+        let geometry = cuboid_geometry(body_part, cuboid);
+        let placement = glm::translate(&glm::Mat4::identity(), &geometry.translation)
+            * glm::scale(&glm::Mat4::identity(), &geometry.scale);
+        let pivot = geometry.pivot;
+        // Meters relative to the pivot
+        let place = |position: [f32; 3]| {
+            let world = placement * glm::vec4(position[0], position[1], position[2], 1.0);
+            (world.xyz() - pivot) * METERS_PER_UNIT
+        };
+        let center = (geometry.translation - pivot) * METERS_PER_UNIT;
+        let uv = |x: f32, y: f32| [x / texture_size.x, y / texture_size.y];
+
+        let cells: Vec<[glm::Vec3; 4]> = geometry.vertexes
+            .chunks(4)
+            .map(|cell| [0, 1, 2, 3].map(|corner| place(cell[corner].position)))
+            .collect();
+
+        let mut mesh = Mesh::default();
+        let mut first_cell = 0;
+        for (_, [x, y, width, height]) in parser.side_regions(&body_part) {
+            let side = &cells[first_cell..first_cell + (width * height) as usize];
+            first_cell += (width * height) as usize;
+
+            if body_part.is_outer() && overlay == OverlayGeometry::Voxels {
+                for (index, cell) in side.iter().enumerate() {
+                    let pixel_x = x + index as u32 % width;
+                    let pixel_y = y + index as u32 / width;
+                    if image.get_pixel(pixel_x, pixel_y)[3] < VOXEL_ALPHA_THRESHOLD {
+                        continue;
+                    }
+                    let texel = uv(pixel_x as f32 + 0.5, pixel_y as f32 + 0.5);
+                    add_voxel(&mut mesh, cell, texel, &center);
+                }
+                continue;
+            }
+
+            let (step_x, step_y) = cell_steps(side, width as usize);
+            let corner = side[0].iter().sum::<glm::Vec3>() / 4.0 - step_x * 0.5 - step_y * 0.5;
+            let (width, height) = (width as f32, height as f32);
+            let (x, y) = (x as f32, y as f32);
+            mesh.add_quad(
+                [
+                    corner,
+                    corner + step_x * width,
+                    corner + step_x * width + step_y * height,
+                    corner + step_y * height,
+                ],
+                [uv(x, y), uv(x + width, y), uv(x + width, y + height), uv(x, y + height)],
+                &center,
+            );
+        }
+        let pivot = (pivot - glm::vec3(0.0, FLOOR, 0.0)) * METERS_PER_UNIT;
+        nodes.push(PartNode { body_part, pivot, mesh });
+    }
+    Ok(nodes)
+}
+
+/// Size of a cell of the side along its rows and its columns, taken from the edges of
+/// the first cell, so sides one cell wide or tall work too. Which edge runs along the
+/// rows, and which way, differs between the sides, so the neighbouring cells tell.
+fn cell_steps(side: &[[glm::Vec3; 4]], width: usize) -> (glm::Vec3, glm::Vec3) {
+    let cell = &side[0];
+    let (mut step_x, mut step_y) = (cell[1] - cell[0], cell[3] - cell[0]);
+    let cell_center = |index: usize| side[index].iter().sum::<glm::Vec3>() / 4.0;
+    let offset = |neighbour: usize| cell_center(neighbour) - cell_center(0);
+
+    let swapped = if width > 1 {
+        offset(1).dot(&step_x).abs() < offset(1).dot(&step_y).abs()
+    } else if side.len() > 1 {
+        offset(width).dot(&step_y).abs() < offset(width).dot(&step_x).abs()
+    } else {
+        false
+    };
+    if swapped {
+        std::mem::swap(&mut step_x, &mut step_y);
+    }
+    if width > 1 && offset(1).dot(&step_x) < 0.0 {
+        step_x = -step_x;
+    }
+    if side.len() > width && offset(width).dot(&step_y) < 0.0 {
+        step_y = -step_y;
+    }
+    (step_x, step_y)
+}
+
+/// A box behind the overlay cell, colored by a single texel.
+fn add_voxel(mesh: &mut Mesh, cell: &[glm::Vec3; 4], texel: [f32; 2], part_center: &glm::Vec3) {
+    let cell_center = cell.iter().sum::<glm::Vec3>() / 4.0;
+    let mut normal = (cell[1] - cell[0]).cross(&(cell[2] - cell[0])).normalize();
+    if normal.dot(&(cell_center - part_center)) < 0.0 {
+        normal = -normal;
+    }
+    let depth = normal * VOXEL_DEPTH * METERS_PER_UNIT;
+    let outer = *cell;
+    let inner = cell.map(|corner| corner - depth);
+    let voxel_center = cell_center - depth / 2.0;
+    let uvs = [texel; 4];
+
+    mesh.add_quad(outer, uvs, &voxel_center);
+    mesh.add_quad(inner, uvs, &voxel_center);
+    for index in 0..4 {
+        let next = (index + 1) % 4;
+        mesh.add_quad([outer[index], outer[next], inner[next], inner[index]], uvs, &voxel_center);
+    }
+}
+
+/// Exports the skin with its texture as a character model.
+pub fn export_model(
+    image: &RgbaImage,
+    model_type: &ModelType,
+    format: ModelFormat,
+    overlay: OverlayGeometry,
+    path: &Path,
+) -> Result<(), ModelExportError> {
+    let nodes = build_parts(image, model_type, overlay)?;
+    match format {
+        ModelFormat::Glb => write_glb(&nodes, image, path),
+        ModelFormat::Obj => write_obj(&nodes, image, path),
+    }
+}
+
+/// Appends `bytes` to the binary buffer as a new buffer view, 4 byte aligned.
+fn push_view(buffer: &mut Vec<u8>, views: &mut Vec<Value>, bytes: &[u8], target: Option<u32>) -> usize {
+    while buffer.len() % 4 != 0 {
+        buffer.push(0);
+    }
+    let mut view = json!({ "buffer": 0, "byteOffset": buffer.len(), "byteLength": bytes.len() });
+    if let Some(target) = target {
+        view["target"] = json!(target);
+    }
+    buffer.extend_from_slice(bytes);
+    views.push(view);
+    views.len() - 1
+}
+
+fn float_bytes<const N: usize>(values: &[[f32; N]]) -> Vec<u8> {
+    values.iter().flatten().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn write_glb(nodes: &[PartNode], image: &RgbaImage, path: &Path) -> Result<(), ModelExportError> {
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+    const NEAREST: u32 = 9728;
+    const CLAMP_TO_EDGE: u32 = 33071;
+
+    let mut buffer = vec![];
+    let mut views = vec![];
+    let mut accessors = vec![];
+    let mut meshes = vec![];
+    let mut json_nodes = vec![];
+
+    for node in nodes {
+        let mut json_node = json!({ "name": node.name() });
+        if node.body_part.is_outer() {
+            // Child of the inner part, which is already at the pivot
+            json_node["translation"] = json!([0.0, 0.0, 0.0]);
+        } else {
+            json_node["translation"] = json!([node.pivot.x, node.pivot.y, node.pivot.z]);
+        }
+
+        if !node.mesh.is_empty() {
+            let mesh = &node.mesh;
+            let mut min = [f32::INFINITY; 3];
+            let mut max = [f32::NEG_INFINITY; 3];
+            for position in &mesh.positions {
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(position[axis]);
+                    max[axis] = max[axis].max(position[axis]);
+                }
+            }
+
+            let count = mesh.positions.len();
+            let view = push_view(&mut buffer, &mut views, &float_bytes(&mesh.positions), Some(ARRAY_BUFFER));
+            accessors.push(json!({ "bufferView": view, "componentType": FLOAT, "count": count, "type": "VEC3", "min": min, "max": max }));
+            let view = push_view(&mut buffer, &mut views, &float_bytes(&mesh.normals), Some(ARRAY_BUFFER));
+            accessors.push(json!({ "bufferView": view, "componentType": FLOAT, "count": count, "type": "VEC3" }));
+            let view = push_view(&mut buffer, &mut views, &float_bytes(&mesh.uvs), Some(ARRAY_BUFFER));
+            accessors.push(json!({ "bufferView": view, "componentType": FLOAT, "count": count, "type": "VEC2" }));
+            let indices: Vec<u8> = mesh.indices.iter().flat_map(|index| index.to_le_bytes()).collect();
+            let view = push_view(&mut buffer, &mut views, &indices, Some(ELEMENT_ARRAY_BUFFER));
+            accessors.push(json!({ "bufferView": view, "componentType": UNSIGNED_INT, "count": mesh.indices.len(), "type": "SCALAR" }));
+
+            let first = accessors.len() - 4;
+            meshes.push(json!({
+                "name": node.name(),
+                "primitives": [{
+                    "attributes": { "POSITION": first, "NORMAL": first + 1, "TEXCOORD_0": first + 2 },
+                    "indices": first + 3,
+                    "material": if node.body_part.is_outer() { 1 } else { 0 },
+                }],
+            }));
+            json_node["mesh"] = json!(meshes.len() - 1);
+        }
+        json_nodes.push(json_node);
+    }
+
+    // Outer layers hang below their inner part, everything below a root node
+    let index_of = |body_part: BodyPart| nodes.iter().position(|node| node.body_part == body_part);
+    let mut roots = vec![];
+    for (index, node) in nodes.iter().enumerate() {
+        match node.body_part.inner_counterpart().and_then(index_of) {
+            Some(parent) => {
+                let mut children = json_nodes[parent]["children"].as_array().cloned().unwrap_or_default();
+                children.push(json!(index));
+                json_nodes[parent]["children"] = Value::Array(children);
+            }
+            None => roots.push(index),
+        }
+    }
+    json_nodes.push(json!({ "name": "Player", "children": roots }));
+
+    let mut png = vec![];
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    let image_view = push_view(&mut buffer, &mut views, &png, None);
+    while buffer.len() % 4 != 0 {
+        buffer.push(0);
+    }
+
+    let material = |name: &str| json!({
+        "name": name,
+        "pbrMetallicRoughness": {
+            "baseColorTexture": { "index": 0 },
+            "metallicFactor": 0.0,
+            "roughnessFactor": 1.0,
+        },
+    });
+    let mut overlay_material = material("overlay");
+    overlay_material["alphaMode"] = json!("MASK");
+    overlay_material["alphaCutoff"] = json!(0.5);
+    overlay_material["doubleSided"] = json!(true);
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "Minecraft Skin Editor" },
+        "scene": 0,
+        "scenes": [{ "name": "Skin", "nodes": [json_nodes.len() - 1] }],
+        "nodes": json_nodes,
+        "meshes": meshes,
+        "materials": [material("skin"), overlay_material],
+        "textures": [{ "sampler": 0, "source": 0 }],
+        "samplers": [{ "magFilter": NEAREST, "minFilter": NEAREST, "wrapS": CLAMP_TO_EDGE, "wrapT": CLAMP_TO_EDGE }],
+        "images": [{ "name": "skin", "bufferView": image_view, "mimeType": "image/png" }],
+        "accessors": accessors,
+        "bufferViews": views,
+        "buffers": [{ "byteLength": buffer.len() }],
+    });
+
+    let mut json_chunk = serde_json::to_vec(&document)?;
+    while json_chunk.len() % 4 != 0 {
+        json_chunk.push(b' ');
+    }
+    let total_length = 12 + 8 + json_chunk.len() + 8 + buffer.len();
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(b"glTF")?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(total_length as u32).to_le_bytes())?;
+    writer.write_all(&(json_chunk.len() as u32).to_le_bytes())?;
+    writer.write_all(b"JSON")?;
+    writer.write_all(&json_chunk)?;
+    writer.write_all(&(buffer.len() as u32).to_le_bytes())?;
+    writer.write_all(b"BIN\0")?;
+    writer.write_all(&buffer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes `<name>.obj`, `<name>.mtl` and the texture as `<name>_texture.png`, so a skin
+/// with the same name is not overwritten. OBJ has no hierarchy, so every part is an
+/// object placed in model space.
+fn write_obj(nodes: &[PartNode], image: &RgbaImage, path: &Path) -> Result<(), ModelExportError> {
+    let material_path = path.with_extension("mtl");
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let texture_path = path.with_file_name(format!("{}_texture.png", stem));
+    let file_name = |path: &Path| path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+    image.save(&texture_path)?;
+    fs::write(&material_path, format!(
+        "# Minecraft Skin Editor\n\
+         newmtl skin\nKd 1 1 1\nmap_Kd {texture}\n\n\
+         newmtl overlay\nKd 1 1 1\nmap_Kd {texture}\nmap_d {texture}\n",
+        texture = file_name(&texture_path),
+    ))?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "# Minecraft Skin Editor")?;
+    writeln!(writer, "mtllib {}", file_name(&material_path))?;
+
+    let mut first_vertex = 1;
+    for node in nodes.iter().filter(|node| !node.mesh.is_empty()) {
+        let mesh = &node.mesh;
+        writeln!(writer, "o {}", node.name())?;
+        writeln!(writer, "usemtl {}", if node.body_part.is_outer() { "overlay" } else { "skin" })?;
+        for position in &mesh.positions {
+            let [x, y, z] = [0, 1, 2].map(|axis| position[axis] + node.pivot[axis]);
+            writeln!(writer, "v {} {} {}", x, y, z)?;
+        }
+        for [u, v] in &mesh.uvs {
+            writeln!(writer, "vt {} {}", u, 1.0 - v)?;
+        }
+        for [x, y, z] in &mesh.normals {
+            writeln!(writer, "vn {} {} {}", x, y, z)?;
+        }
+        for triangle in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| triangle[corner] + first_vertex);
+            writeln!(writer, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+        }
+        first_vertex += mesh.positions.len() as u32;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn head_front_is_mapped_to_its_texture_area() {
+        let image = RgbaImage::from_pixel(64, 64, Rgba([255, 255, 255, 255]));
+        let nodes = build_parts(&image, &ModelType::Classic, OverlayGeometry::None).unwrap();
+        let head = nodes.iter().find(|node| node.body_part == BodyPart::Head).unwrap();
+        assert_eq!(head.mesh.indices.len(), 6 * 6);
+
+        // The front faces +z
+        let front: Vec<usize> = (0..head.mesh.normals.len())
+            .filter(|&index| head.mesh.normals[index][2] > 0.9)
+            .collect();
+        assert_eq!(front.len(), 4);
+        for index in front {
+            let [u, v] = head.mesh.uvs[index];
+            assert!([8.0, 16.0].contains(&(u * 64.0)) && [8.0, 16.0].contains(&(v * 64.0)));
+        }
+    }
+
+    /// Cells of a side `width` cells wide, with corners in the order of the given edges.
+    fn side(width: usize, height: usize, first: glm::Vec3, second: glm::Vec3, along_rows: glm::Vec3) -> Vec<[glm::Vec3; 4]> {
+        let along_columns = if along_rows == first { second } else { first };
+        (0..width * height)
+            .map(|index| {
+                let corner = along_rows * (index % width) as f32 + along_columns * (index / width) as f32;
+                [corner, corner + first, corner + first + second, corner + second]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn steps_come_from_the_cell_corners() {
+        let (x, y) = (glm::vec3(0.5, 0.0, 0.0), glm::vec3(0.0, -0.5, 0.0));
+        for (width, height) in [(3, 2), (1, 4), (4, 1), (1, 1)] {
+            assert_eq!(cell_steps(&side(width, height, x, y, x), width), (x, y));
+        }
+        // Corners with the edges the other way around
+        for (width, height) in [(3, 2), (1, 4), (4, 1)] {
+            assert_eq!(cell_steps(&side(width, height, y, x, x), width), (x, y));
+        }
+        assert_eq!(cell_steps(&side(2, 2, -x, y, x), 2), (x, y));
+    }
+
+    #[test]
+    fn every_side_is_mapped_to_its_texture_area() {
+        let image = RgbaImage::from_pixel(128, 128, Rgba([255, 255, 255, 255]));
+        for node in build_parts(&image, &ModelType::Slim, OverlayGeometry::Shell).unwrap() {
+            assert_eq!(node.mesh.indices.len(), 6 * 6);
+            assert!(node.mesh.positions.iter().flatten().all(|coordinate| coordinate.is_finite()));
+        }
+    }
+
+    #[test]
+    fn voxels_are_only_made_for_opaque_overlay_pixels() {
+        let mut image = RgbaImage::from_pixel(64, 64, Rgba([0, 0, 0, 0]));
+        // Front of the hat
+        image.put_pixel(40, 8, Rgba([255, 0, 0, 255]));
+        image.put_pixel(41, 8, Rgba([255, 0, 0, 60]));
+
+        let nodes = build_parts(&image, &ModelType::Slim, OverlayGeometry::Voxels).unwrap();
+        let outer_quads: usize = nodes
+            .iter()
+            .filter(|node| node.body_part.is_outer())
+            .map(|node| node.mesh.indices.len() / 6)
+            .sum();
+        assert_eq!(outer_quads, 6);
+    }
+
+    #[test]
+    fn textures_that_are_not_skins_are_refused() {
+        for (width, height) in [(32, 32), (192, 192), (64, 48)] {
+            let image = RgbaImage::new(width, height);
+            let result = build_parts(&image, &ModelType::Classic, OverlayGeometry::Voxels);
+            assert!(matches!(result, Err(ModelExportError::TextureError(_))));
+        }
+
+        // Legacy skins keep their layout, with only the hat as outer layer
+        let image = RgbaImage::from_pixel(128, 64, Rgba([255, 255, 255, 255]));
+        let nodes = build_parts(&image, &ModelType::Classic, OverlayGeometry::Voxels).unwrap();
+        assert_eq!(nodes.len(), 7);
+    }
+}
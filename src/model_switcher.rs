@@ -3,6 +3,9 @@ use gtk::glib::Object;
 use gtk::prelude::ToggleButtonExt;
 use gtk::subclass::prelude::*;
 
+use crate::glium_area::arm_conversion::ConversionStrategy;
+use crate::glium_area::overlay_view::{OverlayView, PaintTarget};
+
 mod imp {
     use gtk::CompositeTemplate;
     use gtk::glib;
@@ -28,6 +31,12 @@ mod imp {
         #[template_child]
         pub model_type_selector: TemplateChild<gtk::DropDown>,
         #[template_child]
+        pub conversion_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub overlay_view_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub paint_target_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
         pub inner_layer_toggle: TemplateChild<gtk::ToggleButton>,
         #[template_child]
         pub outer_layer_toggle: TemplateChild<gtk::ToggleButton>,
@@ -95,4 +104,39 @@ impl ModelSwitcher {
     pub fn outer_layer_toggle(&self) -> gtk::ToggleButton {
         self.imp().outer_layer_toggle.get()
     }
-}
\ No newline at end of file
+
+    pub fn conversion_strategy(&self) -> ConversionStrategy {
+        match self.imp().conversion_selector.selected() {
+            1 => ConversionStrategy::DropMiddle,
+            2 => ConversionStrategy::Merge,
+            3 => ConversionStrategy::Stretch,
+            _ => ConversionStrategy::KeepHidden,
+        }
+    }
+
+    pub fn set_conversion_strategy(&self, strategy: ConversionStrategy) {
+        self.imp().conversion_selector.set_selected(match strategy {
+            ConversionStrategy::KeepHidden => 0,
+            ConversionStrategy::DropMiddle => 1,
+            ConversionStrategy::Merge => 2,
+            ConversionStrategy::Stretch => 3,
+        });
+    }
+
+    pub fn overlay_view(&self) -> OverlayView {
+        match self.imp().overlay_view_selector.selected() {
+            1 => OverlayView::Translucent,
+            2 => OverlayView::OuterOnly,
+            3 => OverlayView::XRay,
+            _ => OverlayView::Normal,
+        }
+    }
+
+    pub fn paint_target(&self) -> PaintTarget {
+        match self.imp().paint_target_selector.selected() {
+            1 => PaintTarget::Base,
+            2 => PaintTarget::Outer,
+            _ => PaintTarget::Automatic,
+        }
+    }
+}
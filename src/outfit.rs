@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, HashMap};
+
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use image::imageops::FilterType;
+
+use crate::glium_area::arm_conversion::{convert_part, ConversionStrategy};
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::skin_parser::{texture_layout, ColorMap, ModelType, SkinParser, TextureLoadError, TextureType};
+
+/// A skin body parts can be taken from.
+pub struct OutfitSource {
+    pub name: String,
+    pub image: DynamicImage,
+    pub model_type: ModelType,
+}
+
+/// Which source every body part is taken from. Parts without a pick stay empty.
+#[derive(Clone, Debug)]
+pub struct Outfit {
+    pub model_type: ModelType,
+    pub picks: BTreeMap<BodyPart, usize>,
+}
+
+impl Outfit {
+    /// Every body part taken from the same source.
+    pub fn from_source(model_type: ModelType, source: usize) -> Self {
+        Outfit {
+            model_type,
+            picks: BodyPart::PLAYER.into_iter().map(|body_part| (body_part, source)).collect(),
+        }
+    }
+
+    pub fn set_pick(&mut self, body_part: BodyPart, source: Option<usize>) {
+        match source {
+            Some(source) => self.picks.insert(body_part, source),
+            None => self.picks.remove(&body_part),
+        };
+    }
+}
+
+/// Builds a skin for `outfit.model_type` out of the picked parts, at the highest resolution
+/// of the sources so HD skins keep their detail. Other sources are scaled up to it.
+/// Arms of sources with the other model type are converted with `strategy`.
+pub fn compose(sources: &[OutfitSource], outfit: &Outfit, strategy: ConversionStrategy) -> Result<RgbaImage, TextureLoadError> {
+    let mut resolution = 1;
+    for source in sources {
+        let (texture_type, source_resolution) = texture_layout(source.image.width(), source.image.height())?;
+        if texture_type != TextureType::Normal {
+            return Err(TextureLoadError::ImageDimensionError(format!("{} is a legacy skin", source.name)));
+        }
+        resolution = resolution.max(source_resolution);
+    }
+    let size = 64 * resolution;
+    let target = SkinParser::with_resolution(&outfit.model_type, TextureType::Normal, resolution);
+    let mut image = RgbaImage::new(size, size);
+
+    let mut color_maps: HashMap<usize, ColorMap> = HashMap::new();
+    for (&body_part, &index) in &outfit.picks {
+        let Some(source) = sources.get(index) else {
+            continue;
+        };
+        if !color_maps.contains_key(&index) {
+            let scaled;
+            let source_image = if source.image.dimensions() == (size, size) {
+                &source.image
+            } else {
+                scaled = source.image.resize_exact(size, size, FilterType::Nearest);
+                &scaled
+            };
+            let parser = SkinParser::with_resolution(&source.model_type, TextureType::Normal, resolution);
+            color_maps.insert(index, parser.load_from_bytes(source_image)?);
+        }
+
+        let cells: Vec<Rgba<u8>> = color_maps[&index][&body_part].values().flatten().copied().collect();
+        let cells = convert_cells(&cells, body_part, source.model_type, outfit.model_type, strategy, resolution);
+        target.export_cells(&body_part, &mut image, &cells);
+    }
+
+    Ok(image)
+}
+
+fn convert_cells(
+    cells: &[Rgba<u8>],
+    body_part: BodyPart,
+    from: ModelType,
+    to: ModelType,
+    strategy: ConversionStrategy,
+    resolution: u32,
+) -> Vec<Rgba<u8>> {
+    let colors: Vec<[f32; 4]> = cells.iter().map(|pixel| pixel.0.map(|channel| f32::from(channel) / 255.0)).collect();
+    convert_part(&colors, body_part, from, to, strategy, resolution as usize)
+        .into_iter()
+        .map(|color| Rgba(color.map(|channel| (channel * 255.0).round() as u8)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(name: &str, image: RgbaImage, model_type: ModelType) -> OutfitSource {
+        OutfitSource { name: name.to_string(), image: DynamicImage::ImageRgba8(image), model_type }
+    }
+
+    fn fixture(name: &str) -> RgbaImage {
+        image::open(format!("tests/skins/{}.png", name)).unwrap().to_rgba8()
+    }
+
+    fn fixtures() -> [OutfitSource; 2] {
+        [source("classic", fixture("classic"), ModelType::Classic), source("slim", fixture("slim"), ModelType::Slim)]
+    }
+
+    /// Pixels of a rectangle, with the color of transparent ones ignored.
+    fn region(image: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> Vec<Rgba<u8>> {
+        let mut pixels = vec![];
+        for y in y..y + height {
+            for x in x..x + width {
+                let pixel = *image.get_pixel(x, y);
+                pixels.push(if pixel[3] == 0 { Rgba([0; 4]) } else { pixel });
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn parts_are_mixed_across_sources() {
+        let (classic, slim) = (fixture("classic"), fixture("slim"));
+        let mut outfit = Outfit::from_source(ModelType::Classic, 0);
+        outfit.set_pick(BodyPart::Head, Some(1));
+        outfit.set_pick(BodyPart::HeadOuter, None);
+
+        let image = compose(&fixtures(), &outfit, ConversionStrategy::default()).unwrap();
+        assert_eq!(image.dimensions(), (64, 64));
+        // Head from the slim skin, without its hat
+        assert_eq!(region(&image, 8, 0, 16, 8), region(&slim, 8, 0, 16, 8));
+        assert_eq!(region(&image, 0, 8, 32, 8), region(&slim, 0, 8, 32, 8));
+        assert!(region(&image, 32, 0, 32, 16).iter().all(|pixel| pixel[3] == 0));
+        // Everything else from the classic skin
+        for (x, y, width, height) in [(16, 16, 24, 16), (40, 16, 16, 16), (0, 16, 16, 16), (16, 48, 32, 16), (0, 32, 56, 16)] {
+            assert_eq!(region(&image, x, y, width, height), region(&classic, x, y, width, height));
+        }
+    }
+
+    #[test]
+    fn slim_arms_are_widened_with_the_strategy() {
+        let slim = fixture("slim");
+        let mut outfit = Outfit::from_source(ModelType::Classic, 0);
+        outfit.set_pick(BodyPart::RightArm, Some(1));
+
+        let image = compose(&fixtures(), &outfit, ConversionStrategy::DropMiddle).unwrap();
+        for y in 20..32 {
+            // The front of the right arm keeps both seams and doubles its middle column
+            let front: Vec<Rgba<u8>> = [44, 45, 45, 46].into_iter().flat_map(|x| region(&slim, x, y, 1, 1)).collect();
+            assert_eq!(region(&image, 44, y, 4, 1), front);
+            // The sides of the arm are the same on both models
+            assert_eq!(region(&image, 40, y, 4, 1), region(&slim, 40, y, 4, 1));
+        }
+    }
+
+    #[test]
+    fn classic_arms_are_narrowed_with_the_strategy() {
+        let classic = fixture("classic");
+        let front = |image: &RgbaImage, y: u32| region(image, 44, y, 3, 1);
+
+        let mut outfit = Outfit::from_source(ModelType::Slim, 1);
+        outfit.set_pick(BodyPart::RightArm, Some(0));
+        let keep_hidden = compose(&fixtures(), &outfit, ConversionStrategy::KeepHidden).unwrap();
+        let drop_middle = compose(&fixtures(), &outfit, ConversionStrategy::DropMiddle).unwrap();
+        for y in 20..32 {
+            // The column at the edge of the arm is dropped, or the one next to it
+            assert_eq!(front(&keep_hidden, y), region(&classic, 45, y, 3, 1));
+            let mut kept = region(&classic, 44, y, 1, 1);
+            kept.extend(region(&classic, 46, y, 2, 1));
+            assert_eq!(front(&drop_middle, y), kept);
+        }
+        // Slim arms leave the last columns empty
+        assert!(region(&keep_hidden, 54, 20, 2, 12).iter().all(|pixel| pixel[3] == 0));
+    }
+
+    #[test]
+    fn hd_sources_keep_their_detail() {
+        let hd = RgbaImage::from_fn(128, 128, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let standard = RgbaImage::from_pixel(64, 64, Rgba([0, 0, 255, 255]));
+        let sources = [source("hd", hd.clone(), ModelType::Classic), source("standard", standard, ModelType::Classic)];
+        let mut outfit = Outfit::from_source(ModelType::Classic, 0);
+        outfit.set_pick(BodyPart::Torso, Some(1));
+
+        let image = compose(&sources, &outfit, ConversionStrategy::default()).unwrap();
+        assert_eq!(image.dimensions(), (128, 128));
+        // Neighbouring pixels of the head front stay apart, the torso is scaled up
+        assert_eq!(image.get_pixel(16, 16), hd.get_pixel(16, 16));
+        assert_eq!(image.get_pixel(17, 16), hd.get_pixel(17, 16));
+        assert_eq!(*image.get_pixel(41, 41), Rgba([0, 0, 255, 255]));
+    }
+}
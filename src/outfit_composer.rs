@@ -0,0 +1,260 @@
+use gtk::gio::Cancellable;
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ButtonExt, FileExt, GridExt, RangeExt, WidgetExt};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+use image::{DynamicImage, GenericImageView};
+use libadwaita as adw;
+use libadwaita::prelude::AdwDialogExt;
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::skin_parser::{texture_layout, ModelType, TextureType};
+use crate::glium_area::software_renderer::{render_skin, RenderOptions};
+use crate::outfit::{compose, Outfit, OutfitSource};
+use crate::utils;
+use crate::window::Window;
+
+const PART_ROWS: [(&str, BodyPart, BodyPart); 6] = [
+    ("Head", BodyPart::Head, BodyPart::HeadOuter),
+    ("Torso", BodyPart::Torso, BodyPart::TorsoOuter),
+    ("Right arm", BodyPart::RightArm, BodyPart::RightArmOuter),
+    ("Left arm", BodyPart::LeftArm, BodyPart::LeftArmOuter),
+    ("Right leg", BodyPart::RightLeg, BodyPart::RightLegOuter),
+    ("Left leg", BodyPart::LeftLeg, BodyPart::LeftLegOuter),
+];
+
+mod imp {
+    use std::cell::RefCell;
+
+    use gtk::{glib, TemplateChild};
+    use gtk::CompositeTemplate;
+    use gtk::glib::WeakRef;
+    use gtk::subclass::prelude::{CompositeTemplate, CompositeTemplateInitializingExt, ObjectImpl, ObjectSubclass, WidgetImpl};
+    use gtk::subclass::widget::WidgetClassExt;
+    use libadwaita as adw;
+    use libadwaita::subclass::dialog::AdwDialogImpl;
+
+    use crate::glium_area::skin_parser::ModelType;
+    use crate::outfit::{Outfit, OutfitSource};
+    use crate::window::Window;
+
+    #[derive(CompositeTemplate)]
+    #[template(file = "../resources/ui/outfit-composer.ui")]
+    pub struct OutfitComposer {
+        #[template_child]
+        pub model_type_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub add_source_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub parts_grid: TemplateChild<gtk::Grid>,
+        #[template_child]
+        pub preview: TemplateChild<gtk::Picture>,
+        #[template_child]
+        pub turn_scale: TemplateChild<gtk::Scale>,
+        #[template_child]
+        pub apply_button: TemplateChild<gtk::Button>,
+        /// "None" followed by the names of `sources`, shared by all part pickers.
+        #[template_child]
+        pub source_names: TemplateChild<gtk::StringList>,
+
+        pub sources: RefCell<Vec<OutfitSource>>,
+        pub outfit: RefCell<Outfit>,
+        pub window: WeakRef<Window>,
+    }
+
+    impl Default for OutfitComposer {
+        fn default() -> Self {
+            OutfitComposer {
+                model_type_selector: Default::default(),
+                add_source_button: Default::default(),
+                parts_grid: Default::default(),
+                preview: Default::default(),
+                turn_scale: Default::default(),
+                apply_button: Default::default(),
+                source_names: Default::default(),
+                sources: Default::default(),
+                outfit: RefCell::new(Outfit::from_source(ModelType::Classic, 0)),
+                window: Default::default(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OutfitComposer {
+        const NAME: &'static str = "OutfitComposer";
+        type Type = super::OutfitComposer;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+    impl ObjectImpl for OutfitComposer {}
+    impl WidgetImpl for OutfitComposer {}
+    impl AdwDialogImpl for OutfitComposer {}
+}
+
+glib::wrapper! {
+    pub struct OutfitComposer(ObjectSubclass<imp::OutfitComposer>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::ShortcutManager;
+}
+
+impl OutfitComposer {
+    /// Starts with every part taken from the skin in the editor.
+    /// Wardrobe templates are offered as sources too.
+    pub fn new(win: &Window) -> Self {
+        let composer: OutfitComposer = glib::Object::new();
+        composer.imp().window.set(Some(win));
+
+        let model_type = win.editor().model_type();
+        composer.imp().outfit.replace(Outfit::from_source(model_type, 0));
+        composer.imp().model_type_selector.set_selected(match model_type {
+            ModelType::Slim => 0,
+            ModelType::Classic => 1,
+        });
+
+        // Outfits are composed at the resolution of the current skin, templates are scaled up
+        composer.add_source(OutfitSource {
+            name: "Current skin".to_string(),
+            image: DynamicImage::ImageRgba8(win.export_texture()),
+            model_type,
+        });
+        let entries = win.template_entries();
+        for entry in entries {
+            match image::open(&entry.path) {
+                Ok(image) if image.dimensions() == (64, 64) => composer.add_source(OutfitSource {
+                    name: entry.metadata.display_name.clone(),
+                    image,
+                    model_type: entry.metadata.model_type,
+                }),
+                Ok(_) => println!("Skipping template {:?}: not a 64x64 skin", entry.path),
+                Err(error) => println!("Skipping template {:?}: {:?}", entry.path, error),
+            }
+        }
+
+        composer.build_part_rows();
+        composer.connect_signals();
+        composer.update_preview();
+        composer
+    }
+
+    fn window(&self) -> Window {
+        self.imp().window.upgrade().expect("OutfitComposer has no window")
+    }
+
+    fn add_source(&self, source: OutfitSource) {
+        self.imp().source_names.append(&source.name);
+        self.imp().sources.borrow_mut().push(source);
+    }
+
+    fn build_part_rows(&self) {
+        let grid = &self.imp().parts_grid;
+        grid.attach(&gtk::Label::new(Some("Inner")), 1, 0, 1, 1);
+        grid.attach(&gtk::Label::new(Some("Outer")), 2, 0, 1, 1);
+
+        for (row, (name, inner, outer)) in PART_ROWS.into_iter().enumerate() {
+            let row = row as i32 + 1;
+            let label = gtk::Label::builder().label(name).xalign(0.0).build();
+            grid.attach(&label, 0, row, 1, 1);
+            grid.attach(&self.create_picker(inner), 1, row, 1, 1);
+            grid.attach(&self.create_picker(outer), 2, row, 1, 1);
+        }
+    }
+
+    fn create_picker(&self, body_part: BodyPart) -> gtk::DropDown {
+        let picker = gtk::DropDown::new(Some(self.imp().source_names.get()), None::<gtk::Expression>);
+        let pick = self.imp().outfit.borrow().picks.get(&body_part).copied();
+        picker.set_selected(pick.map(|source| source as u32 + 1).unwrap_or(0));
+
+        picker.connect_selected_notify(clone!(#[weak(rename_to = composer)] self, move |picker| {
+            let source = match picker.selected() {
+                0 | gtk::INVALID_LIST_POSITION => None,
+                selected => Some(selected as usize - 1),
+            };
+            composer.imp().outfit.borrow_mut().set_pick(body_part, source);
+            composer.update_preview();
+        }));
+        picker
+    }
+
+    fn connect_signals(&self) {
+        self.imp().model_type_selector.connect_selected_notify(clone!(#[weak(rename_to = composer)] self, move |selector| {
+            composer.imp().outfit.borrow_mut().model_type = match selector.selected() {
+                0 => ModelType::Slim,
+                _ => ModelType::Classic,
+            };
+            composer.update_preview();
+        }));
+        self.imp().turn_scale.connect_value_changed(clone!(#[weak(rename_to = composer)] self, move |_| {
+            composer.update_preview();
+        }));
+        self.imp().add_source_button.connect_clicked(clone!(#[weak(rename_to = composer)] self, move |_| {
+            composer.choose_source_file();
+        }));
+        self.imp().apply_button.connect_clicked(clone!(#[weak(rename_to = composer)] self, move |_| {
+            composer.apply();
+        }));
+    }
+
+    fn choose_source_file(&self) {
+        let file_dialog = gtk::FileDialog::builder().title("Add a skin").build();
+        file_dialog.open(Some(&self.window()), Cancellable::NONE, clone!(#[weak(rename_to = composer)] self, move |file| {
+            let Some(path) = file.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            let image = match image::open(&path) {
+                Ok(image) if matches!(texture_layout(image.width(), image.height()), Ok((TextureType::Normal, _))) => image,
+                Ok(_) => {
+                    println!("Only 64x64 skins and their HD versions can be used as a source");
+                    return;
+                }
+                Err(error) => {
+                    println!("Error opening {:?}: {:?}", path, error);
+                    return;
+                }
+            };
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let model_type = utils::guess_model_type(&image).model_type;
+            composer.add_source(OutfitSource { name, image, model_type });
+        }));
+    }
+
+    fn compose(&self) -> Option<image::RgbaImage> {
+        let sources = self.imp().sources.borrow();
+        let outfit = self.imp().outfit.borrow();
+        compose(&sources, &outfit, self.window().conversion_strategy())
+            .map_err(|error| println!("Error composing outfit: {:?}", error))
+            .ok()
+    }
+
+    fn update_preview(&self) {
+        let Some(image) = self.compose() else {
+            return;
+        };
+        let model_type = self.imp().outfit.borrow().model_type;
+        let options = RenderOptions::default()
+            .with_size(240, 360)
+            .with_angles(self.imp().turn_scale.value() as f32, 10.0);
+        match render_skin(&DynamicImage::ImageRgba8(image), &model_type, &options) {
+            Ok(render) => self.imp().preview.set_paintable(Some(&utils::texture_from_image(&render))),
+            Err(error) => println!("Error rendering preview: {:?}", error),
+        }
+    }
+
+    fn apply(&self) {
+        let Some(image) = self.compose() else {
+            return;
+        };
+        let model_type = self.imp().outfit.borrow().model_type;
+        self.window().apply_skin_image(DynamicImage::ImageRgba8(image), model_type);
+        self.close();
+    }
+}
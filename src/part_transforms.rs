@@ -0,0 +1,189 @@
+use image::{imageops, RgbaImage};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::cube_side::CubeSide;
+use crate::glium_area::skin_parser::{texture_layout, ModelType, SkinParser, TextureLoadError, TextureType};
+use crate::skin_effects::{changed_cells, CellColor};
+
+#[derive(Debug)]
+pub enum TransformError {
+    TextureLoad(TextureLoadError),
+    /// The faces of the two parts are not the same size.
+    SizeMismatch(BodyPart, BodyPart),
+    /// Only square faces can be turned by a quarter.
+    NotSquare(BodyPart, CubeSide),
+}
+
+impl From<TextureLoadError> for TransformError {
+    fn from(error: TextureLoadError) -> Self {
+        TransformError::TextureLoad(error)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+    HalfTurn,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Transform {
+    /// Paints the mirror image of a part on another one: every face is flipped and the
+    /// left and right sides trade places, like Minecraft does for legacy skins.
+    Mirror { from: BodyPart, to: BodyPart },
+    /// Paints a part on another one as it is, e.g. the base layer on the outer one.
+    Copy { from: BodyPart, to: BodyPart },
+    Swap(BodyPart, BodyPart),
+    Flip { body_part: BodyPart, side: CubeSide, vertical: bool },
+    Rotate { body_part: BodyPart, side: CubeSide, rotation: Rotation },
+}
+
+fn face_of(faces: &[(CubeSide, [u32; 4])], side: CubeSide) -> [u32; 4] {
+    faces.iter().find(|(face_side, _)| *face_side == side).map(|(_, region)| *region).expect("Every body part has six sides")
+}
+
+fn crop(skin: &RgbaImage, [x, y, width, height]: [u32; 4]) -> RgbaImage {
+    imageops::crop_imm(skin, x, y, width, height).to_image()
+}
+
+fn paste(result: &mut RgbaImage, face: &RgbaImage, [x, y, _, _]: [u32; 4]) {
+    imageops::replace(result, face, i64::from(x), i64::from(y));
+}
+
+/// Paints the faces of `from` on the faces of `to`, as a mirror image when `mirrored`.
+fn paint_part(
+    skin: &RgbaImage,
+    result: &mut RgbaImage,
+    parser: &SkinParser,
+    from: BodyPart,
+    to: BodyPart,
+    mirrored: bool,
+) -> Result<(), TransformError> {
+    let (source, target) = (parser.side_regions(&from), parser.side_regions(&to));
+    for (side, target_region) in target {
+        let source_side = match (mirrored, side) {
+            (true, CubeSide::Left) => CubeSide::Right,
+            (true, CubeSide::Right) => CubeSide::Left,
+            _ => side,
+        };
+        let source_region = face_of(&source, source_side);
+        if source_region[2..] != target_region[2..] {
+            return Err(TransformError::SizeMismatch(from, to));
+        }
+        let face = crop(skin, source_region);
+        let face = if mirrored { imageops::flip_horizontal(&face) } else { face };
+        paste(result, &face, target_region);
+    }
+    Ok(())
+}
+
+/// Applies the transform and lists the cells that change, to be painted as one step.
+pub fn transform(skin: &RgbaImage, model_type: &ModelType, transform: &Transform) -> Result<Vec<CellColor>, TransformError> {
+    let (_, resolution) = texture_layout(skin.width(), skin.height())?;
+    let parser = SkinParser::with_resolution(model_type, TextureType::Normal, resolution);
+    let mut result = skin.clone();
+    match *transform {
+        Transform::Mirror { from, to } => paint_part(skin, &mut result, &parser, from, to, true)?,
+        Transform::Copy { from, to } => paint_part(skin, &mut result, &parser, from, to, false)?,
+        Transform::Swap(first, second) => {
+            paint_part(skin, &mut result, &parser, first, second, false)?;
+            paint_part(skin, &mut result, &parser, second, first, false)?;
+        }
+        Transform::Flip { body_part, side, vertical } => {
+            let region = face_of(&parser.side_regions(&body_part), side);
+            let face = crop(skin, region);
+            let face = if vertical { imageops::flip_vertical(&face) } else { imageops::flip_horizontal(&face) };
+            paste(&mut result, &face, region);
+        }
+        Transform::Rotate { body_part, side, rotation } => {
+            let region = face_of(&parser.side_regions(&body_part), side);
+            if rotation != Rotation::HalfTurn && region[2] != region[3] {
+                return Err(TransformError::NotSquare(body_part, side));
+            }
+            let face = crop(skin, region);
+            let face = match rotation {
+                Rotation::Clockwise => imageops::rotate90(&face),
+                Rotation::CounterClockwise => imageops::rotate270(&face),
+                Rotation::HalfTurn => imageops::rotate180(&face),
+            };
+            paste(&mut result, &face, region);
+        }
+    }
+    Ok(changed_cells(&parser, skin, &result))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+    use crate::legacy_skin::upgrade_legacy;
+
+    /// A skin where every pixel has its own opaque color.
+    fn skin(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, ((x * 7 + y * 13) % 256) as u8, 255]))
+    }
+
+    fn apply(skin: &RgbaImage, cells: &[CellColor]) -> RgbaImage {
+        let parser = SkinParser::new(&ModelType::Classic, TextureType::Normal);
+        let mut result = skin.clone();
+        for body_part in BodyPart::PLAYER {
+            let mut colors: Vec<Rgba<u8>> = parser.load_from_bytes(&skin.clone().into()).unwrap()[&body_part].values().flatten().copied().collect();
+            for (_, cell_index, color) in cells.iter().filter(|(part, _, _)| *part == body_part) {
+                colors[*cell_index] = *color;
+            }
+            parser.export_cells(&body_part, &mut result, &colors);
+        }
+        result
+    }
+
+    #[test]
+    fn mirroring_matches_the_legacy_upgrade() {
+        let upgraded = upgrade_legacy(&skin(64, 32)).unwrap().image;
+        let mut scrambled = upgraded.clone();
+        // Upgraded legacy skins have an opaque base layer, the unused corners are black
+        imageops::replace(&mut scrambled, &RgbaImage::from_pixel(16, 16, Rgba([0, 0, 0, 255])), 32, 48);
+        let cells = transform(&scrambled, &ModelType::Classic, &Transform::Mirror { from: BodyPart::RightArm, to: BodyPart::LeftArm }).unwrap();
+        assert!(cells.iter().all(|(part, _, _)| *part == BodyPart::LeftArm));
+        assert_eq!(apply(&scrambled, &cells), upgraded);
+    }
+
+    #[test]
+    fn parts_are_copied_and_swapped() {
+        let original = skin(64, 64);
+        let cells = transform(&original, &ModelType::Classic, &Transform::Swap(BodyPart::RightLeg, BodyPart::LeftLeg)).unwrap();
+        let swapped = apply(&original, &cells);
+        // Right leg front at 4,20 and left leg front at 20,52
+        assert_eq!(swapped.get_pixel(4, 20), original.get_pixel(20, 52));
+        assert_eq!(swapped.get_pixel(20, 52), original.get_pixel(4, 20));
+
+        let cells = transform(&original, &ModelType::Classic, &Transform::Copy { from: BodyPart::Head, to: BodyPart::HeadOuter }).unwrap();
+        let copied = apply(&original, &cells);
+        assert_eq!(copied.get_pixel(40, 8), original.get_pixel(8, 8));
+        assert!(matches!(
+            transform(&original, &ModelType::Classic, &Transform::Copy { from: BodyPart::Head, to: BodyPart::Torso }),
+            Err(TransformError::SizeMismatch(BodyPart::Head, BodyPart::Torso))
+        ));
+    }
+
+    #[test]
+    fn faces_are_flipped_and_turned() {
+        let original = skin(64, 64);
+        // The head top takes 8,0 to 16,8
+        let turned = apply(&original, &transform(&original, &ModelType::Classic, &Transform::Rotate {
+            body_part: BodyPart::Head, side: CubeSide::Top, rotation: Rotation::Clockwise,
+        }).unwrap());
+        assert_eq!(turned.get_pixel(15, 0), original.get_pixel(8, 0));
+        assert_eq!(turned.get_pixel(8, 0), original.get_pixel(8, 7));
+
+        let flipped = apply(&original, &transform(&original, &ModelType::Classic, &Transform::Flip {
+            body_part: BodyPart::Torso, side: CubeSide::Front, vertical: true,
+        }).unwrap());
+        assert_eq!(flipped.get_pixel(20, 20), original.get_pixel(20, 31));
+        assert!(matches!(
+            transform(&original, &ModelType::Classic, &Transform::Rotate { body_part: BodyPart::Torso, side: CubeSide::Front, rotation: Rotation::Clockwise }),
+            Err(TransformError::NotSquare(BodyPart::Torso, CubeSide::Front))
+        ));
+    }
+}
@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+
+/// Glyphs of the 3x5 font, top row first.
+const SMALL_GLYPHS: [(char, [&str; 5]); 49] = [
+    ('A', [".#.", "#.#", "###", "#.#", "#.#"]),
+    ('B', ["##.", "#.#", "##.", "#.#", "##."]),
+    ('C', [".##", "#..", "#..", "#..", ".##"]),
+    ('D', ["##.", "#.#", "#.#", "#.#", "##."]),
+    ('E', ["###", "#..", "##.", "#..", "###"]),
+    ('F', ["###", "#..", "##.", "#..", "#.."]),
+    ('G', [".##", "#..", "#.#", "#.#", ".##"]),
+    ('H', ["#.#", "#.#", "###", "#.#", "#.#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..#", "..#", "..#", "#.#", ".#."]),
+    ('K', ["#.#", "#.#", "##.", "#.#", "#.#"]),
+    ('L', ["#..", "#..", "#..", "#..", "###"]),
+    ('M', ["#.#", "###", "###", "#.#", "#.#"]),
+    ('N', ["#.#", "###", "###", "###", "#.#"]),
+    ('O', [".#.", "#.#", "#.#", "#.#", ".#."]),
+    ('P', ["##.", "#.#", "##.", "#..", "#.."]),
+    ('Q', [".#.", "#.#", "#.#", "###", ".##"]),
+    ('R', ["##.", "#.#", "##.", "#.#", "#.#"]),
+    ('S', [".##", "#..", ".#.", "..#", "##."]),
+    ('T', ["###", ".#.", ".#.", ".#.", ".#."]),
+    ('U', ["#.#", "#.#", "#.#", "#.#", "###"]),
+    ('V', ["#.#", "#.#", "#.#", ".#.", ".#."]),
+    ('W', ["#.#", "#.#", "###", "###", "#.#"]),
+    ('X', ["#.#", "#.#", ".#.", "#.#", "#.#"]),
+    ('Y', ["#.#", "#.#", ".#.", ".#.", ".#."]),
+    ('Z', ["###", "..#", ".#.", "#..", "###"]),
+    ('0', ["###", "#.#", "#.#", "#.#", "###"]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["##.", "..#", ".#.", "#..", "###"]),
+    ('3', ["##.", "..#", ".#.", "..#", "##."]),
+    ('4', ["#.#", "#.#", "###", "..#", "..#"]),
+    ('5', ["###", "#..", "##.", "..#", "##."]),
+    ('6', [".##", "#..", "###", "#.#", "###"]),
+    ('7', ["###", "..#", ".#.", ".#.", ".#."]),
+    ('8', ["###", "#.#", "###", "#.#", "###"]),
+    ('9', ["###", "#.#", "###", "..#", "##."]),
+    (' ', ["...", "...", "...", "...", "..."]),
+    ('.', ["...", "...", "...", "...", ".#."]),
+    (',', ["...", "...", "...", ".#.", "#.."]),
+    ('!', [".#.", ".#.", ".#.", "...", ".#."]),
+    ('?', ["##.", "..#", ".#.", "...", ".#."]),
+    ('-', ["...", "...", "###", "...", "..."]),
+    ('+', ["...", ".#.", "###", ".#.", "..."]),
+    (':', ["...", ".#.", "...", ".#.", "..."]),
+    ('\'', [".#.", ".#.", "...", "...", "..."]),
+    ('/', ["..#", "..#", ".#.", "#..", "#.."]),
+    ('#', ["#.#", "###", "#.#", "###", "#.#"]),
+    ('(', ["..#", ".#.", ".#.", ".#.", "..#"]),
+    (')', ["#..", ".#.", ".#.", ".#.", "#.."]),
+];
+
+/// Glyphs of the 4x6 font, top row first.
+const MEDIUM_GLYPHS: [(char, [&str; 6]); 49] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".###", "#...", "#...", "#...", "#...", ".###"]),
+    ('D', ["###.", "#..#", "#..#", "#..#", "#..#", "###."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".###", "#...", "#.##", "#..#", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', ["###.", ".#..", ".#..", ".#..", ".#..", "###."]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#..#", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('M', ["#..#", "####", "####", "#..#", "#..#", "#..#"]),
+    ('N', ["#..#", "##.#", "#.##", "#..#", "#..#", "#..#"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('Q', [".##.", "#..#", "#..#", "#..#", "#.#.", ".#.#"]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", ".##.", "...#", "...#", "###."]),
+    ('T', ["###.", ".#..", ".#..", ".#..", ".#..", ".#.."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('V', ["#..#", "#..#", "#..#", "#..#", ".##.", ".##."]),
+    ('W', ["#..#", "#..#", "#..#", "####", "####", "#..#"]),
+    ('X', ["#..#", "#..#", ".##.", ".##.", "#..#", "#..#"]),
+    ('Y', ["#.#.", "#.#.", ".#..", ".#..", ".#..", ".#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+    ('0', [".##.", "#..#", "#.##", "##.#", "#..#", ".##."]),
+    ('1', [".#..", "##..", ".#..", ".#..", ".#..", "###."]),
+    ('2', [".##.", "#..#", "..#.", ".#..", "#...", "####"]),
+    ('3', ["###.", "...#", ".##.", "...#", "...#", "###."]),
+    ('4', ["#..#", "#..#", "####", "...#", "...#", "...#"]),
+    ('5', ["####", "#...", "###.", "...#", "...#", "###."]),
+    ('6', [".##.", "#...", "###.", "#..#", "#..#", ".##."]),
+    ('7', ["####", "...#", "..#.", ".#..", ".#..", ".#.."]),
+    ('8', [".##.", "#..#", ".##.", "#..#", "#..#", ".##."]),
+    ('9', [".##.", "#..#", "#..#", ".###", "...#", ".##."]),
+    (' ', ["....", "....", "....", "....", "....", "...."]),
+    ('.', ["....", "....", "....", "....", "....", ".#.."]),
+    (',', ["....", "....", "....", "....", ".#..", "#..."]),
+    ('!', [".#..", ".#..", ".#..", ".#..", "....", ".#.."]),
+    ('?', [".##.", "#..#", "..#.", ".#..", "....", ".#.."]),
+    ('-', ["....", "....", "####", "....", "....", "...."]),
+    ('+', ["....", ".#..", "###.", ".#..", "....", "...."]),
+    (':', ["....", ".#..", "....", "....", ".#..", "...."]),
+    ('\'', [".#..", ".#..", "....", "....", "....", "...."]),
+    ('/', ["...#", "..#.", "..#.", ".#..", ".#..", "#..."]),
+    ('#', [".#.#", "####", ".#.#", "#.#.", "####", "#.#."]),
+    ('(', ["..#.", ".#..", ".#..", ".#..", ".#..", "..#."]),
+    (')', [".#..", "..#.", "..#.", "..#.", "..#.", ".#.."]),
+];
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    InvalidBdf(String),
+}
+
+impl From<io::Error> for FontError {
+    fn from(error: io::Error) -> Self {
+        FontError::Io(error)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BuiltinFont {
+    /// 3x5 pixels, fits on the front of an arm or a leg.
+    Small,
+    /// 4x6 pixels.
+    Medium,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum HorizontalAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum VerticalAlign {
+    Top,
+    #[default]
+    Middle,
+    Bottom,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextOptions {
+    pub align: HorizontalAlign,
+    pub vertical_align: VerticalAlign,
+    /// Cells per font pixel along an edge.
+    pub scale: u32,
+    /// Color of a one cell outline around the letters.
+    pub outline: Option<Rgba<u8>>,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        TextOptions { align: Default::default(), vertical_align: Default::default(), scale: 1, outline: None }
+    }
+}
+
+/// A glyph placed on the baseline: `y_offset` is how far its bottom row is above it.
+#[derive(Clone, Debug)]
+struct Glyph {
+    advance: i32,
+    width: u32,
+    height: u32,
+    x_offset: i32,
+    y_offset: i32,
+    bits: Vec<bool>,
+}
+
+/// Pixels of rendered text, `true` where a letter is.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Bitmap {
+    pub width: u32,
+    pub height: u32,
+    pub bits: Vec<bool>,
+}
+
+impl Bitmap {
+    fn new(width: u32, height: u32) -> Self {
+        Bitmap { width, height, bits: vec![false; (width * height) as usize] }
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> bool {
+        x >= 0 && y >= 0 && x < i64::from(self.width) && y < i64::from(self.height)
+            && self.bits[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    fn set(&mut self, x: i64, y: i64) {
+        if x >= 0 && y >= 0 && x < i64::from(self.width) && y < i64::from(self.height) {
+            self.bits[(y as u32 * self.width + x as u32) as usize] = true;
+        }
+    }
+}
+
+pub struct PixelFont {
+    glyphs: HashMap<char, Glyph>,
+    /// Rows above the baseline.
+    ascent: i32,
+    /// Rows below the baseline.
+    descent: i32,
+    /// Empty rows between lines.
+    line_gap: i32,
+}
+
+impl PixelFont {
+    pub fn builtin(font: BuiltinFont) -> Self {
+        let glyphs = match font {
+            BuiltinFont::Small => SMALL_GLYPHS.iter().map(|(character, rows)| (*character, Self::glyph_from_rows(rows))).collect(),
+            BuiltinFont::Medium => MEDIUM_GLYPHS.iter().map(|(character, rows)| (*character, Self::glyph_from_rows(rows))).collect(),
+        };
+        let ascent = match font {
+            BuiltinFont::Small => 5,
+            BuiltinFont::Medium => 6,
+        };
+        PixelFont { glyphs, ascent, descent: 0, line_gap: 1 }
+    }
+
+    /// Glyphs sit on the baseline, with a column of space after them.
+    fn glyph_from_rows(rows: &[&str]) -> Glyph {
+        let width = rows[0].len() as u32;
+        Glyph {
+            advance: width as i32 + 1,
+            width,
+            height: rows.len() as u32,
+            x_offset: 0,
+            y_offset: 0,
+            bits: rows.iter().flat_map(|row| row.chars().map(|pixel| pixel == '#')).collect(),
+        }
+    }
+
+    pub fn load_bdf(path: &Path) -> Result<Self, FontError> {
+        Self::from_bdf(&fs::read_to_string(path)?)
+    }
+
+    /// Reads a font in the Glyph Bitmap Distribution Format. Encodings are taken as
+    /// Unicode code points.
+    pub fn from_bdf(bdf: &str) -> Result<Self, FontError> {
+        let invalid = |message: &str| FontError::InvalidBdf(message.to_string());
+        let numbers = |values: &[&str]| -> Result<Vec<i32>, FontError> {
+            values.iter().map(|value| value.parse::<i32>().map_err(|_| invalid("a number is expected"))).collect()
+        };
+
+        let mut bounding_box = None;
+        let mut ascent = None;
+        let mut descent = None;
+        let mut glyphs = HashMap::new();
+        let mut lines = bdf.lines();
+        while let Some(line) = lines.next() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["FONTBOUNDINGBOX", values @ ..] => bounding_box = Some(numbers(values)?),
+                ["FONT_ASCENT", value] => ascent = Some(numbers(&[value])?[0]),
+                ["FONT_DESCENT", value] => descent = Some(numbers(&[value])?[0]),
+                ["STARTCHAR", ..] => {
+                    let mut encoding = None;
+                    let mut advance = None;
+                    let mut glyph_box = None;
+                    loop {
+                        let line = lines.next().ok_or(invalid("a glyph has no end"))?;
+                        let words: Vec<&str> = line.split_whitespace().collect();
+                        match words.as_slice() {
+                            ["ENCODING", value, ..] => encoding = Some(numbers(&[value])?[0]),
+                            ["DWIDTH", value, ..] => advance = Some(numbers(&[value])?[0]),
+                            ["BBX", values @ ..] => glyph_box = Some(numbers(values)?),
+                            ["BITMAP"] => break,
+                            _ => {}
+                        }
+                    }
+                    let glyph_box = glyph_box.filter(|values| values.len() == 4).ok_or(invalid("a glyph has no BBX"))?;
+                    let (width, height) = (glyph_box[0].max(0) as u32, glyph_box[1].max(0) as u32);
+                    let mut bits = Vec::with_capacity((width * height) as usize);
+                    for _ in 0..height {
+                        let row = lines.next().ok_or(invalid("a bitmap is cut short"))?.trim();
+                        let row: Vec<u8> = row.as_bytes()
+                            .chunks(2)
+                            .map(|pair| std::str::from_utf8(pair).ok().and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+                            .collect::<Option<_>>()
+                            .ok_or(invalid("a bitmap row is not hex"))?;
+                        // Rows are padded to whole bytes, the leftmost pixel is the highest bit
+                        bits.extend((0..width as usize).map(|column| row.get(column / 8).is_some_and(|byte| byte >> (7 - column % 8) & 1 == 1)));
+                    }
+                    let glyph = Glyph {
+                        advance: advance.unwrap_or(width as i32),
+                        width,
+                        height,
+                        x_offset: glyph_box[2],
+                        y_offset: glyph_box[3],
+                        bits,
+                    };
+                    if let Some(character) = encoding.and_then(|encoding| u32::try_from(encoding).ok()).and_then(char::from_u32) {
+                        glyphs.insert(character, glyph);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let bounding_box = bounding_box.filter(|values| values.len() == 4).ok_or(invalid("the font has no FONTBOUNDINGBOX"))?;
+        if glyphs.is_empty() {
+            return Err(invalid("the font has no glyphs"));
+        }
+        Ok(PixelFont {
+            glyphs,
+            ascent: ascent.unwrap_or(bounding_box[1] + bounding_box[3]),
+            descent: descent.unwrap_or(-bounding_box[3]),
+            line_gap: 0,
+        })
+    }
+
+    /// Lowercase letters fall back to uppercase ones, unknown characters to `?`.
+    fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs
+            .get(&character)
+            .or_else(|| character.to_uppercase().next().and_then(|upper| self.glyphs.get(&upper)))
+            .or_else(|| self.glyphs.get(&'?'))
+    }
+
+    fn line_width(&self, line: &str) -> i32 {
+        let glyphs: Vec<&Glyph> = line.chars().filter_map(|character| self.glyph(character)).collect();
+        let Some(last) = glyphs.last() else {
+            return 0;
+        };
+        let advances: i32 = glyphs.iter().map(|glyph| glyph.advance).sum();
+        // The line ends with the last letter, not with the space after it
+        (advances - last.advance + last.x_offset + last.width as i32).max(0)
+    }
+
+    /// Renders the lines of the text, each one aligned within the widest one.
+    pub fn render(&self, text: &str, align: HorizontalAlign) -> Bitmap {
+        let lines: Vec<&str> = text.lines().collect();
+        let width = lines.iter().map(|line| self.line_width(line)).max().unwrap_or(0);
+        let line_height = self.ascent + self.descent;
+        let height = (line_height + self.line_gap) * lines.len() as i32 - self.line_gap;
+        let mut bitmap = Bitmap::new(width.max(0) as u32, height.max(0) as u32);
+
+        for (index, line) in lines.iter().enumerate() {
+            let mut pen = match align {
+                HorizontalAlign::Left => 0,
+                HorizontalAlign::Center => (width - self.line_width(line)) / 2,
+                HorizontalAlign::Right => width - self.line_width(line),
+            };
+            let baseline = index as i32 * (line_height + self.line_gap) + self.ascent;
+            for glyph in line.chars().filter_map(|character| self.glyph(character)) {
+                let top = baseline - glyph.y_offset - glyph.height as i32;
+                for row in 0..glyph.height {
+                    for column in 0..glyph.width {
+                        if glyph.bits[(row * glyph.width + column) as usize] {
+                            bitmap.set(i64::from(pen + glyph.x_offset) + i64::from(column), i64::from(top) + i64::from(row));
+                        }
+                    }
+                }
+                pen += glyph.advance;
+            }
+        }
+        bitmap
+    }
+}
+
+/// Text in the given color on an image of `width` by `height` cells, transparent
+/// around it. Text that doesn't fit is cut at the edges.
+pub fn text_image(font: &PixelFont, text: &str, color: Rgba<u8>, options: &TextOptions, width: u32, height: u32) -> RgbaImage {
+    let bitmap = font.render(text, options.align);
+    let scale = options.scale.max(1);
+    let padding = u32::from(options.outline.is_some());
+    let text_width = i64::from(bitmap.width * scale + 2 * padding);
+    let text_height = i64::from(bitmap.height * scale + 2 * padding);
+
+    let left = match options.align {
+        HorizontalAlign::Left => 0,
+        HorizontalAlign::Center => (i64::from(width) - text_width) / 2,
+        HorizontalAlign::Right => i64::from(width) - text_width,
+    };
+    let top = match options.vertical_align {
+        VerticalAlign::Top => 0,
+        VerticalAlign::Middle => (i64::from(height) - text_height) / 2,
+        VerticalAlign::Bottom => i64::from(height) - text_height,
+    };
+    let is_text = |x: i64, y: i64| {
+        let (x, y) = (x - left - i64::from(padding), y - top - i64::from(padding));
+        x >= 0 && y >= 0 && bitmap.get(x / i64::from(scale), y / i64::from(scale))
+    };
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (x, y) = (i64::from(x), i64::from(y));
+        if is_text(x, y) {
+            return color;
+        }
+        match options.outline {
+            Some(outline) if (-1..=1).any(|dy| (-1..=1).any(|dx| is_text(x + dx, y + dy))) => outline,
+            _ => Rgba([0; 4]),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+    fn rows(bitmap: &Bitmap) -> Vec<String> {
+        (0..bitmap.height)
+            .map(|y| (0..bitmap.width).map(|x| if bitmap.get(x.into(), y.into()) { '#' } else { '.' }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn builtin_fonts_space_letters_and_lines() {
+        let font = PixelFont::builtin(BuiltinFont::Small);
+        assert_eq!(rows(&font.render("17", HorizontalAlign::Left)), [".#..###", "##....#", ".#...#.", ".#...#.", "###..#."]);
+
+        // Lowercase letters are drawn uppercase, short lines are centered
+        let lines = font.render("i\nHI", HorizontalAlign::Center);
+        assert_eq!((lines.width, lines.height), (7, 11));
+        assert_eq!(rows(&lines)[6], "#.#.###");
+        assert_eq!(rows(&lines)[0], "..###..");
+
+        let medium = PixelFont::builtin(BuiltinFont::Medium);
+        assert_eq!(medium.render("10", HorizontalAlign::Left).height, 6);
+    }
+
+    #[test]
+    fn bdf_glyphs_sit_on_the_baseline() {
+        let bdf = "STARTFONT 2.1\nFONTBOUNDINGBOX 4 6 0 -1\nSTARTPROPERTIES 2\nFONT_ASCENT 5\nFONT_DESCENT 1\nENDPROPERTIES\nCHARS 2\n\
+            STARTCHAR T\nENCODING 84\nDWIDTH 4 0\nBBX 3 3 0 2\nBITMAP\nE0\n40\n40\nENDCHAR\n\
+            STARTCHAR comma\nENCODING 44\nDWIDTH 2 0\nBBX 1 2 0 -1\nBITMAP\n80\n80\nENDCHAR\nENDFONT\n";
+        let font = PixelFont::from_bdf(bdf).unwrap();
+        assert_eq!(rows(&font.render("T,", HorizontalAlign::Left)), ["###..", ".#...", ".#...", ".....", "....#", "....#"]);
+        // Letter spacing comes from the glyphs, not from the gap between lines
+        assert_eq!(rows(&font.render(",\nT", HorizontalAlign::Right))[4..9], ["..#", "..#", "###", ".#.", ".#."]);
+        assert!(PixelFont::from_bdf("STARTFONT 2.1\nENDFONT\n").is_err());
+
+        // Rows of glyphs wider than any integer are read byte by byte
+        let wide = format!(
+            "STARTFONT 2.1\nFONTBOUNDINGBOX 136 1 0 0\nSTARTPROPERTIES 2\nFONT_ASCENT 1\nFONT_DESCENT 0\nENDPROPERTIES\nCHARS 1\n\
+            STARTCHAR W\nENCODING 87\nDWIDTH 136 0\nBBX 136 1 0 0\nBITMAP\n{}01\nENDCHAR\nENDFONT\n",
+            "00".repeat(16),
+        );
+        let line = &rows(&PixelFont::from_bdf(&wide).unwrap().render("W", HorizontalAlign::Left))[0];
+        assert_eq!(line.len(), 136);
+        assert_eq!(line.find('#'), Some(135));
+    }
+
+    #[test]
+    fn text_is_aligned_on_the_face_with_an_outline() {
+        let font = PixelFont::builtin(BuiltinFont::Small);
+        let options = TextOptions { outline: Some(BLACK), ..Default::default() };
+        let image = text_image(&font, "1", WHITE, &options, 8, 12);
+        // 3x5 letter and its outline make 5x7, centered on 8x12
+        assert_eq!(image.pixels().filter(|pixel| **pixel == WHITE).count(), 8);
+        assert_eq!(*image.get_pixel(2, 4), WHITE);
+        assert_eq!(*image.get_pixel(1, 3), BLACK);
+        assert_eq!(image.get_pixel(0, 3)[3], 0);
+
+        let options = TextOptions { align: HorizontalAlign::Right, vertical_align: VerticalAlign::Bottom, scale: 2, outline: None };
+        let image = text_image(&font, "1", WHITE, &options, 8, 12);
+        assert_eq!(*image.get_pixel(4, 11), WHITE);
+        assert_eq!(*image.get_pixel(7, 11), WHITE);
+        assert_eq!(image.get_pixel(1, 11)[3], 0);
+    }
+}
@@ -0,0 +1,92 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::{DynamicImage, RgbaImage};
+use serde_json::json;
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::skin_parser::{ModelType, TextureLoadError};
+use crate::glium_area::software_renderer::{render_skin, RenderOptions};
+use crate::utils;
+
+/// The game only shows head textures hosted by Mojang.
+pub const TEXTURE_URL_PREFIX: &str = "http://textures.minecraft.net/texture/";
+
+/// Value of the `textures` property of a player profile: base64 of
+/// `{"textures":{"SKIN":{"url":...}}}`.
+pub fn textures_value(url: &str) -> String {
+    let textures = json!({ "textures": { "SKIN": { "url": url } } });
+    STANDARD.encode(textures.to_string())
+}
+
+/// `/give` command for Java Edition 1.20.5 and newer, where item data is stored in components.
+pub fn give_command(value: &str) -> String {
+    format!(
+        "/give @p minecraft:player_head[minecraft:profile={{properties:[{{name:\"textures\",value:\"{}\"}}]}}] 1",
+        value,
+    )
+}
+
+/// `/give` command for Java Edition 1.16 to 1.20.4, where item data is NBT.
+pub fn legacy_give_command(value: &str) -> String {
+    let [a, b, c, d] = profile_id(value);
+    format!(
+        "/give @p minecraft:player_head{{SkullOwner:{{Id:[I;{},{},{},{}],Properties:{{textures:[{{Value:\"{}\"}}]}}}}}} 1",
+        a, b, c, d, value,
+    )
+}
+
+/// Legacy heads need a profile id. Deriving it from the texture keeps heads with the
+/// same texture stackable.
+fn profile_id(value: &str) -> [i32; 4] {
+    let high = utils::fnv1a(value.as_bytes());
+    let low = utils::fnv1a(format!("{}#", value).as_bytes());
+    [(high >> 32) as i32, high as i32, (low >> 32) as i32, low as i32]
+}
+
+/// The head with its outer layer, seen from above at an angle like a placed head.
+pub fn render_head(image: &DynamicImage, model_type: &ModelType, size: u32) -> Result<RgbaImage, TextureLoadError> {
+    let mut options = RenderOptions::default()
+        .with_size(size, size)
+        .with_angles(45.0, 30.0);
+    options.parts = [BodyPart::Head, BodyPart::HeadOuter].into_iter().collect();
+    render_skin(image, model_type, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    const URL: &str = "http://textures.minecraft.net/texture/3b60a1f6d562f52aaebbf1434f1de147933a3affe0e764fa49ea057536623cd3";
+
+    #[test]
+    fn textures_value_is_base64_profile_json() {
+        let json = STANDARD.decode(textures_value(URL)).unwrap();
+        let textures: Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(textures, json!({ "textures": { "SKIN": { "url": URL } } }));
+    }
+
+    #[test]
+    fn give_command_uses_components() {
+        assert_eq!(
+            give_command("dGVzdA=="),
+            "/give @p minecraft:player_head[minecraft:profile={properties:[{name:\"textures\",value:\"dGVzdA==\"}]}] 1",
+        );
+    }
+
+    #[test]
+    fn legacy_give_command_uses_nbt() {
+        assert_eq!(
+            legacy_give_command("dGVzdA=="),
+            "/give @p minecraft:player_head{SkullOwner:{Id:[I;680854631,-212532323,1245330082,2037734362],Properties:{textures:[{Value:\"dGVzdA==\"}]}}} 1",
+        );
+    }
+
+    #[test]
+    fn profile_id_follows_the_texture() {
+        let value = textures_value(URL);
+        assert_eq!(profile_id(&value), profile_id(&textures_value(URL)));
+        assert_ne!(profile_id(&value), profile_id("dGVzdA=="));
+    }
+}
@@ -0,0 +1,210 @@
+use gtk::gio::Cancellable;
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ButtonExt, EditableExt, FileExt, TextBufferExt, TextViewExt, WidgetExt};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+use image::DynamicImage;
+use libadwaita as adw;
+
+use crate::glium_area::skin_parser::ModelType;
+use crate::player_head::{give_command, legacy_give_command, render_head, textures_value, TEXTURE_URL_PREFIX};
+use crate::utils;
+use crate::window::Window;
+
+const PREVIEW_SIZE: u32 = 256;
+const IMAGE_SIZES: [u32; 4] = [64, 128, 256, 512];
+
+mod imp {
+    use std::cell::RefCell;
+
+    use gtk::{glib, TemplateChild};
+    use gtk::CompositeTemplate;
+    use gtk::glib::WeakRef;
+    use gtk::subclass::prelude::{CompositeTemplate, CompositeTemplateInitializingExt, ObjectImpl, ObjectSubclass, WidgetImpl};
+    use gtk::subclass::widget::WidgetClassExt;
+    use image::DynamicImage;
+    use libadwaita as adw;
+    use libadwaita::subclass::dialog::AdwDialogImpl;
+
+    use crate::glium_area::skin_parser::ModelType;
+    use crate::window::Window;
+
+    #[derive(CompositeTemplate)]
+    #[template(file = "../resources/ui/player-head-dialog.ui")]
+    pub struct PlayerHeadDialog {
+        #[template_child]
+        pub preview: TemplateChild<gtk::Picture>,
+        #[template_child]
+        pub size_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub save_image_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub url_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub url_warning: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub value_view: TemplateChild<gtk::TextView>,
+        #[template_child]
+        pub copy_value_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub command_view: TemplateChild<gtk::TextView>,
+        #[template_child]
+        pub copy_command_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub legacy_command_view: TemplateChild<gtk::TextView>,
+        #[template_child]
+        pub copy_legacy_command_button: TemplateChild<gtk::Button>,
+
+        /// The skin as it was when the dialog was opened.
+        pub image: RefCell<DynamicImage>,
+        pub model_type: RefCell<ModelType>,
+        pub window: WeakRef<Window>,
+    }
+
+    impl Default for PlayerHeadDialog {
+        fn default() -> Self {
+            PlayerHeadDialog {
+                preview: Default::default(),
+                size_selector: Default::default(),
+                save_image_button: Default::default(),
+                url_entry: Default::default(),
+                url_warning: Default::default(),
+                value_view: Default::default(),
+                copy_value_button: Default::default(),
+                command_view: Default::default(),
+                copy_command_button: Default::default(),
+                legacy_command_view: Default::default(),
+                copy_legacy_command_button: Default::default(),
+                image: RefCell::new(DynamicImage::new_rgba8(64, 64)),
+                model_type: RefCell::new(ModelType::Classic),
+                window: Default::default(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PlayerHeadDialog {
+        const NAME: &'static str = "PlayerHeadDialog";
+        type Type = super::PlayerHeadDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+    impl ObjectImpl for PlayerHeadDialog {}
+    impl WidgetImpl for PlayerHeadDialog {}
+    impl AdwDialogImpl for PlayerHeadDialog {}
+}
+
+glib::wrapper! {
+    pub struct PlayerHeadDialog(ObjectSubclass<imp::PlayerHeadDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::ShortcutManager;
+}
+
+impl PlayerHeadDialog {
+    /// Exports the skin in the editor. The texture value and commands are filled in
+    /// once the URL the skin is hosted at is entered.
+    pub fn new(win: &Window) -> Self {
+        let dialog: PlayerHeadDialog = glib::Object::new();
+        dialog.imp().window.set(Some(win));
+        dialog.imp().image.replace(DynamicImage::ImageRgba8(win.export_texture()));
+        dialog.imp().model_type.replace(win.editor().model_type());
+
+        dialog.connect_signals();
+        dialog.update_preview();
+        dialog.update_commands();
+        dialog
+    }
+
+    fn window(&self) -> Window {
+        self.imp().window.upgrade().expect("PlayerHeadDialog has no window")
+    }
+
+    fn connect_signals(&self) {
+        self.imp().url_entry.connect_changed(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_commands();
+        }));
+        self.imp().save_image_button.connect_clicked(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.choose_image_file();
+        }));
+
+        let copy_buttons = [
+            (&self.imp().copy_value_button, &self.imp().value_view),
+            (&self.imp().copy_command_button, &self.imp().command_view),
+            (&self.imp().copy_legacy_command_button, &self.imp().legacy_command_view),
+        ];
+        for (button, view) in copy_buttons {
+            button.connect_clicked(clone!(#[weak] view, move |button| {
+                let buffer = view.buffer();
+                let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+                button.clipboard().set_text(&text);
+            }));
+        }
+    }
+
+    fn url(&self) -> String {
+        self.imp().url_entry.text().trim().to_string()
+    }
+
+    fn update_commands(&self) {
+        let url = self.url();
+        self.imp().url_warning.set_visible(!url.is_empty() && !url.starts_with(TEXTURE_URL_PREFIX));
+
+        let (value, command, legacy_command) = if url.is_empty() {
+            (String::new(), String::new(), String::new())
+        } else {
+            let value = textures_value(&url);
+            let command = give_command(&value);
+            let legacy_command = legacy_give_command(&value);
+            (value, command, legacy_command)
+        };
+        self.imp().value_view.buffer().set_text(&value);
+        self.imp().command_view.buffer().set_text(&command);
+        self.imp().legacy_command_view.buffer().set_text(&legacy_command);
+
+        for button in [&self.imp().copy_value_button, &self.imp().copy_command_button, &self.imp().copy_legacy_command_button] {
+            button.set_sensitive(!url.is_empty());
+        }
+    }
+
+    fn update_preview(&self) {
+        let image = self.imp().image.borrow();
+        let model_type = *self.imp().model_type.borrow();
+        match render_head(&image, &model_type, PREVIEW_SIZE) {
+            Ok(render) => self.imp().preview.set_paintable(Some(&utils::texture_from_image(&render))),
+            Err(error) => println!("Error rendering head preview: {:?}", error),
+        }
+    }
+
+    fn image_size(&self) -> u32 {
+        IMAGE_SIZES
+            .get(self.imp().size_selector.selected() as usize)
+            .copied()
+            .unwrap_or(PREVIEW_SIZE)
+    }
+
+    fn choose_image_file(&self) {
+        let file_dialog = gtk::FileDialog::builder().title("Save the head").build();
+        file_dialog.set_initial_name(Some("head.png"));
+        file_dialog.save(Some(&self.window()), Cancellable::NONE, clone!(#[weak(rename_to = dialog)] self, move |file| {
+            let Some(path) = file.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            let image = dialog.imp().image.borrow();
+            let model_type = *dialog.imp().model_type.borrow();
+            let result = render_head(&image, &model_type, dialog.image_size())
+                .map_err(|error| format!("{:?}", error))
+                .and_then(|render| render.save(&path).map_err(|error| error.to_string()));
+            match result {
+                Ok(_) => println!("Saved at {:?}", path),
+                Err(error) => println!("Error saving the head: {}", error),
+            }
+        }));
+    }
+}
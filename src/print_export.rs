@@ -0,0 +1,397 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use nalgebra_glm as glm;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::renderer::part_geometry;
+use crate::glium_area::skin_parser::{ModelType, SkinParser, TextureType};
+
+/// Skin pixels in a model unit.
+const PIXELS_PER_UNIT: f32 = 8.0;
+/// Overlay pixels at least this opaque are printed.
+const VOXEL_ALPHA_THRESHOLD: u8 = 128;
+/// Color of faces no texel covers, like the top of an overlay voxel next to the head.
+const UNCOLORED: [u8; 3] = [128, 128, 128];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PrintFormat {
+    /// Binary STL, colored per triangle the way VisCAM and SolidView read it.
+    Stl,
+    /// 3D Manufacturing Format with a color group.
+    ThreeMf,
+    /// Binary PLY with vertex colors.
+    Ply,
+}
+
+impl PrintFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            PrintFormat::Stl => "stl",
+            PrintFormat::ThreeMf => "3mf",
+            PrintFormat::Ply => "ply",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PrintExportError {
+    IoError(io::Error),
+    ZipError(ZipError),
+}
+
+impl From<io::Error> for PrintExportError {
+    fn from(error: io::Error) -> Self {
+        PrintExportError::IoError(error)
+    }
+}
+
+impl From<ZipError> for PrintExportError {
+    fn from(error: ZipError) -> Self {
+        PrintExportError::ZipError(error)
+    }
+}
+
+/// Exports the skin as one solid made of pixel sized voxels, standing on z = 0.
+pub struct PrintExport {
+    pub format: PrintFormat,
+    /// Edge of a voxel in millimetres.
+    pub pixel_size: f32,
+}
+
+impl Default for PrintExport {
+    fn default() -> Self {
+        PrintExport { format: PrintFormat::Stl, pixel_size: 2.0 }
+    }
+}
+
+/// A voxel in skin pixels, y up like the model.
+type Voxel = [i32; 3];
+
+/// Outward directions of the voxel faces: the axis and whether it points the positive way.
+const DIRECTIONS: [(usize, bool); 6] = [(0, true), (0, false), (1, true), (1, false), (2, true), (2, false)];
+
+/// The whole figure on a pixel grid: every part box is filled and every opaque overlay
+/// pixel is a voxel around it, so the surface of the grid is closed.
+#[derive(Default)]
+struct VoxelFigure {
+    voxels: BTreeSet<Voxel>,
+    /// Color of the face of a voxel that shows a texel.
+    face_colors: HashMap<(Voxel, (usize, bool)), [u8; 3]>,
+    /// Color of the other faces of overlay voxels.
+    voxel_colors: HashMap<Voxel, [u8; 3]>,
+}
+
+impl VoxelFigure {
+    fn new(image: &RgbaImage, model_type: &ModelType) -> Self {
+        let parser = SkinParser::new(model_type, TextureType::Normal);
+        let mut figure = VoxelFigure::default();
+
+        for body_part in BodyPart::PLAYER {
+            let geometry = part_geometry(body_part, model_type);
+            let positions: Vec<glm::Vec3> = geometry.vertexes
+                .iter()
+                .map(|vertex| glm::make_vec3(&vertex.position))
+                .collect();
+
+            if !body_part.is_outer() {
+                let pixels = |position: &glm::Vec3| (geometry.translation + position) * PIXELS_PER_UNIT;
+                let low = positions.iter().map(pixels).fold(glm::Vec3::repeat(f32::MAX), |a, b| glm::min2(&a, &b));
+                let high = positions.iter().map(pixels).fold(glm::Vec3::repeat(f32::MIN), |a, b| glm::max2(&a, &b));
+                let low = low.map(|value| value.round() as i32);
+                let high = high.map(|value| value.round() as i32);
+                for x in low.x..high.x {
+                    for y in low.y..high.y {
+                        for z in low.z..high.z {
+                            figure.voxels.insert([x, y, z]);
+                        }
+                    }
+                }
+            }
+
+            let cells = positions.chunks(4);
+            let mut texels = vec![];
+            for (_, [x, y, width, height]) in parser.side_regions(&body_part) {
+                for row in y..y + height {
+                    for column in x..x + width {
+                        texels.push(*image.get_pixel(column, row));
+                    }
+                }
+            }
+
+            for (cell, texel) in cells.zip(texels) {
+                let center = cell.iter().sum::<glm::Vec3>() / 4.0;
+                let (axis, positive) = cell_direction(cell, &center);
+                let mut position = (geometry.translation + center) * PIXELS_PER_UNIT;
+                let color = [texel[0], texel[1], texel[2]];
+
+                if body_part.is_outer() {
+                    if texel[3] < VOXEL_ALPHA_THRESHOLD {
+                        continue;
+                    }
+                    // The shell is inflated by less than a pixel, so along the normal the
+                    // scaled cell lands in the layer of voxels around the part.
+                    position[axis] = (geometry.translation[axis] + center[axis] * geometry.scale[axis]) * PIXELS_PER_UNIT;
+                    let voxel = voxel_at(&position);
+                    figure.voxels.insert(voxel);
+                    figure.voxel_colors.insert(voxel, color);
+                    figure.face_colors.insert((voxel, (axis, positive)), color);
+                } else {
+                    position[axis] += if positive { -0.5 } else { 0.5 };
+                    figure.face_colors.insert((voxel_at(&position), (axis, positive)), color);
+                }
+            }
+        }
+        figure
+    }
+
+    fn face_color(&self, voxel: Voxel, direction: (usize, bool)) -> [u8; 3] {
+        self.face_colors.get(&(voxel, direction))
+            .or_else(|| self.voxel_colors.get(&voxel))
+            .copied()
+            .unwrap_or(UNCOLORED)
+    }
+
+    /// Faces between filled and empty voxels.
+    fn surface(&self) -> Vec<(Voxel, (usize, bool))> {
+        let mut faces = vec![];
+        for &voxel in &self.voxels {
+            for (axis, positive) in DIRECTIONS {
+                let mut neighbor = voxel;
+                neighbor[axis] += if positive { 1 } else { -1 };
+                if !self.voxels.contains(&neighbor) {
+                    faces.push((voxel, (axis, positive)));
+                }
+            }
+        }
+        faces
+    }
+}
+
+/// Axis and sign of the normal of a cell pointing out of its part.
+fn cell_direction(cell: &[glm::Vec3], center: &glm::Vec3) -> (usize, bool) {
+    let normal = (cell[1] - cell[0]).cross(&(cell[2] - cell[0]));
+    let axis = normal.iamax();
+    (axis, center[axis] > 0.0)
+}
+
+fn voxel_at(position: &glm::Vec3) -> Voxel {
+    [position.x.floor() as i32, position.y.floor() as i32, position.z.floor() as i32]
+}
+
+/// Corners of a voxel face counter-clockwise seen from outside, in skin pixels.
+fn face_corners(voxel: Voxel, (axis, positive): (usize, bool)) -> [Voxel; 4] {
+    let (u, w) = ((axis + 1) % 3, (axis + 2) % 3);
+    let mut corners = [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(du, dw)| {
+        let mut corner = voxel;
+        corner[axis] += positive as i32;
+        corner[u] += du;
+        corner[w] += dw;
+        corner
+    });
+    if !positive {
+        corners.reverse();
+    }
+    corners
+}
+
+/// Triangles of the figure in millimetres, z up with the feet at 0.
+struct PrintMesh {
+    vertices: Vec<[f32; 3]>,
+    /// Quads of shared vertices, counter-clockwise from outside.
+    quads: Vec<([u32; 4], [u8; 3])>,
+}
+
+impl PrintMesh {
+    fn new(figure: &VoxelFigure, pixel_size: f32) -> Self {
+        let floor = figure.voxels.iter().map(|voxel| voxel[1]).min().unwrap_or(0);
+        let mut indices = HashMap::new();
+        let mut vertices = vec![];
+        let mut quads = vec![];
+
+        for (voxel, direction) in figure.surface() {
+            let corners = face_corners(voxel, direction).map(|[x, y, z]| {
+                *indices.entry([x, y, z]).or_insert_with(|| {
+                    // The model looks at +z, printers want it looking at -y with z up
+                    vertices.push([x as f32 * pixel_size, -z as f32 * pixel_size, (y - floor) as f32 * pixel_size]);
+                    vertices.len() as u32 - 1
+                })
+            });
+            quads.push((corners, figure.face_color(voxel, direction)));
+        }
+        PrintMesh { vertices, quads }
+    }
+
+    fn triangles(&self) -> impl Iterator<Item = ([u32; 3], [u8; 3])> + '_ {
+        self.quads.iter().flat_map(|&([a, b, c, d], color)| [([a, b, c], color), ([a, c, d], color)])
+    }
+}
+
+impl PrintExport {
+    /// HD skins are printed with a voxel per pixel of the 64x64 skin.
+    pub fn export(&self, image: &RgbaImage, model_type: &ModelType, path: &Path) -> Result<(), PrintExportError> {
+        let image = imageops::resize(image, 64, 64, FilterType::Nearest);
+        let mesh = PrintMesh::new(&VoxelFigure::new(&image, model_type), self.pixel_size);
+        match self.format {
+            PrintFormat::Stl => write_stl(&mesh, path),
+            PrintFormat::ThreeMf => write_3mf(&mesh, path),
+            PrintFormat::Ply => write_ply(&mesh, path),
+        }
+    }
+}
+
+fn write_stl(mesh: &PrintMesh, path: &Path) -> Result<(), PrintExportError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut header = [0u8; 80];
+    let title = b"Minecraft skin";
+    header[..title.len()].copy_from_slice(title);
+    writer.write_all(&header)?;
+    writer.write_all(&(mesh.quads.len() as u32 * 2).to_le_bytes())?;
+
+    for (triangle, color) in mesh.triangles() {
+        let [a, b, c] = triangle.map(|index| glm::make_vec3(&mesh.vertices[index as usize]));
+        let normal = (b - a).cross(&(c - a)).normalize();
+        for point in [normal, a, b, c] {
+            for value in point.iter() {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        // Bit 15 marks the color as valid, then 5 bits each of red, green and blue
+        let [red, green, blue] = color.map(|channel| (channel >> 3) as u16);
+        writer.write_all(&(0x8000 | red << 10 | green << 5 | blue).to_le_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_ply(mesh: &PrintMesh, path: &Path) -> Result<(), PrintExportError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    // Vertex colors need a vertex per face corner, faces of other colors meet at equal positions
+    write!(
+        writer,
+        "ply\nformat binary_little_endian 1.0\ncomment Minecraft skin, millimetres\n\
+         element vertex {}\nproperty float x\nproperty float y\nproperty float z\n\
+         property uchar red\nproperty uchar green\nproperty uchar blue\n\
+         element face {}\nproperty list uchar uint vertex_indices\nend_header\n",
+        mesh.quads.len() * 4,
+        mesh.quads.len(),
+    )?;
+    for (corners, color) in &mesh.quads {
+        for index in corners {
+            for value in mesh.vertices[*index as usize] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.write_all(color)?;
+        }
+    }
+    for quad in 0..mesh.quads.len() as u32 {
+        writer.write_all(&[4])?;
+        for corner in 0..4 {
+            writer.write_all(&(quad * 4 + corner).to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+  <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>
+</Types>
+"#;
+
+const RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+</Relationships>
+"#;
+
+fn write_3mf(mesh: &PrintMesh, path: &Path) -> Result<(), PrintExportError> {
+    let mut colors: Vec<[u8; 3]> = mesh.quads.iter().map(|(_, color)| *color).collect();
+    colors.sort();
+    colors.dedup();
+
+    let mut model = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<model unit=\"millimeter\" xml:lang=\"en-US\" ",
+        "xmlns=\"http://schemas.microsoft.com/3dmanufacturing/core/2015/02\" ",
+        "xmlns:m=\"http://schemas.microsoft.com/3dmanufacturing/material/2015/02\">\n",
+        "  <resources>\n    <m:colorgroup id=\"1\">\n",
+    ));
+    for [red, green, blue] in &colors {
+        model += &format!("      <m:color color=\"#{:02X}{:02X}{:02X}FF\"/>\n", red, green, blue);
+    }
+    model += "    </m:colorgroup>\n    <object id=\"2\" type=\"model\" name=\"Skin\" pid=\"1\" pindex=\"0\">\n      <mesh>\n        <vertices>\n";
+    for [x, y, z] in &mesh.vertices {
+        model += &format!("          <vertex x=\"{}\" y=\"{}\" z=\"{}\"/>\n", x, y, z);
+    }
+    model += "        </vertices>\n        <triangles>\n";
+    for ([v1, v2, v3], color) in mesh.triangles() {
+        let color_index = colors.binary_search(&color).unwrap_or(0);
+        model += &format!("          <triangle v1=\"{}\" v2=\"{}\" v3=\"{}\" pid=\"1\" p1=\"{}\"/>\n", v1, v2, v3, color_index);
+    }
+    model += "        </triangles>\n      </mesh>\n    </object>\n  </resources>\n";
+    model += "  <build>\n    <item objectid=\"2\"/>\n  </build>\n</model>\n";
+
+    let mut writer = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    writer.start_file("[Content_Types].xml", options)?;
+    writer.write_all(CONTENT_TYPES.as_bytes())?;
+    writer.start_file("_rels/.rels", options)?;
+    writer.write_all(RELATIONSHIPS.as_bytes())?;
+    writer.start_file("3D/3dmodel.model", options)?;
+    writer.write_all(model.as_bytes())?;
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every edge of a closed surface is shared by exactly two faces, once in each direction.
+    #[test]
+    fn printed_figure_is_closed() {
+        let mut image = RgbaImage::from_pixel(64, 64, image::Rgba([90, 60, 30, 255]));
+        // Clear half of the overlay so voxels stick out unevenly
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            if (x + y) % 2 == 0 {
+                pixel[3] = 0;
+            }
+        }
+        let mesh = PrintMesh::new(&VoxelFigure::new(&image, &ModelType::Slim), 1.0);
+
+        let mut edges = HashMap::new();
+        for (triangle, _) in mesh.triangles() {
+            for index in 0..3 {
+                *edges.entry((triangle[index], triangle[(index + 1) % 3])).or_insert(0) += 1;
+            }
+        }
+        for (&(from, to), &count) in &edges {
+            assert_eq!(edges.get(&(to, from)), Some(&count));
+        }
+    }
+
+    #[test]
+    fn overlay_voxels_sit_around_the_head() {
+        let mut image = RgbaImage::new(64, 64);
+        // Top left pixel of the front of the hat and of the head
+        image.put_pixel(40, 8, image::Rgba([255, 0, 0, 255]));
+        image.put_pixel(8, 8, image::Rgba([0, 0, 255, 255]));
+        let figure = VoxelFigure::new(&image, &ModelType::Classic);
+
+        // The head spans 8 pixels from -4 and is 8 pixels below the top at 16
+        let hat_voxel = [-4, 15, 4];
+        assert!(figure.voxels.contains(&hat_voxel));
+        assert_eq!(figure.face_color(hat_voxel, (2, true)), [255, 0, 0]);
+        assert_eq!(figure.face_color([-4, 15, 3], (2, true)), [0, 0, 255]);
+    }
+}
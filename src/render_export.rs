@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, ImageError};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::skin_parser::{ModelType, TextureLoadError};
+use crate::glium_area::software_renderer::{render_skin, Pose, RenderOptions};
+
+/// Renders bigger than this are not supersampled, the edges are smooth enough anyway.
+const SUPERSAMPLE_LIMIT: u32 = 1024;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum RenderView {
+    Isometric,
+    Front,
+    Back,
+    Bust,
+    Head,
+}
+
+impl RenderView {
+    pub const ALL: [RenderView; 5] = [
+        RenderView::Isometric,
+        RenderView::Front,
+        RenderView::Back,
+        RenderView::Bust,
+        RenderView::Head,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RenderView::Isometric => "Isometric",
+            RenderView::Front => "Front",
+            RenderView::Back => "Back",
+            RenderView::Bust => "Bust",
+            RenderView::Head => "Head",
+        }
+    }
+
+    fn file_suffix(self) -> &'static str {
+        match self {
+            RenderView::Isometric => "isometric",
+            RenderView::Front => "front",
+            RenderView::Back => "back",
+            RenderView::Bust => "bust",
+            RenderView::Head => "head",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RenderExportError {
+    TextureLoadError(TextureLoadError),
+    ImageError(ImageError),
+}
+
+impl From<TextureLoadError> for RenderExportError {
+    fn from(error: TextureLoadError) -> Self {
+        RenderExportError::TextureLoadError(error)
+    }
+}
+
+impl From<ImageError> for RenderExportError {
+    fn from(error: ImageError) -> Self {
+        RenderExportError::ImageError(error)
+    }
+}
+
+/// Settings shared by all views of one export. Renders are square and transparent.
+#[derive(Clone, Debug)]
+pub struct RenderExport {
+    pub size: u32,
+    pub pose: Pose,
+    /// 0.0 is flat colors, 1.0 full shading.
+    pub lighting: f32,
+    pub show_outer_layer: bool,
+}
+
+impl Default for RenderExport {
+    fn default() -> Self {
+        RenderExport { size: 512, pose: Pose::Standing, lighting: 0.35, show_outer_layer: true }
+    }
+}
+
+impl RenderExport {
+    pub fn options(&self, view: RenderView) -> RenderOptions {
+        let mut options = RenderOptions::default().with_size(self.size, self.size);
+        options.pose = self.pose;
+        options.lighting = self.lighting;
+        options.supersample = if self.size > SUPERSAMPLE_LIMIT { 1 } else { 2 };
+
+        options = match view {
+            // Looking down the diagonal of a cube
+            RenderView::Isometric => options.with_angles(45.0, 35.264),
+            RenderView::Front => options.with_angles(0.0, 0.0),
+            RenderView::Back => options.with_angles(180.0, 0.0),
+            RenderView::Bust => options.with_angles(25.0, 10.0).bust(),
+            RenderView::Head => {
+                options.parts = [BodyPart::Head, BodyPart::HeadOuter].into_iter().collect();
+                options.with_angles(25.0, 10.0)
+            }
+        };
+        if !self.show_outer_layer {
+            options.parts.retain(|part| !part.is_outer());
+        }
+        options
+    }
+
+    pub fn render(&self, image: &DynamicImage, model_type: &ModelType, view: RenderView) -> Result<image::RgbaImage, TextureLoadError> {
+        render_skin(image, model_type, &self.options(view))
+    }
+
+    /// Writes `<name>-<view>.png` into `folder` for every view and returns the written files.
+    pub fn export(
+        &self,
+        image: &DynamicImage,
+        model_type: &ModelType,
+        views: &[RenderView],
+        folder: &Path,
+        name: &str,
+    ) -> Result<Vec<PathBuf>, RenderExportError> {
+        let mut paths = vec![];
+        for &view in views {
+            let path = folder.join(format!("{}-{}.png", name, view.file_suffix()));
+            self.render(image, model_type, view)?.save(&path)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
@@ -0,0 +1,212 @@
+use gtk::gio::Cancellable;
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{BoxExt, ButtonExt, CheckButtonExt, FileExt, RangeExt, WidgetExt};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+use image::DynamicImage;
+use libadwaita as adw;
+use libadwaita::prelude::AdwDialogExt;
+
+use crate::glium_area::software_renderer::Pose;
+use crate::render_export::{RenderExport, RenderView};
+use crate::utils;
+use crate::window::Window;
+
+const PREVIEW_SIZE: u32 = 128;
+
+mod imp {
+    use std::cell::RefCell;
+
+    use gtk::{glib, TemplateChild};
+    use gtk::CompositeTemplate;
+    use gtk::glib::WeakRef;
+    use gtk::subclass::prelude::{CompositeTemplate, CompositeTemplateInitializingExt, ObjectImpl, ObjectSubclass, WidgetImpl};
+    use gtk::subclass::widget::WidgetClassExt;
+    use image::DynamicImage;
+    use libadwaita as adw;
+    use libadwaita::subclass::dialog::AdwDialogImpl;
+
+    use crate::glium_area::skin_parser::ModelType;
+    use crate::render_export::RenderView;
+    use crate::window::Window;
+
+    #[derive(CompositeTemplate)]
+    #[template(file = "../resources/ui/render-export-dialog.ui")]
+    pub struct RenderExportDialog {
+        #[template_child]
+        pub views_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub size_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub pose_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub lighting_scale: TemplateChild<gtk::Scale>,
+        #[template_child]
+        pub outer_layer_button: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub export_button: TemplateChild<gtk::Button>,
+
+        pub views: RefCell<Vec<(RenderView, gtk::Picture, gtk::CheckButton)>>,
+        /// The skin as it was when the dialog was opened.
+        pub image: RefCell<DynamicImage>,
+        pub model_type: RefCell<ModelType>,
+        pub window: WeakRef<Window>,
+    }
+
+    impl Default for RenderExportDialog {
+        fn default() -> Self {
+            RenderExportDialog {
+                views_box: Default::default(),
+                size_button: Default::default(),
+                pose_selector: Default::default(),
+                lighting_scale: Default::default(),
+                outer_layer_button: Default::default(),
+                export_button: Default::default(),
+                views: Default::default(),
+                image: RefCell::new(DynamicImage::new_rgba8(64, 64)),
+                model_type: RefCell::new(ModelType::Classic),
+                window: Default::default(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RenderExportDialog {
+        const NAME: &'static str = "RenderExportDialog";
+        type Type = super::RenderExportDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+    impl ObjectImpl for RenderExportDialog {}
+    impl WidgetImpl for RenderExportDialog {}
+    impl AdwDialogImpl for RenderExportDialog {}
+}
+
+glib::wrapper! {
+    pub struct RenderExportDialog(ObjectSubclass<imp::RenderExportDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::ShortcutManager;
+}
+
+impl RenderExportDialog {
+    /// Renders the skin in the editor. Every view is checked at first.
+    pub fn new(win: &Window) -> Self {
+        let dialog: RenderExportDialog = glib::Object::new();
+        dialog.imp().window.set(Some(win));
+        dialog.imp().image.replace(DynamicImage::ImageRgba8(win.export_texture()));
+        dialog.imp().model_type.replace(win.editor().model_type());
+
+        dialog.build_views();
+        dialog.connect_signals();
+        dialog.update_previews();
+        dialog
+    }
+
+    fn window(&self) -> Window {
+        self.imp().window.upgrade().expect("RenderExportDialog has no window")
+    }
+
+    fn build_views(&self) {
+        for view in RenderView::ALL {
+            let picture = gtk::Picture::builder()
+                .content_fit(gtk::ContentFit::Contain)
+                .vexpand(true)
+                .build();
+            let check_button = gtk::CheckButton::builder()
+                .label(view.label())
+                .active(true)
+                .halign(gtk::Align::Center)
+                .build();
+            check_button.connect_toggled(clone!(#[weak(rename_to = dialog)] self, move |_| {
+                dialog.update_export_button();
+            }));
+
+            let card = gtk::Box::new(gtk::Orientation::Vertical, 6);
+            card.append(&picture);
+            card.append(&check_button);
+            self.imp().views_box.append(&card);
+            self.imp().views.borrow_mut().push((view, picture, check_button));
+        }
+    }
+
+    fn connect_signals(&self) {
+        self.imp().pose_selector.connect_selected_notify(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_previews();
+        }));
+        self.imp().lighting_scale.connect_value_changed(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_previews();
+        }));
+        self.imp().outer_layer_button.connect_toggled(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_previews();
+        }));
+        self.imp().export_button.connect_clicked(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.choose_folder();
+        }));
+    }
+
+    fn settings(&self) -> RenderExport {
+        RenderExport {
+            size: self.imp().size_button.value() as u32,
+            pose: Pose::ALL
+                .get(self.imp().pose_selector.selected() as usize)
+                .copied()
+                .unwrap_or_default(),
+            lighting: self.imp().lighting_scale.value() as f32,
+            show_outer_layer: self.imp().outer_layer_button.is_active(),
+        }
+    }
+
+    fn checked_views(&self) -> Vec<RenderView> {
+        self.imp()
+            .views
+            .borrow()
+            .iter()
+            .filter(|(_, _, check_button)| check_button.is_active())
+            .map(|(view, _, _)| *view)
+            .collect()
+    }
+
+    fn update_export_button(&self) {
+        self.imp().export_button.set_sensitive(!self.checked_views().is_empty());
+    }
+
+    /// Previews use the same settings as the export, only smaller.
+    fn update_previews(&self) {
+        let settings = RenderExport { size: PREVIEW_SIZE, ..self.settings() };
+        let image = self.imp().image.borrow();
+        let model_type = *self.imp().model_type.borrow();
+        for (view, picture, _) in self.imp().views.borrow().iter() {
+            match settings.render(&image, &model_type, *view) {
+                Ok(render) => picture.set_paintable(Some(&utils::texture_from_image(&render))),
+                Err(error) => println!("Error rendering {:?} preview: {:?}", view, error),
+            }
+        }
+    }
+
+    fn choose_folder(&self) {
+        let file_dialog = gtk::FileDialog::builder().title("Export render to").build();
+        file_dialog.select_folder(Some(&self.window()), Cancellable::NONE, clone!(#[weak(rename_to = dialog)] self, move |folder| {
+            let Some(folder) = folder.ok().and_then(|folder| folder.path()) else {
+                return;
+            };
+            let image = dialog.imp().image.borrow();
+            let model_type = *dialog.imp().model_type.borrow();
+            let views = dialog.checked_views();
+            match dialog.settings().export(&image, &model_type, &views, &folder, "skin") {
+                Ok(paths) => {
+                    println!("Saved {} renders in {:?}", paths.len(), folder);
+                    drop(image);
+                    dialog.close();
+                }
+                Err(error) => println!("Error exporting render: {:?}", error),
+            }
+        }));
+    }
+}
@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::glium_area::arm_conversion::ConversionStrategy;
+use crate::glium_area::skin_parser::ModelType;
+use crate::template_library::unix_now;
+use crate::thumbnails::ThumbnailView;
+use crate::ROOT_DIR;
+
+const MAX_RECENT_FILES: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    pub model_type: ModelType,
+    pub opened: u64,
+}
+
+/// Preferences persisted in `settings.json` inside the app folder.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Settings {
+    #[serde(default)]
+    pub recent_files: Vec<RecentFile>,
+    #[serde(default)]
+    pub thumbnail_view: ThumbnailView,
+    /// External folder whose skins are mirrored into the wardrobe.
+    #[serde(default)]
+    pub sync_folder: Option<PathBuf>,
+    /// How arms are converted when the model type changes.
+    #[serde(default)]
+    pub arm_conversion: ConversionStrategy,
+}
+
+impl Settings {
+    fn path() -> PathBuf {
+        ROOT_DIR.join("settings.json")
+    }
+
+    /// Reads the settings, falling back to defaults when the file is missing or broken.
+    pub fn load() -> Self {
+        let Ok(json) = fs::read_to_string(Self::path()) else {
+            return Settings::default();
+        };
+        serde_json::from_str(&json).unwrap_or_else(|error| {
+            println!("Error reading settings: {:?}", error);
+            Settings::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let result = serde_json::to_string_pretty(self)
+            .map_err(|error| error.to_string())
+            .and_then(|json| {
+                fs::create_dir_all(&*ROOT_DIR).map_err(|error| error.to_string())?;
+                fs::write(Self::path(), json).map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            println!("Error saving settings: {}", error);
+        }
+    }
+
+    /// Loads the settings, applies `change` and writes them back.
+    pub fn update(change: impl FnOnce(&mut Settings)) {
+        let mut settings = Settings::load();
+        change(&mut settings);
+        settings.save();
+    }
+
+    /// Moves `path` to the top of the recent files list.
+    pub fn add_recent_file(&mut self, path: &Path, model_type: ModelType) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.recent_files.retain(|recent| recent.path != path);
+        self.recent_files.insert(0, RecentFile { path, model_type, opened: unix_now() });
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Recent files that still exist on disk, newest first.
+    pub fn existing_recent_files(&self) -> Vec<RecentFile> {
+        self.recent_files
+            .iter()
+            .filter(|recent| recent.path.exists())
+            .cloned()
+            .collect()
+    }
+}
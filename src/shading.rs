@@ -0,0 +1,212 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::image_stamp::BAYER_4X4;
+use crate::utils::{fnv1a, hsv_to_rgba, rgba_to_hsv};
+
+/// Hue that highlights drift to and shadows drift away from, in degrees.
+const LIGHT_HUE: f32 = 60.0;
+const SHADOW_HUE: f32 = 240.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum ShadeDirection {
+    #[default]
+    Lighten,
+    Darken,
+}
+
+/// One step along a color ramp, the way pixel artists shade: lighter colors are warmer and
+/// less saturated, darker ones cooler and more saturated.
+#[derive(Clone, Copy, Debug)]
+pub struct Shade {
+    pub direction: ShadeDirection,
+    /// In degrees.
+    pub hue_shift: f32,
+    pub saturation_step: f32,
+    pub value_step: f32,
+}
+
+impl Default for Shade {
+    fn default() -> Self {
+        Shade { direction: Default::default(), hue_shift: 8.0, saturation_step: 0.05, value_step: 0.08 }
+    }
+}
+
+/// Largest random change of each HSV channel, the hue in degrees.
+#[derive(Clone, Copy, Debug)]
+pub struct Jitter {
+    pub hue: f32,
+    pub saturation: f32,
+    pub value: f32,
+}
+
+impl Default for Jitter {
+    fn default() -> Self {
+        Jitter { hue: 4.0, saturation: 0.05, value: 0.1 }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum GradientDirection {
+    #[default]
+    Down,
+    Up,
+    Right,
+    Left,
+}
+
+/// From the active color to `end_color` across a face, in `steps` flat bands.
+#[derive(Clone, Copy, Debug)]
+pub struct Gradient {
+    pub end_color: [f32; 4],
+    pub direction: GradientDirection,
+    pub steps: u32,
+    /// Mixes neighbouring bands with ordered dithering instead of hard edges.
+    pub dither: bool,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Gradient { end_color: [0.0, 0.0, 0.0, 1.0], direction: Default::default(), steps: 4, dither: true }
+    }
+}
+
+/// Settings of the shade, noise and gradient tools.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ShadingSettings {
+    pub shade: Shade,
+    pub jitter: Jitter,
+    /// The noise brush gives a cell the same change for the same seed.
+    pub seed: u64,
+    pub gradient: Gradient,
+}
+
+/// Moves `hue` toward `target` by at most `shift`, all in degrees.
+fn shift_hue(hue: f32, target: f32, shift: f32) -> f32 {
+    let distance = (target - hue + 540.0).rem_euclid(360.0) - 180.0;
+    (hue + distance.signum() * distance.abs().min(shift)).rem_euclid(360.0)
+}
+
+pub fn shade(color: [f32; 4], shade: &Shade) -> [f32; 4] {
+    let [hue, saturation, value, alpha] = rgba_to_hsv(color);
+    let (target_hue, sign) = match shade.direction {
+        ShadeDirection::Lighten => (LIGHT_HUE, 1.0),
+        ShadeDirection::Darken => (SHADOW_HUE, -1.0),
+    };
+    // Grays have no hue to shift, giving them one would tint them red
+    let (hue, saturation) = if saturation == 0.0 {
+        (hue, saturation)
+    } else {
+        (
+            shift_hue(hue * 360.0, target_hue, shade.hue_shift) / 360.0,
+            (saturation - sign * shade.saturation_step).clamp(0.0, 1.0),
+        )
+    };
+    let value = (value + sign * shade.value_step).clamp(0.0, 1.0);
+    hsv_to_rgba([hue, saturation, value, alpha])
+}
+
+/// Random change of the color, the same for a cell as long as the seed stays.
+pub fn jitter(color: [f32; 4], jitter: &Jitter, seed: u64, body_part: BodyPart, cell_index: usize) -> [f32; 4] {
+    let part_index = BodyPart::PLAYER.iter().position(|part| *part == body_part).unwrap_or(0);
+    let key = [seed.to_le_bytes(), (part_index as u64).to_le_bytes(), (cell_index as u64).to_le_bytes()].concat();
+    let mut rng = StdRng::seed_from_u64(fnv1a(&key));
+    let mut spread = |limit: f32| if limit > 0.0 { rng.random_range(-limit..=limit) } else { 0.0 };
+
+    let [hue, saturation, value, alpha] = rgba_to_hsv(color);
+    let hue = (hue * 360.0 + spread(jitter.hue)).rem_euclid(360.0) / 360.0;
+    let saturation = (saturation + spread(jitter.saturation)).clamp(0.0, 1.0);
+    let value = (value + spread(jitter.value)).clamp(0.0, 1.0);
+    hsv_to_rgba([hue, saturation, value, alpha])
+}
+
+/// Colors of a face of `width` by `height` cells, row by row.
+pub fn gradient(start: [f32; 4], gradient: &Gradient, width: usize, height: usize) -> Vec<[f32; 4]> {
+    let steps = gradient.steps.max(2) as usize;
+    let along = |x: usize, y: usize| {
+        let (position, length) = match gradient.direction {
+            GradientDirection::Down => (y, height),
+            GradientDirection::Up => (height - 1 - y, height),
+            GradientDirection::Right => (x, width),
+            GradientDirection::Left => (width - 1 - x, width),
+        };
+        if length > 1 { position as f32 / (length - 1) as f32 } else { 0.0 }
+    };
+
+    let mut colors = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let band = along(x, y) * (steps - 1) as f32;
+            let threshold = if gradient.dither {
+                (f32::from(BAYER_4X4[y % 4][x % 4]) + 0.5) / 16.0
+            } else {
+                0.5
+            };
+            let level = (band.floor() + if band.fract() >= threshold { 1.0 } else { 0.0 }).min((steps - 1) as f32);
+            let t = level / (steps - 1) as f32;
+            colors.push(std::array::from_fn(|channel| start[channel] + (gradient.end_color[channel] - start[channel]) * t));
+        }
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hue(color: [f32; 4]) -> f32 {
+        rgba_to_hsv(color)[0] * 360.0
+    }
+
+    #[test]
+    fn shading_steps_along_a_hue_shifted_ramp() {
+        let green = [0.2, 0.6, 0.2, 1.0];
+        let light = shade(green, &Shade::default());
+        let dark = shade(green, &Shade { direction: ShadeDirection::Darken, ..Default::default() });
+        assert!(rgba_to_hsv(light)[2] > rgba_to_hsv(green)[2]);
+        assert!(rgba_to_hsv(dark)[2] < rgba_to_hsv(green)[2]);
+        // Green is at 120 degrees: highlights go toward yellow, shadows toward blue
+        assert!((hue(light) - 112.0).abs() < 0.5);
+        assert!((hue(dark) - 128.0).abs() < 0.5);
+
+        let gray = [0.5, 0.5, 0.5, 1.0];
+        let dark_gray = shade(gray, &Shade { direction: ShadeDirection::Darken, ..Default::default() });
+        assert_eq!(dark_gray[0], dark_gray[1]);
+        assert_eq!(dark_gray[1], dark_gray[2]);
+    }
+
+    #[test]
+    fn noise_is_the_same_for_a_seed() {
+        let color = [0.4, 0.3, 0.8, 1.0];
+        let noisy = jitter(color, &Jitter::default(), 7, BodyPart::Head, 3);
+        assert_eq!(noisy, jitter(color, &Jitter::default(), 7, BodyPart::Head, 3));
+        let cells: Vec<[f32; 4]> = (0..16).map(|cell| jitter(color, &Jitter::default(), 7, BodyPart::Head, cell)).collect();
+        assert!(cells.iter().any(|cell| *cell != noisy));
+
+        let still = Jitter { hue: 0.0, saturation: 0.0, value: 0.0 };
+        let unchanged = jitter(color, &still, 7, BodyPart::Head, 3);
+        assert!(unchanged.iter().zip(color).all(|(a, b)| (a - b).abs() < 1e-5));
+    }
+
+    #[test]
+    fn gradients_band_and_dither_across_the_face() {
+        let white = [1.0; 4];
+        let black = [0.0, 0.0, 0.0, 1.0];
+        let bands = Gradient { end_color: black, steps: 2, dither: false, ..Default::default() };
+        let colors = gradient(white, &bands, 2, 4);
+        assert_eq!(colors[0], white);
+        assert_eq!(colors[2], white);
+        assert_eq!(colors[4], black);
+        assert_eq!(colors[7], black);
+
+        let dithered = gradient(white, &Gradient { dither: true, ..bands }, 4, 8);
+        assert_eq!(dithered[0], white);
+        assert_eq!(dithered[31], black);
+        // Rows in the middle mix both colors
+        assert!(dithered[12..16].contains(&white) && dithered[12..16].contains(&black));
+
+        let sideways = gradient(white, &Gradient { direction: GradientDirection::Left, ..bands }, 4, 1);
+        assert_eq!(sideways, vec![black, black, white, white]);
+    }
+}
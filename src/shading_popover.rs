@@ -0,0 +1,146 @@
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ButtonExt, CheckButtonExt};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+
+use crate::shading::{Gradient, GradientDirection, Jitter, Shade, ShadeDirection, ShadingSettings};
+use crate::utils;
+use crate::window::Window;
+
+mod imp {
+    use gtk::{CompositeTemplate, glib, TemplateChild};
+    use gtk::subclass::popover::PopoverImpl;
+    use gtk::subclass::prelude::{CompositeTemplate, CompositeTemplateInitializingExt, ObjectImpl, ObjectSubclass, WidgetImpl};
+    use gtk::subclass::widget::WidgetClassExt;
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(file = "../resources/ui/shading-popover.ui")]
+    pub struct ShadingPopover {
+        #[template_child]
+        pub shade_direction_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub hue_shift_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub saturation_step_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub value_step_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub hue_jitter_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub saturation_jitter_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub value_jitter_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub seed_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub new_seed_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub end_color_button: TemplateChild<gtk::ColorDialogButton>,
+        #[template_child]
+        pub gradient_direction_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub steps_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub dither_button: TemplateChild<gtk::CheckButton>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ShadingPopover {
+        const NAME: &'static str = "ShadingPopover";
+        type Type = super::ShadingPopover;
+        type ParentType = gtk::Popover;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+    impl ObjectImpl for ShadingPopover {}
+    impl WidgetImpl for ShadingPopover {}
+    impl PopoverImpl for ShadingPopover {}
+}
+
+glib::wrapper! {
+    pub struct ShadingPopover(ObjectSubclass<imp::ShadingPopover>)
+        @extends gtk::Widget, gtk::Popover,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::ShortcutManager;
+}
+
+impl ShadingPopover {
+    pub fn new(win: &Window) -> Self {
+        let popover: ShadingPopover = glib::Object::new();
+        let color_dialog = gtk::ColorDialog::builder().with_alpha(false).build();
+        popover.imp().end_color_button.set_dialog(&color_dialog);
+        popover.connect_signals(win);
+        popover.apply(win);
+        popover
+    }
+
+    /// Every change goes straight to the editor.
+    fn connect_signals(&self, win: &Window) {
+        let imp = self.imp();
+        for selector in [&imp.shade_direction_selector, &imp.gradient_direction_selector] {
+            selector.connect_selected_notify(clone!(#[weak(rename_to = popover)] self, #[weak] win, move |_| {
+                popover.apply(&win);
+            }));
+        }
+        for spin_button in [
+            &imp.hue_shift_spin, &imp.saturation_step_spin, &imp.value_step_spin,
+            &imp.hue_jitter_spin, &imp.saturation_jitter_spin, &imp.value_jitter_spin,
+            &imp.seed_spin, &imp.steps_spin,
+        ] {
+            spin_button.connect_value_changed(clone!(#[weak(rename_to = popover)] self, #[weak] win, move |_| {
+                popover.apply(&win);
+            }));
+        }
+        imp.end_color_button.connect_rgba_notify(clone!(#[weak(rename_to = popover)] self, #[weak] win, move |_| {
+            popover.apply(&win);
+        }));
+        imp.dither_button.connect_toggled(clone!(#[weak(rename_to = popover)] self, #[weak] win, move |_| {
+            popover.apply(&win);
+        }));
+        imp.new_seed_button.connect_clicked(clone!(#[weak(rename_to = popover)] self, move |_| {
+            popover.imp().seed_spin.set_value(f64::from(rand::random::<u32>() % 100_000));
+        }));
+    }
+
+    fn settings(&self) -> ShadingSettings {
+        let imp = self.imp();
+        let percent = |spin_button: &gtk::SpinButton| spin_button.value() as f32 / 100.0;
+        ShadingSettings {
+            shade: Shade {
+                direction: match imp.shade_direction_selector.selected() {
+                    1 => ShadeDirection::Darken,
+                    _ => ShadeDirection::Lighten,
+                },
+                hue_shift: imp.hue_shift_spin.value() as f32,
+                saturation_step: percent(&imp.saturation_step_spin),
+                value_step: percent(&imp.value_step_spin),
+            },
+            jitter: Jitter {
+                hue: imp.hue_jitter_spin.value() as f32,
+                saturation: percent(&imp.saturation_jitter_spin),
+                value: percent(&imp.value_jitter_spin),
+            },
+            seed: imp.seed_spin.value() as u64,
+            gradient: Gradient {
+                end_color: utils::rgba_to_f32(imp.end_color_button.rgba()),
+                direction: match imp.gradient_direction_selector.selected() {
+                    1 => GradientDirection::Up,
+                    2 => GradientDirection::Right,
+                    3 => GradientDirection::Left,
+                    _ => GradientDirection::Down,
+                },
+                steps: imp.steps_spin.value() as u32,
+                dither: imp.dither_button.is_active(),
+            },
+        }
+    }
+
+    fn apply(&self, win: &Window) {
+        win.editor_mut().set_shading(self.settings());
+    }
+}
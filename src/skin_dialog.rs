@@ -7,12 +7,15 @@ use libadwaita as adw;
 use libadwaita::prelude::AdwDialogExt;
 
 use crate::glium_area::skin_parser::ModelType;
+use crate::utils::{guess_model_type, Confidence, ModelTypeGuess};
 use crate::window::Window;
 
 mod imp {
-    use std::cell::Cell;
+    use std::cell::{Cell, RefCell};
     use std::path::PathBuf;
 
+    use image::DynamicImage;
+
     use gtk::{glib, TemplateChild};
     use gtk::CompositeTemplate;
     use gtk::subclass::prelude::{CompositeTemplate, CompositeTemplateInitializingExt, ObjectImpl, ObjectSubclass, WidgetImpl};
@@ -25,10 +28,14 @@ mod imp {
     #[template(file = "../resources/ui/skin-dialog.ui")]
     pub struct SkinDialog {
         pub texture_path: Cell<Option<PathBuf>>,
+        /// Skin without a file, set instead of the path.
+        pub texture: RefCell<Option<DynamicImage>>,
         #[template_child]
         pub slim_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub classic_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub detected_label: TemplateChild<gtk::Label>,
     }
 
     #[glib::object_subclass]
@@ -64,10 +71,40 @@ glib::wrapper! {
 impl SkinDialog {
     pub fn new(texture_path: PathBuf, window: Window) -> Self {
         let dialog: SkinDialog = glib::Object::new();
+        if let Ok(image) = image::open(&texture_path) {
+            dialog.preselect(guess_model_type(&image));
+        }
         dialog.imp().texture_path.replace(Some(texture_path));
         dialog.connect_signals(window.clone());
         dialog
     }
+
+    /// Asks for the model type of a skin that has no file, e.g. a pasted one.
+    pub fn for_image(image: DynamicImage, window: Window) -> Self {
+        let dialog: SkinDialog = glib::Object::new();
+        dialog.preselect(guess_model_type(&image));
+        dialog.imp().texture.replace(Some(image));
+        dialog.connect_signals(window.clone());
+        dialog
+    }
+
+    /// Highlights the model type detected from the texture.
+    fn preselect(&self, guess: ModelTypeGuess) {
+        let (button, name) = match guess.model_type {
+            ModelType::Slim => (&self.imp().slim_button, "slim"),
+            ModelType::Classic => (&self.imp().classic_button, "classic"),
+        };
+        button.add_css_class("suggested-action");
+        self.set_default_widget(Some(&button.get()));
+        self.set_focus(Some(&button.get()));
+
+        let hint = match guess.confidence {
+            Confidence::High => format!("Looks like a {} skin", name),
+            Confidence::Medium | Confidence::Low => format!("Probably a {} skin", name),
+        };
+        self.imp().detected_label.set_label(&hint);
+        self.imp().detected_label.set_visible(true);
+    }
     
     fn connect_signals(&self, window: Window) {
         let handler = self.get_handler(window.clone(), ModelType::Slim);
@@ -80,11 +117,16 @@ impl SkinDialog {
     fn get_handler(&self, window: Window, model_type: ModelType) -> impl Fn(&gtk::Button) {
         let dialog = self.clone();
         move |_| {
-            let texture_path = dialog.imp().texture_path.take()
-                .expect("Texture path is not set. This can happen if the dialog was not created using 'new()' method");
-            let texture_path = texture_path.to_str().unwrap();
+            let result = match dialog.imp().texture_path.take() {
+                Some(texture_path) => window.open_skin_file(texture_path.to_str().unwrap(), model_type),
+                None => {
+                    let image = dialog.imp().texture.take()
+                        .expect("Texture is not set. This can happen if the dialog was not created using 'new()' or 'for_image()' method");
+                    window.open_skin_image(&image, model_type)
+                }
+            };
 
-            if let Err(error) = window.open_skin_file(texture_path, model_type) {
+            if let Err(error) = result {
                 println!("Error loading skin: {:?}", error);
             } else {
                 dialog.close();
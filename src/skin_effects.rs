@@ -0,0 +1,252 @@
+use image::{Rgba, RgbaImage};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::cube_side::CubeSide;
+use crate::glium_area::skin_parser::{texture_layout, ModelType, SkinParser, TextureLoadError, TextureType};
+
+/// Rows of a side face darkened next to a joint, in skin pixels.
+const SEAM_DEPTH: u32 = 2;
+/// Share of the strength used on the border cells of every face.
+const EDGE_SHARE: f32 = 0.35;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Effect {
+    /// A darker ring of cells around the opaque regions of each face.
+    Outline,
+    /// Darker cells at the joints between body parts and along the edges of each cuboid.
+    AmbientOcclusion,
+    /// The opaque cells of the outer layer darken the base layer a pixel below them.
+    DropShadow,
+}
+
+impl Effect {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Effect::Outline => "Outline",
+            Effect::AmbientOcclusion => "Ambient occlusion",
+            Effect::DropShadow => "Drop shadow",
+        }
+    }
+}
+
+/// A cell of a body part and its new color.
+pub type CellColor = (BodyPart, usize, Rgba<u8>);
+
+/// Applies the effect to the given parts with a strength between 0 and 1 and lists the
+/// cells that change.
+pub fn apply_effect(
+    skin: &RgbaImage,
+    model_type: &ModelType,
+    parts: &[BodyPart],
+    effect: Effect,
+    strength: f32,
+) -> Result<Vec<CellColor>, TextureLoadError> {
+    let (_, resolution) = texture_layout(skin.width(), skin.height())?;
+    let parser = SkinParser::with_resolution(model_type, TextureType::Normal, resolution);
+    let strength = strength.clamp(0.0, 1.0);
+    let mut result = skin.clone();
+    match effect {
+        Effect::Outline => {
+            for body_part in parts {
+                for (_, region) in parser.side_regions(body_part) {
+                    outline_face(skin, &mut result, region, strength);
+                }
+            }
+        }
+        Effect::AmbientOcclusion => {
+            for body_part in parts {
+                for (side, region) in parser.side_regions(body_part) {
+                    occlude_face(skin, &mut result, *body_part, side, region, resolution, strength);
+                }
+            }
+        }
+        Effect::DropShadow => {
+            let mut inner_parts: Vec<BodyPart> = parts.iter().map(|part| part.inner_counterpart().unwrap_or(*part)).collect();
+            inner_parts.sort();
+            inner_parts.dedup();
+            for inner in inner_parts {
+                let Some(outer) = inner.outer_counterpart() else {
+                    continue;
+                };
+                let faces = parser.side_regions(&inner).into_iter().zip(parser.side_regions(&outer));
+                for ((side, inner_region), (_, outer_region)) in faces {
+                    // The shadow falls down the sides, the caps are shaded right under the overlay
+                    let offset = match side {
+                        CubeSide::Top | CubeSide::Bottom => 0,
+                        _ => resolution,
+                    };
+                    shadow_face(skin, &mut result, outer_region, inner_region, offset, strength);
+                }
+            }
+        }
+    }
+    Ok(changed_cells(&parser, skin, &result))
+}
+
+fn darken(color: Rgba<u8>, amount: f32) -> Rgba<u8> {
+    let [red, green, blue, alpha] = color.0;
+    let scale = |channel: u8| (f32::from(channel) * (1.0 - amount)).round() as u8;
+    Rgba([scale(red), scale(green), scale(blue), alpha])
+}
+
+/// Empty cells next to opaque ones take the darkened average of their opaque neighbours.
+fn outline_face(skin: &RgbaImage, result: &mut RgbaImage, [x, y, width, height]: [u32; 4], strength: f32) {
+    let opaque = |face_x: i64, face_y: i64| {
+        (face_x >= 0 && face_y >= 0 && face_x < i64::from(width) && face_y < i64::from(height))
+            .then(|| *skin.get_pixel(x + face_x as u32, y + face_y as u32))
+            .filter(|pixel| pixel[3] != 0)
+    };
+    for face_y in 0..i64::from(height) {
+        for face_x in 0..i64::from(width) {
+            if opaque(face_x, face_y).is_some() {
+                continue;
+            }
+            let neighbours: Vec<Rgba<u8>> = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .into_iter()
+                .filter_map(|(dx, dy)| opaque(face_x + dx, face_y + dy))
+                .collect();
+            if neighbours.is_empty() {
+                continue;
+            }
+            let average = Rgba(std::array::from_fn(|channel| {
+                let sum: u32 = neighbours.iter().map(|pixel| u32::from(pixel[channel])).sum();
+                (sum / neighbours.len() as u32) as u8
+            }));
+            result.put_pixel(x + face_x as u32, y + face_y as u32, darken(Rgba([average[0], average[1], average[2], 255]), strength));
+        }
+    }
+}
+
+/// Ends of the side faces that touch another body part: top and bottom.
+fn joints(body_part: BodyPart) -> (bool, bool) {
+    match body_part.inner_counterpart().unwrap_or(body_part) {
+        BodyPart::Head => (false, true),
+        BodyPart::Torso => (true, true),
+        _ => (true, false),
+    }
+}
+
+fn occlude_face(
+    skin: &RgbaImage,
+    result: &mut RgbaImage,
+    body_part: BodyPart,
+    side: CubeSide,
+    [x, y, width, height]: [u32; 4],
+    resolution: u32,
+    strength: f32,
+) {
+    let (top_joint, bottom_joint) = joints(body_part);
+    let is_side = !matches!(side, CubeSide::Top | CubeSide::Bottom);
+    let depth = SEAM_DEPTH * resolution;
+    for face_y in 0..height {
+        for face_x in 0..width {
+            let pixel = *skin.get_pixel(x + face_x, y + face_y);
+            if pixel[3] == 0 {
+                continue;
+            }
+            let on_edge = face_x < resolution || face_y < resolution || face_x + resolution >= width || face_y + resolution >= height;
+            let mut amount: f32 = if on_edge { strength * EDGE_SHARE } else { 0.0 };
+            if is_side {
+                let seam_distance = [(top_joint, face_y), (bottom_joint, height - 1 - face_y)]
+                    .into_iter()
+                    .filter_map(|(joint, distance)| joint.then_some(distance))
+                    .min();
+                if let Some(distance) = seam_distance.filter(|distance| *distance < depth) {
+                    amount = amount.max(strength * (1.0 - distance as f32 / depth as f32));
+                }
+            }
+            if amount > 0.0 {
+                result.put_pixel(x + face_x, y + face_y, darken(pixel, amount));
+            }
+        }
+    }
+}
+
+fn shadow_face(skin: &RgbaImage, result: &mut RgbaImage, outer: [u32; 4], inner: [u32; 4], offset: u32, strength: f32) {
+    let [outer_x, outer_y, width, height] = outer;
+    let [inner_x, inner_y, _, _] = inner;
+    for face_y in 0..height {
+        for face_x in 0..width {
+            let cover = skin.get_pixel(outer_x + face_x, outer_y + face_y)[3];
+            let shaded_y = face_y + offset;
+            if cover == 0 || shaded_y >= height {
+                continue;
+            }
+            let base = *result.get_pixel(inner_x + face_x, inner_y + shaded_y);
+            if base[3] == 0 {
+                continue;
+            }
+            result.put_pixel(inner_x + face_x, inner_y + shaded_y, darken(base, strength * f32::from(cover) / 255.0));
+        }
+    }
+}
+
+pub(crate) fn changed_cells(parser: &SkinParser, before: &RgbaImage, after: &RgbaImage) -> Vec<CellColor> {
+    let mut cells = vec![];
+    for body_part in BodyPart::PLAYER {
+        let mut first_cell = 0;
+        for (_, [x, y, width, height]) in parser.side_regions(&body_part) {
+            for face_y in 0..height {
+                for face_x in 0..width {
+                    let pixel = *after.get_pixel(x + face_x, y + face_y);
+                    if pixel != *before.get_pixel(x + face_x, y + face_y) {
+                        cells.push((body_part, first_cell + (face_y * width + face_x) as usize, pixel));
+                    }
+                }
+            }
+            first_cell += (width * height) as usize;
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAY: Rgba<u8> = Rgba([200, 200, 200, 255]);
+
+    fn color_of(cells: &[CellColor], body_part: BodyPart, cell_index: usize) -> Option<Rgba<u8>> {
+        cells.iter().find(|(part, index, _)| *part == body_part && *index == cell_index).map(|(_, _, color)| *color)
+    }
+
+    #[test]
+    fn outlines_ring_the_opaque_cells() {
+        let mut skin = RgbaImage::new(64, 64);
+        // A single cell in the middle of the jacket front, which starts at 20,36
+        skin.put_pixel(23, 40, GRAY);
+        let cells = apply_effect(&skin, &ModelType::Classic, &[BodyPart::TorsoOuter], Effect::Outline, 0.5).unwrap();
+        assert_eq!(cells.len(), 4);
+        // Front is 8 cells wide, the cell is at 3,4 and the one above it at 3,3
+        assert_eq!(color_of(&cells, BodyPart::TorsoOuter, 3 * 8 + 3), Some(Rgba([100, 100, 100, 255])));
+        assert!(color_of(&cells, BodyPart::TorsoOuter, 4 * 8 + 3).is_none());
+
+        assert!(apply_effect(&skin, &ModelType::Classic, &[BodyPart::Torso], Effect::Outline, 0.5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn occlusion_is_strongest_at_the_joints() {
+        let skin = RgbaImage::from_pixel(64, 64, GRAY);
+        let cells = apply_effect(&skin, &ModelType::Classic, &[BodyPart::RightLeg], Effect::AmbientOcclusion, 0.5).unwrap();
+        assert!(cells.iter().all(|(part, _, _)| *part == BodyPart::RightLeg));
+        // Front of the leg is 4x12: the top row meets the torso, the middle is untouched
+        assert_eq!(color_of(&cells, BodyPart::RightLeg, 1), Some(Rgba([100, 100, 100, 255])));
+        assert_eq!(color_of(&cells, BodyPart::RightLeg, 4 * 5 + 1), None);
+        let edge = color_of(&cells, BodyPart::RightLeg, 4 * 5).unwrap();
+        assert!(edge[0] > 100 && edge[0] < 200);
+    }
+
+    #[test]
+    fn the_overlay_casts_a_shadow_on_the_base() {
+        let mut skin = RgbaImage::from_pixel(64, 64, GRAY);
+        // The jacket takes 16,32 to 56,48, its front starts at 20,36 and the torso front at 20,20
+        for x in 16..56 {
+            for y in 32..48 {
+                skin.put_pixel(x, y, Rgba([0; 4]));
+            }
+        }
+        skin.put_pixel(22, 36, Rgba([255, 0, 0, 255]));
+        let cells = apply_effect(&skin, &ModelType::Classic, &[BodyPart::TorsoOuter], Effect::DropShadow, 0.5).unwrap();
+        assert_eq!(cells, vec![(BodyPart::Torso, 8 + 2, Rgba([100, 100, 100, 255]))]);
+    }
+}
@@ -15,7 +15,7 @@ use image::{DynamicImage, EncodableLayout, GenericImage, GenericImageView};
 use tokio::runtime::Runtime;
 use tokio::sync::{Mutex, oneshot};
 
-use crate::glium_area::skin_parser::TextureType;
+use crate::glium_area::skin_parser::texture_layout;
 use crate::utils::guess_model_type;
 use crate::window::Window;
 
@@ -153,16 +153,13 @@ impl SkinLoaderPopover {
         texture_button.set_child(Some(&inner_box));
 
         texture_button.connect_clicked(move |_| {
-            let model_type = guess_model_type(texture.as_bytes());
-            let texture_type = match texture.dimensions() {
-                (64, 64) => TextureType::Normal,
-                (64, 32) => TextureType::Legacy,
-                _ => panic!("Wrong texture dimensions")
-            };
-            match win.load_skin_from_image(&texture, model_type.unwrap(), texture_type) {
-                Ok(_) => println!("Texture loaded"),
-                Err(error) => println!("Error loading texture: {:?}", error),
+            let model_type = guess_model_type(&texture).model_type;
+            if let Err(error) = texture_layout(texture.width(), texture.height()) {
+                println!("Error loading texture: {:?}", error);
+                return;
             }
+            win.load_skin_from_image(&texture, model_type);
+            println!("Texture loaded");
         });
 
         texture_button
@@ -0,0 +1,304 @@
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{AdjustmentExt, ButtonExt, CheckButtonExt, WidgetExt};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use libadwaita as adw;
+use libadwaita::prelude::AdwDialogExt;
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::cube_side::CubeSide;
+use crate::glium_area::renderer::ModelCell;
+use crate::glium_area::skin_parser::{texture_layout, SkinParser, TextureType};
+use crate::image_stamp::{skin_palette, stamp, Dither, Fit, Stamp, StampOptions, StampTarget, PALETTE_SIZE};
+use crate::utils;
+use crate::window::Window;
+
+const PREVIEW_SIZE: u32 = 256;
+/// Faces in the order of the face selector.
+const SIDES: [CubeSide; 6] = [CubeSide::Front, CubeSide::Left, CubeSide::Back, CubeSide::Right, CubeSide::Top, CubeSide::Bottom];
+
+mod imp {
+    use std::cell::RefCell;
+
+    use gtk::{glib, TemplateChild};
+    use gtk::CompositeTemplate;
+    use gtk::glib::WeakRef;
+    use gtk::subclass::prelude::{CompositeTemplate, CompositeTemplateInitializingExt, ObjectImpl, ObjectSubclass, WidgetImpl};
+    use gtk::subclass::widget::WidgetClassExt;
+    use image::RgbaImage;
+    use libadwaita as adw;
+    use libadwaita::subclass::dialog::AdwDialogImpl;
+
+    use crate::glium_area::skin_parser::ModelType;
+    use crate::window::Window;
+
+    #[derive(CompositeTemplate)]
+    #[template(file = "../resources/ui/stamp-dialog.ui")]
+    pub struct StampDialog {
+        #[template_child]
+        pub preview: TemplateChild<gtk::Picture>,
+        #[template_child]
+        pub part_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub side_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub region_x: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub region_y: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub region_width: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub region_height: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub crop_x: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub crop_y: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub crop_width: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub crop_height: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub fit_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub palette_button: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub dither_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub apply_button: TemplateChild<gtk::Button>,
+
+        /// The picture to stamp.
+        pub picture: RefCell<RgbaImage>,
+        /// The skin as it was when the dialog was opened.
+        pub skin: RefCell<RgbaImage>,
+        pub model_type: RefCell<ModelType>,
+        pub window: WeakRef<Window>,
+    }
+
+    impl Default for StampDialog {
+        fn default() -> Self {
+            StampDialog {
+                preview: Default::default(),
+                part_selector: Default::default(),
+                side_selector: Default::default(),
+                region_x: Default::default(),
+                region_y: Default::default(),
+                region_width: Default::default(),
+                region_height: Default::default(),
+                crop_x: Default::default(),
+                crop_y: Default::default(),
+                crop_width: Default::default(),
+                crop_height: Default::default(),
+                fit_selector: Default::default(),
+                palette_button: Default::default(),
+                dither_selector: Default::default(),
+                apply_button: Default::default(),
+                picture: RefCell::new(RgbaImage::new(1, 1)),
+                skin: RefCell::new(RgbaImage::new(64, 64)),
+                model_type: RefCell::new(ModelType::Classic),
+                window: Default::default(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for StampDialog {
+        const NAME: &'static str = "StampDialog";
+        type Type = super::StampDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+    impl ObjectImpl for StampDialog {}
+    impl WidgetImpl for StampDialog {}
+    impl AdwDialogImpl for StampDialog {}
+}
+
+glib::wrapper! {
+    pub struct StampDialog(ObjectSubclass<imp::StampDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::ShortcutManager;
+}
+
+impl StampDialog {
+    /// Stamps the picture on the torso front at first, the whole picture fit inside it.
+    pub fn new(win: &Window, picture: RgbaImage) -> Self {
+        let dialog: StampDialog = glib::Object::new();
+        dialog.imp().window.set(Some(win));
+        dialog.imp().skin.replace(win.export_texture());
+        dialog.imp().model_type.replace(win.editor().model_type());
+
+        let labels: Vec<&str> = BodyPart::PLAYER.iter().map(|body_part| body_part.label()).collect();
+        dialog.imp().part_selector.set_model(Some(&gtk::StringList::new(&labels)));
+        let torso = BodyPart::PLAYER.iter().position(|body_part| *body_part == BodyPart::Torso).unwrap_or(0);
+        dialog.imp().part_selector.set_selected(torso as u32);
+
+        let imp = dialog.imp();
+        for (spin_button, upper, value) in [
+            (&imp.crop_x, picture.width() - 1, 0),
+            (&imp.crop_y, picture.height() - 1, 0),
+            (&imp.crop_width, picture.width(), picture.width()),
+            (&imp.crop_height, picture.height(), picture.height()),
+        ] {
+            spin_button.adjustment().set_upper(f64::from(upper));
+            spin_button.set_value(f64::from(value));
+        }
+        imp.picture.replace(picture);
+
+        dialog.reset_region();
+        dialog.connect_signals();
+        dialog.update_dither_selector();
+        dialog.update_preview();
+        dialog
+    }
+
+    fn window(&self) -> Window {
+        self.imp().window.upgrade().expect("StampDialog has no window")
+    }
+
+    fn connect_signals(&self) {
+        let imp = self.imp();
+        for selector in [&imp.part_selector, &imp.side_selector] {
+            selector.connect_selected_notify(clone!(#[weak(rename_to = dialog)] self, move |_| {
+                dialog.reset_region();
+                dialog.update_preview();
+            }));
+        }
+        for selector in [&imp.fit_selector, &imp.dither_selector] {
+            selector.connect_selected_notify(clone!(#[weak(rename_to = dialog)] self, move |_| {
+                dialog.update_preview();
+            }));
+        }
+        for spin_button in [
+            &imp.region_x, &imp.region_y, &imp.region_width, &imp.region_height,
+            &imp.crop_x, &imp.crop_y, &imp.crop_width, &imp.crop_height,
+        ] {
+            spin_button.connect_value_changed(clone!(#[weak(rename_to = dialog)] self, move |_| {
+                dialog.update_preview();
+            }));
+        }
+        imp.palette_button.connect_toggled(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_dither_selector();
+            dialog.update_preview();
+        }));
+        imp.apply_button.connect_clicked(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.apply();
+        }));
+    }
+
+    fn target(&self) -> StampTarget {
+        let imp = self.imp();
+        StampTarget {
+            body_part: BodyPart::PLAYER[imp.part_selector.selected() as usize % BodyPart::PLAYER.len()],
+            side: SIDES[imp.side_selector.selected() as usize % SIDES.len()],
+            region: Some([
+                imp.region_x.value() as u32,
+                imp.region_y.value() as u32,
+                imp.region_width.value() as u32,
+                imp.region_height.value() as u32,
+            ]),
+        }
+    }
+
+    fn options(&self) -> StampOptions {
+        let imp = self.imp();
+        let palette = imp.palette_button.is_active().then(|| skin_palette(&imp.skin.borrow(), PALETTE_SIZE));
+        StampOptions {
+            crop: Some([
+                imp.crop_x.value() as u32,
+                imp.crop_y.value() as u32,
+                imp.crop_width.value() as u32,
+                imp.crop_height.value() as u32,
+            ]),
+            fit: match imp.fit_selector.selected() {
+                1 => Fit::Cover,
+                2 => Fit::Stretch,
+                _ => Fit::Contain,
+            },
+            palette,
+            dither: match imp.dither_selector.selected() {
+                1 => Dither::Ordered,
+                2 => Dither::FloydSteinberg,
+                _ => Dither::None,
+            },
+        }
+    }
+
+    /// Makes the region the whole face of the selected body part.
+    fn reset_region(&self) {
+        let imp = self.imp();
+        let skin = imp.skin.borrow();
+        let Ok((_, resolution)) = texture_layout(skin.width(), skin.height()) else {
+            return;
+        };
+        let target = self.target();
+        let parser = SkinParser::with_resolution(&imp.model_type.borrow(), TextureType::Normal, resolution);
+        let Some((_, [_, _, width, height])) = parser
+            .side_regions(&target.body_part)
+            .into_iter()
+            .find(|(side, _)| *side == target.side) else {
+            return;
+        };
+        for (spin_button, upper, value) in [
+            (&imp.region_x, width - 1, 0),
+            (&imp.region_y, height - 1, 0),
+            (&imp.region_width, width, width),
+            (&imp.region_height, height, height),
+        ] {
+            spin_button.adjustment().set_upper(f64::from(upper));
+            spin_button.set_value(f64::from(value));
+        }
+    }
+
+    fn update_dither_selector(&self) {
+        self.imp().dither_selector.set_sensitive(self.imp().palette_button.is_active());
+    }
+
+    fn stamp(&self) -> Option<Stamp> {
+        let imp = self.imp();
+        let result = stamp(&imp.skin.borrow(), &imp.model_type.borrow(), &self.target(), &imp.picture.borrow(), &self.options());
+        match result {
+            Ok(stamp) => Some(stamp),
+            Err(error) => {
+                println!("Error stamping image: {:?}", error);
+                None
+            }
+        }
+    }
+
+    /// Shows the face with the picture on it, each cell a block of pixels.
+    fn update_preview(&self) {
+        let Some(stamp) = self.stamp() else {
+            return;
+        };
+        let [x, y, width, height] = stamp.face;
+        let face = imageops::crop_imm(&stamp.image, x, y, width, height).to_image();
+        let scale = (PREVIEW_SIZE / width.max(height)).max(1);
+        let preview = imageops::resize(&face, width * scale, height * scale, FilterType::Nearest);
+        self.imp().preview.set_paintable(Some(&utils::texture_from_image(&preview)));
+    }
+
+    fn apply(&self) {
+        let Some(stamp) = self.stamp() else {
+            return;
+        };
+        let body_part = self.target().body_part;
+        let cells = stamp.cells
+            .iter()
+            .map(|(cell_index, color)| ModelCell {
+                body_part,
+                cell_index: *cell_index,
+                color: color.0.map(|channel| f32::from(channel) / 255.0),
+            })
+            .collect();
+        self.window().paint_cells(cells);
+        self.close();
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use image::{ImageError, RgbaImage};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::glium_area::skin_parser::{texture_layout, TextureType};
+use crate::template_library::{
+    metadata_for_file, pixel_hash, ConflictPolicy, ImportSummary, TemplateEntry, TemplateLibrary,
+    TemplateLibraryError, TemplateMetadata, TemplateSource,
+};
+use crate::utils;
+use crate::utils::Confidence;
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Io(io::Error),
+    Zip(ZipError),
+    Image(ImageError),
+    Metadata(serde_json::Error),
+    Library(TemplateLibraryError),
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(error: io::Error) -> Self {
+        ArchiveError::Io(error)
+    }
+}
+
+impl From<ZipError> for ArchiveError {
+    fn from(error: ZipError) -> Self {
+        ArchiveError::Zip(error)
+    }
+}
+
+impl From<ImageError> for ArchiveError {
+    fn from(error: ImageError) -> Self {
+        ArchiveError::Image(error)
+    }
+}
+
+impl From<serde_json::Error> for ArchiveError {
+    fn from(error: serde_json::Error) -> Self {
+        ArchiveError::Metadata(error)
+    }
+}
+
+impl From<TemplateLibraryError> for ArchiveError {
+    fn from(error: TemplateLibraryError) -> Self {
+        ArchiveError::Library(error)
+    }
+}
+
+/// A template read from an archive or a synced folder, not yet in the wardrobe.
+pub struct IncomingTemplate {
+    pub image: RgbaImage,
+    pub metadata: TemplateMetadata,
+}
+
+/// Writes the templates to a zip: `<name>.png` with its `<name>.json` sidecar for each one.
+pub fn export_templates(entries: &[TemplateEntry], path: &Path) -> Result<(), ArchiveError> {
+    let mut writer = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for entry in entries {
+        let stem = entry.path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("template");
+
+        writer.start_file(format!("{}.png", stem), options)?;
+        writer.write_all(&fs::read(&entry.path)?)?;
+
+        writer.start_file(format!("{}.json", stem), options)?;
+        writer.write_all(serde_json::to_string_pretty(&entry.metadata)?.as_bytes())?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// Reads the templates of an archive made by [`export_templates`]. Plain PNG skins
+/// without a sidecar are accepted as well.
+pub fn read_archive(path: &Path) -> Result<Vec<IncomingTemplate>, ArchiveError> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+
+    let mut images: Vec<(String, Vec<u8>)> = vec![];
+    let mut sidecars: HashMap<String, Vec<u8>> = HashMap::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().to_string();
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)?;
+
+        if let Some(stem) = name.strip_suffix(".png") {
+            images.push((stem.to_string(), bytes));
+        } else if let Some(stem) = name.strip_suffix(".json") {
+            sidecars.insert(stem.to_string(), bytes);
+        }
+    }
+
+    let mut templates = vec![];
+    for (stem, bytes) in images {
+        let image = match image::load_from_memory(&bytes) {
+            Ok(image) if is_skin_size(&image) => image.to_rgba8(),
+            _ => {
+                println!("Skipping {}.png: not a skin", stem);
+                continue;
+            }
+        };
+        let metadata = match sidecars.get(&stem) {
+            // Synced paths belong to the machine the archive was made on
+            Some(json) => TemplateMetadata { synced_from: None, ..serde_json::from_slice(json)? },
+            None => {
+                let display_name = Path::new(&stem)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or(stem.clone());
+                let model_type = utils::guess_model_type(&image).model_type;
+                TemplateMetadata::new(&display_name, model_type, TemplateSource::File)
+            }
+        };
+        templates.push(IncomingTemplate { image, metadata });
+    }
+    Ok(templates)
+}
+
+/// Reads skin files to import as templates, with how sure the detection of their
+/// model type is. Files that aren't skins are left out.
+pub fn read_skin_files(paths: &[PathBuf]) -> Vec<(IncomingTemplate, Confidence)> {
+    let mut templates = vec![];
+    for path in paths {
+        let image = match image::open(path) {
+            Ok(image) if is_skin_size(&image) => image.to_rgba8(),
+            _ => {
+                println!("Skipping {:?}: not a skin", path);
+                continue;
+            }
+        };
+        let guess = utils::guess_model_type(&image);
+        let metadata = TemplateMetadata { model_type: guess.model_type, ..metadata_for_file(path) };
+        templates.push((IncomingTemplate { image, metadata }, guess.confidence));
+    }
+    templates
+}
+
+/// Brings the skins of an external folder into the wardrobe. Templates that came
+/// from a file follow its changes and go away with it; new files are imported unless
+/// their pixels already are in the wardrobe. Sidecars in the folder are used when they
+/// can be read as template metadata.
+pub fn sync_folder(library: &mut TemplateLibrary, dir: &Path) -> Result<ImportSummary, ArchiveError> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .flatten()
+        .map(|dir_entry| dir_entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "png"))
+        .collect();
+    paths.sort();
+
+    let mut hashes = library.pixel_hashes();
+    let mut summary = ImportSummary::default();
+    for path in paths {
+        let image = match image::open(&path) {
+            Ok(image) if is_skin_size(&image) => image.to_rgba8(),
+            _ => {
+                println!("Skipping {:?}: not a skin", path);
+                continue;
+            }
+        };
+        let hash = pixel_hash(&image);
+
+        if let Some(existing) = library.find_synced(&path).map(|entry| entry.path.clone()) {
+            if hashes.get(&hash) == Some(&existing) {
+                summary.skipped += 1;
+            } else {
+                library.replace_image(&existing, &image)?;
+                hashes.retain(|_, template| *template != existing);
+                hashes.insert(hash, existing);
+                summary.replaced += 1;
+            }
+            continue;
+        }
+
+        let mut metadata = match fs::read_to_string(path.with_extension("json")) {
+            // Other JSON files, like a Bedrock geo.json, can share the name of a skin
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|error| {
+                println!("Ignoring {:?}: not template metadata: {:?}", path.with_extension("json"), error);
+                metadata_for_file(&path)
+            }),
+            Err(_) => metadata_for_file(&path),
+        };
+        metadata.synced_from = Some(path.clone());
+        let outcome = library.import(&image, metadata, ConflictPolicy::Skip, &mut hashes)?;
+        summary.record(&outcome);
+    }
+
+    let removed: Vec<PathBuf> = library.entries()
+        .iter()
+        .filter(|entry| entry.metadata.synced_from.as_deref().is_some_and(|source| {
+            source.parent() == Some(dir) && !source.exists()
+        }))
+        .map(|entry| entry.path.clone())
+        .collect();
+    for path in removed {
+        library.delete(&path)?;
+        summary.removed += 1;
+    }
+    Ok(summary)
+}
+
+/// How many of the templates already are in the wardrobe.
+pub fn count_duplicates(templates: &[IncomingTemplate], hashes: &HashMap<u64, PathBuf>) -> usize {
+    templates
+        .iter()
+        .filter(|template| hashes.contains_key(&pixel_hash(&template.image)))
+        .count()
+}
+
+pub fn import_templates(
+    library: &mut TemplateLibrary,
+    templates: Vec<IncomingTemplate>,
+    policy: ConflictPolicy,
+) -> Result<ImportSummary, ArchiveError> {
+    let mut hashes = library.pixel_hashes();
+    let mut summary = ImportSummary::default();
+    for template in templates {
+        let outcome = library.import(&template.image, template.metadata, policy, &mut hashes)?;
+        summary.record(&outcome);
+    }
+    Ok(summary)
+}
+
+/// Templates are applied with the 64x64 layout or its HD versions, so legacy skins are left out.
+fn is_skin_size(image: &image::DynamicImage) -> bool {
+    matches!(texture_layout(image.width(), image.height()), Ok((TextureType::Normal, _)))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use crate::glium_area::skin_parser::ModelType;
+
+    use super::*;
+
+    fn empty_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("skin-editor-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn skin(color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(64, 64, Rgba(color))
+    }
+
+    fn incoming(name: &str, color: [u8; 4]) -> IncomingTemplate {
+        IncomingTemplate {
+            image: skin(color),
+            metadata: TemplateMetadata::new(name, ModelType::Slim, TemplateSource::Fetched),
+        }
+    }
+
+    fn library_with(name: &str, templates: &[(&str, [u8; 4])]) -> TemplateLibrary {
+        let mut library = TemplateLibrary::new(empty_dir(name));
+        for &(name, color) in templates {
+            library.add(&skin(color), TemplateMetadata::new(name, ModelType::Classic, TemplateSource::File)).unwrap();
+        }
+        library
+    }
+
+    #[test]
+    fn pixel_hash_ignores_hidden_colors() {
+        let mut image = skin([255, 0, 0, 255]);
+        image.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+        let mut resaved = image.clone();
+        resaved.put_pixel(0, 0, Rgba([255, 255, 255, 0]));
+        assert_eq!(pixel_hash(&image), pixel_hash(&resaved));
+
+        let mut edited = image.clone();
+        edited.put_pixel(1, 0, Rgba([254, 0, 0, 255]));
+        assert_ne!(pixel_hash(&image), pixel_hash(&edited));
+        assert_ne!(pixel_hash(&image), pixel_hash(&RgbaImage::from_pixel(64, 32, Rgba([255, 0, 0, 255]))));
+    }
+
+    #[test]
+    fn duplicates_are_found_by_pixels() {
+        let library = library_with("duplicates", &[("Red", [255, 0, 0, 255])]);
+        let templates = [incoming("Also red", [255, 0, 0, 255]), incoming("Green", [0, 255, 0, 255])];
+        assert_eq!(count_duplicates(&templates, &library.pixel_hashes()), 1);
+    }
+
+    #[test]
+    fn skip_keeps_the_wardrobe_as_it_is() {
+        let mut library = library_with("skip", &[("Red", [255, 0, 0, 255])]);
+        let templates = vec![incoming("Also red", [255, 0, 0, 255]), incoming("Green", [0, 255, 0, 255])];
+
+        let summary = import_templates(&mut library, templates, ConflictPolicy::Skip).unwrap();
+        assert_eq!((summary.added, summary.replaced, summary.skipped), (1, 0, 1));
+        let mut names: Vec<&str> = library.entries().iter().map(|entry| entry.metadata.display_name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["Green", "Red"]);
+    }
+
+    #[test]
+    fn keep_both_adds_duplicates() {
+        let mut library = library_with("keep-both", &[("Red", [255, 0, 0, 255])]);
+        let templates = vec![incoming("Also red", [255, 0, 0, 255]), incoming("Green", [0, 255, 0, 255])];
+
+        let summary = import_templates(&mut library, templates, ConflictPolicy::KeepBoth).unwrap();
+        assert_eq!((summary.added, summary.replaced, summary.skipped), (2, 0, 0));
+        assert_eq!(library.entries().len(), 3);
+    }
+
+    #[test]
+    fn replace_takes_over_the_metadata() {
+        let mut library = library_with("replace", &[("Red", [255, 0, 0, 255])]);
+        let original = library.entries()[0].path.clone();
+        let mut template = incoming("Also red", [255, 0, 0, 255]);
+        template.metadata.tags = vec!["armor".to_string()];
+
+        let summary = import_templates(&mut library, vec![template], ConflictPolicy::Replace).unwrap();
+        assert_eq!((summary.added, summary.replaced, summary.skipped), (0, 1, 0));
+        assert_eq!(library.entries().len(), 1);
+        let entry = library.get(&original).unwrap();
+        assert_eq!(entry.metadata.display_name, "Also red");
+        assert_eq!(entry.metadata.tags, ["armor"]);
+        assert_eq!(entry.metadata.model_type, ModelType::Slim);
+    }
+
+    #[test]
+    fn exported_templates_read_back() {
+        let mut library = library_with("export", &[("Red", [255, 0, 0, 255]), ("Green", [0, 255, 0, 255])]);
+        let first = library.entries()[0].path.clone();
+        library.set_tags(&first, vec!["knight".to_string()]).unwrap();
+        let archive = library.dir().join("pack.zip");
+
+        export_templates(library.entries(), &archive).unwrap();
+        let templates = read_archive(&archive).unwrap();
+        assert_eq!(templates.len(), 2);
+        for entry in library.entries() {
+            let template = templates
+                .iter()
+                .find(|template| template.metadata.display_name == entry.metadata.display_name)
+                .unwrap();
+            assert_eq!(template.image, image::open(&entry.path).unwrap().to_rgba8());
+            assert_eq!(template.metadata.tags, entry.metadata.tags);
+            assert_eq!(template.metadata.model_type, entry.metadata.model_type);
+            assert_eq!(template.metadata.created, entry.metadata.created);
+        }
+    }
+
+    #[test]
+    fn synced_folders_tolerate_foreign_json_and_deletions() {
+        let mut library = library_with("sync-wardrobe", &[]);
+        let folder = empty_dir("sync-folder");
+        skin([255, 0, 0, 255]).save(folder.join("red.png")).unwrap();
+        skin([0, 255, 0, 255]).save(folder.join("green.png")).unwrap();
+        fs::write(folder.join("red.json"), r#"{"format_version": "1.12.0"}"#).unwrap();
+
+        let summary = sync_folder(&mut library, &folder).unwrap();
+        assert_eq!(summary.added, 2);
+        assert!(library.entries().iter().any(|entry| entry.metadata.display_name == "red"));
+
+        fs::remove_file(folder.join("green.png")).unwrap();
+        let summary = sync_folder(&mut library, &folder).unwrap();
+        assert_eq!((summary.skipped, summary.removed), (1, 1));
+        assert_eq!(library.entries().len(), 1);
+    }
+}
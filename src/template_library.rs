@@ -0,0 +1,517 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::{ImageBuffer, ImageError, Rgba};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::glium_area::skin_parser::ModelType;
+use crate::utils;
+
+/// Where a template originally came from.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateSource {
+    #[default]
+    File,
+    Fetched,
+    Drawn,
+}
+
+/// Sidecar metadata stored next to every template PNG as `<name>.json`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TemplateMetadata {
+    pub display_name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub model_type: ModelType,
+    #[serde(default)]
+    pub author: Option<String>,
+    pub created: u64,
+    pub modified: u64,
+    #[serde(default)]
+    pub source: TemplateSource,
+    /// File in the synced folder this template mirrors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synced_from: Option<PathBuf>,
+}
+
+impl TemplateMetadata {
+    pub fn new(display_name: &str, model_type: ModelType, source: TemplateSource) -> Self {
+        let now = unix_now();
+        TemplateMetadata {
+            display_name: display_name.to_string(),
+            tags: vec![],
+            model_type,
+            author: None,
+            created: now,
+            modified: now,
+            source,
+            synced_from: None,
+        }
+    }
+
+    fn touch(&mut self) {
+        self.modified = unix_now();
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TemplateEntry {
+    pub path: PathBuf,
+    pub metadata: TemplateMetadata,
+}
+
+impl TemplateEntry {
+    pub fn sidecar_path(&self) -> PathBuf {
+        sidecar_path(&self.path)
+    }
+
+    fn matches(&self, search: &str) -> bool {
+        if search.is_empty() {
+            return true;
+        }
+        let search = search.to_lowercase();
+        let metadata = &self.metadata;
+        metadata.display_name.to_lowercase().contains(&search)
+            || metadata.tags.iter().any(|tag| tag.to_lowercase().contains(&search))
+            || metadata.author.as_ref().is_some_and(|author| author.to_lowercase().contains(&search))
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TemplateSort {
+    Name,
+    Newest,
+    RecentlyModified,
+}
+
+/// What to do when an imported template has the same pixels as one in the wardrobe.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ConflictPolicy {
+    Skip,
+    KeepBoth,
+    /// Keeps the existing file but takes over the imported name, tags and author.
+    Replace,
+}
+
+#[derive(Clone, Debug)]
+pub enum ImportOutcome {
+    Added(TemplateEntry),
+    Replaced(TemplateEntry),
+    Skipped(PathBuf),
+}
+
+#[derive(Default, Debug)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub replaced: usize,
+    pub skipped: usize,
+    /// Synced templates whose file is gone from the folder.
+    pub removed: usize,
+}
+
+impl ImportSummary {
+    pub fn record(&mut self, outcome: &ImportOutcome) {
+        match outcome {
+            ImportOutcome::Added(_) => self.added += 1,
+            ImportOutcome::Replaced(_) => self.replaced += 1,
+            ImportOutcome::Skipped(_) => self.skipped += 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TemplateLibraryError {
+    Io(io::Error),
+    Metadata(serde_json::Error),
+    Image(ImageError),
+    NotFound(PathBuf),
+}
+
+impl From<io::Error> for TemplateLibraryError {
+    fn from(error: io::Error) -> Self {
+        TemplateLibraryError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for TemplateLibraryError {
+    fn from(error: serde_json::Error) -> Self {
+        TemplateLibraryError::Metadata(error)
+    }
+}
+
+impl From<ImageError> for TemplateLibraryError {
+    fn from(error: ImageError) -> Self {
+        TemplateLibraryError::Image(error)
+    }
+}
+
+/// Templates in a directory together with their sidecar metadata.
+pub struct TemplateLibrary {
+    dir: PathBuf,
+    entries: Vec<TemplateEntry>,
+}
+
+impl TemplateLibrary {
+    pub fn new(dir: PathBuf) -> Self {
+        TemplateLibrary { dir, entries: vec![] }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Rescans the directory without writing to it. PNGs without a readable sidecar get
+    /// defaults, which are saved once the template is edited.
+    pub fn reload(&mut self) {
+        self.entries.clear();
+        let Ok(read_dir) = fs::read_dir(&self.dir) else {
+            println!("No such dir: {:?}", self.dir);
+            return;
+        };
+
+        for dir_entry in read_dir.flatten() {
+            let path = dir_entry.path();
+            if path.extension().is_none_or(|extension| extension != "png") {
+                continue;
+            }
+            self.entries.push(load_entry(&path));
+        }
+    }
+
+    pub fn entries(&self) -> &[TemplateEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&TemplateEntry> {
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// All tags used by the templates, sorted and without duplicates.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.entries
+            .iter()
+            .flat_map(|entry| entry.metadata.tags.iter().cloned())
+            .collect();
+        tags.sort_by_key(|tag| tag.to_lowercase());
+        tags.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        tags
+    }
+
+    pub fn query(&self, search: &str, tag: Option<&str>, sort: TemplateSort) -> Vec<TemplateEntry> {
+        let search = search.trim();
+        let mut result: Vec<TemplateEntry> = self.entries
+            .iter()
+            .filter(|entry| entry.matches(search))
+            .filter(|entry| tag.is_none_or(|tag| {
+                entry.metadata.tags.iter().any(|entry_tag| entry_tag.eq_ignore_ascii_case(tag))
+            }))
+            .cloned()
+            .collect();
+
+        match sort {
+            TemplateSort::Name => result.sort_by_key(|entry| entry.metadata.display_name.to_lowercase()),
+            TemplateSort::Newest => result.sort_by(|a, b| b.metadata.created.cmp(&a.metadata.created)),
+            TemplateSort::RecentlyModified => result.sort_by(|a, b| b.metadata.modified.cmp(&a.metadata.modified)),
+        }
+        result
+    }
+
+    pub fn add(
+        &mut self,
+        image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+        metadata: TemplateMetadata,
+    ) -> Result<TemplateEntry, TemplateLibraryError> {
+        let path = self.dir.join(utils::generate_random_filename());
+        image.save(&path)?;
+        let entry = TemplateEntry { path, metadata };
+        write_sidecar(&entry)?;
+        self.entries.push(entry.clone());
+        Ok(entry)
+    }
+
+    pub fn rename(&mut self, path: &Path, display_name: &str) -> Result<(), TemplateLibraryError> {
+        self.update(path, |metadata| metadata.display_name = display_name.trim().to_string())
+    }
+
+    pub fn set_tags(&mut self, path: &Path, tags: Vec<String>) -> Result<(), TemplateLibraryError> {
+        self.update(path, |metadata| metadata.tags = tags)
+    }
+
+    pub fn delete(&mut self, path: &Path) -> Result<(), TemplateLibraryError> {
+        let index = self.index_of(path)?;
+        let entry = self.entries.remove(index);
+        fs::remove_file(&entry.path)?;
+        let sidecar = entry.sidecar_path();
+        if sidecar.exists() {
+            fs::remove_file(sidecar)?;
+        }
+        Ok(())
+    }
+
+    pub fn duplicate(&mut self, path: &Path) -> Result<TemplateEntry, TemplateLibraryError> {
+        let index = self.index_of(path)?;
+        let original = self.entries[index].clone();
+
+        let mut metadata = original.metadata.clone();
+        metadata.display_name = format!("{} (copy)", metadata.display_name);
+        metadata.synced_from = None;
+        let now = unix_now();
+        metadata.created = now;
+        metadata.modified = now;
+
+        let copy = TemplateEntry {
+            path: self.dir.join(utils::generate_random_filename()),
+            metadata,
+        };
+        fs::copy(&original.path, &copy.path)?;
+        write_sidecar(&copy)?;
+        self.entries.push(copy.clone());
+        Ok(copy)
+    }
+
+    pub fn find_synced(&self, source: &Path) -> Option<&TemplateEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.metadata.synced_from.as_deref() == Some(source))
+    }
+
+    /// Overwrites the template's image, keeping its metadata.
+    pub fn replace_image(&mut self, path: &Path, image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Result<(), TemplateLibraryError> {
+        image.save(path)?;
+        self.update(path, |_| {})
+    }
+
+    /// Pixel hashes of all templates, to find duplicates of imported images.
+    pub fn pixel_hashes(&self) -> HashMap<u64, PathBuf> {
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let image = image::open(&entry.path).ok()?.to_rgba8();
+                Some((pixel_hash(&image), entry.path.clone()))
+            })
+            .collect()
+    }
+
+    /// Adds an image coming from outside of the wardrobe. `hashes` comes from
+    /// [`TemplateLibrary::pixel_hashes`] and is kept up to date for the next import.
+    pub fn import(
+        &mut self,
+        image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+        metadata: TemplateMetadata,
+        policy: ConflictPolicy,
+        hashes: &mut HashMap<u64, PathBuf>,
+    ) -> Result<ImportOutcome, TemplateLibraryError> {
+        let hash = pixel_hash(image);
+        let existing = hashes.get(&hash).cloned();
+
+        match (existing, policy) {
+            (Some(path), ConflictPolicy::Skip) => Ok(ImportOutcome::Skipped(path)),
+            (Some(path), ConflictPolicy::Replace) => {
+                self.update(&path, |current| {
+                    current.display_name = metadata.display_name;
+                    current.tags = metadata.tags;
+                    current.author = metadata.author;
+                    current.model_type = metadata.model_type;
+                })?;
+                let index = self.index_of(&path)?;
+                Ok(ImportOutcome::Replaced(self.entries[index].clone()))
+            }
+            (_, _) => {
+                let entry = self.add(image, metadata)?;
+                hashes.entry(hash).or_insert_with(|| entry.path.clone());
+                Ok(ImportOutcome::Added(entry))
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        path: &Path,
+        change: impl FnOnce(&mut TemplateMetadata),
+    ) -> Result<(), TemplateLibraryError> {
+        let index = self.index_of(path)?;
+        let entry = &mut self.entries[index];
+        change(&mut entry.metadata);
+        entry.metadata.touch();
+        write_sidecar(entry)
+    }
+
+    fn index_of(&self, path: &Path) -> Result<usize, TemplateLibraryError> {
+        self.entries
+            .iter()
+            .position(|entry| entry.path == path)
+            .ok_or_else(|| TemplateLibraryError::NotFound(path.to_path_buf()))
+    }
+}
+
+/// Splits user input like `"armor, red , knight"` into tags.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    input
+        .split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty() && seen.insert(tag.to_lowercase()))
+        .collect()
+}
+
+/// Hash of the visible pixels: the color of fully transparent pixels is ignored,
+/// so re-saving a skin in another editor doesn't make it look like a new one.
+pub fn pixel_hash(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> u64 {
+    let mut bytes = Vec::with_capacity(image.as_raw().len() + 8);
+    bytes.extend_from_slice(&image.width().to_le_bytes());
+    bytes.extend_from_slice(&image.height().to_le_bytes());
+    for pixel in image.pixels() {
+        match pixel[3] {
+            0 => bytes.extend_from_slice(&[0, 0, 0, 0]),
+            _ => bytes.extend_from_slice(&pixel.0),
+        }
+    }
+    utils::fnv1a(&bytes)
+}
+
+fn sidecar_path(png_path: &Path) -> PathBuf {
+    png_path.with_extension("json")
+}
+
+fn load_entry(path: &Path) -> TemplateEntry {
+    let sidecar = sidecar_path(path);
+    let metadata = match fs::read_to_string(&sidecar) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|error| {
+            println!("Ignoring corrupt sidecar {:?}: {:?}", sidecar, error);
+            metadata_for_file(path)
+        }),
+        // Templates saved before sidecars existed
+        Err(_) => metadata_for_file(path),
+    };
+    TemplateEntry { path: path.to_path_buf(), metadata }
+}
+
+/// Metadata for a skin file that doesn't have a sidecar: derives what it can from the file.
+pub fn metadata_for_file(path: &Path) -> TemplateMetadata {
+    let display_name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("Untitled")
+        .to_string();
+    let model_type = match image::open(path) {
+        Ok(image) => utils::guess_model_type(&image).model_type,
+        Err(_) => ModelType::Classic,
+    };
+    let file_time = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(system_time_to_unix)
+        .unwrap_or_else(|_| unix_now());
+
+    let mut metadata = TemplateMetadata::new(&display_name, model_type, TemplateSource::File);
+    metadata.created = file_time;
+    metadata.modified = file_time;
+    metadata
+}
+
+fn write_sidecar(entry: &TemplateEntry) -> Result<(), TemplateLibraryError> {
+    let json = serde_json::to_string_pretty(&entry.metadata)?;
+    fs::write(entry.sidecar_path(), json)?;
+    Ok(())
+}
+
+fn system_time_to_unix(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
+
+pub fn unix_now() -> u64 {
+    system_time_to_unix(SystemTime::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("skin-editor-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entry(name: &str, tags: &[&str], created: u64, modified: u64) -> TemplateEntry {
+        let mut metadata = TemplateMetadata::new(name, ModelType::Classic, TemplateSource::File);
+        metadata.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        metadata.created = created;
+        metadata.modified = modified;
+        TemplateEntry { path: PathBuf::from(format!("{}.png", name)), metadata }
+    }
+
+    fn library() -> TemplateLibrary {
+        let mut knight = entry("Knight", &["armor", "Red"], 10, 40);
+        knight.metadata.author = Some("Smith".to_string());
+        TemplateLibrary {
+            dir: PathBuf::new(),
+            entries: vec![
+                knight,
+                entry("alex", &["red"], 30, 30),
+                entry("Zombie", &["mob"], 20, 50),
+            ],
+        }
+    }
+
+    fn names(entries: &[TemplateEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.metadata.display_name.as_str()).collect()
+    }
+
+    #[test]
+    fn search_matches_names_tags_and_authors() {
+        let library = library();
+        assert_eq!(names(&library.query("", None, TemplateSort::Name)), ["alex", "Knight", "Zombie"]);
+        assert_eq!(names(&library.query(" zom ", None, TemplateSort::Name)), ["Zombie"]);
+        assert_eq!(names(&library.query("ARMOR", None, TemplateSort::Name)), ["Knight"]);
+        assert_eq!(names(&library.query("smith", None, TemplateSort::Name)), ["Knight"]);
+        assert!(library.query("creeper", None, TemplateSort::Name).is_empty());
+    }
+
+    #[test]
+    fn tag_filter_ignores_case() {
+        let library = library();
+        assert_eq!(names(&library.query("", Some("RED"), TemplateSort::Name)), ["alex", "Knight"]);
+        assert_eq!(names(&library.query("alex", Some("red"), TemplateSort::Name)), ["alex"]);
+        assert!(library.query("", Some("re"), TemplateSort::Name).is_empty());
+        assert_eq!(library.tags(), ["armor", "mob", "Red"]);
+    }
+
+    #[test]
+    fn sorting() {
+        let library = library();
+        assert_eq!(names(&library.query("", None, TemplateSort::Newest)), ["alex", "Zombie", "Knight"]);
+        assert_eq!(names(&library.query("", None, TemplateSort::RecentlyModified)), ["Zombie", "Knight", "alex"]);
+    }
+
+    #[test]
+    fn tags_are_trimmed_and_deduplicated() {
+        assert_eq!(parse_tags("armor, red , knight"), ["armor", "red", "knight"]);
+        assert_eq!(parse_tags("Red, red,, RED ,"), ["Red"]);
+        assert!(parse_tags(" , ").is_empty());
+    }
+
+    #[test]
+    fn scanning_does_not_write_sidecars() {
+        let dir = empty_dir("scan");
+        ImageBuffer::<Rgba<u8>, Vec<u8>>::new(64, 64).save(dir.join("old.png")).unwrap();
+        ImageBuffer::<Rgba<u8>, Vec<u8>>::new(64, 64).save(dir.join("broken.png")).unwrap();
+        fs::write(dir.join("broken.json"), "{\"display_name\": ").unwrap();
+
+        let mut library = TemplateLibrary::new(dir.clone());
+        library.reload();
+        let mut names = names(library.entries());
+        names.sort();
+        assert_eq!(names, ["broken", "old"]);
+        assert!(!dir.join("old.json").exists());
+        assert_eq!(fs::read_to_string(dir.join("broken.json")).unwrap(), "{\"display_name\": ");
+
+        library.rename(&dir.join("old.png"), "Renamed").unwrap();
+        library.reload();
+        assert_eq!(library.get(&dir.join("old.png")).unwrap().metadata.display_name, "Renamed");
+    }
+}
@@ -1,25 +1,117 @@
-use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
 
-use gtk::glib;
-use gtk::prelude::{ButtonExt, WidgetExt};
+use gtk::{gdk, gio, glib};
+use gtk::glib::clone;
+use gtk::gio::Cancellable;
+use gtk::prelude::{ActionMapExt, ButtonExt, Cast, EditableExt, FileExt, FileMonitorExt, ListModelExtManual, ToggleButtonExt, WidgetExt};
 use gtk::subclass::prelude::ObjectSubclassIsExt;
+use libadwaita as adw;
+use libadwaita::prelude::{AdwDialogExt, AlertDialogExt};
 
+use crate::glium_area::skin_parser::{texture_layout, ModelType, TextureType};
+use crate::outfit_composer::OutfitComposer;
+use crate::settings::{RecentFile, Settings};
+use crate::template_archive::{count_duplicates, export_templates, import_templates, read_archive, read_skin_files, sync_folder, IncomingTemplate};
+use crate::template_library::{parse_tags, ConflictPolicy, TemplateEntry, TemplateMetadata, TemplateSort, TemplateSource};
+use crate::thumbnails::{ThumbnailCache, ThumbnailError, ThumbnailView};
 use crate::template_widget_item::TemplateWidgetItem;
-use crate::TEMPLATES_DIR;
+use crate::utils::{guess_model_type, image_from_texture, skin_drop_target, Confidence, DroppedSkins};
 use crate::window::Window;
 
 mod imp {
-    use gtk::{CompositeTemplate, glib, TemplateChild};
+    use std::cell::{Cell, OnceCell, RefCell};
+    use std::collections::{BTreeSet, HashMap};
+    use std::path::PathBuf;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::{mpsc, Arc};
+
+    use gtk::{CompositeTemplate, gio, glib, TemplateChild};
     use gtk::glib::subclass::InitializingObject;
+    use gtk::glib::WeakRef;
     use gtk::subclass::prelude::{BoxImpl, CompositeTemplate, CompositeTemplateInitializingExt, ObjectImpl, ObjectSubclass, WidgetImpl};
     use gtk::subclass::widget::WidgetClassExt;
 
-    #[derive(CompositeTemplate, Default)]
+    use crate::template_library::TemplateLibrary;
+    use crate::thumbnails::{ThumbnailCache, ThumbnailView};
+    use crate::{TEMPLATES_DIR, THUMBNAILS_DIR};
+    use crate::window::Window;
+
+    #[derive(CompositeTemplate)]
     #[template(file = "../resources/ui/templates-list.ui")]
     pub struct TemplateList {
         #[template_child]
         pub list: TemplateChild<gtk::FlowBox>,
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub tag_filter: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub tag_model: TemplateChild<gtk::StringList>,
+        #[template_child]
+        pub sort_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub thumbnail_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub recent_list: TemplateChild<gtk::FlowBox>,
+        #[template_child]
+        pub import_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub compose_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub select_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub export_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub sync_button: TemplateChild<gtk::MenuButton>,
+
+        pub library: RefCell<TemplateLibrary>,
+        pub window: WeakRef<Window>,
+        pub thumbnails: ThumbnailCache,
+        /// Thumbnails already rendered during this session, by skin file and view.
+        pub thumbnail_paths: RefCell<HashMap<(PathBuf, ThumbnailView), PathBuf>>,
+        /// Bumped on every rebuild so pending thumbnail renders for old items stop.
+        pub thumbnail_generation: Arc<AtomicU64>,
+        /// The same for the recent files list, which is rebuilt on its own.
+        pub recent_thumbnail_generation: Arc<AtomicU64>,
+        /// The thread rendering thumbnails, started with the first batch.
+        pub thumbnail_worker: OnceCell<mpsc::Sender<super::ThumbnailBatch>>,
+        /// Templates picked for export while the select button is active.
+        pub selection: RefCell<BTreeSet<PathBuf>>,
+        pub sync_monitor: RefCell<Option<gio::FileMonitor>>,
+        pub sync_scheduled: Cell<bool>,
+    }
+
+    impl Default for TemplateList {
+        fn default() -> Self {
+            TemplateList {
+                list: Default::default(),
+                search_entry: Default::default(),
+                tag_filter: Default::default(),
+                tag_model: Default::default(),
+                sort_selector: Default::default(),
+                thumbnail_selector: Default::default(),
+                recent_list: Default::default(),
+                import_button: Default::default(),
+                compose_button: Default::default(),
+                select_button: Default::default(),
+                export_button: Default::default(),
+                sync_button: Default::default(),
+                library: RefCell::new(TemplateLibrary::new(TEMPLATES_DIR.clone())),
+                window: Default::default(),
+                thumbnails: ThumbnailCache::new(THUMBNAILS_DIR.clone()),
+                thumbnail_paths: Default::default(),
+                thumbnail_generation: Default::default(),
+                recent_thumbnail_generation: Default::default(),
+                thumbnail_worker: Default::default(),
+                selection: Default::default(),
+                sync_monitor: Default::default(),
+                sync_scheduled: Default::default(),
+            }
+        }
     }
 
     #[glib::object_subclass]
@@ -59,54 +151,747 @@ impl TemplateList {
         glib::Object::new()
     }
 
-    pub fn load_list(&self, win: &Window) {
-        let png_files = TemplateList::find_png_files(TEMPLATES_DIR.as_path());
+    pub fn setup(&self, win: &Window) {
+        self.imp().window.set(Some(win));
+
+        self.imp().search_entry.connect_search_changed(clone!(#[weak(rename_to = list)] self, move |_| {
+            list.populate();
+        }));
+        self.imp().tag_filter.connect_selected_notify(clone!(#[weak(rename_to = list)] self, move |_| {
+            list.populate();
+        }));
+        self.imp().sort_selector.connect_selected_notify(clone!(#[weak(rename_to = list)] self, move |_| {
+            list.populate();
+        }));
+
+        let thumbnail_view = Settings::load().thumbnail_view;
+        self.imp().thumbnail_selector.set_selected(match thumbnail_view {
+            ThumbnailView::FullBody => 0,
+            ThumbnailView::Bust => 1,
+        });
+        self.imp().thumbnail_selector.connect_selected_notify(clone!(#[weak(rename_to = list)] self, move |_| {
+            let thumbnail_view = list.thumbnail_view();
+            Settings::update(|settings| settings.thumbnail_view = thumbnail_view);
+            list.populate();
+            list.populate_recent();
+        }));
+
+        self.imp().select_button.connect_toggled(clone!(#[weak(rename_to = list)] self, move |button| {
+            list.imp().selection.borrow_mut().clear();
+            list.imp().export_button.set_visible(button.is_active());
+            list.update_export_button();
+            list.populate();
+        }));
+        self.imp().export_button.connect_clicked(clone!(#[weak(rename_to = list)] self, move |_| {
+            list.choose_export_file();
+        }));
+        self.imp().import_button.connect_clicked(clone!(#[weak(rename_to = list)] self, move |_| {
+            list.choose_import_file();
+        }));
+        self.imp().compose_button.connect_clicked(clone!(#[weak(rename_to = list)] self, move |_| {
+            let win = list.window();
+            OutfitComposer::new(&win).present(Some(&win));
+        }));
+
+        let sync_actions = gio::SimpleActionGroup::new();
+        let choose_sync_folder = gio::SimpleAction::new("choose-sync-folder", None);
+        choose_sync_folder.connect_activate(clone!(#[weak(rename_to = list)] self, move |_, _| {
+            list.choose_sync_folder();
+        }));
+        sync_actions.add_action(&choose_sync_folder);
+        let stop_sync = gio::SimpleAction::new("stop-sync", None);
+        stop_sync.connect_activate(clone!(#[weak(rename_to = list)] self, move |_, _| {
+            list.stop_sync();
+            Settings::update(|settings| settings.sync_folder = None);
+        }));
+        sync_actions.add_action(&stop_sync);
+        self.insert_action_group("templates", Some(&sync_actions));
+
+        self.add_controller(skin_drop_target(clone!(#[weak(rename_to = list)] self, move |dropped| {
+            match dropped {
+                DroppedSkins::Files(paths) => list.import_paths(paths),
+                DroppedSkins::Image(texture) => list.import_texture(&texture),
+            }
+        })));
+
+        if let Some(folder) = Settings::load().sync_folder {
+            self.start_sync(folder);
+        }
+    }
+
+    /// Rescans the templates folder and rebuilds the templates and recent files lists.
+    pub fn load_list(&self) {
+        self.imp().library.borrow_mut().reload();
+        // Files may have been edited outside of the app since their thumbnails were looked up
+        self.imp().thumbnail_paths.borrow_mut().clear();
+        self.update_tag_filter();
+        self.populate();
+        self.populate_recent();
+    }
+
+    pub fn library(&self) -> std::cell::RefMut<'_, crate::template_library::TemplateLibrary> {
+        self.imp().library.borrow_mut()
+    }
+
+    fn window(&self) -> Window {
+        self.imp().window.upgrade().expect("TemplateList is not set up")
+    }
+
+    fn update_tag_filter(&self) {
+        let selected_tag = self.selected_tag();
+        let tags = self.imp().library.borrow().tags();
+
+        let tag_model = &self.imp().tag_model;
+        tag_model.splice(1, tag_model.n_items() - 1, &tags.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let selected = selected_tag
+            .and_then(|tag| tags.iter().position(|t| *t == tag))
+            .map(|index| index as u32 + 1)
+            .unwrap_or(0);
+        self.imp().tag_filter.set_selected(selected);
+    }
+
+    fn selected_tag(&self) -> Option<String> {
+        let selected = self.imp().tag_filter.selected();
+        if selected == 0 || selected == gtk::INVALID_LIST_POSITION {
+            return None;
+        }
+        self.imp().tag_model.string(selected).map(|tag| tag.to_string())
+    }
+
+    fn selected_sort(&self) -> TemplateSort {
+        match self.imp().sort_selector.selected() {
+            1 => TemplateSort::Newest,
+            2 => TemplateSort::RecentlyModified,
+            _ => TemplateSort::Name,
+        }
+    }
+
+    fn thumbnail_view(&self) -> ThumbnailView {
+        match self.imp().thumbnail_selector.selected() {
+            1 => ThumbnailView::Bust,
+            _ => ThumbnailView::FullBody,
+        }
+    }
+
+    fn populate(&self) {
+        let search = self.imp().search_entry.text();
+        let tag = self.selected_tag();
+        let entries = self.imp().library.borrow().query(search.as_str(), tag.as_deref(), self.selected_sort());
+
         self.imp().list.remove_all();
-        for file in png_files {
-            let title = Path::new(file.as_str())
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap();
-
-            let w = win.clone();
-            let list_item = TemplateWidgetItem::new(file.as_str(), title);
-            list_item.connect_clicked(move |_| {
-                match w.load_template(file.as_str()) {
-                    Ok(_) => println!("Template successfully loaded"),
-                    Err(_) => println!("Error loading template"),
-                }
-            });
+        let mut items = vec![];
+        for entry in entries {
+            let list_item = self.create_item(&entry);
             self.imp().list.append(&list_item);
+            items.push((list_item, entry.path.clone(), entry.metadata.model_type));
+        }
+        self.render_thumbnails(items, false);
+    }
+
+    fn populate_recent(&self) {
+        let recent_files = Settings::load().existing_recent_files();
+
+        self.imp().recent_list.remove_all();
+        let mut items = vec![];
+        for recent in recent_files {
+            let list_item = self.create_recent_item(&recent);
+            self.imp().recent_list.append(&list_item);
+            items.push((list_item, recent.path.clone(), recent.model_type));
         }
+        self.render_thumbnails(items, true);
     }
 
-    fn find_png_files<P: AsRef<Path>>(path: P) -> Vec<String> {
-        let mut png_files = Vec::new();
+    fn thumbnail_generation(&self, recent: bool) -> &Arc<AtomicU64> {
+        if recent {
+            &self.imp().recent_thumbnail_generation
+        } else {
+            &self.imp().thumbnail_generation
+        }
+    }
 
-        if let Ok(entries) = fs::read_dir(path) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if let Some(extension) = path.extension() {
-                        if extension == "png" {
-                            if let Some(path) = path.to_str() {
-                                png_files.push(path.to_string());
-                            }
+    /// Swaps the flat texture covers for 3D thumbnails of the templates or the `recent`
+    /// files. Thumbnails that are not cached yet are rendered on the worker thread and show
+    /// up as they get ready.
+    fn render_thumbnails(&self, items: Vec<(TemplateWidgetItem, PathBuf, ModelType)>, recent: bool) {
+        let view = self.thumbnail_view();
+        let generation = self.thumbnail_generation(recent).fetch_add(1, Ordering::Relaxed) + 1;
 
-                            // if let Some(file_name) = path.file_name() {
-                            //     if let Some(file_name_str) = file_name.to_str() {
-                            //         png_files.push(file_name_str.to_string());
-                            //     }
-                            // }
-                        }
+        let mut pending = vec![];
+        for (item, path, model_type) in items {
+            let known = self.imp().thumbnail_paths.borrow().get(&(path.clone(), view)).cloned();
+            match known {
+                Some(thumbnail) => item.set_cover(&thumbnail),
+                None => pending.push((item, path, model_type)),
+            }
+        }
+        if pending.is_empty() {
+            return;
+        }
+
+        let (sender, receiver) = async_channel::unbounded();
+        let batch = ThumbnailBatch {
+            jobs: pending.iter().map(|(_, path, model_type)| (path.clone(), *model_type)).collect(),
+            view,
+            generation: self.thumbnail_generation(recent).clone(),
+            started_at: generation,
+            results: sender,
+        };
+        if self.thumbnail_worker().send(batch).is_err() {
+            println!("Thumbnail worker stopped");
+            return;
+        }
+
+        glib::spawn_future_local(clone!(#[weak(rename_to = list)] self, async move {
+            while let Ok((index, result)) = receiver.recv().await {
+                if list.thumbnail_generation(recent).load(Ordering::Relaxed) != generation {
+                    return;
+                }
+                let (item, path, _) = &pending[index];
+                match result {
+                    Ok(thumbnail) => {
+                        item.set_cover(&thumbnail);
+                        list.imp().thumbnail_paths.borrow_mut().insert((path.clone(), view), thumbnail);
                     }
+                    Err(error) => println!("Error rendering thumbnail for {:?}: {:?}", path, error),
+                }
+            }
+        }));
+    }
+
+    fn thumbnail_worker(&self) -> &mpsc::Sender<ThumbnailBatch> {
+        self.imp().thumbnail_worker.get_or_init(|| {
+            let cache = self.imp().thumbnails.clone();
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                for batch in receiver {
+                    render_batch(&cache, batch);
+                }
+            });
+            sender
+        })
+    }
+
+    fn create_item(&self, entry: &TemplateEntry) -> TemplateWidgetItem {
+        let list_item = TemplateWidgetItem::new(&entry.path, &entry.metadata.display_name);
+        list_item.set_subtitle(&Self::describe(entry));
+        list_item.set_selection_mode(self.selection_mode());
+        list_item.set_selected(self.imp().selection.borrow().contains(&entry.path));
+
+        let win = self.window();
+        let path = entry.path.clone();
+        list_item.connect_clicked(clone!(#[weak] win, #[weak(rename_to = list)] self, #[strong] path, move |item| {
+            if list.selection_mode() {
+                list.toggle_selected(item, &path);
+                return;
+            }
+            match win.load_template(&path) {
+                Ok(_) => println!("Template successfully loaded"),
+                Err(_) => println!("Error loading template"),
+            }
+        }));
+
+        list_item.insert_action_group("template", Some(&self.item_actions(&path)));
+        list_item.set_context_menu(&Self::item_menu());
+        list_item
+    }
+
+    fn selection_mode(&self) -> bool {
+        self.imp().select_button.is_active()
+    }
+
+    fn toggle_selected(&self, item: &TemplateWidgetItem, path: &Path) {
+        let selected = {
+            let mut selection = self.imp().selection.borrow_mut();
+            if !selection.remove(path) {
+                selection.insert(path.to_path_buf());
+            }
+            selection.contains(path)
+        };
+        item.set_selected(selected);
+        self.update_export_button();
+    }
+
+    fn update_export_button(&self) {
+        let count = self.imp().selection.borrow().len();
+        self.imp().export_button.set_sensitive(count > 0);
+        self.imp().export_button.set_label(&match count {
+            0 => "Export…".to_string(),
+            count => format!("Export {}…", count),
+        });
+    }
+
+    fn choose_export_file(&self) {
+        let file_dialog = gtk::FileDialog::builder()
+            .title("Export templates")
+            .initial_name("templates.zip")
+            .build();
+        file_dialog.save(Some(&self.window()), Cancellable::NONE, clone!(#[weak(rename_to = list)] self, move |file| {
+            let Some(path) = file.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            let entries: Vec<TemplateEntry> = {
+                let library = list.imp().library.borrow();
+                list.imp().selection
+                    .borrow()
+                    .iter()
+                    .filter_map(|selected| library.get(selected).cloned())
+                    .collect()
+            };
+            match export_templates(&entries, &path) {
+                Ok(_) => println!("Exported {} templates to {:?}", entries.len(), path),
+                Err(error) => println!("Error exporting templates: {:?}", error),
+            }
+            list.imp().select_button.set_active(false);
+        }));
+    }
+
+    fn choose_import_file(&self) {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("Template archives and skins"));
+        filter.add_pattern("*.zip");
+        filter.add_pattern("*.png");
+        let file_dialog = gtk::FileDialog::builder()
+            .title("Import templates")
+            .default_filter(&filter)
+            .build();
+        file_dialog.open_multiple(Some(&self.window()), Cancellable::NONE, clone!(#[weak(rename_to = list)] self, move |files| {
+            let Ok(files) = files else {
+                return;
+            };
+            let paths: Vec<PathBuf> = files
+                .iter::<gio::File>()
+                .flatten()
+                .filter_map(|file| file.path())
+                .collect();
+            list.import_paths(paths);
+        }));
+    }
+
+    /// Imports template archives and skin files.
+    fn import_paths(&self, paths: Vec<PathBuf>) {
+        let mut templates = vec![];
+        let mut skin_paths = vec![];
+        for path in paths {
+            if path.extension().is_some_and(|extension| extension == "zip") {
+                match read_archive(&path) {
+                    Ok(archive) => templates.extend(archive),
+                    Err(error) => println!("Error reading template archive: {:?}", error),
                 }
+            } else {
+                skin_paths.push(path);
             }
+        }
+        self.import_skins(templates, read_skin_files(&skin_paths));
+    }
+
+    /// Imports an image dragged from another app.
+    fn import_texture(&self, texture: &gdk::Texture) {
+        let image = match image_from_texture(texture) {
+            Ok(image) => image,
+            Err(error) => {
+                println!("Error reading image: {:?}", error);
+                return;
+            }
+        };
+        if !matches!(texture_layout(image.width(), image.height()), Ok((TextureType::Normal, _))) {
+            println!("Skipping dropped image: not a skin");
+            return;
+        }
+        let image = image.to_rgba8();
+        let guess = guess_model_type(&image);
+        let metadata = TemplateMetadata::new("Dropped skin", guess.model_type, TemplateSource::File);
+        self.import_skins(vec![], vec![(IncomingTemplate { image, metadata }, guess.confidence)]);
+    }
+
+    fn import_skins(&self, mut templates: Vec<IncomingTemplate>, skins: Vec<(IncomingTemplate, Confidence)>) {
+        // Skins whose arms tell their model type for sure don't need a question
+        let mut uncertain = vec![];
+        for (template, confidence) in skins {
+            if confidence == Confidence::High {
+                templates.push(template);
+            } else {
+                uncertain.push(template);
+            }
+        }
+        if uncertain.is_empty() {
+            self.resolve_conflicts(templates);
         } else {
-            println!("No such dir");
+            self.ask_model_type(templates, uncertain);
         }
+    }
+
+    /// Asks which model the skins whose model type couldn't be detected were made for.
+    fn ask_model_type(&self, templates: Vec<IncomingTemplate>, uncertain: Vec<IncomingTemplate>) {
+        let dialog = adw::AlertDialog::new(
+            Some("Choose the model"),
+            Some(&format!(
+                "The model of {} of the skins could not be detected for sure.",
+                uncertain.len(),
+            )),
+        );
+        dialog.add_responses(&[
+            ("cancel", "_Cancel"),
+            ("slim", "_Slim"),
+            ("classic", "C_lassic"),
+            ("detected", "_As detected"),
+        ]);
+        dialog.set_response_appearance("detected", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("detected"));
+        dialog.set_close_response("cancel");
 
-        png_files
+        let pending = std::cell::RefCell::new(Some((templates, uncertain)));
+        dialog.connect_response(None, clone!(#[weak(rename_to = list)] self, move |_, response| {
+            let model_type = match response {
+                "slim" => Some(ModelType::Slim),
+                "classic" => Some(ModelType::Classic),
+                "detected" => None,
+                _ => return,
+            };
+            let Some((mut templates, uncertain)) = pending.take() else {
+                return;
+            };
+            for mut template in uncertain {
+                if let Some(model_type) = model_type {
+                    template.metadata.model_type = model_type;
+                }
+                templates.push(template);
+            }
+            list.resolve_conflicts(templates);
+        }));
+        dialog.present(Some(&self.window()));
     }
-}
\ No newline at end of file
+
+    /// Imports right away when nothing clashes, otherwise asks what to do with
+    /// templates whose pixels already are in the wardrobe.
+    fn resolve_conflicts(&self, templates: Vec<IncomingTemplate>) {
+        let duplicates = count_duplicates(&templates, &self.imp().library.borrow().pixel_hashes());
+        if duplicates == 0 {
+            self.import(templates, ConflictPolicy::Skip);
+            return;
+        }
+
+        let dialog = adw::AlertDialog::new(
+            Some("Duplicate templates"),
+            Some(&format!(
+                "{} of {} imported templates already are in the wardrobe.",
+                duplicates,
+                templates.len(),
+            )),
+        );
+        dialog.add_responses(&[
+            ("cancel", "_Cancel"),
+            ("skip", "_Skip"),
+            ("keep", "_Keep both"),
+            ("replace", "_Replace"),
+        ]);
+        dialog.set_response_appearance("skip", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("skip"));
+        dialog.set_close_response("cancel");
+
+        let templates = std::cell::RefCell::new(Some(templates));
+        dialog.connect_response(None, clone!(#[weak(rename_to = list)] self, move |_, response| {
+            let policy = match response {
+                "skip" => ConflictPolicy::Skip,
+                "keep" => ConflictPolicy::KeepBoth,
+                "replace" => ConflictPolicy::Replace,
+                _ => return,
+            };
+            if let Some(templates) = templates.take() {
+                list.import(templates, policy);
+            }
+        }));
+        dialog.present(Some(&self.window()));
+    }
+
+    fn import(&self, templates: Vec<IncomingTemplate>, policy: ConflictPolicy) {
+        let result = import_templates(&mut self.library(), templates, policy);
+        match result {
+            Ok(summary) => println!(
+                "Imported templates: {} added, {} replaced, {} skipped",
+                summary.added, summary.replaced, summary.skipped,
+            ),
+            Err(error) => println!("Error importing templates: {:?}", error),
+        }
+        self.load_list();
+    }
+
+    fn choose_sync_folder(&self) {
+        let file_dialog = gtk::FileDialog::builder().title("Folder to sync templates from").build();
+        file_dialog.select_folder(Some(&self.window()), Cancellable::NONE, clone!(#[weak(rename_to = list)] self, move |file| {
+            let Some(folder) = file.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            Settings::update(|settings| settings.sync_folder = Some(folder.clone()));
+            list.start_sync(folder);
+        }));
+    }
+
+    /// Syncs `folder` now and again whenever files in it change.
+    fn start_sync(&self, folder: PathBuf) {
+        self.stop_sync();
+        self.imp().sync_button.set_tooltip_text(Some(&format!("Syncing {}", folder.display())));
+
+        let monitor = gio::File::for_path(&folder).monitor_directory(gio::FileMonitorFlags::NONE, Cancellable::NONE);
+        match monitor {
+            Ok(monitor) => {
+                monitor.connect_changed(clone!(#[weak(rename_to = list)] self, move |_, _, _, _| {
+                    list.schedule_sync();
+                }));
+                self.imp().sync_monitor.replace(Some(monitor));
+            }
+            Err(error) => println!("Cannot watch {:?}: {}", folder, error),
+        }
+        self.sync_now();
+    }
+
+    fn stop_sync(&self) {
+        if let Some(monitor) = self.imp().sync_monitor.take() {
+            monitor.cancel();
+        }
+        self.imp().sync_button.set_tooltip_text(Some("Sync templates with a folder"));
+    }
+
+    /// Copying a pack fires many events, sync once they calm down.
+    fn schedule_sync(&self) {
+        if self.imp().sync_scheduled.replace(true) {
+            return;
+        }
+        glib::timeout_add_local_once(Duration::from_millis(500), clone!(#[weak(rename_to = list)] self, move || {
+            list.imp().sync_scheduled.set(false);
+            list.sync_now();
+        }));
+    }
+
+    fn sync_now(&self) {
+        let Some(folder) = Settings::load().sync_folder else {
+            return;
+        };
+        if folder == self.imp().library.borrow().dir() {
+            println!("The templates folder cannot be synced with itself");
+            return;
+        }
+
+        let result = {
+            let mut library = self.library();
+            // Duplicates are found against what is on disk right now
+            library.reload();
+            sync_folder(&mut library, &folder)
+        };
+        match result {
+            Ok(summary) if summary.added + summary.replaced + summary.removed > 0 => {
+                println!(
+                    "Synced {:?}: {} added, {} updated, {} removed",
+                    folder, summary.added, summary.replaced, summary.removed,
+                );
+                self.load_list();
+            }
+            Ok(_) => {}
+            Err(error) => println!("Error syncing {:?}: {:?}", folder, error),
+        }
+    }
+
+    fn create_recent_item(&self, recent: &RecentFile) -> TemplateWidgetItem {
+        let title = recent.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let list_item = TemplateWidgetItem::new(&recent.path, &title);
+        list_item.set_subtitle(Self::model_type_name(recent.model_type));
+        list_item.set_tooltip_text(Some(&recent.path.display().to_string()));
+
+        let win = self.window();
+        let path = recent.path.clone();
+        let model_type = recent.model_type;
+        list_item.connect_clicked(clone!(#[weak] win, #[strong] path, move |_| {
+            let Some(path) = path.to_str() else {
+                println!("Can't open {:?}: not a UTF-8 path", path);
+                return;
+            };
+            if let Err(error) = win.open_skin_file(path, model_type) {
+                println!("Error opening recent file: {:?}", error);
+            }
+        }));
+
+        let group = gio::SimpleActionGroup::new();
+        let forget = gio::SimpleAction::new("forget", None);
+        forget.connect_activate(clone!(#[weak(rename_to = list)] self, #[to_owned] path, move |_, _| {
+            Settings::update(|settings| settings.recent_files.retain(|recent| recent.path != path));
+            list.populate_recent();
+        }));
+        group.add_action(&forget);
+        list_item.insert_action_group("recent", Some(&group));
+
+        let menu = gio::Menu::new();
+        menu.append(Some("Remove from recent"), Some("recent.forget"));
+        list_item.set_context_menu(&menu.upcast::<gio::MenuModel>());
+        list_item
+    }
+
+    fn model_type_name(model_type: ModelType) -> &'static str {
+        match model_type {
+            ModelType::Slim => "Slim",
+            ModelType::Classic => "Classic",
+        }
+    }
+
+    fn describe(entry: &TemplateEntry) -> String {
+        let model_type = Self::model_type_name(entry.metadata.model_type);
+        if entry.metadata.tags.is_empty() {
+            model_type.to_string()
+        } else {
+            format!("{} · {}", model_type, entry.metadata.tags.join(", "))
+        }
+    }
+
+    fn item_menu() -> gio::MenuModel {
+        let menu = gio::Menu::new();
+        menu.append(Some("Rename…"), Some("template.rename"));
+        menu.append(Some("Edit tags…"), Some("template.tags"));
+        menu.append(Some("Duplicate"), Some("template.duplicate"));
+        menu.append(Some("Delete"), Some("template.delete"));
+        menu.upcast()
+    }
+
+    fn item_actions(&self, path: &Path) -> gio::SimpleActionGroup {
+        let group = gio::SimpleActionGroup::new();
+
+        let rename = gio::SimpleAction::new("rename", None);
+        rename.connect_activate(clone!(#[weak(rename_to = list)] self, #[to_owned] path, move |_, _| {
+            list.prompt_rename(&path);
+        }));
+        group.add_action(&rename);
+
+        let tags = gio::SimpleAction::new("tags", None);
+        tags.connect_activate(clone!(#[weak(rename_to = list)] self, #[to_owned] path, move |_, _| {
+            list.prompt_tags(&path);
+        }));
+        group.add_action(&tags);
+
+        let duplicate = gio::SimpleAction::new("duplicate", None);
+        duplicate.connect_activate(clone!(#[weak(rename_to = list)] self, #[to_owned] path, move |_, _| {
+            if let Err(error) = list.library().duplicate(&path) {
+                println!("Error duplicating template: {:?}", error);
+            }
+            list.populate();
+        }));
+        group.add_action(&duplicate);
+
+        let delete = gio::SimpleAction::new("delete", None);
+        delete.connect_activate(clone!(#[weak(rename_to = list)] self, #[to_owned] path, move |_, _| {
+            list.confirm_delete(&path);
+        }));
+        group.add_action(&delete);
+
+        group
+    }
+
+    fn prompt_rename(&self, path: &Path) {
+        let Some(current) = self.imp().library.borrow().get(path).map(|entry| entry.metadata.display_name.clone()) else {
+            return;
+        };
+        let path = path.to_path_buf();
+        let list = self.clone();
+        prompt_text(&self.window(), "Rename template", &current, "Rename", move |name| {
+            if name.trim().is_empty() {
+                return;
+            }
+            if let Err(error) = list.library().rename(&path, &name) {
+                println!("Error renaming template: {:?}", error);
+            }
+            list.populate();
+        });
+    }
+
+    fn prompt_tags(&self, path: &Path) {
+        let Some(current) = self.imp().library.borrow().get(path).map(|entry| entry.metadata.tags.join(", ")) else {
+            return;
+        };
+        let path = path.to_path_buf();
+        let list = self.clone();
+        prompt_text(&self.window(), "Edit tags (comma separated)", &current, "Save", move |tags| {
+            if let Err(error) = list.library().set_tags(&path, parse_tags(&tags)) {
+                println!("Error saving tags: {:?}", error);
+            }
+            list.update_tag_filter();
+            list.populate();
+        });
+    }
+
+    fn confirm_delete(&self, path: &Path) {
+        let Some(name) = self.imp().library.borrow().get(path).map(|entry| entry.metadata.display_name.clone()) else {
+            return;
+        };
+        let dialog = adw::AlertDialog::new(
+            Some("Delete template?"),
+            Some(&format!("“{}” will be removed from the wardrobe.", name)),
+        );
+        dialog.add_responses(&[("cancel", "_Cancel"), ("delete", "_Delete")]);
+        dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+        dialog.set_close_response("cancel");
+
+        let path: PathBuf = path.to_path_buf();
+        dialog.connect_response(None, clone!(#[weak(rename_to = list)] self, move |_, response| {
+            if response != "delete" {
+                return;
+            }
+            if let Err(error) = list.library().delete(&path) {
+                println!("Error deleting template: {:?}", error);
+            }
+            list.update_tag_filter();
+            list.populate();
+        }));
+        dialog.present(Some(&self.window()));
+    }
+}
+
+/// Thumbnails to render for the items of one list rebuild, sent back by their index.
+pub(crate) struct ThumbnailBatch {
+    jobs: Vec<(PathBuf, ModelType)>,
+    view: ThumbnailView,
+    generation: Arc<AtomicU64>,
+    started_at: u64,
+    results: async_channel::Sender<(usize, Result<PathBuf, ThumbnailError>)>,
+}
+
+/// Stops as soon as the list is rebuilt, so typing in the search doesn't queue up
+/// renders and file reads for items that are gone.
+fn render_batch(cache: &ThumbnailCache, batch: ThumbnailBatch) {
+    for (index, (path, model_type)) in batch.jobs.into_iter().enumerate() {
+        if batch.generation.load(Ordering::Relaxed) != batch.started_at {
+            return;
+        }
+        let result = cache.get_or_render(&path, model_type, batch.view);
+        if batch.results.send_blocking((index, result)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Asks for a single line of text and passes it to `on_accept` when confirmed.
+pub fn prompt_text(
+    win: &Window,
+    heading: &str,
+    initial: &str,
+    accept_label: &str,
+    on_accept: impl Fn(String) + 'static,
+) {
+    let entry = gtk::Entry::builder()
+        .text(initial)
+        .activates_default(true)
+        .build();
+
+    let dialog = adw::AlertDialog::new(Some(heading), None);
+    dialog.add_responses(&[("cancel", "_Cancel"), ("accept", accept_label)]);
+    dialog.set_response_appearance("accept", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("accept"));
+    dialog.set_close_response("cancel");
+    dialog.set_extra_child(Some(&entry));
+
+    dialog.connect_response(None, move |_, response| {
+        if response == "accept" {
+            on_accept(entry.text().to_string());
+        }
+    });
+    dialog.present(Some(win));
+}
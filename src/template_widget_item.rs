@@ -1,5 +1,7 @@
-use gtk::glib;
-use gtk::prelude::MediaFileExt;
+use std::path::Path;
+
+use gtk::{gio, glib};
+use gtk::prelude::{CheckButtonExt, GestureExt, GestureSingleExt, PopoverExt, WidgetExt};
 use gtk::subclass::prelude::ObjectSubclassIsExt;
 
 mod imp {
@@ -16,28 +18,32 @@ mod imp {
         #[template_child]
         pub title: TemplateChild<gtk::Label>,
         #[template_child]
+        pub subtitle: TemplateChild<gtk::Label>,
+        #[template_child]
         pub cover: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub select_check: TemplateChild<gtk::CheckButton>,
     }
-    
+
     #[glib::object_subclass]
     impl ObjectSubclass for TemplateWidgetItem {
         const NAME: &'static str = "TemplateWidgetItem";
         type Type = super::TemplateWidgetItem;
         type ParentType = gtk::Button;
-    
+
         fn class_init(klass: &mut Self::Class) {
             Self::bind_template(klass);
         }
-    
+
         fn instance_init(obj: &InitializingObject<Self>) {
             obj.init_template();
         }
     }
-    
+
     impl ObjectImpl for TemplateWidgetItem {}
-    
+
     impl WidgetImpl for TemplateWidgetItem {}
-    
+
     impl ButtonImpl for TemplateWidgetItem {}
 
 }
@@ -49,7 +55,7 @@ glib::wrapper! {
 }
 
 impl TemplateWidgetItem {
-    pub fn new(cover: &str, title: &str) -> Self {
+    pub fn new(cover: &Path, title: &str) -> Self {
         let template_widget_item = glib::Object::builder::<TemplateWidgetItem>().build();
         template_widget_item.set_cover(cover);
         template_widget_item.set_title(title);
@@ -58,9 +64,41 @@ impl TemplateWidgetItem {
 
     pub fn set_title(&self, title: &str) {
         self.imp().title.set_label(title);
+        self.set_tooltip_text(Some(title));
+    }
+
+    pub fn set_subtitle(&self, subtitle: &str) {
+        self.imp().subtitle.set_label(subtitle);
+        self.imp().subtitle.set_visible(!subtitle.is_empty());
     }
 
-    pub fn set_cover(&self, cover: &str) {
+    pub fn set_cover(&self, cover: &Path) {
         self.imp().cover.set_from_file(Some(cover));
     }
-}
\ No newline at end of file
+
+    pub fn set_selection_mode(&self, selection_mode: bool) {
+        self.imp().select_check.set_visible(selection_mode);
+    }
+
+    pub fn set_selected(&self, selected: bool) {
+        self.imp().select_check.set_active(selected);
+    }
+
+    /// Shows `menu` on right click. Actions are resolved against the item's action groups.
+    pub fn set_context_menu(&self, menu: &gio::MenuModel) {
+        let popover = gtk::PopoverMenu::from_model(Some(menu));
+        popover.set_parent(self);
+        popover.set_has_arrow(false);
+
+        let right_click = gtk::GestureClick::new();
+        right_click.set_button(gtk::gdk::BUTTON_SECONDARY);
+        right_click.connect_pressed(glib::clone!(#[weak] popover, move |gesture, _, x, y| {
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+            popover.set_pointing_to(Some(&gtk::gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+            popover.popup();
+        }));
+        self.add_controller(right_click);
+
+        self.connect_destroy(move |_| popover.unparent());
+    }
+}
@@ -0,0 +1,306 @@
+use gtk::gio::Cancellable;
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ButtonExt, CheckButtonExt, FileExt, TextBufferExt, TextViewExt, WidgetExt};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+use image::imageops::{self, FilterType};
+use image::Rgba;
+use libadwaita as adw;
+use libadwaita::prelude::AdwDialogExt;
+
+use crate::editor_host::EditorHost;
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::cube_side::CubeSide;
+use crate::glium_area::renderer::ModelCell;
+use crate::image_stamp::{face_region, paint_face, Stamp, StampTarget};
+use crate::pixel_font::{text_image, BuiltinFont, HorizontalAlign, PixelFont, TextOptions, VerticalAlign};
+use crate::utils;
+use crate::window::Window;
+
+const PREVIEW_SIZE: u32 = 256;
+/// Faces in the order of the face selector.
+const SIDES: [CubeSide; 6] = [CubeSide::Front, CubeSide::Left, CubeSide::Back, CubeSide::Right, CubeSide::Top, CubeSide::Bottom];
+/// Position of the loaded BDF font in the font selector.
+const BDF_FONT: u32 = 2;
+
+fn pixel(rgba: gtk::gdk::RGBA) -> Rgba<u8> {
+    Rgba(utils::rgba_to_f32(rgba).map(|channel| (channel * 255.0).round() as u8))
+}
+
+mod imp {
+    use std::cell::RefCell;
+
+    use gtk::{glib, TemplateChild};
+    use gtk::CompositeTemplate;
+    use gtk::glib::WeakRef;
+    use gtk::subclass::prelude::{CompositeTemplate, CompositeTemplateInitializingExt, ObjectImpl, ObjectSubclass, WidgetImpl};
+    use gtk::subclass::widget::WidgetClassExt;
+    use image::{Rgba, RgbaImage};
+    use libadwaita as adw;
+    use libadwaita::subclass::dialog::AdwDialogImpl;
+
+    use crate::glium_area::skin_parser::ModelType;
+    use crate::pixel_font::PixelFont;
+    use crate::window::Window;
+
+    #[derive(CompositeTemplate)]
+    #[template(file = "../resources/ui/text-dialog.ui")]
+    pub struct TextDialog {
+        #[template_child]
+        pub preview: TemplateChild<gtk::Picture>,
+        #[template_child]
+        pub text_view: TemplateChild<gtk::TextView>,
+        #[template_child]
+        pub font_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub load_font_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub part_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub side_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub align_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub vertical_align_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub scale_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub outline_button: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub outline_color_button: TemplateChild<gtk::ColorDialogButton>,
+        #[template_child]
+        pub apply_button: TemplateChild<gtk::Button>,
+
+        /// The skin as it was when the dialog was opened.
+        pub skin: RefCell<RgbaImage>,
+        pub model_type: RefCell<ModelType>,
+        /// The active color, used for the letters.
+        pub color: RefCell<Rgba<u8>>,
+        pub bdf_font: RefCell<Option<PixelFont>>,
+        pub window: WeakRef<Window>,
+    }
+
+    impl Default for TextDialog {
+        fn default() -> Self {
+            TextDialog {
+                preview: Default::default(),
+                text_view: Default::default(),
+                font_selector: Default::default(),
+                load_font_button: Default::default(),
+                part_selector: Default::default(),
+                side_selector: Default::default(),
+                align_selector: Default::default(),
+                vertical_align_selector: Default::default(),
+                scale_spin: Default::default(),
+                outline_button: Default::default(),
+                outline_color_button: Default::default(),
+                apply_button: Default::default(),
+                skin: RefCell::new(RgbaImage::new(64, 64)),
+                model_type: RefCell::new(ModelType::Classic),
+                color: RefCell::new(Rgba([0, 0, 0, 255])),
+                bdf_font: RefCell::new(None),
+                window: Default::default(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TextDialog {
+        const NAME: &'static str = "TextDialog";
+        type Type = super::TextDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+    impl ObjectImpl for TextDialog {}
+    impl WidgetImpl for TextDialog {}
+    impl AdwDialogImpl for TextDialog {}
+}
+
+glib::wrapper! {
+    pub struct TextDialog(ObjectSubclass<imp::TextDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::ShortcutManager;
+}
+
+impl TextDialog {
+    /// Writes on the face that was clicked, in the active color.
+    pub fn new(win: &Window, body_part: BodyPart, side: CubeSide) -> Self {
+        let dialog: TextDialog = glib::Object::new();
+        let imp = dialog.imp();
+        imp.window.set(Some(win));
+        imp.skin.replace(win.export_texture());
+        imp.model_type.replace(win.editor().model_type());
+        imp.color.replace(pixel(win.active_color()));
+
+        let labels: Vec<&str> = BodyPart::PLAYER.iter().map(|body_part| body_part.label()).collect();
+        imp.part_selector.set_model(Some(&gtk::StringList::new(&labels)));
+        let part = BodyPart::PLAYER.iter().position(|part| *part == body_part).unwrap_or(0);
+        imp.part_selector.set_selected(part as u32);
+        imp.side_selector.set_selected(SIDES.iter().position(|face| *face == side).unwrap_or(0) as u32);
+        imp.align_selector.set_selected(1);
+        imp.vertical_align_selector.set_selected(1);
+
+        let color_dialog = gtk::ColorDialog::builder().with_alpha(false).build();
+        imp.outline_color_button.set_dialog(&color_dialog);
+        imp.outline_color_button.set_rgba(&gtk::gdk::RGBA::BLACK);
+
+        dialog.connect_signals();
+        dialog.update_preview();
+        dialog
+    }
+
+    fn window(&self) -> Window {
+        self.imp().window.upgrade().expect("TextDialog has no window")
+    }
+
+    fn connect_signals(&self) {
+        let imp = self.imp();
+        imp.text_view.buffer().connect_changed(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_preview();
+        }));
+        imp.font_selector.connect_selected_notify(clone!(#[weak(rename_to = dialog)] self, move |selector| {
+            if selector.selected() == BDF_FONT && dialog.imp().bdf_font.borrow().is_none() {
+                dialog.load_font();
+                return;
+            }
+            dialog.update_preview();
+        }));
+        for selector in [&imp.part_selector, &imp.side_selector, &imp.align_selector, &imp.vertical_align_selector] {
+            selector.connect_selected_notify(clone!(#[weak(rename_to = dialog)] self, move |_| {
+                dialog.update_preview();
+            }));
+        }
+        imp.scale_spin.connect_value_changed(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_preview();
+        }));
+        imp.outline_button.connect_toggled(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_preview();
+        }));
+        imp.outline_color_button.connect_rgba_notify(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_preview();
+        }));
+        imp.load_font_button.connect_clicked(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.load_font();
+        }));
+        imp.apply_button.connect_clicked(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.apply();
+        }));
+    }
+
+    /// Asks for a BDF font and selects it, going back to the 3x5 font if there is none.
+    fn load_font(&self) {
+        let file_dialog = gtk::FileDialog::builder().title("Open a BDF font").build();
+        let window = self.window();
+        file_dialog.open(Some(&window), Cancellable::NONE, clone!(#[weak(rename_to = dialog)] self, move |file| {
+            let imp = dialog.imp();
+            if let Some(path) = file.ok().and_then(|file| file.path()) {
+                match PixelFont::load_bdf(&path) {
+                    Ok(font) => {
+                        imp.bdf_font.replace(Some(font));
+                    }
+                    Err(error) => println!("Error loading font: {:?}", error),
+                }
+            }
+            if imp.bdf_font.borrow().is_some() {
+                imp.font_selector.set_selected(BDF_FONT);
+            } else if imp.font_selector.selected() == BDF_FONT {
+                imp.font_selector.set_selected(0);
+            }
+            dialog.update_preview();
+        }));
+    }
+
+    fn text(&self) -> String {
+        let buffer = self.imp().text_view.buffer();
+        buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).to_string()
+    }
+
+    fn target(&self) -> StampTarget {
+        let imp = self.imp();
+        StampTarget {
+            body_part: BodyPart::PLAYER[imp.part_selector.selected() as usize % BodyPart::PLAYER.len()],
+            side: SIDES[imp.side_selector.selected() as usize % SIDES.len()],
+            region: None,
+        }
+    }
+
+    fn options(&self) -> TextOptions {
+        let imp = self.imp();
+        let outline = imp.outline_button.is_active().then(|| pixel(imp.outline_color_button.rgba()));
+        TextOptions {
+            align: match imp.align_selector.selected() {
+                0 => HorizontalAlign::Left,
+                2 => HorizontalAlign::Right,
+                _ => HorizontalAlign::Center,
+            },
+            vertical_align: match imp.vertical_align_selector.selected() {
+                0 => VerticalAlign::Top,
+                2 => VerticalAlign::Bottom,
+                _ => VerticalAlign::Middle,
+            },
+            scale: imp.scale_spin.value() as u32,
+            outline,
+        }
+    }
+
+    /// The skin with the text on the selected face.
+    fn stamp(&self) -> Option<Stamp> {
+        let imp = self.imp();
+        let skin = imp.skin.borrow();
+        let model_type = imp.model_type.borrow();
+        let target = self.target();
+        let result = face_region(&skin, &model_type, target.body_part, target.side).and_then(|(_, [_, _, width, height])| {
+            let color: Rgba<u8> = *imp.color.borrow();
+            let pixels = match (imp.font_selector.selected(), imp.bdf_font.borrow().as_ref()) {
+                (BDF_FONT, Some(font)) => text_image(font, &self.text(), color, &self.options(), width, height),
+                (1, _) => text_image(&PixelFont::builtin(BuiltinFont::Medium), &self.text(), color, &self.options(), width, height),
+                _ => text_image(&PixelFont::builtin(BuiltinFont::Small), &self.text(), color, &self.options(), width, height),
+            };
+            paint_face(&skin, &model_type, &target, &pixels)
+        });
+        match result {
+            Ok(stamp) => Some(stamp),
+            Err(error) => {
+                println!("Error drawing text: {:?}", error);
+                None
+            }
+        }
+    }
+
+    /// Shows the face with the text on it, each cell a block of pixels.
+    fn update_preview(&self) {
+        let Some(stamp) = self.stamp() else {
+            return;
+        };
+        let [x, y, width, height] = stamp.face;
+        let face = imageops::crop_imm(&stamp.image, x, y, width, height).to_image();
+        let scale = (PREVIEW_SIZE / width.max(height)).max(1);
+        let preview = imageops::resize(&face, width * scale, height * scale, FilterType::Nearest);
+        self.imp().preview.set_paintable(Some(&utils::texture_from_image(&preview)));
+        self.imp().apply_button.set_sensitive(!stamp.cells.is_empty());
+    }
+
+    fn apply(&self) {
+        let Some(stamp) = self.stamp() else {
+            return;
+        };
+        let body_part = self.target().body_part;
+        let cells = stamp.cells
+            .iter()
+            .map(|(cell_index, color)| ModelCell {
+                body_part,
+                cell_index: *cell_index,
+                color: color.0.map(|channel| f32::from(channel) / 255.0),
+            })
+            .collect();
+        self.window().paint_cells(cells);
+        self.close();
+    }
+}
@@ -0,0 +1,165 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use image::{imageops, ImageError, RgbaImage};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::glium_area::skin_parser::{ModelType, TextureLoadError};
+use crate::glium_area::software_renderer::{render_skin, RenderOptions};
+use crate::utils;
+
+/// Bump when the look of thumbnails changes so old cache files are not reused.
+const THUMBNAIL_VERSION: u32 = 1;
+const THUMBNAIL_SIZE: u32 = 128;
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailView {
+    #[default]
+    FullBody,
+    Bust,
+}
+
+#[derive(Debug)]
+pub enum ThumbnailError {
+    Io(io::Error),
+    Image(ImageError),
+    Texture(TextureLoadError),
+}
+
+impl From<io::Error> for ThumbnailError {
+    fn from(error: io::Error) -> Self {
+        ThumbnailError::Io(error)
+    }
+}
+
+impl From<ImageError> for ThumbnailError {
+    fn from(error: ImageError) -> Self {
+        ThumbnailError::Image(error)
+    }
+}
+
+impl From<TextureLoadError> for ThumbnailError {
+    fn from(error: TextureLoadError) -> Self {
+        ThumbnailError::Texture(error)
+    }
+}
+
+/// Rendered previews of skin files, stored as PNGs named after the skin's content,
+/// so edited files get a new thumbnail and unchanged ones are rendered only once.
+#[derive(Clone)]
+pub struct ThumbnailCache {
+    dir: PathBuf,
+}
+
+impl ThumbnailCache {
+    pub fn new(dir: PathBuf) -> Self {
+        ThumbnailCache { dir }
+    }
+
+    /// Path of the thumbnail if it was already rendered.
+    pub fn cached(&self, skin: &Path, model_type: ModelType, view: ThumbnailView) -> Option<PathBuf> {
+        let path = self.thumbnail_path(skin, model_type, view).ok()?;
+        path.exists().then_some(path)
+    }
+
+    /// Returns the cached thumbnail, rendering it first when needed.
+    pub fn get_or_render(&self, skin: &Path, model_type: ModelType, view: ThumbnailView) -> Result<PathBuf, ThumbnailError> {
+        let path = self.thumbnail_path(skin, model_type, view)?;
+        if path.exists() {
+            return Ok(path);
+        }
+
+        let image = image::open(skin)?;
+        let thumbnail = render_thumbnail(&image, model_type, view)?;
+        fs::create_dir_all(&self.dir)?;
+        thumbnail.save(&path)?;
+        Ok(path)
+    }
+
+    fn thumbnail_path(&self, skin: &Path, model_type: ModelType, view: ThumbnailView) -> Result<PathBuf, io::Error> {
+        let mut key = fs::read(skin)?;
+        key.extend_from_slice(format!("{:?}{:?}{}", model_type, view, THUMBNAIL_VERSION).as_bytes());
+        Ok(self.dir.join(format!("{:016x}.png", utils::fnv1a(&key))))
+    }
+}
+
+/// Front and back of the skin next to each other.
+pub fn render_thumbnail(image: &image::DynamicImage, model_type: ModelType, view: ThumbnailView) -> Result<RgbaImage, TextureLoadError> {
+    let half = RenderOptions::default().with_size(THUMBNAIL_SIZE / 2, THUMBNAIL_SIZE);
+    let half = match view {
+        ThumbnailView::FullBody => half,
+        ThumbnailView::Bust => half.bust(),
+    };
+    let front = render_skin(image, &model_type, &half.clone().with_angles(25.0, 10.0))?;
+    let back = render_skin(image, &model_type, &half.with_angles(205.0, 10.0))?;
+
+    let mut thumbnail = RgbaImage::new(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    imageops::overlay(&mut thumbnail, &front, 0, 0);
+    imageops::overlay(&mut thumbnail, &back, i64::from(THUMBNAIL_SIZE / 2), 0);
+    Ok(thumbnail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("skin-editor-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn thumbnails_are_keyed_by_content_model_type_and_view() {
+        let dir = empty_dir("thumbnail-keys");
+        let cache = ThumbnailCache::new(dir.join("cache"));
+        let skin = dir.join("skin.png");
+        let copy = dir.join("copy.png");
+        fs::copy("tests/skins/classic.png", &skin).unwrap();
+        fs::copy("tests/skins/classic.png", &copy).unwrap();
+
+        assert_eq!(cache.cached(&skin, ModelType::Classic, ThumbnailView::FullBody), None);
+        let thumbnail = cache.get_or_render(&skin, ModelType::Classic, ThumbnailView::FullBody).unwrap();
+        assert_eq!(image::open(&thumbnail).unwrap().to_rgba8().dimensions(), (THUMBNAIL_SIZE, THUMBNAIL_SIZE));
+        assert_eq!(cache.cached(&skin, ModelType::Classic, ThumbnailView::FullBody), Some(thumbnail.clone()));
+        assert_eq!(cache.cached(&copy, ModelType::Classic, ThumbnailView::FullBody), Some(thumbnail.clone()));
+
+        let slim = cache.thumbnail_path(&skin, ModelType::Slim, ThumbnailView::FullBody).unwrap();
+        let bust = cache.thumbnail_path(&skin, ModelType::Classic, ThumbnailView::Bust).unwrap();
+        assert_ne!(slim, thumbnail);
+        assert_ne!(bust, thumbnail);
+        assert_ne!(slim, bust);
+        assert_eq!(cache.cached(&skin, ModelType::Slim, ThumbnailView::FullBody), None);
+    }
+
+    #[test]
+    fn edited_skins_get_a_new_thumbnail() {
+        let dir = empty_dir("thumbnail-edits");
+        let cache = ThumbnailCache::new(dir.join("cache"));
+        let skin = dir.join("skin.png");
+        fs::copy("tests/skins/classic.png", &skin).unwrap();
+        let before = cache.get_or_render(&skin, ModelType::Classic, ThumbnailView::Bust).unwrap();
+
+        let mut image = image::open(&skin).unwrap().to_rgba8();
+        image.put_pixel(8, 8, image::Rgba([255, 0, 255, 255]));
+        image.save(&skin).unwrap();
+        assert_eq!(cache.cached(&skin, ModelType::Classic, ThumbnailView::Bust), None);
+        let after = cache.get_or_render(&skin, ModelType::Classic, ThumbnailView::Bust).unwrap();
+        assert_ne!(after, before);
+        assert!(before.exists());
+    }
+
+    #[test]
+    fn missing_skins_are_an_error() {
+        let dir = empty_dir("thumbnail-missing");
+        let cache = ThumbnailCache::new(dir.join("cache"));
+        assert_eq!(cache.cached(&dir.join("gone.png"), ModelType::Classic, ThumbnailView::FullBody), None);
+        assert!(matches!(
+            cache.get_or_render(&dir.join("gone.png"), ModelType::Classic, ThumbnailView::FullBody),
+            Err(ThumbnailError::Io(_)),
+        ));
+    }
+}
@@ -0,0 +1,309 @@
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{DynamicImage, Delay, Frame, ImageError, Rgba, RgbaImage};
+use nalgebra_glm as glm;
+
+use crate::glium_area::camera::Camera;
+use crate::glium_area::skin_parser::{ModelType, TextureLoadError};
+use crate::glium_area::software_renderer::{render_skin, Pose, RenderOptions};
+
+/// How far the limbs swing in [`Motion::Walk`], in degrees.
+const WALK_SWING: f32 = 30.0;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+    WebP,
+}
+
+impl AnimationFormat {
+    pub const ALL: [AnimationFormat; 3] = [AnimationFormat::Gif, AnimationFormat::Apng, AnimationFormat::WebP];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+            AnimationFormat::WebP => "webp",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Motion {
+    /// The camera orbits the standing model once.
+    Turntable,
+    /// One walk cycle seen from a fixed angle.
+    Walk,
+}
+
+#[derive(Debug)]
+pub enum AnimationError {
+    TextureLoadError(TextureLoadError),
+    ImageError(ImageError),
+    PngError(png::EncodingError),
+    WebPError(image_webp::EncodingError),
+    IoError(io::Error),
+}
+
+impl From<TextureLoadError> for AnimationError {
+    fn from(error: TextureLoadError) -> Self {
+        AnimationError::TextureLoadError(error)
+    }
+}
+
+impl From<ImageError> for AnimationError {
+    fn from(error: ImageError) -> Self {
+        AnimationError::ImageError(error)
+    }
+}
+
+impl From<png::EncodingError> for AnimationError {
+    fn from(error: png::EncodingError) -> Self {
+        AnimationError::PngError(error)
+    }
+}
+
+impl From<image_webp::EncodingError> for AnimationError {
+    fn from(error: image_webp::EncodingError) -> Self {
+        AnimationError::WebPError(error)
+    }
+}
+
+impl From<io::Error> for AnimationError {
+    fn from(error: io::Error) -> Self {
+        AnimationError::IoError(error)
+    }
+}
+
+/// Settings of an animated render. Frames are square.
+#[derive(Clone, Debug)]
+pub struct Turntable {
+    pub motion: Motion,
+    pub format: AnimationFormat,
+    pub frames: u32,
+    pub size: u32,
+    /// Milliseconds every frame is shown.
+    pub frame_delay: u32,
+    /// `None` keeps the background transparent.
+    pub background: Option<Rgba<u8>>,
+    /// Plays the animation once when false.
+    pub loop_forever: bool,
+    /// Camera angles of the first frame, in degrees.
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Default for Turntable {
+    fn default() -> Self {
+        Turntable {
+            motion: Motion::Turntable,
+            format: AnimationFormat::Gif,
+            frames: 36,
+            size: 256,
+            frame_delay: 60,
+            background: None,
+            loop_forever: true,
+            yaw: 25.0,
+            pitch: 10.0,
+        }
+    }
+}
+
+impl Turntable {
+    /// Renders every frame, calling `progress` with the number of frames done so far.
+    pub fn render_frames(
+        &self,
+        image: &DynamicImage,
+        model_type: &ModelType,
+        mut progress: impl FnMut(u32),
+    ) -> Result<Vec<RgbaImage>, TextureLoadError> {
+        let frames = self.frames.max(1);
+        let mut options = RenderOptions::default().with_size(self.size, self.size);
+        options.background = self.background;
+        // GIF has no partial transparency, smoothed edges would get a fringe
+        if self.format == AnimationFormat::Gif && self.background.is_none() {
+            options.supersample = 1;
+        }
+        options.steady_frame = true;
+        // Room for the swinging limbs
+        options.margin = 0.1;
+
+        let mut camera = Camera::new();
+        camera.set_yaw_and_pitch(self.yaw, self.pitch);
+        let step = 360.0 / frames as f32;
+
+        let mut rendered = vec![];
+        for frame in 0..frames {
+            options = options.with_angles(camera.yaw, camera.pitch);
+            match self.motion {
+                Motion::Turntable => {
+                    camera.update_yaw_and_pitch(glm::vec2(step / Camera::SENSITIVITY, 0.0));
+                }
+                Motion::Walk => {
+                    let phase = frame as f32 / frames as f32 * TAU;
+                    options.pose = Pose::Stride(WALK_SWING * phase.sin());
+                }
+            }
+            rendered.push(render_skin(image, model_type, &options)?);
+            progress(frame + 1);
+        }
+        Ok(rendered)
+    }
+
+    pub fn save(&self, frames: Vec<RgbaImage>, path: &Path) -> Result<(), AnimationError> {
+        let writer = BufWriter::new(File::create(path)?);
+        match self.format {
+            AnimationFormat::Gif => self.write_gif(frames, writer),
+            AnimationFormat::Apng => self.write_apng(frames, writer),
+            AnimationFormat::WebP => self.write_webp(frames, writer),
+        }
+    }
+
+    fn write_gif(&self, frames: Vec<RgbaImage>, writer: impl Write) -> Result<(), AnimationError> {
+        let mut encoder = GifEncoder::new_with_speed(writer, 10);
+        encoder.set_repeat(if self.loop_forever { Repeat::Infinite } else { Repeat::Finite(0) })?;
+        let delay = Delay::from_numer_denom_ms(self.frame_delay, 1);
+        encoder.encode_frames(frames.into_iter().map(|frame| Frame::from_parts(frame, 0, 0, delay)))?;
+        Ok(())
+    }
+
+    fn write_apng(&self, frames: Vec<RgbaImage>, writer: impl Write) -> Result<(), AnimationError> {
+        let mut encoder = png::Encoder::new(writer, self.size, self.size);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frames.len() as u32, if self.loop_forever { 0 } else { 1 })?;
+        encoder.set_frame_delay(self.frame_delay.min(u16::MAX as u32) as u16, 1000)?;
+        encoder.set_dispose_op(png::DisposeOp::Background)?;
+
+        let mut writer = encoder.write_header()?;
+        for frame in frames {
+            writer.write_image_data(frame.as_raw())?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// `image-webp` only writes still images, so its lossless frames are put into an
+    /// animated container here.
+    fn write_webp(&self, frames: Vec<RgbaImage>, mut writer: impl Write) -> Result<(), AnimationError> {
+        let mut chunks = vec![];
+
+        let mut header = vec![0b0001_0010, 0, 0, 0]; // alpha and animation
+        header.extend_from_slice(&u24(self.size - 1));
+        header.extend_from_slice(&u24(self.size - 1));
+        write_chunk(&mut chunks, b"VP8X", &header)?;
+
+        let mut animation = self.background.map(|Rgba([r, g, b, a])| vec![b, g, r, a]).unwrap_or(vec![0; 4]);
+        let loop_count: u16 = if self.loop_forever { 0 } else { 1 };
+        animation.extend_from_slice(&loop_count.to_le_bytes());
+        write_chunk(&mut chunks, b"ANIM", &animation)?;
+
+        for frame in frames {
+            let mut still = vec![];
+            image_webp::WebPEncoder::new(&mut still).encode(frame.as_raw(), self.size, self.size, image_webp::ColorType::Rgba8)?;
+            // Skip the RIFF header of the still image, keeping its VP8L chunk
+            let image_chunk = &still[12..];
+
+            let mut frame_data = vec![];
+            frame_data.extend_from_slice(&u24(0)); // x
+            frame_data.extend_from_slice(&u24(0)); // y
+            frame_data.extend_from_slice(&u24(self.size - 1));
+            frame_data.extend_from_slice(&u24(self.size - 1));
+            frame_data.extend_from_slice(&u24(self.frame_delay.min(0xFF_FFFF)));
+            frame_data.push(0b10); // replace the canvas instead of blending
+            frame_data.extend_from_slice(image_chunk);
+            write_chunk(&mut chunks, b"ANMF", &frame_data)?;
+        }
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(chunks.len() as u32 + 4).to_le_bytes())?;
+        writer.write_all(b"WEBP")?;
+        writer.write_all(&chunks)?;
+        Ok(())
+    }
+}
+
+fn u24(value: u32) -> [u8; 3] {
+    let [a, b, c, _] = value.to_le_bytes();
+    [a, b, c]
+}
+
+/// RIFF chunks are padded to an even size.
+fn write_chunk(writer: &mut impl Write, name: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(name)?;
+    writer.write_all(&(data.len() as u32).to_le_bytes())?;
+    writer.write_all(data)?;
+    if data.len() % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::num::NonZeroU16;
+
+    use image_webp::{LoopCount, WebPDecoder};
+
+    use super::*;
+
+    const COLORS: [Rgba<u8>; 3] = [Rgba([255, 0, 0, 255]), Rgba([0, 255, 0, 128]), Rgba([0, 0, 255, 0])];
+
+    fn turntable(format: AnimationFormat, loop_forever: bool) -> Turntable {
+        Turntable { format, size: 12, frame_delay: 80, loop_forever, ..Turntable::default() }
+    }
+
+    fn frames() -> Vec<RgbaImage> {
+        COLORS.iter().map(|&color| RgbaImage::from_pixel(12, 12, color)).collect()
+    }
+
+    #[test]
+    fn apng_round_trip() {
+        for (loop_forever, plays) in [(true, 0), (false, 1)] {
+            let mut bytes = vec![];
+            turntable(AnimationFormat::Apng, loop_forever).write_apng(frames(), &mut bytes).unwrap();
+
+            let mut reader = png::Decoder::new(Cursor::new(bytes)).read_info().unwrap();
+            let info = reader.info();
+            assert_eq!((info.width, info.height), (12, 12));
+            let animation = *info.animation_control().unwrap();
+            assert_eq!((animation.num_frames, animation.num_plays), (3, plays));
+            let delay = info.frame_control().unwrap();
+            assert_eq!((delay.delay_num, delay.delay_den), (80, 1000));
+
+            let mut buffer = vec![0; reader.output_buffer_size().unwrap()];
+            let mut decoded = vec![];
+            while reader.next_frame(&mut buffer).is_ok() {
+                decoded.push(RgbaImage::from_raw(12, 12, buffer.clone()).unwrap());
+            }
+            assert_eq!(decoded, frames());
+        }
+    }
+
+    #[test]
+    fn webp_round_trip() {
+        for (loop_forever, loop_count) in [(true, LoopCount::Forever), (false, LoopCount::Times(NonZeroU16::MIN))] {
+            let mut bytes = vec![];
+            turntable(AnimationFormat::WebP, loop_forever).write_webp(frames(), &mut bytes).unwrap();
+
+            let mut decoder = WebPDecoder::new(Cursor::new(bytes)).unwrap();
+            assert!(decoder.is_animated());
+            assert_eq!(decoder.dimensions(), (12, 12));
+            assert_eq!(decoder.num_frames(), 3);
+            assert_eq!(decoder.loop_count(), loop_count);
+
+            let mut buffer = vec![0; decoder.output_buffer_size().unwrap()];
+            for color in COLORS {
+                assert_eq!(decoder.read_frame(&mut buffer).unwrap(), 80);
+                let frame = RgbaImage::from_raw(12, 12, buffer.clone()).unwrap();
+                assert!(frame.pixels().all(|pixel| *pixel == color || (pixel[3] == 0 && color[3] == 0)));
+            }
+        }
+    }
+}
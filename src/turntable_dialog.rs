@@ -0,0 +1,288 @@
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use gtk::gio::Cancellable;
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ButtonExt, CheckButtonExt, FileExt, WidgetExt};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+use image::{DynamicImage, Rgba};
+use libadwaita as adw;
+use libadwaita::prelude::AdwDialogExt;
+
+use crate::turntable::{AnimationError, AnimationFormat, Motion, Turntable};
+use crate::utils;
+use crate::window::Window;
+
+const PREVIEW_SIZE: u32 = 160;
+
+enum ExportProgress {
+    Frame(u32),
+    Done(Result<(), AnimationError>),
+}
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+
+    use gtk::{gdk, glib, TemplateChild};
+    use gtk::CompositeTemplate;
+    use gtk::glib::{SourceId, WeakRef};
+    use gtk::subclass::prelude::{CompositeTemplate, CompositeTemplateInitializingExt, ObjectImpl, ObjectSubclass, WidgetImpl};
+    use gtk::subclass::widget::WidgetClassExt;
+    use image::DynamicImage;
+    use libadwaita as adw;
+    use libadwaita::subclass::dialog::AdwDialogImpl;
+
+    use crate::glium_area::skin_parser::ModelType;
+    use crate::window::Window;
+
+    #[derive(CompositeTemplate)]
+    #[template(file = "../resources/ui/turntable-dialog.ui")]
+    pub struct TurntableDialog {
+        #[template_child]
+        pub preview: TemplateChild<gtk::Picture>,
+        #[template_child]
+        pub motion_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub format_selector: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub frames_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub delay_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub size_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub background_button: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub background_color_button: TemplateChild<gtk::ColorDialogButton>,
+        #[template_child]
+        pub loop_button: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub export_button: TemplateChild<gtk::Button>,
+
+        pub preview_frames: RefCell<Vec<gdk::MemoryTexture>>,
+        pub preview_index: Cell<usize>,
+        pub preview_timer: RefCell<Option<SourceId>>,
+        /// The skin as it was when the dialog was opened.
+        pub image: RefCell<DynamicImage>,
+        pub model_type: RefCell<ModelType>,
+        pub window: WeakRef<Window>,
+    }
+
+    impl Default for TurntableDialog {
+        fn default() -> Self {
+            TurntableDialog {
+                preview: Default::default(),
+                motion_selector: Default::default(),
+                format_selector: Default::default(),
+                frames_button: Default::default(),
+                delay_button: Default::default(),
+                size_button: Default::default(),
+                background_button: Default::default(),
+                background_color_button: Default::default(),
+                loop_button: Default::default(),
+                progress_bar: Default::default(),
+                export_button: Default::default(),
+                preview_frames: Default::default(),
+                preview_index: Default::default(),
+                preview_timer: Default::default(),
+                image: RefCell::new(DynamicImage::new_rgba8(64, 64)),
+                model_type: RefCell::new(ModelType::Classic),
+                window: Default::default(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TurntableDialog {
+        const NAME: &'static str = "TurntableDialog";
+        type Type = super::TurntableDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+    impl ObjectImpl for TurntableDialog {}
+    impl WidgetImpl for TurntableDialog {}
+    impl AdwDialogImpl for TurntableDialog {}
+}
+
+glib::wrapper! {
+    pub struct TurntableDialog(ObjectSubclass<imp::TurntableDialog>)
+        @extends gtk::Widget, adw::Dialog,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::ShortcutManager;
+}
+
+impl TurntableDialog {
+    /// Animates the skin in the editor, starting from the angle it is seen at in the viewport.
+    pub fn new(win: &Window) -> Self {
+        let dialog: TurntableDialog = glib::Object::new();
+        dialog.imp().window.set(Some(win));
+        dialog.imp().image.replace(DynamicImage::ImageRgba8(win.export_texture()));
+        dialog.imp().model_type.replace(win.editor().model_type());
+
+        let color_dialog = gtk::ColorDialog::builder().with_alpha(false).build();
+        dialog.imp().background_color_button.set_dialog(&color_dialog);
+        dialog.imp().background_color_button.set_rgba(&gtk::gdk::RGBA::WHITE);
+
+        dialog.connect_signals();
+        dialog.update_preview();
+        dialog
+    }
+
+    fn window(&self) -> Window {
+        self.imp().window.upgrade().expect("TurntableDialog has no window")
+    }
+
+    fn connect_signals(&self) {
+        for selector in [&self.imp().motion_selector, &self.imp().format_selector] {
+            selector.connect_selected_notify(clone!(#[weak(rename_to = dialog)] self, move |_| {
+                dialog.update_preview();
+            }));
+        }
+        for button in [&self.imp().frames_button, &self.imp().delay_button] {
+            button.connect_value_changed(clone!(#[weak(rename_to = dialog)] self, move |_| {
+                dialog.update_preview();
+            }));
+        }
+        self.imp().background_button.connect_toggled(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_preview();
+        }));
+        self.imp().background_color_button.connect_rgba_notify(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.update_preview();
+        }));
+        self.imp().export_button.connect_clicked(clone!(#[weak(rename_to = dialog)] self, move |_| {
+            dialog.choose_file();
+        }));
+        self.connect_closed(|dialog| {
+            if let Some(timer) = dialog.imp().preview_timer.take() {
+                timer.remove();
+            }
+        });
+    }
+
+    fn settings(&self) -> Turntable {
+        let background = self.imp().background_button.is_active().then(|| {
+            let rgba = self.imp().background_color_button.rgba();
+            let to_u8 = |value: f32| (value * 255.0).round() as u8;
+            Rgba([to_u8(rgba.red()), to_u8(rgba.green()), to_u8(rgba.blue()), 255])
+        });
+        let camera = self.window().gl_area().camera_angles();
+        Turntable {
+            motion: match self.imp().motion_selector.selected() {
+                1 => Motion::Walk,
+                _ => Motion::Turntable,
+            },
+            format: AnimationFormat::ALL
+                .get(self.imp().format_selector.selected() as usize)
+                .copied()
+                .unwrap_or(AnimationFormat::Gif),
+            frames: self.imp().frames_button.value() as u32,
+            size: self.imp().size_button.value() as u32,
+            frame_delay: self.imp().delay_button.value() as u32,
+            background,
+            loop_forever: self.imp().loop_button.is_active(),
+            yaw: camera.map(|(yaw, _)| yaw).unwrap_or(Turntable::default().yaw),
+            pitch: camera.map(|(_, pitch)| pitch).unwrap_or(Turntable::default().pitch),
+        }
+    }
+
+    /// Renders the animation small and plays it in a loop.
+    fn update_preview(&self) {
+        let settings = Turntable { size: PREVIEW_SIZE, ..self.settings() };
+        let image = self.imp().image.borrow();
+        let model_type = *self.imp().model_type.borrow();
+        let frames = match settings.render_frames(&image, &model_type, |_| {}) {
+            Ok(frames) => frames,
+            Err(error) => {
+                println!("Error rendering animation preview: {:?}", error);
+                return;
+            }
+        };
+        self.imp().preview_frames.replace(frames.iter().map(utils::texture_from_image).collect());
+        self.imp().preview_index.set(0);
+
+        if let Some(timer) = self.imp().preview_timer.take() {
+            timer.remove();
+        }
+        let delay = Duration::from_millis(settings.frame_delay as u64);
+        let timer = glib::timeout_add_local(delay, clone!(#[weak(rename_to = dialog)] self, #[upgrade_or] glib::ControlFlow::Break, move || {
+            dialog.show_next_preview_frame();
+            glib::ControlFlow::Continue
+        }));
+        self.imp().preview_timer.replace(Some(timer));
+        self.show_next_preview_frame();
+    }
+
+    fn show_next_preview_frame(&self) {
+        let frames = self.imp().preview_frames.borrow();
+        if frames.is_empty() {
+            return;
+        }
+        let index = self.imp().preview_index.get() % frames.len();
+        self.imp().preview.set_paintable(Some(&frames[index]));
+        self.imp().preview_index.set(index + 1);
+    }
+
+    fn choose_file(&self) {
+        let settings = self.settings();
+        let file_dialog = gtk::FileDialog::builder().title("Export animation").build();
+        file_dialog.set_initial_name(Some(&format!("skin.{}", settings.format.extension())));
+        file_dialog.save(Some(&self.window()), Cancellable::NONE, clone!(#[weak(rename_to = dialog)] self, move |file| {
+            let Some(path) = file.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            dialog.export(settings, path);
+        }));
+    }
+
+    /// Renders and encodes in the background, the dialog closes when it is done.
+    fn export(&self, settings: Turntable, path: PathBuf) {
+        let image = self.imp().image.borrow().clone();
+        let model_type = *self.imp().model_type.borrow();
+        let frame_count = settings.frames.max(1);
+
+        self.imp().export_button.set_sensitive(false);
+        self.imp().progress_bar.set_fraction(0.0);
+        self.imp().progress_bar.set_visible(true);
+
+        let (sender, receiver) = async_channel::unbounded();
+        thread::spawn(move || {
+            let frames = settings.render_frames(&image, &model_type, |frame| {
+                let _ = sender.send_blocking(ExportProgress::Frame(frame));
+            });
+            let result = frames
+                .map_err(AnimationError::from)
+                .and_then(|frames| settings.save(frames, &path));
+            let _ = sender.send_blocking(ExportProgress::Done(result));
+        });
+
+        glib::spawn_future_local(clone!(#[weak(rename_to = dialog)] self, async move {
+            while let Ok(progress) = receiver.recv().await {
+                match progress {
+                    ExportProgress::Frame(frame) => {
+                        dialog.imp().progress_bar.set_fraction(frame as f64 / frame_count as f64);
+                    }
+                    ExportProgress::Done(Ok(())) => {
+                        dialog.close();
+                        return;
+                    }
+                    ExportProgress::Done(Err(error)) => {
+                        println!("Error exporting animation: {:?}", error);
+                        dialog.imp().progress_bar.set_visible(false);
+                        dialog.imp().export_button.set_sensitive(true);
+                        return;
+                    }
+                }
+            }
+        }));
+    }
+}
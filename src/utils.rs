@@ -1,11 +1,12 @@
+use std::path::PathBuf;
+
 use gtk::gdk::RGBA;
+use gtk::prelude::{FileExt, StaticType, TextureExt};
+use gtk::{gdk, glib};
+use image::{DynamicImage, GenericImageView, ImageError, Rgba, RgbaImage};
 use rand::Rng;
 use rand::distr::Alphanumeric;
 
-use crate::glium_area::body_part::BodyPart;
-use crate::glium_area::body_part::BodyPart::*;
-use crate::glium_area::body_part::BodyPart::RightArmOuter;
-use crate::glium_area::model_object::ModelObject;
 use crate::glium_area::skin_parser::ModelType;
 
 pub fn rgba_to_f32(rgba: RGBA) -> [f32; 4] {
@@ -15,8 +16,116 @@ pub fn f32_to_rgba(color: [f32; 4]) -> RGBA {
     RGBA::new(color[0], color[1], color[2], color[3])
 }
 
-pub fn guess_model_type(bytes: &[u8]) -> Result<ModelType, ()> {
-    Ok(ModelType::Classic)
+/// Wraps an image so GTK widgets can show it.
+pub fn texture_from_image(image: &RgbaImage) -> gdk::MemoryTexture {
+    gdk::MemoryTexture::new(
+        image.width() as i32,
+        image.height() as i32,
+        gdk::MemoryFormat::R8g8b8a8,
+        &glib::Bytes::from(image.as_raw().as_slice()),
+        image.width() as usize * 4,
+    )
+}
+
+/// Copies a texture from GTK, e.g. one pasted or dropped from another app.
+pub fn image_from_texture(texture: &gdk::Texture) -> Result<DynamicImage, ImageError> {
+    image::load_from_memory(&texture.save_to_png_bytes())
+}
+
+/// Skins dropped on a [`skin_drop_target`].
+pub enum DroppedSkins {
+    Files(Vec<PathBuf>),
+    Image(gdk::Texture),
+}
+
+/// Accepts files from a file manager and images dragged from browsers or chat apps.
+pub fn skin_drop_target(on_drop: impl Fn(DroppedSkins) + 'static) -> gtk::DropTarget {
+    let target = gtk::DropTarget::new(glib::Type::INVALID, gdk::DragAction::COPY);
+    target.set_types(&[gdk::FileList::static_type(), gdk::Texture::static_type()]);
+    target.connect_drop(move |_, value, _, _| {
+        if let Ok(file_list) = value.get::<gdk::FileList>() {
+            let paths = file_list.files().iter().filter_map(|file| file.path()).collect();
+            on_drop(DroppedSkins::Files(paths));
+            return true;
+        }
+        if let Ok(texture) = value.get::<gdk::Texture>() {
+            on_drop(DroppedSkins::Image(texture));
+            return true;
+        }
+        false
+    });
+    target
+}
+
+/// Columns of the arm textures (x, y, width, height on a 64x64 skin) that only classic
+/// arms use: the last two columns of the top and bottom, and of the back.
+const CLASSIC_ONLY_ARM_REGIONS: [(u32, u32, u32, u32); 4] = [
+    (50, 16, 2, 4),
+    (54, 20, 2, 12),
+    (42, 48, 2, 4),
+    (46, 52, 2, 12),
+];
+
+/// Front of the arms, which both model types paint.
+const SHARED_ARM_REGIONS: [(u32, u32, u32, u32); 2] = [
+    (44, 20, 3, 12),
+    (36, 52, 3, 12),
+];
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ModelTypeGuess {
+    pub model_type: ModelType,
+    pub confidence: Confidence,
+}
+
+/// Tells slim skins from classic ones by the arm columns only classic arms use:
+/// slim skins leave them transparent, classic ones paint them. Skins without arms
+/// are a low confidence guess.
+pub fn guess_model_type<I: GenericImageView<Pixel = Rgba<u8>>>(image: &I) -> ModelTypeGuess {
+    let (width, height) = image.dimensions();
+    if width != height || width % 64 != 0 {
+        // Legacy skins predate slim arms
+        let confidence = if (width, height) == (64, 32) { Confidence::High } else { Confidence::Low };
+        return ModelTypeGuess { model_type: ModelType::Classic, confidence };
+    }
+
+    let scale = width / 64;
+    let region_pixels = |regions: &[(u32, u32, u32, u32)]| {
+        let mut pixels = vec![];
+        for &(x, y, region_width, region_height) in regions {
+            for pixel_y in y * scale..(y + region_height) * scale {
+                for pixel_x in x * scale..(x + region_width) * scale {
+                    pixels.push(image.get_pixel(pixel_x, pixel_y));
+                }
+            }
+        }
+        pixels
+    };
+    let pixels = region_pixels(&CLASSIC_ONLY_ARM_REGIONS);
+    let opaque = pixels.iter().filter(|pixel| pixel[3] != 0).count();
+
+    if opaque == 0 && region_pixels(&SHARED_ARM_REGIONS).iter().all(|pixel| pixel[3] == 0) {
+        // Without arms there is nothing to tell the model types apart
+        ModelTypeGuess { model_type: ModelType::Classic, confidence: Confidence::Low }
+    } else if opaque == 0 {
+        ModelTypeGuess { model_type: ModelType::Slim, confidence: Confidence::High }
+    } else if opaque == pixels.len() {
+        // Some slim skins fill the unused columns with a single color
+        let uniform = pixels.iter().all(|pixel| *pixel == pixels[0]);
+        let confidence = if uniform { Confidence::Medium } else { Confidence::High };
+        ModelTypeGuess { model_type: ModelType::Classic, confidence }
+    } else if opaque * 2 < pixels.len() {
+        ModelTypeGuess { model_type: ModelType::Slim, confidence: Confidence::Low }
+    } else {
+        ModelTypeGuess { model_type: ModelType::Classic, confidence: Confidence::Low }
+    }
 }
 
 pub fn random_brightness(color: [f32; 4]) -> [f32; 4] {
@@ -49,7 +158,7 @@ fn get_adjustments(start: f32, stop: f32, step: f32) -> Vec<f32> {
     adjustments
 }
 
-fn rgba_to_hsv(rgba: [f32; 4]) -> [f32; 4] {
+pub fn rgba_to_hsv(rgba: [f32; 4]) -> [f32; 4] {
     let (r, g, b, a) = (rgba[0], rgba[1], rgba[2], rgba[3]);
 
     let max = r.max(g).max(b);
@@ -77,7 +186,7 @@ fn rgba_to_hsv(rgba: [f32; 4]) -> [f32; 4] {
     [hue / 360.0, saturation, value, a]
 }
 
-fn hsv_to_rgba(hsv: [f32; 4]) -> [f32; 4] {
+pub fn hsv_to_rgba(hsv: [f32; 4]) -> [f32; 4] {
     let (h, s, v, a) = (hsv[0] * 360.0, hsv[1], hsv[2], hsv[3]);
 
     let c = v * s;
@@ -101,72 +210,6 @@ fn hsv_to_rgba(hsv: [f32; 4]) -> [f32; 4] {
     [r + m, g + m, b + m, a]
 }
 
-pub fn classic_to_slim_arm(classic_arm: &ModelObject, slim_arm: &mut ModelObject, arm_side: BodyPart) {
-    let colors = classic_arm.get_pixels();
-    let (front, right, back, left, top, bottom) = match arm_side {
-        LeftArm | LeftArmOuter => ((0, 2), (48, 51), (97, 99), (144, 147), (192, 194), (208, 210)),
-        RightArm | RightArmOuter => ((1, 3), (48, 51), (96, 98), (144, 147), (193, 195), (209, 211)),
-        _ => panic!("Wrong arm type passed to the function")
-    };
-
-    move_classic_to_slim_by_side(slim_arm, &colors, front.0, front.1, 12, 0);     // FRONT
-    move_classic_to_slim_by_side(slim_arm, &colors, right.0, right.1, 12, 36);    // RIGHT
-    move_classic_to_slim_by_side(slim_arm, &colors, back.0, back.1, 12, 84);      // BACK
-    move_classic_to_slim_by_side(slim_arm, &colors, left.0, left.1, 12, 120);     // LEFT
-    move_classic_to_slim_by_side(slim_arm, &colors, top.0, top.1, 4, 168);        // TOP
-    move_classic_to_slim_by_side(slim_arm, &colors, bottom.0, bottom.1, 4, 180);  // BOTTOM
-}
-
-fn move_classic_to_slim_by_side(
-    target_arm: &mut ModelObject,
-    colors: &Vec<[f32; 4]>,
-    start: usize,
-    stop: usize,
-    side_height: usize,
-    mut cell_index: usize,
-) {
-    let indexes: Vec<(usize, usize)> = (0..side_height).map(|n| (start + n * 4, stop + n * 4)).collect();
-    let colors: Vec<[f32; 4]> = indexes.iter()
-        .flat_map(|(start, stop)| colors[*start..=*stop].to_vec())
-        .collect();
-    for color in colors {
-        target_arm.paint(cell_index, color);
-        cell_index += 1;
-    }
-}
-
-pub fn slim_to_classic_arm(slim_arm: &ModelObject, classic_arm: &mut ModelObject, arm_side: BodyPart) {
-    let colors = slim_arm.get_pixels();
-
-    let (front, right, back, left, top, bottom) = match arm_side {
-        LeftArm | LeftArmOuter => ((0, 2), (48, 51), (97, 99), (144, 147), (192, 194), (208, 210)),
-        RightArm | RightArmOuter => ((1, 3), (48, 51), (96, 98), (144, 147), (193, 195), (209, 211)),
-        _ => panic!("Wrong arm type passed to the function")
-    };
-
-    move_slim_to_classic_by_side(classic_arm, &colors, front.0, front.1, 12, 0);     // FRONT
-    move_slim_to_classic_by_side(classic_arm, &colors, right.0, right.1, 12, 36);    // RIGHT
-    move_slim_to_classic_by_side(classic_arm, &colors, back.0, back.1, 12, 84);      // BACK
-    move_slim_to_classic_by_side(classic_arm, &colors, left.0, left.1, 12, 120);     // LEFT
-    move_slim_to_classic_by_side(classic_arm, &colors, top.0, top.1, 4, 168);        // TOP
-    move_slim_to_classic_by_side(classic_arm, &colors, bottom.0, bottom.1, 4, 180);  // BOTTOM
-}
-
-fn move_slim_to_classic_by_side(
-    target_arm: &mut ModelObject,
-    colors: &Vec<[f32; 4]>,
-    start: usize,
-    stop: usize,
-    side_height: usize,
-    mut color_index: usize
-) {
-    let cells: Vec<usize> = (0..side_height).flat_map(|n| (start + n * 4)..=(stop + n * 4)).collect();
-    for cell in cells {
-        target_arm.paint(cell, colors[color_index]);
-        color_index += 1;
-    }
-}
-
 pub fn generate_random_filename() -> String {
     let mut rng = rand::thread_rng();
     let random_bytes: Vec<u8> = std::iter::repeat(())
@@ -177,4 +220,47 @@ pub fn generate_random_filename() -> String {
     let mut filename = String::from_utf8(random_bytes).expect("Error creating random filename");
     filename.push_str(".png");
     filename
-}
\ No newline at end of file
+}
+
+/// FNV-1a hash, stable between runs so it can name files on disk.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::imageops::FilterType;
+
+    use super::*;
+
+    fn fixture(name: &str) -> DynamicImage {
+        image::open(format!("tests/skins/{}.png", name)).unwrap()
+    }
+
+    #[test]
+    fn fixtures_are_recognized() {
+        let guess = guess_model_type(&fixture("classic"));
+        assert_eq!(guess, ModelTypeGuess { model_type: ModelType::Classic, confidence: Confidence::High });
+        let guess = guess_model_type(&fixture("slim"));
+        assert_eq!(guess, ModelTypeGuess { model_type: ModelType::Slim, confidence: Confidence::High });
+    }
+
+    #[test]
+    fn hd_skins_are_recognized() {
+        for (name, model_type) in [("classic", ModelType::Classic), ("slim", ModelType::Slim)] {
+            let hd = fixture(name).resize_exact(128, 128, FilterType::Nearest);
+            assert_eq!(guess_model_type(&hd), ModelTypeGuess { model_type, confidence: Confidence::High });
+        }
+    }
+
+    #[test]
+    fn empty_skins_are_a_low_confidence_guess() {
+        let guess = guess_model_type(&RgbaImage::new(64, 64));
+        assert_eq!(guess.confidence, Confidence::Low);
+        let guess = guess_model_type(&RgbaImage::new(128, 128));
+        assert_eq!(guess.confidence, Confidence::Low);
+    }
+}
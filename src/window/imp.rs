@@ -9,9 +9,11 @@ use libadwaita as adw;
 use libadwaita::subclass::application_window::AdwApplicationWindowImpl;
 
 use crate::APP_ID;
+use crate::compare_panel::ComparePanel;
 use crate::editor_session::EditorSession;
 use crate::glium_area::GliumArea;
 use crate::model_switcher::ModelSwitcher;
+use crate::template_library::TemplateSource;
 use crate::template_list::TemplateList;
 
 #[derive(CompositeTemplate, Default)]
@@ -30,6 +32,8 @@ pub struct Window {
     #[template_child]
     pub grid_toggle: TemplateChild<gtk::ToggleButton>,
     #[template_child]
+    pub compare_toggle: TemplateChild<gtk::ToggleButton>,
+    #[template_child]
     pub color_button: TemplateChild<gtk::ColorDialogButton>,
     #[template_child]
     pub content_box: TemplateChild<gtk::Box>,
@@ -50,8 +54,20 @@ pub struct Window {
     #[template_child]
     pub replace_color: TemplateChild<gtk::ToggleButton>,
     #[template_child]
+    pub text_tool: TemplateChild<gtk::ToggleButton>,
+    #[template_child]
+    pub shade: TemplateChild<gtk::ToggleButton>,
+    #[template_child]
+    pub noise: TemplateChild<gtk::ToggleButton>,
+    #[template_child]
+    pub gradient: TemplateChild<gtk::ToggleButton>,
+    #[template_child]
+    pub shading_button: TemplateChild<gtk::MenuButton>,
+    #[template_child]
     pub gl_area: TemplateChild<GliumArea>,
     #[template_child]
+    pub compare_panel: TemplateChild<ComparePanel>,
+    #[template_child]
     pub model_switcher: TemplateChild<ModelSwitcher>,
     #[template_child]
     pub reset_skin_button: TemplateChild<gtk::Button>,
@@ -63,6 +79,8 @@ pub struct Window {
     pub save_as_template_button: TemplateChild<gtk::Button>,
 
     pub opening_new_skin: Cell<bool>,
+    /// Origin of the skin in the editor, recorded when it is saved as a template.
+    pub skin_source: Cell<TemplateSource>,
     pub editor: RefCell<Option<EditorSession>>,
 }
 
@@ -76,11 +94,11 @@ impl ObjectSubclass for Window {
         Self::bind_template(klass);
 
         klass.install_action("win.undo", None, move |win, _, _| {
-            win.editor_mut().undo();
+            win.undo();
         });
 
         klass.install_action("win.redo", None, move |win, _, _| {
-            win.editor_mut().redo();
+            win.redo();
         });
 
         klass.install_action("win.about", None, move |win, _, _| {
@@ -1,19 +1,30 @@
 use std::cell::{Ref, RefCell, RefMut};
+use std::path::Path;
 
 use gtk::gio;
 use gtk::glib;
 use gtk::prelude::{ButtonExt, ColorChooserExt, ToggleButtonExt, WidgetExt};
 use gtk::subclass::prelude::ObjectSubclassIsExt;
 use libadwaita as adw;
+use libadwaita::prelude::AdwDialogExt;
 
 use crate::application::Application;
-use crate::command::{Action, Tool};
+use crate::command::{Action, ChangeModelType, ChangeResolution, LoadImage, LoadRig, Paint, Tool};
 use crate::editor_host::EditorHost;
 use crate::editor_session::EditorSession;
+use crate::glium_area::arm_conversion::ConversionStrategy;
 use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::overlay_view::{OverlayView, PaintTarget};
 use crate::glium_area::renderer::ModelCell;
-use crate::glium_area::skin_parser::{ModelType, TextureLoadError, TextureType};
+use crate::glium_area::rig::Rig;
+use crate::glium_area::skin_parser::{texture_layout, ModelType, TextureLoadError};
+use crate::image_stamp::cell_side;
+use crate::settings::Settings;
+use crate::shading::ShadingSettings;
+use crate::shading_popover::ShadingPopover;
 use crate::skin_loader_popover::SkinLoaderPopover;
+use crate::template_library::{TemplateEntry, TemplateLibraryError, TemplateMetadata, TemplateSource};
+use crate::text_dialog::TextDialog;
 
 mod imp;
 mod signals;
@@ -47,9 +58,13 @@ impl Window {
         let color_dialog = gtk::ColorDialog::builder().with_alpha(false).build();
         self.imp().color_button.set_dialog(&color_dialog);
 
+        self.imp().template_list.setup(self);
+
         let gl_area = self.imp().gl_area.get();
         gl_area.setup(self.clone());
         self.imp().editor.replace(Some(EditorSession::new(gl_area)));
+        self.imp().shading_button.set_popover(Some(&ShadingPopover::new(self)));
+        self.imp().compare_panel.setup(self);
         self.set_tool_active(true);
     }
 
@@ -119,6 +134,18 @@ impl Window {
             .set_selected(model_type_index);
     }
 
+    /// Updates the model type dropdown without converting the skin again.
+    pub fn sync_model_type_selector(&self, model_type: ModelType) {
+        let index = match model_type {
+            ModelType::Slim => 0,
+            ModelType::Classic => 1,
+        };
+        let selector = &self.imp().model_switcher.imp().model_type_selector;
+        if selector.selected() != index {
+            self.begin_skin_import(index);
+        }
+    }
+
     pub fn clear_drawing_history(&self) {
         self.editor_mut().clear_history();
     }
@@ -128,38 +155,84 @@ impl Window {
     }
 
     pub fn open_skin_file(&self, path: &str, model_type: ModelType) -> Result<(), TextureLoadError> {
-        let item_num = match model_type {
-            ModelType::Slim => 0,
-            ModelType::Classic => 1,
-        };
-        self.begin_skin_import(item_num);
+        self.sync_model_type_selector(model_type);
         self.editor_mut()
             .load_skin_from_path(path, &model_type, false)?;
+        self.imp().skin_source.set(TemplateSource::File);
+        Settings::update(|settings| settings.add_recent_file(Path::new(path), model_type));
         self.clear_drawing_history();
         self.request_viewport_redraw();
         Ok(())
     }
 
-    pub fn load_skin_from_image(
-        &self,
-        image: &image::DynamicImage,
-        model_type: ModelType,
-        texture_type: TextureType,
-    ) -> Result<(), TextureLoadError> {
+    /// Opens a skin that has no file, e.g. one pasted from the clipboard.
+    pub fn open_skin_image(&self, image: &image::DynamicImage, model_type: ModelType) -> Result<(), TextureLoadError> {
+        let (texture_type, _) = texture_layout(image.width(), image.height())?;
+        self.sync_model_type_selector(model_type);
         self.editor_mut()
             .load_skin_from_image(image, model_type, texture_type, false)?;
+        self.imp().skin_source.set(TemplateSource::File);
+        self.clear_drawing_history();
         self.request_viewport_redraw();
         Ok(())
     }
 
-    pub fn load_template(&self, path: &str) -> Result<(), TextureLoadError> {
-        self.editor_mut().load_template(path)?;
+    /// Replaces the skin with a fetched one as one undoable step.
+    pub fn load_skin_from_image(&self, image: &image::DynamicImage, model_type: ModelType) {
+        self.apply_skin_image(image.clone(), model_type);
+        self.imp().skin_source.set(TemplateSource::Fetched);
+    }
+
+    /// Applies a wardrobe template as one undoable step, switching to the model type it was made for.
+    pub fn load_template(&self, path: &Path) -> Result<(), TextureLoadError> {
+        let model_type = self.imp().template_list.library()
+            .get(path)
+            .map(|entry| entry.metadata.model_type)
+            .unwrap_or_else(|| self.editor().model_type());
+        let image = image::open(path).map_err(TextureLoadError::Image)?;
+        self.add_command(Box::new(LoadImage::over(self.gl_area(), image, model_type)));
+        self.sync_model_type_selector(model_type);
         self.request_viewport_redraw();
         Ok(())
     }
 
+    /// Body parts shown by the part and layer toggles.
+    pub fn visible_body_parts(&self) -> Vec<BodyPart> {
+        let Some(renderer) = self.gl_area().renderer() else {
+            return vec![];
+        };
+        let parts = renderer.borrow().visible_body_parts();
+        parts
+    }
+
+    /// Paints the cells as one undoable step.
+    pub fn paint_cells(&self, cells: Vec<ModelCell>) {
+        self.add_command(Box::new(Paint::new(self.gl_area(), cells)));
+        self.request_viewport_redraw();
+    }
+
+    /// Replaces the skin in the editor as one undoable step.
+    pub fn apply_skin_image(&self, image: image::DynamicImage, model_type: ModelType) {
+        let command = LoadImage::new(self.gl_area(), image, model_type);
+        self.add_command(Box::new(command));
+        self.sync_model_type_selector(model_type);
+        self.request_viewport_redraw();
+    }
+
+    pub fn save_as_template(&self) -> Result<(), TemplateLibraryError> {
+        let image = self.export_texture();
+        let model_type = self.editor().model_type();
+        let mut library = self.imp().template_list.library();
+        let display_name = format!("Template {}", library.entries().len() + 1);
+        let metadata = TemplateMetadata::new(&display_name, model_type, self.imp().skin_source.get());
+        let entry = library.add(&image, metadata)?;
+        println!("Saved as template at {:?}", entry.path);
+        Ok(())
+    }
+
     pub fn reset_skin(&self) {
         self.editor_mut().reset_skin();
+        self.imp().skin_source.set(TemplateSource::Drawn);
         self.set_grid_visible(true);
     }
 
@@ -173,8 +246,12 @@ impl Window {
         self.editor().export_texture()
     }
 
+    pub fn template_entries(&self) -> Vec<TemplateEntry> {
+        self.imp().template_list.library().entries().to_vec()
+    }
+
     pub fn refresh_template_list(&self) {
-        self.imp().template_list.load_list(self);
+        self.imp().template_list.load_list();
     }
 
     pub fn set_body_part_visible(&self, body_part: &BodyPart, visible: bool) {
@@ -187,11 +264,67 @@ impl Window {
         self.request_viewport_redraw();
     }
 
+    pub fn set_overlay_view(&self, overlay_view: OverlayView) {
+        if let Some(renderer) = self.gl_area().renderer() {
+            renderer.borrow_mut().set_overlay_view(overlay_view);
+        }
+        self.request_viewport_redraw();
+    }
+
+    pub fn set_paint_target(&self, paint_target: PaintTarget) {
+        if let Some(renderer) = self.gl_area().renderer() {
+            renderer.borrow_mut().set_paint_target(paint_target);
+        }
+        self.request_viewport_redraw();
+    }
+
+    /// Picks the model type in the dropdown, which converts the skin like choosing it by hand.
+    pub fn select_model_type(&self, model_type: ModelType) {
+        let index = match model_type {
+            ModelType::Slim => 0,
+            ModelType::Classic => 1,
+        };
+        self.imp().model_switcher.imp().model_type_selector.set_selected(index);
+    }
+
+    /// How arms are converted between the model types, as picked in the model switcher.
+    pub fn conversion_strategy(&self) -> ConversionStrategy {
+        self.imp().model_switcher.conversion_strategy()
+    }
+
+    /// Converts the skin to the other model type as one undoable step.
     pub fn change_model_type(&self, model_type: ModelType) {
-        self.editor_mut().reset_model_type(&model_type);
+        let command = ChangeModelType::new(self.gl_area(), model_type, self.conversion_strategy());
+        self.add_command(Box::new(command));
         self.request_viewport_redraw();
     }
 
+    /// Scales the skin to `resolution` cells per skin pixel as one undoable step.
+    pub fn change_resolution(&self, resolution: u32) {
+        let command = ChangeResolution::new(self.gl_area(), resolution);
+        self.add_command(Box::new(command));
+        self.request_viewport_redraw();
+    }
+
+    /// Edits the cuboids of `rig` instead of the player model, painted with the current skin.
+    pub fn load_rig(&self, rig: Rig) {
+        let command = LoadRig::new(self.gl_area(), rig, self.export_texture().into());
+        self.add_command(Box::new(command));
+        self.request_viewport_redraw();
+    }
+
+    pub fn undo(&self) {
+        self.editor_mut().undo();
+        let model_type = self.editor().model_type();
+        self.sync_model_type_selector(model_type);
+    }
+
+    pub fn redo(&self) {
+        self.editor_mut().redo();
+        let model_type = self.editor().model_type();
+        self.sync_model_type_selector(model_type);
+    }
+
     pub(super) fn consume_skin_import_model_change(&self) -> bool {
         self.imp().opening_new_skin.take()
     }
@@ -203,6 +336,8 @@ impl Window {
     pub fn save_skin_to_path(&self, path: &str) -> Result<(), image::ImageError> {
         self.export_texture().save(path)?;
         self.editor_mut().clear_dirty();
+        let model_type = self.editor().model_type();
+        Settings::update(|settings| settings.add_recent_file(Path::new(path), model_type));
         Ok(())
     }
 
@@ -224,6 +359,10 @@ impl EditorHost for Window {
         self.editor().tool()
     }
 
+    fn shading(&self) -> ShadingSettings {
+        self.editor().shading()
+    }
+
     fn active_color(&self) -> gtk::gdk::RGBA {
         self.imp().color_button.rgba()
     }
@@ -248,4 +387,12 @@ impl EditorHost for Window {
     fn add_command(&self, command: Box<dyn Action>) {
         self.editor_mut().add_command(command);
     }
+
+    fn place_text(&self, cell: ModelCell) {
+        let (model_type, resolution) = (self.editor().model_type(), self.editor().resolution());
+        let Some(side) = cell_side(&model_type, resolution, cell.body_part, cell.cell_index) else {
+            return;
+        };
+        TextDialog::new(self, cell.body_part, side).present(Some(self));
+    }
 }
@@ -0,0 +1,57 @@
+use gtk::gdk;
+use gtk::gio::{ActionEntry, Cancellable};
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ActionMapExtManual, WidgetExt};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+use libadwaita::prelude::AdwDialogExt;
+
+use crate::glium_area::skin_parser::texture_layout;
+use crate::skin_dialog::SkinDialog;
+use crate::utils::{image_from_texture, skin_drop_target, DroppedSkins};
+use crate::window::Window;
+
+use super::file_io::open_file;
+
+pub(super) fn connect(win: &Window) {
+    let paste = ActionEntry::builder("paste-skin")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            win.clipboard().read_texture_async(Cancellable::NONE, clone!(#[weak] win, move |texture| {
+                match texture {
+                    Ok(Some(texture)) => open_texture(&win, &texture),
+                    Ok(None) => println!("The clipboard has no image"),
+                    Err(error) => println!("Error reading the clipboard: {:?}", error),
+                }
+            }));
+        }))
+        .build();
+    win.add_action_entries([paste]);
+
+    // One skin is opened at a time, the wardrobe takes several
+    let drop_target = skin_drop_target(clone!(#[weak(rename_to = win)] win, move |dropped| {
+        match dropped {
+            DroppedSkins::Files(paths) => match paths.into_iter().next() {
+                Some(path) => open_file(&win, path),
+                None => println!("Only local files can be opened"),
+            },
+            DroppedSkins::Image(texture) => open_texture(&win, &texture),
+        }
+    }));
+    win.imp().gl_area.add_controller(drop_target);
+}
+
+/// Asks for the model type of a pasted or dropped image, like for a file.
+fn open_texture(win: &Window, texture: &gdk::Texture) {
+    let image = match image_from_texture(texture) {
+        Ok(image) => image,
+        Err(error) => {
+            println!("Error reading image: {:?}", error);
+            return;
+        }
+    };
+    if let Err(error) = texture_layout(image.width(), image.height()) {
+        println!("Error loading skin: {:?}", error);
+        return;
+    }
+    SkinDialog::for_image(image, win.clone()).present(Some(win));
+}
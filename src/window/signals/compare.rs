@@ -0,0 +1,15 @@
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ToggleButtonExt, WidgetExt};
+use gtk::subclass::prelude::ObjectSubclassIsExt;
+
+use crate::window::Window;
+
+pub(super) fn connect(win: &Window) {
+    win.imp().compare_toggle.connect_toggled(clone!(#[weak(rename_to = win)] win, move |btn| {
+        win.imp().compare_panel.set_visible(btn.is_active());
+        if btn.is_active() {
+            win.imp().compare_panel.refresh();
+        }
+    }));
+}
@@ -0,0 +1,65 @@
+use gtk::gio::ActionEntry;
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ActionMapExtManual, RangeExt, ScaleExt};
+use libadwaita as adw;
+use libadwaita::prelude::{AdwDialogExt, AlertDialogExt};
+
+use crate::glium_area::renderer::ModelCell;
+use crate::skin_effects::{apply_effect, Effect};
+use crate::window::Window;
+
+pub(super) fn connect(win: &Window) {
+    let entries = [
+        ("add-outline", Effect::Outline),
+        ("add-ambient-occlusion", Effect::AmbientOcclusion),
+        ("add-drop-shadow", Effect::DropShadow),
+    ]
+    .map(|(name, effect)| {
+        ActionEntry::builder(name)
+            .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+                ask_strength(&win, effect);
+            }))
+            .build()
+    });
+    win.add_action_entries(entries);
+}
+
+/// Asks how strong the effect is, then applies it to the visible parts as one step.
+fn ask_strength(win: &Window, effect: Effect) {
+    let strength_scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, 5.0, 100.0, 5.0);
+    strength_scale.set_value(30.0);
+    strength_scale.set_draw_value(true);
+    strength_scale.set_format_value_func(|_, value| format!("{value:.0} %"));
+
+    let dialog = adw::AlertDialog::new(
+        Some(effect.label()),
+        Some("Applies to the body parts and layers that are shown."),
+    );
+    dialog.set_extra_child(Some(&strength_scale));
+    dialog.add_responses(&[("cancel", "_Cancel"), ("apply", "_Apply")]);
+    dialog.set_response_appearance("apply", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("apply"));
+    dialog.set_close_response("cancel");
+
+    dialog.connect_response(Some("apply"), clone!(#[weak] win, move |_, _| {
+        let parts = win.visible_body_parts();
+        let strength = strength_scale.value() as f32 / 100.0;
+        match apply_effect(&win.export_texture(), &win.editor().model_type(), &parts, effect, strength) {
+            Ok(cells) if cells.is_empty() => {}
+            Ok(cells) => {
+                let cells = cells
+                    .into_iter()
+                    .map(|(body_part, cell_index, color)| ModelCell {
+                        body_part,
+                        cell_index,
+                        color: color.0.map(|channel| f32::from(channel) / 255.0),
+                    })
+                    .collect();
+                win.paint_cells(cells);
+            }
+            Err(error) => println!("Error applying {}: {:?}", effect.label().to_lowercase(), error),
+        }
+    }));
+    dialog.present(Some(win));
+}
@@ -1,13 +1,26 @@
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+
 use gtk::gio::{ActionEntry, Cancellable};
 use gtk::glib;
 use gtk::glib::clone;
-use gtk::prelude::{ActionMapExtManual, ButtonExt, FileExt, NativeDialogExtManual, WidgetExt};
+use gtk::prelude::{ActionMapExtManual, BoxExt, ButtonExt, FileExt, NativeDialogExtManual, WidgetExt};
 use gtk::subclass::prelude::ObjectSubclassIsExt;
-use libadwaita::prelude::AdwDialogExt;
+use libadwaita as adw;
+use libadwaita::prelude::{AdwDialogExt, AlertDialogExt};
 
-use crate::utils;
-use crate::{TEMPLATES_DIR};
+use crate::bedrock_geometry::{export_player_geometry, load_geometries};
+use crate::blockbench::{export_project, import_project};
+use crate::glium_area::skin_parser::ModelType;
+use crate::glium_area::software_renderer::{render_facets, RenderOptions};
+use crate::layered_image::{export_layered, import_layered, LayeredFormat};
+use crate::model_export::{export_model, ModelFormat, OverlayGeometry};
+use crate::player_head_dialog::PlayerHeadDialog;
+use crate::print_export::{PrintExport, PrintFormat};
+use crate::render_export_dialog::RenderExportDialog;
 use crate::skin_dialog::SkinDialog;
+use crate::turntable_dialog::TurntableDialog;
+use crate::utils::texture_from_image;
 use crate::window::Window;
 
 pub(super) fn connect(win: &Window) {
@@ -23,29 +36,140 @@ fn connect_open(win: &Window) {
                 Ok(file) => file,
                 Err(_) => return,
             };
-            let texture_path = file.path().unwrap();
-            let skin_dialog = SkinDialog::new(texture_path, win.clone());
-            skin_dialog.present(Some(&win));
+            open_file(&win, file.path().unwrap());
         }));
     }));
 }
 
+/// Opens a skin, or a project or geometry file, by its extension.
+pub(super) fn open_file(win: &Window, texture_path: PathBuf) {
+    if texture_path.extension().is_some_and(|extension| extension == "bbmodel") {
+        match import_project(&texture_path) {
+            Ok(skin) => win.apply_skin_image(skin.image.into(), skin.model_type),
+            Err(error) => println!("Error importing Blockbench project: {:?}", error),
+        }
+        return;
+    }
+    if LayeredFormat::from_path(&texture_path).is_some() {
+        match import_layered(&texture_path) {
+            Ok(skin) => win.apply_skin_image(skin.image.into(), skin.model_type),
+            Err(error) => println!("Error importing layered image: {:?}", error),
+        }
+        return;
+    }
+    if texture_path.extension().is_some_and(|extension| extension == "json") {
+        show_bedrock_geometry(win, &texture_path);
+        return;
+    }
+    let skin_dialog = SkinDialog::new(texture_path, win.clone());
+    skin_dialog.present(Some(win));
+}
+
 fn connect_save(win: &Window) {
     let action = ActionEntry::builder("action")
         .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
-            let img = win.export_texture();
-            let random_filename = utils::generate_random_filename();
-            let path = TEMPLATES_DIR.as_path().join(random_filename);
-            match img.save(&path) {
-                Ok(_) => {
-                    println!("Saved as template at {:?}", path.as_path());
-                    win.refresh_template_list();
+            match win.save_as_template() {
+                Ok(_) => win.refresh_template_list(),
+                Err(error) => println!("Error saving template: {:?}", error),
+            }
+        }))
+        .build();
+    let export_player_head = ActionEntry::builder("export-player-head")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            if !player_model_loaded(&win) {
+                return;
+            }
+            PlayerHeadDialog::new(&win).present(Some(&win));
+        }))
+        .build();
+    let export_render = ActionEntry::builder("export-render")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            if !player_model_loaded(&win) {
+                return;
+            }
+            RenderExportDialog::new(&win).present(Some(&win));
+        }))
+        .build();
+    let export_animation = ActionEntry::builder("export-animation")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            if !player_model_loaded(&win) {
+                return;
+            }
+            TurntableDialog::new(&win).present(Some(&win));
+        }))
+        .build();
+    let export_model = ActionEntry::builder("export-model")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            if !player_model_loaded(&win) {
+                return;
+            }
+            ask_model_export(&win);
+        }))
+        .build();
+    let export_print = ActionEntry::builder("export-print")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            if !player_model_loaded(&win) {
+                return;
+            }
+            ask_print_export(&win);
+        }))
+        .build();
+    let export_blockbench = ActionEntry::builder("export-blockbench")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            if !player_model_loaded(&win) {
+                return;
+            }
+            let file_dialog = gtk::FileDialog::builder().title("Export Blockbench project").build();
+            file_dialog.set_initial_name(Some("skin.bbmodel"));
+            file_dialog.save(Some(&win), Cancellable::NONE, clone!(#[weak] win, move |file| {
+                let Some(path) = file.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                let model_type = win.editor().model_type();
+                match export_project(&win.export_texture(), &model_type, &path) {
+                    Ok(_) => println!("Saved at {:?}", path),
+                    Err(error) => println!("Error exporting Blockbench project: {:?}", error),
                 }
-                Err(error) => println!("{}", error.to_string()),
+            }));
+        }))
+        .build();
+    let export_bedrock = ActionEntry::builder("export-bedrock")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            if !player_model_loaded(&win) {
+                return;
+            }
+            let file_dialog = gtk::FileDialog::builder().title("Export Bedrock geometry").build();
+            file_dialog.set_initial_name(Some("skin.geo.json"));
+            file_dialog.save(Some(&win), Cancellable::NONE, clone!(#[weak] win, move |file| {
+                let Some(path) = file.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                match export_player_geometry(&win.editor().model_type(), &path) {
+                    Ok(_) => println!("Saved at {:?}", path),
+                    Err(error) => println!("Error exporting Bedrock geometry: {:?}", error),
+                }
+            }));
+        }))
+        .build();
+    let export_layered = ActionEntry::builder("export-layered")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            if !player_model_loaded(&win) {
+                return;
             }
+            ask_layered_export(&win);
         }))
         .build();
-    win.add_action_entries([action]);
+    win.add_action_entries([
+        action,
+        export_player_head,
+        export_render,
+        export_animation,
+        export_model,
+        export_print,
+        export_blockbench,
+        export_bedrock,
+        export_layered,
+    ]);
 
     win.imp().save_button.connect_clicked(clone!(#[weak(rename_to = win)] win, move |_| {
         let mut file_dialog = gtk::FileDialog::builder().title("Save a skin").build();
@@ -78,3 +202,204 @@ fn connect_save(win: &Window) {
         }));
     }));
 }
+
+/// Whether the player model is edited, which the skin exports are built from. Tells
+/// otherwise that the cubes of a loaded geometry cannot be exported that way.
+fn player_model_loaded(win: &Window) -> bool {
+    if !win.editor().has_custom_rig() {
+        return true;
+    }
+    let dialog = adw::AlertDialog::new(
+        Some("Player model needed"),
+        Some("This export is built from the player model. Open a skin to stop editing the geometry."),
+    );
+    dialog.add_responses(&[("close", "_Close")]);
+    dialog.present(Some(win));
+    false
+}
+
+/// Asks for the format and how the outer layer is built, then for the file.
+fn ask_model_export(win: &Window) {
+    let format_selector = gtk::DropDown::from_strings(&["glTF binary (.glb)", "OBJ with MTL"]);
+    let overlay_selector = gtk::DropDown::from_strings(&["Inflated outer layer", "Opaque overlay voxels", "No outer layer"]);
+    let options = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    options.append(&format_selector);
+    options.append(&overlay_selector);
+
+    let dialog = adw::AlertDialog::new(
+        Some("Export 3D model"),
+        Some("The model is textured with the skin and has a node for every body part."),
+    );
+    dialog.set_extra_child(Some(&options));
+    dialog.add_responses(&[("cancel", "_Cancel"), ("export", "_Export…")]);
+    dialog.set_response_appearance("export", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("export"));
+    dialog.set_close_response("cancel");
+
+    dialog.connect_response(Some("export"), clone!(#[weak] win, move |_, _| {
+        let format = match format_selector.selected() {
+            1 => ModelFormat::Obj,
+            _ => ModelFormat::Glb,
+        };
+        let overlay = match overlay_selector.selected() {
+            1 => OverlayGeometry::Voxels,
+            2 => OverlayGeometry::None,
+            _ => OverlayGeometry::Shell,
+        };
+
+        let file_dialog = gtk::FileDialog::builder().title("Export 3D model").build();
+        file_dialog.set_initial_name(Some(&format!("skin.{}", format.extension())));
+        file_dialog.save(Some(&win), Cancellable::NONE, clone!(#[weak] win, move |file| {
+            let Some(path) = file.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            let model_type = win.editor().model_type();
+            match export_model(&win.export_texture(), &model_type, format, overlay, &path) {
+                Ok(_) => println!("Saved at {:?}", path),
+                Err(error) => println!("Error exporting model: {:?}", error),
+            }
+        }));
+    }));
+    dialog.present(Some(win));
+}
+
+/// Asks for the format and the size of a pixel, then for the file.
+fn ask_print_export(win: &Window) {
+    let defaults = PrintExport::default();
+    let format_selector = gtk::DropDown::from_strings(&["STL", "3MF", "PLY"]);
+    let pixel_size = gtk::SpinButton::with_range(0.1, 10.0, 0.1);
+    pixel_size.set_value(defaults.pixel_size as f64);
+    let pixel_size_row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    let pixel_size_label = gtk::Label::new(Some("Pixel size (mm)"));
+    pixel_size_label.set_hexpand(true);
+    pixel_size_label.set_xalign(0.0);
+    pixel_size_row.append(&pixel_size_label);
+    pixel_size_row.append(&pixel_size);
+    let options = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    options.append(&format_selector);
+    options.append(&pixel_size_row);
+
+    let dialog = adw::AlertDialog::new(
+        Some("Export for 3D printing"),
+        Some("Opaque overlay pixels become voxels around the body, the figure is about 34 pixels tall."),
+    );
+    dialog.set_extra_child(Some(&options));
+    dialog.add_responses(&[("cancel", "_Cancel"), ("export", "_Export…")]);
+    dialog.set_response_appearance("export", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("export"));
+    dialog.set_close_response("cancel");
+
+    dialog.connect_response(Some("export"), clone!(#[weak] win, move |_, _| {
+        let format = match format_selector.selected() {
+            1 => PrintFormat::ThreeMf,
+            2 => PrintFormat::Ply,
+            _ => PrintFormat::Stl,
+        };
+        let export = PrintExport { format, pixel_size: pixel_size.value() as f32 };
+
+        let file_dialog = gtk::FileDialog::builder().title("Export for 3D printing").build();
+        file_dialog.set_initial_name(Some(&format!("skin.{}", format.extension())));
+        file_dialog.save(Some(&win), Cancellable::NONE, clone!(#[weak] win, move |file| {
+            let Some(path) = file.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            let model_type = win.editor().model_type();
+            match export.export(&win.export_texture(), &model_type, &path) {
+                Ok(_) => println!("Saved at {:?}", path),
+                Err(error) => println!("Error exporting for 3D printing: {:?}", error),
+            }
+        }));
+    }));
+    dialog.present(Some(win));
+}
+
+/// Asks for the format of the layered image, then for the file.
+fn ask_layered_export(win: &Window) {
+    let format_selector = gtk::DropDown::from_strings(&["OpenRaster (.ora)", "Photoshop (.psd)"]);
+    let dialog = adw::AlertDialog::new(
+        Some("Export layered image"),
+        Some("Every body part and outer layer gets a layer of its own. Opening the file again keeps layers named after a body part inside that part."),
+    );
+    dialog.set_extra_child(Some(&format_selector));
+    dialog.add_responses(&[("cancel", "_Cancel"), ("export", "_Export…")]);
+    dialog.set_response_appearance("export", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("export"));
+    dialog.set_close_response("cancel");
+
+    dialog.connect_response(Some("export"), clone!(#[weak] win, move |_, _| {
+        let format = match format_selector.selected() {
+            1 => LayeredFormat::Psd,
+            _ => LayeredFormat::Ora,
+        };
+
+        let file_dialog = gtk::FileDialog::builder().title("Export layered image").build();
+        file_dialog.set_initial_name(Some(&format!("skin.{}", format.extension())));
+        file_dialog.save(Some(&win), Cancellable::NONE, clone!(#[weak] win, move |file| {
+            let Some(path) = file.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            let model_type = win.editor().model_type();
+            match export_layered(&win.export_texture(), &model_type, format, &path) {
+                Ok(_) => println!("Saved at {:?}", path),
+                Err(error) => println!("Error exporting layered image: {:?}", error),
+            }
+        }));
+    }));
+    dialog.present(Some(win));
+}
+
+/// Previews a Bedrock geometry painted with the current skin. Its cubes can be edited
+/// instead of the player model, or the skin can take the arm width of the geometry.
+fn show_bedrock_geometry(win: &Window, path: &Path) {
+    let geometry = match load_geometries(path) {
+        Ok(mut geometries) => geometries.remove(0),
+        Err(error) => {
+            println!("Error loading Bedrock geometry: {:?}", error);
+            return;
+        }
+    };
+
+    let options = RenderOptions { width: 256, height: 256, yaw: 35.0, pitch: 20.0, ..Default::default() };
+    let preview = render_facets(&geometry.facets(&win.export_texture()), &options);
+    let picture = gtk::Picture::for_paintable(&texture_from_image(&preview));
+    picture.set_content_fit(gtk::ContentFit::Contain);
+    picture.set_size_request(256, 256);
+
+    let extra_bones = geometry.extra_bones();
+    let body = if extra_bones.is_empty() {
+        "The geometry only has the parts of the player model.".to_string()
+    } else {
+        format!("Parts that are not in the player model: {}.", extra_bones.join(", "))
+    };
+    let dialog = adw::AlertDialog::new(Some(&geometry.description.identifier), Some(&body));
+    dialog.set_extra_child(Some(&picture));
+    dialog.add_responses(&[("close", "_Close")]);
+    dialog.set_close_response("close");
+
+    match geometry.to_rig() {
+        Ok(rig) => {
+            dialog.add_response("edit", "_Edit Geometry");
+            let rig = Cell::new(Some(rig));
+            dialog.connect_response(Some("edit"), clone!(#[weak] win, move |_, _| {
+                if let Some(rig) = rig.take() {
+                    win.load_rig(rig);
+                }
+            }));
+        }
+        Err(error) => println!("Error building the cubes of the geometry: {:?}", error),
+    }
+
+    let model_type = geometry.player_model_type();
+    if let Some(model_type) = model_type.filter(|model_type| *model_type != win.editor().model_type()) {
+        let label = match model_type {
+            ModelType::Slim => "Use _Slim Arms",
+            ModelType::Classic => "Use _Classic Arms",
+        };
+        dialog.add_response("arms", label);
+        dialog.set_response_appearance("arms", adw::ResponseAppearance::Suggested);
+        dialog.connect_response(Some("arms"), clone!(#[weak] win, move |_, _| {
+            win.select_model_type(model_type);
+        }));
+    }
+    dialog.present(Some(win));
+}
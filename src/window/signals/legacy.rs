@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use gtk::gio::{ActionEntry, Cancellable};
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ActionMapExtManual, FileExt};
+use image::RgbaImage;
+use libadwaita as adw;
+use libadwaita::prelude::{AdwDialogExt, AlertDialogExt};
+
+use crate::glium_area::skin_parser::ModelType;
+use crate::legacy_skin::{export_legacy, upgrade_legacy};
+use crate::window::Window;
+
+pub(super) fn connect(win: &Window) {
+    let export = ActionEntry::builder("export-legacy")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            ask_legacy_export(&win);
+        }))
+        .build();
+    let upgrade = ActionEntry::builder("upgrade-legacy")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            let file_dialog = gtk::FileDialog::builder().title("Upgrade a legacy skin").build();
+            file_dialog.open(Some(&win), Cancellable::NONE, clone!(#[weak] win, move |file| {
+                let Some(path) = file.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                upgrade_legacy_file(&win, &path);
+            }));
+        }))
+        .build();
+    win.add_action_entries([export, upgrade]);
+}
+
+/// Warns about what the legacy layout cannot hold before asking for the file.
+fn ask_legacy_export(win: &Window) {
+    let export = match export_legacy(&win.export_texture(), &win.editor().model_type()) {
+        Ok(export) => export,
+        Err(error) => {
+            println!("Error exporting legacy skin: {:?}", error);
+            return;
+        }
+    };
+    if export.lost_parts.is_empty() && !export.widened_arms {
+        save_legacy(win, export.image);
+        return;
+    }
+
+    let mut lines = vec![];
+    if export.widened_arms {
+        lines.push("Legacy skins only have classic arms, the slim arms are widened.".to_string());
+    }
+    if !export.lost_parts.is_empty() {
+        let parts: Vec<&str> = export.lost_parts.iter().map(|body_part| body_part.label()).collect();
+        lines.push(format!("These parts will look different in game: {}.", parts.join(", ")));
+    }
+    let dialog = adw::AlertDialog::new(Some("Export legacy skin"), Some(&lines.join("\n")));
+    dialog.add_responses(&[("cancel", "_Cancel"), ("export", "_Export Anyway…")]);
+    dialog.set_response_appearance("export", adw::ResponseAppearance::Destructive);
+    dialog.set_close_response("cancel");
+    let image = export.image;
+    dialog.connect_response(Some("export"), clone!(#[weak] win, move |_, _| {
+        save_legacy(&win, image.clone());
+    }));
+    dialog.present(Some(win));
+}
+
+fn save_legacy(win: &Window, image: RgbaImage) {
+    let file_dialog = gtk::FileDialog::builder().title("Export legacy skin").build();
+    file_dialog.set_initial_name(Some("skin.png"));
+    file_dialog.save(Some(win), Cancellable::NONE, move |file| {
+        let Some(path) = file.ok().and_then(|file| file.path()) else {
+            return;
+        };
+        match image.save(&path) {
+            Ok(_) => println!("Saved at {:?}", path),
+            Err(error) => println!("Error exporting legacy skin: {:?}", error),
+        }
+    });
+}
+
+/// Loads a 64x32 skin as a 64x64 one and lists the faces that were mirrored.
+fn upgrade_legacy_file(win: &Window, path: &Path) {
+    let upgrade = match image::open(path).map(|image| image.to_rgba8()) {
+        Ok(image) => upgrade_legacy(&image),
+        Err(error) => {
+            println!("Error opening legacy skin: {:?}", error);
+            return;
+        }
+    };
+    let upgrade = match upgrade {
+        Ok(upgrade) => upgrade,
+        Err(error) => {
+            println!("Error upgrading legacy skin: {:?}", error);
+            return;
+        }
+    };
+
+    let mut report: Vec<String> = upgrade.mirrored.iter().map(|face| face.describe()).collect();
+    if upgrade.hat_cleared {
+        report.push("Hat cleared, it had no transparent pixels".to_string());
+    }
+    win.apply_skin_image(upgrade.image.into(), ModelType::Classic);
+
+    let dialog = adw::AlertDialog::new(
+        Some("Legacy skin upgraded"),
+        Some("The left arm and leg were copied from the right ones, the way the game does it. The outer layers other than the hat are empty."),
+    );
+    let label = gtk::Label::new(Some(&report.join("\n")));
+    label.set_xalign(0.0);
+    label.set_selectable(true);
+    let scrolled = gtk::ScrolledWindow::builder().child(&label).min_content_height(200).build();
+    dialog.set_extra_child(Some(&scrolled));
+    dialog.add_responses(&[("close", "_Close")]);
+    dialog.set_close_response("close");
+    dialog.present(Some(win));
+}
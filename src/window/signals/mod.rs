@@ -1,8 +1,14 @@
+mod clipboard;
+mod compare;
+mod effects;
 mod file_io;
 mod grid;
+mod legacy;
 mod model_switcher;
 mod reset_skin;
+mod resolution;
 mod tools;
+mod transforms;
 mod wardrobe;
 
 use super::Window;
@@ -10,8 +16,14 @@ use super::Window;
 pub(super) fn connect(win: &Window) {
     wardrobe::connect(win);
     reset_skin::connect(win);
+    resolution::connect(win);
     tools::connect(win);
     grid::connect(win);
     file_io::connect(win);
+    clipboard::connect(win);
     model_switcher::connect(win);
+    compare::connect(win);
+    legacy::connect(win);
+    effects::connect(win);
+    transforms::connect(win);
 }
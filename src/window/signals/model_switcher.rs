@@ -4,9 +4,9 @@ use gtk::prelude::{ComboBoxExt, ToggleButtonExt};
 use gtk::subclass::prelude::ObjectSubclassIsExt;
 
 use crate::glium_area::body_part::BodyPart;
-use crate::glium_area::body_part::BodyPart::*;
 use crate::glium_area::skin_parser::ModelType;
 use crate::model_switcher::ModelSwitcher;
+use crate::settings::Settings;
 use crate::window::Window;
 
 pub(super) fn connect(win: &Window) {
@@ -28,12 +28,12 @@ pub(super) fn connect(win: &Window) {
         move |_| sync_outer_layer(&win, &model_switcher)
     ));
 
-    connect_part_toggle(&model_switcher, win.clone(), Head, HeadOuter, |ms| ms.head());
-    connect_part_toggle(&model_switcher, win.clone(), Torso, TorsoOuter, |ms| ms.torso());
-    connect_part_toggle(&model_switcher, win.clone(), LeftArm, LeftArmOuter, |ms| ms.left_arm());
-    connect_part_toggle(&model_switcher, win.clone(), RightArm, RightArmOuter, |ms| ms.right_arm());
-    connect_part_toggle(&model_switcher, win.clone(), LeftLeg, LeftLegOuter, |ms| ms.left_leg());
-    connect_part_toggle(&model_switcher, win.clone(), RightLeg, RightLegOuter, |ms| ms.right_leg());
+    connect_part_toggle(&model_switcher, win.clone(), BodyPart::Head, BodyPart::HeadOuter, |ms| ms.head());
+    connect_part_toggle(&model_switcher, win.clone(), BodyPart::Torso, BodyPart::TorsoOuter, |ms| ms.torso());
+    connect_part_toggle(&model_switcher, win.clone(), BodyPart::LeftArm, BodyPart::LeftArmOuter, |ms| ms.left_arm());
+    connect_part_toggle(&model_switcher, win.clone(), BodyPart::RightArm, BodyPart::RightArmOuter, |ms| ms.right_arm());
+    connect_part_toggle(&model_switcher, win.clone(), BodyPart::LeftLeg, BodyPart::LeftLegOuter, |ms| ms.left_leg());
+    connect_part_toggle(&model_switcher, win.clone(), BodyPart::RightLeg, BodyPart::RightLegOuter, |ms| ms.right_leg());
 
     model_switcher.imp().model_type_selector.connect_selected_notify(clone!(
         #[weak(rename_to = win)]
@@ -50,29 +50,55 @@ pub(super) fn connect(win: &Window) {
             win.change_model_type(model_type);
         }
     ));
+
+    model_switcher.set_conversion_strategy(Settings::load().arm_conversion);
+    model_switcher.imp().conversion_selector.connect_selected_notify(clone!(
+        #[weak]
+        model_switcher,
+        move |_| {
+            let strategy = model_switcher.conversion_strategy();
+            Settings::update(|settings| settings.arm_conversion = strategy);
+        }
+    ));
+
+    model_switcher.imp().overlay_view_selector.connect_selected_notify(clone!(
+        #[weak]
+        model_switcher,
+        #[weak(rename_to = win)]
+        win,
+        move |_| win.set_overlay_view(model_switcher.overlay_view())
+    ));
+
+    model_switcher.imp().paint_target_selector.connect_selected_notify(clone!(
+        #[weak]
+        model_switcher,
+        #[weak(rename_to = win)]
+        win,
+        move |_| win.set_paint_target(model_switcher.paint_target())
+    ));
 }
 
 fn sync_inner_layer(win: &Window, model_switcher: &ModelSwitcher) {
     let layer_active = model_switcher.inner_layer_toggle().is_active();
     win.set_body_parts_visible(&[
-        (&Head, layer_active && model_switcher.head().is_active()),
-        (&Torso, layer_active && model_switcher.torso().is_active()),
-        (&RightArm, layer_active && model_switcher.right_arm().is_active()),
-        (&LeftArm, layer_active && model_switcher.left_arm().is_active()),
-        (&RightLeg, layer_active && model_switcher.right_leg().is_active()),
-        (&LeftLeg, layer_active && model_switcher.left_leg().is_active()),
+        (&BodyPart::Head, layer_active && model_switcher.head().is_active()),
+        (&BodyPart::Torso, layer_active && model_switcher.torso().is_active()),
+        (&BodyPart::RightArm, layer_active && model_switcher.right_arm().is_active()),
+        (&BodyPart::LeftArm, layer_active && model_switcher.left_arm().is_active()),
+        (&BodyPart::RightLeg, layer_active && model_switcher.right_leg().is_active()),
+        (&BodyPart::LeftLeg, layer_active && model_switcher.left_leg().is_active()),
     ]);
 }
 
 fn sync_outer_layer(win: &Window, model_switcher: &ModelSwitcher) {
     let layer_active = model_switcher.outer_layer_toggle().is_active();
     win.set_body_parts_visible(&[
-        (&HeadOuter, layer_active && model_switcher.head().is_active()),
-        (&TorsoOuter, layer_active && model_switcher.torso().is_active()),
-        (&RightArmOuter, layer_active && model_switcher.right_arm().is_active()),
-        (&LeftArmOuter, layer_active && model_switcher.left_arm().is_active()),
-        (&RightLegOuter, layer_active && model_switcher.right_leg().is_active()),
-        (&LeftLegOuter, layer_active && model_switcher.left_leg().is_active()),
+        (&BodyPart::HeadOuter, layer_active && model_switcher.head().is_active()),
+        (&BodyPart::TorsoOuter, layer_active && model_switcher.torso().is_active()),
+        (&BodyPart::RightArmOuter, layer_active && model_switcher.right_arm().is_active()),
+        (&BodyPart::LeftArmOuter, layer_active && model_switcher.left_arm().is_active()),
+        (&BodyPart::RightLegOuter, layer_active && model_switcher.right_leg().is_active()),
+        (&BodyPart::LeftLegOuter, layer_active && model_switcher.left_leg().is_active()),
     ]);
 }
 
@@ -0,0 +1,32 @@
+use gtk::gio::ActionEntry;
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::ActionMapExtManual;
+
+use crate::glium_area::rig::RESOLUTIONS;
+use crate::window::Window;
+
+pub(super) fn connect(win: &Window) {
+    let upscale = ActionEntry::builder("upscale-skin")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            step_resolution(&win, 1);
+        }))
+        .build();
+    let downscale = ActionEntry::builder("downscale-skin")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            step_resolution(&win, -1);
+        }))
+        .build();
+    win.add_action_entries([upscale, downscale]);
+}
+
+/// Moves `steps` along [`RESOLUTIONS`], staying at the ends.
+fn step_resolution(win: &Window, steps: isize) {
+    let current = win.editor().resolution();
+    let index = RESOLUTIONS.iter().position(|resolution| *resolution == current).unwrap_or(0);
+    let Some(&resolution) = index.checked_add_signed(steps).and_then(|index| RESOLUTIONS.get(index)) else {
+        println!("The skin is already {0}x{0}", 64 * current);
+        return;
+    };
+    win.change_resolution(resolution);
+}
@@ -1,9 +1,12 @@
+use gtk::gio::{ActionEntry, Cancellable};
 use gtk::glib;
 use gtk::glib::clone;
-use gtk::prelude::ToggleButtonExt;
+use gtk::prelude::{ActionMapExtManual, FileExt, ToggleButtonExt};
 use gtk::subclass::prelude::ObjectSubclassIsExt;
+use libadwaita::prelude::AdwDialogExt;
 
 use crate::command::Tool;
+use crate::stamp_dialog::StampDialog;
 use crate::window::Window;
 
 pub(super) fn connect(win: &Window) {
@@ -25,4 +28,32 @@ pub(super) fn connect(win: &Window) {
     win.imp().replace_color.connect_toggled(clone!(#[weak(rename_to = win)] win, move |_| {
         win.editor_mut().set_tool(Tool::Replace);
     }));
+    win.imp().text_tool.connect_toggled(clone!(#[weak(rename_to = win)] win, move |_| {
+        win.editor_mut().set_tool(Tool::Text);
+    }));
+    win.imp().shade.connect_toggled(clone!(#[weak(rename_to = win)] win, move |_| {
+        win.editor_mut().set_tool(Tool::Shade);
+    }));
+    win.imp().noise.connect_toggled(clone!(#[weak(rename_to = win)] win, move |_| {
+        win.editor_mut().set_tool(Tool::Noise);
+    }));
+    win.imp().gradient.connect_toggled(clone!(#[weak(rename_to = win)] win, move |_| {
+        win.editor_mut().set_tool(Tool::Gradient);
+    }));
+
+    let stamp_image = ActionEntry::builder("stamp-image")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            let file_dialog = gtk::FileDialog::builder().title("Stamp an image").build();
+            file_dialog.open(Some(&win), Cancellable::NONE, clone!(#[weak] win, move |file| {
+                let Some(path) = file.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                match image::open(&path) {
+                    Ok(picture) => StampDialog::new(&win, picture.to_rgba8()).present(Some(&win)),
+                    Err(error) => println!("Error opening image: {:?}", error),
+                }
+            }));
+        }))
+        .build();
+    win.add_action_entries([stamp_image]);
 }
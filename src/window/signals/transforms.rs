@@ -0,0 +1,126 @@
+use gtk::gio::ActionEntry;
+use gtk::glib;
+use gtk::glib::clone;
+use gtk::prelude::{ActionMapExtManual, GridExt, WidgetExt};
+use libadwaita as adw;
+use libadwaita::prelude::{AdwDialogExt, AlertDialogExt};
+
+use crate::glium_area::body_part::BodyPart;
+use crate::glium_area::cube_side::CubeSide;
+use crate::glium_area::renderer::ModelCell;
+use crate::part_transforms::{transform, Rotation, Transform};
+use crate::window::Window;
+
+const OPERATIONS: [&str; 8] = [
+    "Mirror onto",
+    "Copy onto",
+    "Swap with",
+    "Flip face horizontally",
+    "Flip face vertically",
+    "Rotate face clockwise",
+    "Rotate face counter-clockwise",
+    "Rotate face 180°",
+];
+/// Faces in the order of the face selector.
+const SIDES: [CubeSide; 6] = [CubeSide::Front, CubeSide::Left, CubeSide::Back, CubeSide::Right, CubeSide::Top, CubeSide::Bottom];
+
+pub(super) fn connect(win: &Window) {
+    let transform_parts = ActionEntry::builder("transform-parts")
+        .activate(clone!(#[weak(rename_to = win)] win, move |_, _, _| {
+            ask_transform(&win);
+        }))
+        .build();
+    win.add_action_entries([transform_parts]);
+}
+
+/// The other arm or leg for mirrors and swaps, the other layer for copies.
+fn default_target(operation: u32, body_part: BodyPart) -> BodyPart {
+    if operation == 1 {
+        return body_part.outer_counterpart().or(body_part.inner_counterpart()).unwrap_or(body_part);
+    }
+    match body_part {
+        BodyPart::RightArm => BodyPart::LeftArm,
+        BodyPart::LeftArm => BodyPart::RightArm,
+        BodyPart::RightLeg => BodyPart::LeftLeg,
+        BodyPart::LeftLeg => BodyPart::RightLeg,
+        BodyPart::RightArmOuter => BodyPart::LeftArmOuter,
+        BodyPart::LeftArmOuter => BodyPart::RightArmOuter,
+        BodyPart::RightLegOuter => BodyPart::LeftLegOuter,
+        BodyPart::LeftLegOuter => BodyPart::RightLegOuter,
+        other => other,
+    }
+}
+
+fn ask_transform(win: &Window) {
+    let labels: Vec<&str> = BodyPart::PLAYER.iter().map(|body_part| body_part.label()).collect();
+    let operation_selector = gtk::DropDown::from_strings(&OPERATIONS);
+    let part_selector = gtk::DropDown::from_strings(&labels);
+    let target_selector = gtk::DropDown::from_strings(&labels);
+    let side_selector = gtk::DropDown::from_strings(&["Front", "Left", "Back", "Right", "Top", "Bottom"]);
+
+    let grid = gtk::Grid::builder().row_spacing(6).column_spacing(10).build();
+    for (row, (text, selector)) in [
+        ("Operation", &operation_selector),
+        ("Body part", &part_selector),
+        ("Onto", &target_selector),
+        ("Face", &side_selector),
+    ].into_iter().enumerate() {
+        grid.attach(&gtk::Label::builder().label(text).xalign(0.0).build(), 0, row as i32, 1, 1);
+        selector.set_hexpand(true);
+        grid.attach(selector, 1, row as i32, 1, 1);
+    }
+
+    let body_part = |selector: &gtk::DropDown| BodyPart::PLAYER[selector.selected() as usize % BodyPart::PLAYER.len()];
+    let update = clone!(#[weak] operation_selector, #[weak] part_selector, #[weak] target_selector, #[weak] side_selector, move || {
+        let operation = operation_selector.selected();
+        let target = default_target(operation, body_part(&part_selector));
+        target_selector.set_selected(BodyPart::PLAYER.iter().position(|part| *part == target).unwrap_or(0) as u32);
+        target_selector.set_sensitive(operation < 3);
+        side_selector.set_sensitive(operation >= 3);
+    });
+    let on_change = update.clone();
+    operation_selector.connect_selected_notify(move |_| on_change());
+    let on_change = update.clone();
+    part_selector.connect_selected_notify(move |_| on_change());
+    // Starts on the right arm, the usual source of a mirror
+    part_selector.set_selected(BodyPart::PLAYER.iter().position(|part| *part == BodyPart::RightArm).unwrap_or(0) as u32);
+    update();
+
+    let dialog = adw::AlertDialog::new(Some("Transform parts"), None);
+    dialog.set_extra_child(Some(&grid));
+    dialog.add_responses(&[("cancel", "_Cancel"), ("apply", "_Apply")]);
+    dialog.set_response_appearance("apply", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("apply"));
+    dialog.set_close_response("cancel");
+
+    dialog.connect_response(Some("apply"), clone!(#[weak] win, move |_, _| {
+        let (from, to) = (body_part(&part_selector), body_part(&target_selector));
+        let side = SIDES[side_selector.selected() as usize % SIDES.len()];
+        let operation = match operation_selector.selected() {
+            0 => Transform::Mirror { from, to },
+            1 => Transform::Copy { from, to },
+            2 => Transform::Swap(from, to),
+            3 => Transform::Flip { body_part: from, side, vertical: false },
+            4 => Transform::Flip { body_part: from, side, vertical: true },
+            5 => Transform::Rotate { body_part: from, side, rotation: Rotation::Clockwise },
+            6 => Transform::Rotate { body_part: from, side, rotation: Rotation::CounterClockwise },
+            _ => Transform::Rotate { body_part: from, side, rotation: Rotation::HalfTurn },
+        };
+        match transform(&win.export_texture(), &win.editor().model_type(), &operation) {
+            Ok(cells) if cells.is_empty() => {}
+            Ok(cells) => {
+                let cells = cells
+                    .into_iter()
+                    .map(|(body_part, cell_index, color)| ModelCell {
+                        body_part,
+                        cell_index,
+                        color: color.0.map(|channel| f32::from(channel) / 255.0),
+                    })
+                    .collect();
+                win.paint_cells(cells);
+            }
+            Err(error) => println!("Error transforming parts: {:?}", error),
+        }
+    }));
+    dialog.present(Some(win));
+}